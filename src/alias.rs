@@ -0,0 +1,67 @@
+use crate::{
+    files::to_extended_path,
+    ops::{FsOp, Ops},
+    Error,
+};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Name of the file in the slots directory which maps aliases to slot names.
+const ALIASES: &str = ".aliases.json";
+
+/// Read the aliases in the given slots directory, by alias.
+pub(crate) fn read(slots: &Path) -> Result<BTreeMap<String, String>, Error> {
+    let path = slots.join(ALIASES);
+
+    if !path.is_file() {
+        return Ok(BTreeMap::new());
+    }
+
+    let bytes = fs::read(to_extended_path(&path)).map_err(|e| Error::io(&path, e))?;
+
+    serde_json::from_slice(&bytes).map_err(|source| Error::InvalidManifest { path, source })
+}
+
+/// Write the aliases into the given slots directory.
+pub(crate) fn write(
+    ops: &Ops,
+    slots: &Path,
+    aliases: &BTreeMap<String, String>,
+) -> Result<(), Error> {
+    let path = slots.join(ALIASES);
+
+    let bytes = serde_json::to_vec_pretty(aliases).map_err(|source| Error::InvalidManifest {
+        path: path.clone(),
+        source,
+    })?;
+
+    ops.run(FsOp::Write(&path, &bytes))
+}
+
+/// Check that an alias is made up of letters, digits, `-` and `_`, and isn't `profile` which
+/// would be ambiguous with `@profile`.
+pub(crate) fn validate(alias: &str) -> Result<&str, Error> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+
+    if alias.is_empty() || alias == "profile" || !alias.chars().all(valid) {
+        return Err(Error::InvalidAlias {
+            alias: alias.to_string(),
+        });
+    }
+
+    Ok(alias)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_aliases() {
+        assert!(validate("1").is_ok());
+        assert!(validate("before-heist_2").is_ok());
+        assert!(validate("").is_err());
+        assert!(validate("profile").is_err());
+        assert!(validate("@1").is_err());
+        assert!(validate("a b").is_err());
+    }
+}
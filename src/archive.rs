@@ -0,0 +1,489 @@
+use crate::{
+    compress,
+    crypto::{self, Passphrase},
+    files::{
+        self, check_file_name, is_save_file_name, list_save_files, to_extended_path, total_size,
+    },
+    hash,
+    manifest::{ManifestFile, MANIFEST},
+    ops::{FsOp, Ops},
+    space, Error, SaveFilePatterns,
+};
+use chrono::{Datelike as _, TimeZone as _, Timelike as _};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsStr,
+    fs,
+    io::{self, Cursor, Read as _, Seek, Write},
+    path::Path,
+    time::SystemTime,
+};
+use zip::write::FileOptions;
+
+/// The most a single entry of an archive or bundle is decompressed to, which is far more than any
+/// save file, so that a small archive can't be used to exhaust memory.
+pub(crate) const MAX_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The most all entries of an archive or bundle are decompressed to together.
+pub(crate) const MAX_EXTRACTED_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Reads the entries of the archive at a path, keeping track of how much they've been
+/// decompressed to.
+pub(crate) struct EntryReader<'a> {
+    path: &'a Path,
+    /// The most a single entry is decompressed to.
+    entry_limit: u64,
+    /// How much more all entries together are decompressed to.
+    remaining: u64,
+}
+
+impl<'a> EntryReader<'a> {
+    pub(crate) fn new(path: &'a Path) -> Self {
+        Self {
+            path,
+            entry_limit: MAX_ENTRY_SIZE,
+            remaining: MAX_EXTRACTED_SIZE,
+        }
+    }
+
+    /// Read the entry with the given name.
+    ///
+    /// Errors with [Error::ArchiveEntryTooLarge] if it's larger than [MAX_ENTRY_SIZE], and with
+    /// [Error::ArchiveTooLarge] if the entries read so far are larger than [MAX_EXTRACTED_SIZE]
+    /// together, without reading any more than that.
+    pub(crate) fn read(&mut self, entry: impl io::Read, name: &str) -> Result<Vec<u8>, Error> {
+        let limit = self.entry_limit.min(self.remaining);
+
+        let mut contents = Vec::new();
+        entry
+            .take(limit + 1)
+            .read_to_end(&mut contents)
+            .map_err(|e| Error::io(self.path, e))?;
+
+        let len = contents.len() as u64;
+
+        if len > self.entry_limit {
+            return Err(Error::ArchiveEntryTooLarge {
+                path: self.path.to_owned(),
+                name: name.to_string(),
+                limit: self.entry_limit,
+            });
+        }
+
+        if len > self.remaining {
+            return Err(Error::ArchiveTooLarge {
+                path: self.path.to_owned(),
+                limit: MAX_EXTRACTED_SIZE,
+            });
+        }
+
+        self.remaining -= len;
+        Ok(contents)
+    }
+}
+
+/// Describes the contents of an exported archive.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ArchiveManifest {
+    /// The name of the slot which was exported.
+    pub(crate) slot: String,
+    /// When the archive was created, in RFC 3339 format.
+    pub(crate) exported: String,
+    /// The save files in the archive.
+    pub(crate) files: Vec<ManifestFile>,
+}
+
+/// Write all save files in `dir` into a new zip archive at `path`, along with a manifest
+/// naming them after `slot`.
+///
+/// The archive is encrypted if a passphrase is given. The partially written archive is removed
+/// if this fails. Errors before writing anything if the save files wouldn't fit uncompressed.
+pub(crate) fn export(
+    dir: &Path,
+    slot: &str,
+    path: &Path,
+    passphrase: Option<&Passphrase>,
+    patterns: &SaveFilePatterns,
+) -> Result<(), Error> {
+    space::check(path, total_size(&list_save_files(dir, patterns)?)?)?;
+
+    let result = match passphrase {
+        Some(passphrase) => write_archive(dir, slot, Cursor::new(Vec::new()), path, patterns)
+            .and_then(|zip| crypto::encrypt(&zip.into_inner(), passphrase, path))
+            .and_then(|encrypted| {
+                fs::write(to_extended_path(path), encrypted).map_err(|e| Error::io(path, e))
+            }),
+        None => fs::File::create(to_extended_path(path))
+            .map_err(|e| Error::io(path, e))
+            .and_then(|file| write_archive(dir, slot, file, path, patterns))
+            .map(drop),
+    };
+
+    if result.is_err() {
+        let _ = fs::remove_file(to_extended_path(path));
+    }
+
+    result
+}
+
+fn write_archive<W>(
+    dir: &Path,
+    slot: &str,
+    file: W,
+    path: &Path,
+    patterns: &SaveFilePatterns,
+) -> Result<W, Error>
+where
+    W: Write + Seek,
+{
+    let zip_error = |source| Error::Zip {
+        path: path.to_owned(),
+        source,
+    };
+
+    let mut save_files = list_save_files(dir, patterns)?;
+    save_files.sort();
+
+    let mut zip = zip::ZipWriter::new(file);
+    let mut files = Vec::new();
+
+    for (name, save_file) in save_files {
+        let contents = compress::read(&save_file)?;
+        let modified = fs::metadata(to_extended_path(&save_file))
+            .and_then(|m| m.modified())
+            .map_err(|e| Error::io(&save_file, e))?;
+
+        let options = FileOptions::default().last_modified_time(zip_time(modified));
+        let name = name.to_string_lossy().into_owned();
+        zip.start_file(name.as_str(), options).map_err(zip_error)?;
+        zip.write_all(&contents).map_err(|e| Error::io(path, e))?;
+
+        files.push(ManifestFile::new(name, hash::hash_bytes(&contents)));
+    }
+
+    let manifest = ArchiveManifest {
+        slot: slot.to_string(),
+        exported: chrono::Local::now().to_rfc3339(),
+        files,
+    };
+
+    let options = FileOptions::default().last_modified_time(zip_time(SystemTime::now()));
+    zip.start_file(MANIFEST, options).map_err(zip_error)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest)
+        .map_err(|e| Error::io(path, io::Error::from(e)))?;
+    zip.finish().map_err(zip_error)
+}
+
+/// Extract the save files in the zip archive at `path` into `dir`.
+///
+/// Encrypted archives are decrypted with the passphrase, and error with
+/// [Error::PassphraseRequired] if there isn't one. If the archive contains a manifest, the
+/// extracted files are checked against it. Errors if the archive doesn't contain any save
+/// files, or contains entries which aren't valid file names on Windows, which could otherwise
+/// be used to write outside of `dir` or into an alternate data stream.
+///
+/// Entries are read with an [EntryReader], so that an archive which decompresses to more than
+/// would fit in memory errors instead.
+pub(crate) fn extract(
+    ops: &Ops,
+    path: &Path,
+    dir: &Path,
+    passphrase: Option<&Passphrase>,
+) -> Result<(), Error> {
+    let zip_error = |source| Error::Zip {
+        path: path.to_owned(),
+        source,
+    };
+
+    let mut contents = fs::read(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+
+    if crypto::is_encrypted(&contents) {
+        let passphrase = passphrase.ok_or_else(|| Error::PassphraseRequired {
+            path: path.to_owned(),
+        })?;
+
+        contents = crypto::decrypt(&contents, passphrase, path)?;
+    }
+
+    let mut zip = zip::ZipArchive::new(Cursor::new(contents)).map_err(zip_error)?;
+    let mut save_files = Vec::new();
+    let mut manifest = None;
+    let mut reader = EntryReader::new(path);
+
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index).map_err(zip_error)?;
+        let name = entry.name().to_string();
+
+        if check_file_name(&name).is_err() {
+            return Err(Error::UnsafeArchiveEntry {
+                path: path.to_owned(),
+                name,
+            });
+        }
+
+        let contents = reader.read(&mut entry, &name)?;
+
+        if name == MANIFEST {
+            let parsed = serde_json::from_slice::<ArchiveManifest>(&contents)
+                .map_err(|e| Error::io(path, io::Error::from(e)))?;
+            manifest = Some(parsed);
+        } else if is_save_file_name(OsStr::new(&name), &ops.patterns) {
+            save_files.push((name, contents, entry.last_modified()));
+        }
+    }
+
+    if save_files.is_empty() {
+        return Err(Error::EmptyArchive {
+            path: path.to_owned(),
+        });
+    }
+
+    if let Some(manifest) = manifest {
+        for expected in &manifest.files {
+            let reason = match save_files.iter().find(|(n, ..)| *n == expected.name) {
+                Some((_, contents, _)) => {
+                    let actual = hash::hash_bytes(contents).to_string();
+
+                    if actual == expected.sha256 {
+                        continue;
+                    }
+
+                    format!("expected SHA-256 {}, got {}", expected.sha256, actual)
+                }
+                None => String::from("missing from the archive"),
+            };
+
+            return Err(Error::Corrupt {
+                from: path.join(&expected.name),
+                to: dir.join(&expected.name),
+                reason,
+            });
+        }
+    }
+
+    for (name, contents, modified) in save_files {
+        let dest = dir.join(&name);
+        ops.run(FsOp::Write(&dest, &contents))?;
+
+        if ops.dry_run {
+            continue;
+        }
+
+        if let Some(modified) = system_time(modified) {
+            files::set_modified(&dest, modified)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert a point in time into the local time stored in zip archives, which only has a
+/// resolution of two seconds and can't represent times before 1980.
+pub(crate) fn zip_time(time: SystemTime) -> zip::DateTime {
+    let time = chrono::DateTime::<chrono::Local>::from(time);
+
+    zip::DateTime::from_date_and_time(
+        time.year() as u16,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .unwrap_or_default()
+}
+
+/// Convert the local time stored in a zip archive into a point in time.
+pub(crate) fn system_time(time: zip::DateTime) -> Option<SystemTime> {
+    let time = chrono::Local
+        .ymd_opt(time.year().into(), time.month().into(), time.day().into())
+        .and_hms_opt(
+            time.hour().into(),
+            time.minute().into(),
+            time.second().into(),
+        )
+        .earliest()?;
+
+    Some(time.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_zip;
+
+    #[test]
+    fn export_writes_save_files_and_manifest() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("SGTA00000"), b"first")?;
+        fs::write(dir.path().join("SGTA00001"), b"second")?;
+        fs::write(dir.path().join("notes.txt"), b"ignored")?;
+
+        let out = tempfile::tempdir()?;
+        let path = out.path().join("foo.zip");
+        export(dir.path(), "foo", &path, None, &SaveFilePatterns::default())?;
+
+        let mut zip = zip::ZipArchive::new(fs::File::open(&path)?)?;
+        let mut names = zip.file_names().map(String::from).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["SGTA00000", "SGTA00001", MANIFEST]);
+
+        let mut contents = String::new();
+        zip.by_name("SGTA00001")?.read_to_string(&mut contents)?;
+        assert_eq!(contents, "second");
+
+        let manifest: ArchiveManifest = serde_json::from_reader(zip.by_name(MANIFEST)?)?;
+        assert_eq!(manifest.slot, "foo");
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(manifest.files[1].size, 6);
+        assert_eq!(
+            manifest.files[1].sha256,
+            hash::hash_bytes(b"second").to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn extract_exported_archive() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("SGTA00000"), b"first")?;
+        let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(dir.path().join("SGTA00000"), old)?;
+
+        let out = tempfile::tempdir()?;
+        let path = out.path().join("foo.zip");
+        export(dir.path(), "foo", &path, None, &SaveFilePatterns::default())?;
+
+        let to = tempfile::tempdir()?;
+        let dry_run = Ops {
+            dry_run: true,
+            ..Ops::default()
+        };
+        extract(&dry_run, &path, to.path(), None)?;
+        assert!(fs::read_dir(to.path())?.next().is_none());
+
+        extract(&Ops::default(), &path, to.path(), None)?;
+        assert_eq!(fs::read(to.path().join("SGTA00000"))?, b"first");
+        assert!(!to.path().join(MANIFEST).exists());
+
+        let meta = fs::metadata(to.path().join("SGTA00000"))?;
+        let mtime = filetime::FileTime::from_last_modification_time(&meta);
+        assert!((mtime.unix_seconds() - old.unix_seconds()).abs() <= 2);
+        Ok(())
+    }
+
+    #[test]
+    fn extract_encrypted_archive() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("SGTA00000"), b"first")?;
+
+        let out = tempfile::tempdir()?;
+        let path = out.path().join("foo.zip");
+        let passphrase = Passphrase::new(String::from("hunter2"));
+        export(
+            dir.path(),
+            "foo",
+            &path,
+            Some(&passphrase),
+            &SaveFilePatterns::default(),
+        )?;
+        assert!(crypto::is_encrypted_archive(&path)?);
+
+        let to = tempfile::tempdir()?;
+
+        assert!(matches!(
+            extract(&Ops::default(), &path, to.path(), None),
+            Err(Error::PassphraseRequired { .. })
+        ));
+
+        let wrong = Passphrase::new(String::from("hunter3"));
+        assert!(matches!(
+            extract(&Ops::default(), &path, to.path(), Some(&wrong)),
+            Err(Error::DecryptionFailed { .. })
+        ));
+        assert!(fs::read_dir(to.path())?.next().is_none());
+
+        extract(&Ops::default(), &path, to.path(), Some(&passphrase))?;
+        assert_eq!(fs::read(to.path().join("SGTA00000"))?, b"first");
+        Ok(())
+    }
+
+    #[test]
+    fn extract_rejects_unsafe_and_corrupt_archives() -> Result<(), Box<dyn std::error::Error>> {
+        let out = tempfile::tempdir()?;
+        let to = tempfile::tempdir()?;
+
+        let long = "SGTA".repeat(100);
+
+        for name in &[
+            "../SGTA00000",
+            "foo/SGTA00000",
+            "..\\SGTA00000",
+            "..",
+            "SGTA00000:evil",
+            "CON",
+            long.as_str(),
+        ] {
+            let path = out.path().join("unsafe.zip");
+            write_zip(&path, &[("SGTA00001", b"ok"), (name, b"evil")])?;
+
+            assert!(matches!(
+                extract(&Ops::default(), &path, to.path(), None),
+                Err(Error::UnsafeArchiveEntry { .. })
+            ));
+        }
+
+        let path = out.path().join("empty.zip");
+        write_zip(&path, &[("readme.txt", b"hello")])?;
+        assert!(matches!(
+            extract(&Ops::default(), &path, to.path(), None),
+            Err(Error::EmptyArchive { .. })
+        ));
+
+        let manifest = br#"{
+            "slot": "foo",
+            "exported": "",
+            "files": [{"name": "SGTA00000", "size": 2, "sha256": "00"}]
+        }"#;
+        let path = out.path().join("corrupt.zip");
+        write_zip(&path, &[("SGTA00000", b"ok"), (MANIFEST, manifest)])?;
+        assert!(matches!(
+            extract(&Ops::default(), &path, to.path(), None),
+            Err(Error::Corrupt { .. })
+        ));
+
+        assert!(fs::read_dir(to.path())?.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn entry_reader_caps_decompressed_size() -> Result<(), Box<dyn std::error::Error>> {
+        let path = Path::new("bomb.zip");
+
+        let mut reader = EntryReader {
+            path,
+            entry_limit: 4,
+            remaining: 6,
+        };
+
+        assert!(matches!(
+            reader.read(&b"abcde"[..], "SGTA00001"),
+            Err(Error::ArchiveEntryTooLarge { name, limit: 4, .. }) if name == "SGTA00001"
+        ));
+        assert_eq!(reader.read(&b"abcd"[..], "SGTA00000")?, b"abcd");
+        assert_eq!(reader.read(&b"ab"[..], "SGTA00002")?, b"ab");
+        assert!(matches!(
+            reader.read(&b"a"[..], "SGTA00003"),
+            Err(Error::ArchiveTooLarge { .. })
+        ));
+
+        // Nothing beyond the limit is read from an entry.
+        let mut reader = EntryReader::new(path);
+        let mut endless = io::repeat(0);
+        assert!(matches!(
+            reader.read(&mut endless, "SGTA00000"),
+            Err(Error::ArchiveEntryTooLarge { .. })
+        ));
+        Ok(())
+    }
+}
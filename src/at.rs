@@ -0,0 +1,194 @@
+use crate::{prune, Error, Slot};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone as _};
+use std::time::SystemTime;
+
+/// Parse a point in local time, like `2024-03-01 21:00`, `2024-03-01`, `21:00`, `yesterday`, or
+/// how long ago like `2h`.
+///
+/// A date without a time means the end of that day, so that it picks the last slot saved that
+/// day. A time without a date means today. Durations ago are given in minutes, hours, days or
+/// weeks, as in `90m`, `2h`, `3d` and `1w`.
+pub fn parse_time(value: &str) -> Result<SystemTime, Error> {
+    match parse(value, Local::now()) {
+        Some(time) => Ok(time.into()),
+        None => Err(Error::InvalidTime {
+            value: value.to_string(),
+        }),
+    }
+}
+
+fn parse(value: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let value = value.trim().to_lowercase();
+
+    if value == "now" {
+        return Some(now);
+    }
+
+    if let Some(ago) = parse_ago(&value) {
+        return now.checked_sub_signed(ago);
+    }
+
+    let local = |time: NaiveDateTime| Local.from_local_datetime(&time).earliest();
+
+    // RFC 3339 style, with the `T` lowercased like the rest.
+    for format in &["%Y-%m-%dt%H:%M:%S", "%Y-%m-%dt%H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(&value, format) {
+            return local(time);
+        }
+    }
+
+    let mut parts = value.split_whitespace();
+    let first = parts.next()?;
+    let second = parts.next();
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let today = now.date().naive_local();
+
+    let date = match first {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        _ => NaiveDate::parse_from_str(first, "%Y-%m-%d").ok(),
+    };
+
+    match (date, second) {
+        (Some(date), Some(time)) => local(date.and_time(parse_time_of_day(time)?)),
+        (Some(date), None) => local(date.and_hms_opt(23, 59, 59)?),
+        (None, None) => local(today.and_time(parse_time_of_day(first)?)),
+        (None, Some(..)) => None,
+    }
+}
+
+/// Parse how long ago something was, like `2h`.
+fn parse_ago(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let number = i64::from(value[..value.len() - unit.len_utf8()].parse::<u32>().ok()?);
+
+    match unit {
+        'm' => Some(Duration::minutes(number)),
+        'h' => Some(Duration::hours(number)),
+        'd' => Some(Duration::days(number)),
+        'w' => Some(Duration::weeks(number)),
+        _ => None,
+    }
+}
+
+fn parse_time_of_day(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .ok()
+}
+
+/// Select the dated slot saved last at or before `time`, going by the time in its name parsed
+/// with `format`, and when it was saved. Slots saved at the same time are told apart by name.
+pub(crate) fn select(
+    slots: Vec<Slot>,
+    time: SystemTime,
+    format: &str,
+) -> Option<(Slot, SystemTime)> {
+    slots
+        .into_iter()
+        .map(|s| (prune::saved_at(&s, format), s))
+        .filter(|(saved, _)| *saved <= time)
+        .max_by(|(a, a_slot), (b, b_slot)| a.cmp(b).then_with(|| a_slot.name.cmp(&b_slot.name)))
+        .map(|(saved, slot)| (slot, saved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DATED_FORMAT;
+    use std::path::PathBuf;
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        Local
+            .ymd_opt(y, m, d)
+            .and_hms_opt(h, min, 0)
+            .earliest()
+            .expect("valid local time")
+    }
+
+    fn dated(name: &str) -> Slot {
+        Slot {
+            name: format!("dated-{}", name),
+            path: PathBuf::from(name),
+            modified: SystemTime::UNIX_EPOCH,
+            files: 1,
+            size: 1,
+            note: None,
+            tags: Vec::new(),
+            title: None,
+            pinned: false,
+            save_numbers: vec![0],
+            suspicious: Vec::new(),
+            dated: true,
+            game_version: None,
+            headers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parse_times() {
+        let now = local(2024, 3, 2, 12, 30);
+
+        assert_eq!(
+            parse("2024-03-01 21:00", now),
+            Some(local(2024, 3, 1, 21, 0))
+        );
+        assert_eq!(
+            parse("2024-03-01T21:00:00", now),
+            Some(local(2024, 3, 1, 21, 0))
+        );
+        assert_eq!(
+            parse("2024-03-01", now),
+            Some(local(2024, 3, 1, 23, 59) + Duration::seconds(59))
+        );
+        assert_eq!(parse("21:00", now), Some(local(2024, 3, 2, 21, 0)));
+        assert_eq!(parse("Yesterday 8:15", now), Some(local(2024, 3, 1, 8, 15)));
+        assert_eq!(parse("today", now), parse("2024-03-02", now));
+        assert_eq!(parse("now", now), Some(now));
+        assert_eq!(parse("2h", now), Some(local(2024, 3, 2, 10, 30)));
+        assert_eq!(parse("90m", now), Some(local(2024, 3, 2, 11, 0)));
+        assert_eq!(parse("1w", now), Some(local(2024, 2, 24, 12, 30)));
+
+        for invalid in &[
+            "",
+            "h",
+            "2y",
+            "2024-13-01",
+            "21:00 2024-03-01",
+            "today 21:00 x",
+        ] {
+            assert_eq!(parse(invalid, now), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn select_latest_slot_before() {
+        let slots = || {
+            vec![
+                dated("2024-03-01_180000"),
+                dated("2024-03-01_210000"),
+                dated("2024-02-28_090000"),
+                dated("2024-03-01_230000"),
+            ]
+        };
+
+        let at = |h| local(2024, 3, 1, h, 0).into();
+        let name = |time| select(slots(), time, DATED_FORMAT).map(|(slot, _)| slot.name);
+
+        assert_eq!(name(at(21)).as_deref(), Some("dated-2024-03-01_210000"));
+        assert_eq!(name(at(22)).as_deref(), Some("dated-2024-03-01_210000"));
+        assert_eq!(name(at(12)).as_deref(), Some("dated-2024-02-28_090000"));
+        assert_eq!(
+            select(slots(), at(22), DATED_FORMAT).map(|(_, saved)| saved),
+            Some(at(21))
+        );
+
+        // Nothing is picked if every slot is newer.
+        assert_eq!(name(local(2024, 2, 1, 0, 0).into()), None);
+        assert!(select(Vec::new(), at(21), DATED_FORMAT).is_none());
+    }
+}
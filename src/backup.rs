@@ -0,0 +1,155 @@
+use crate::{
+    files::{self, list_save_files, to_extended_path},
+    manifest, Error, SaveFilePatterns, DATED_FORMAT,
+};
+use std::{
+    cmp::Reverse,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// The number of backups kept by [crate::SaveManager::backup_to] unless told otherwise.
+pub const DEFAULT_KEEP_BACKUPS: usize = 10;
+
+/// Save files which were backed up out of the profile with [crate::SaveManager::backup_to].
+#[derive(Debug, Clone)]
+pub struct BackupEntry {
+    /// The name of the backup, which is when it was made like `2019-10-30_180412`.
+    pub name: String,
+    /// The directory of the backup.
+    pub path: PathBuf,
+    /// When the save files were backed up.
+    pub created: SystemTime,
+    /// The number of save files in the backup.
+    pub files: usize,
+}
+
+/// List the backups in the given directory, with the most recent first.
+pub(crate) fn entries(dir: &Path, patterns: &SaveFilePatterns) -> Result<Vec<BackupEntry>, Error> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut dated = Vec::new();
+
+    for (name, path) in files::find_matching(dir, |p| p.is_dir(), |_| true)? {
+        // Anything which isn't named like a backup was put there by someone else, and must
+        // never be rotated away.
+        if let Some(key) = name.to_str().and_then(parse_name) {
+            dated.push((key, name, path));
+        }
+    }
+
+    dated.sort_by_key(|(key, ..)| Reverse(*key));
+
+    let mut out = Vec::new();
+
+    for (_, name, path) in dated {
+        out.push(read_entry(files::display_name(&name), path, patterns)?);
+    }
+
+    Ok(out)
+}
+
+/// The directory of a new backup made at the given time in the given directory, which doesn't
+/// exist yet. A suffix like `-2` is added if another backup was made in the same second.
+pub(crate) fn next_entry(dir: &Path, time: chrono::DateTime<chrono::Local>) -> PathBuf {
+    let base = time.format(DATED_FORMAT).to_string();
+    let mut path = dir.join(&base);
+    let mut suffix = 1;
+
+    while path.exists() {
+        suffix += 1;
+        path = dir.join(format!("{}-{}", base, suffix));
+    }
+
+    path
+}
+
+/// Parse the name of a backup into something which sorts in the order they were made.
+fn parse_name(name: &str) -> Option<(chrono::NaiveDateTime, u32)> {
+    let (time, suffix) = match name.rsplit_once('-') {
+        Some((time, suffix))
+            if !suffix.is_empty() && suffix.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            (time, suffix.parse().ok()?)
+        }
+        _ => (name, 1),
+    };
+
+    let time = chrono::NaiveDateTime::parse_from_str(time, DATED_FORMAT).ok()?;
+    Some((time, suffix))
+}
+
+fn read_entry(
+    name: String,
+    path: PathBuf,
+    patterns: &SaveFilePatterns,
+) -> Result<BackupEntry, Error> {
+    let files = list_save_files(&path, patterns)?.len();
+
+    // The manifest records when the backup was made, which the directory might not if it was
+    // copied around.
+    let created = manifest::read(&path)
+        .ok()
+        .flatten()
+        .and_then(|m| chrono::DateTime::parse_from_rfc3339(&m.created).ok());
+
+    let created = match created {
+        Some(created) => created.into(),
+        None => fs::metadata(to_extended_path(&path))
+            .and_then(|m| m.modified())
+            .map_err(|e| Error::io(&path, e))?,
+    };
+
+    Ok(BackupEntry {
+        name,
+        path,
+        created,
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone as _;
+
+    #[test]
+    fn list_backups_newest_first() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let time = chrono::Local
+            .ymd_opt(2019, 10, 30)
+            .and_hms_opt(18, 4, 12)
+            .earliest()
+            .expect("valid time");
+
+        let first = next_entry(dir.path(), time);
+        assert!(first.ends_with("2019-10-30_180412"));
+        fs::create_dir(&first)?;
+
+        let second = next_entry(dir.path(), time);
+        assert!(second.ends_with("2019-10-30_180412-2"));
+        fs::create_dir(&second)?;
+
+        fs::create_dir(dir.path().join("2019-10-29_235959"))?;
+        fs::create_dir(dir.path().join("not a backup"))?;
+        fs::write(second.join("SGTA00000"), b"save")?;
+
+        let names = entries(dir.path(), &SaveFilePatterns::default())?
+            .into_iter()
+            .map(|e| (e.name, e.files))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            vec![
+                (String::from("2019-10-30_180412-2"), 1),
+                (String::from("2019-10-30_180412"), 0),
+                (String::from("2019-10-29_235959"), 0),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -1,241 +1,5809 @@
 #![windows_subsystem = "windows"]
 
-use clap::{App, Arg};
-use failure::Error;
+use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, SubCommand};
+use gtav_helpers::{
+    debug,
+    doctor::{self, Status},
+    eout, error,
+    game::{self, SessionEnd},
+    hotkey::{self, Hotkey, HotkeyAction},
+    out,
+    output::{self, Captured, Level, ProgressStyle, Table},
+    parse_time, pipe, report,
+    serve::{self, Request, Response},
+    tray::{self, TrayCommand},
+    validate_tag, Bundle, CheckStatus, Config, Difference, Edition, Error, FileOperation, GameDef,
+    GameDir, Keep, Remote, RemoteConfig, SaveFilePatterns, SaveManager, Slot, WatchOptions,
+    PROFILE, SLOT_BUNDLE_EXTENSION,
+};
+use serde::{Deserialize, Serialize};
 use std::{
-    env, fs,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    env,
+    ffi::OsStr,
+    fmt, fs,
+    io::{self, IsTerminal as _, Write as _},
+    iter,
     path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, SystemTime},
 };
 
-fn list_save_files(path: &Path) -> Result<Vec<(String, PathBuf)>, Error> {
-    return find_matching(path, |p| p.is_file(), |n| n.starts_with("SGTA"));
+/// Help text describing the exit codes, which scripts are free to rely on.
+const EXIT_CODES: &str = "EXIT CODES:
+    0    Success.
+    1    Other errors, like invalid arguments, `verify`,
+         `diff-slots --identical-ok` or `fsck` found differences,
+         or `repair` couldn't repair a slot.
+    2    No profiles were found.
+    3    The slot or backup was not found or is empty, or there is nothing to undo.
+    4    A file could not be read or written, a download or request to the
+         remote failed, or there isn't enough space.
+    5    The slot index is out of range.
+    6    Another instance is already running.
+    7    `doctor` found possible problems.
+    8    `doctor` found problems which prevent saving or loading.
+    9    The command was cancelled with Ctrl+C or by closing the console.
+    10   The name given to `load-save-file` matches several directories
+         and none was picked.";
+
+/// The actions the picker offers, with the subcommand each runs and if it's run on a slot.
+const PICKER_ACTIONS: &[(&str, &str, bool)] = &[
+    ("Load a slot", "load", true),
+    ("Save a dated slot", "save-dated", false),
+    ("Show a slot", "show-slot", true),
+    ("Verify a slot", "verify", true),
+    ("Delete a slot", "delete-slot", true),
+    ("Undo the last command", "undo", false),
+];
+
+/// How many slots the picker shows at once, scrolling through the rest.
+const PICKER_SLOTS: usize = 15;
+
+/// How long to wait for another instance to finish before giving up.
+const LOCK_WAIT: Duration = Duration::from_secs(5);
+
+/// The tooltip of the tray icon, also used as the title of its notifications.
+const TRAY_TOOLTIP: &str = "GTA V save slots";
+
+/// The note and tags to attach to a newly saved slot.
+#[derive(Default)]
+struct Annotations<'a> {
+    note: Option<&'a str>,
+    tags: Vec<&'a str>,
+}
+
+impl<'a> Annotations<'a> {
+    /// Parse the annotations, checking the tags up front so that nothing is saved if they're
+    /// invalid.
+    fn from_matches(matches: &'a ArgMatches<'a>) -> Result<Self, Error> {
+        let mut tags = Vec::new();
+
+        for tag in matches.values_of("tag").into_iter().flatten() {
+            tags.push(validate_tag(tag)?);
+        }
+
+        Ok(Self {
+            note: matches.value_of("note"),
+            tags,
+        })
+    }
+
+    /// Attach the note and tags to the given slot, if there are any.
+    fn apply(&self, manager: &SaveManager, slot: &str) -> Result<(), Error> {
+        if self.note.is_none() && self.tags.is_empty() {
+            return Ok(());
+        }
+
+        manager.annotate(slot, self.note, &self.tags)
+    }
+}
+
+/// Which slots to list, and how.
+struct Filter<'a> {
+    /// Only list slots with this tag.
+    tag: Option<&'a str>,
+    /// Only list slots whose name, note or in-game titles contain this, ignoring case.
+    search: Option<String>,
+    /// List the in-game title and save time recorded for every save file in each slot.
+    detail: bool,
+}
+
+impl Filter<'_> {
+    fn matches(&self, slot: &Slot) -> bool {
+        if let Some(tag) = self.tag {
+            if !slot.tags.iter().any(|t| t == tag) {
+                return false;
+            }
+        }
+
+        if let Some(search) = &self.search {
+            let titles = slot
+                .headers
+                .iter()
+                .flat_map(|(_, h)| h.title.as_deref())
+                .chain(slot.title.as_deref());
+
+            let mut fields = iter::once(slot.name.as_str())
+                .chain(slot.note.as_deref())
+                .chain(titles);
+
+            if !fields.any(|f| f.to_lowercase().contains(search.as_str())) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Where the effective value of a setting came from, as printed by `--print-config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Origin {
+    CommandLine,
+    Environment,
+    ConfigFile,
+    Default,
+}
+
+impl Origin {
+    /// A stable name for the origin, used in `--format json` output.
+    fn name(self) -> &'static str {
+        match self {
+            Origin::CommandLine => "command_line",
+            Origin::Environment => "environment",
+            Origin::ConfigFile => "config_file",
+            Origin::Default => "default",
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::CommandLine => "command line".fmt(f),
+            Origin::Environment => "GTAV_SAVE_DIR".fmt(f),
+            Origin::ConfigFile => "config file".fmt(f),
+            Origin::Default => "default".fmt(f),
+        }
+    }
+}
+
+/// The effective value of a setting, and where it came from.
+#[derive(Debug)]
+struct Setting<T> {
+    value: T,
+    origin: Origin,
+}
+
+impl<T> Setting<T> {
+    /// Pick the value given on the command line, then the one in the config file, falling back
+    /// to the default.
+    fn resolve(flag: Option<T>, config: Option<T>, default: T) -> Self {
+        match (flag, config) {
+            (Some(value), _) => Self::new(value, Origin::CommandLine),
+            (None, Some(value)) => Self::new(value, Origin::ConfigFile),
+            (None, None) => Self::new(default, Origin::Default),
+        }
+    }
+
+    fn new(value: T, origin: Origin) -> Self {
+        Self { value, origin }
+    }
+}
+
+/// The settings which can be given both in the config file and on the command line, merged.
+#[derive(Debug)]
+struct Settings {
+    /// The config file which was read, if there is one.
+    config_path: Option<PathBuf>,
+    base_path: Setting<Option<PathBuf>>,
+    profile: Setting<Option<String>>,
+    backup_before_load: Setting<bool>,
+    verify_checksums: Setting<bool>,
+    keep_dated: Setting<Option<usize>>,
+    keep_backups: Setting<usize>,
+    date_format: Setting<String>,
+    dated_name: Setting<String>,
+    slots_dir: Setting<Option<PathBuf>>,
+    shared_slots: Setting<bool>,
+    compress_dated: Setting<bool>,
+    compression_level: Setting<i32>,
+    include_settings: Setting<bool>,
+    min_save_size: Setting<u64>,
+    hydrate_timeout: Setting<u64>,
+    include: Setting<Vec<String>>,
+    exclude: Setting<Vec<String>>,
+    hotkeys: Setting<BTreeMap<String, String>>,
+    notify: Setting<bool>,
+    edition: Setting<Option<String>>,
+    game_path: Setting<Option<PathBuf>>,
+    game: Setting<GameDef>,
+    remote: Setting<Option<RemoteConfig>>,
+}
+
+impl Settings {
+    /// Read the config file given with `--config`, or the default one if it exists.
+    fn load(matches: &ArgMatches<'_>) -> Result<Self, Error> {
+        let (config_path, required) = match matches.value_of_os("config") {
+            Some(path) => (Some(PathBuf::from(path)), true),
+            None => (gtav_helpers::default_config_path(), false),
+        };
+
+        let config = match &config_path {
+            Some(path) => Config::load(path, required)?,
+            None => Config::default(),
+        };
+
+        Self::new(matches, config, config_path)
+    }
+
+    /// Merge the command line flags with the config file, where the flags take precedence.
+    ///
+    /// Errors if the game which is picked isn't defined.
+    fn new(
+        matches: &ArgMatches<'_>,
+        config: Config,
+        config_path: Option<PathBuf>,
+    ) -> Result<Self, Error> {
+        // Clap doesn't count values taken from the environment as occurrences.
+        let base_path = match matches.value_of_os("base-path") {
+            Some(base) if matches.occurrences_of("base-path") > 0 => {
+                Setting::new(Some(PathBuf::from(base)), Origin::CommandLine)
+            }
+            Some(base) => Setting::new(Some(PathBuf::from(base)), Origin::Environment),
+            None => Setting::resolve(None, config.base_path.map(Some), None),
+        };
+
+        let profile = if matches.is_present("all-profiles") {
+            Setting::new(None, Origin::CommandLine)
+        } else {
+            let flag = matches.value_of("profile").map(|p| Some(p.to_string()));
+            Setting::resolve(flag, config.profile.map(Some), None)
+        };
+
+        let game = Setting::resolve(
+            matches.value_of("game").map(str::to_string),
+            config.game,
+            String::from(gtav_helpers::DEFAULT_GAME),
+        );
+
+        let games = config.games.unwrap_or_default();
+        let game = Setting::new(GameDef::find(&game.value, &games)?, game.origin);
+
+        let patterns = |name| {
+            matches
+                .values_of(name)
+                .map(|values| values.map(str::to_string).collect::<Vec<_>>())
+        };
+
+        let toggle = |on, off| {
+            if matches.is_present(on) {
+                Some(true)
+            } else if matches.is_present(off) {
+                Some(false)
+            } else {
+                None
+            }
+        };
+
+        Ok(Self {
+            config_path,
+            base_path,
+            profile,
+            backup_before_load: Setting::resolve(
+                toggle("backup", "no-backup"),
+                config.backup_before_load,
+                true,
+            ),
+            verify_checksums: Setting::resolve(
+                toggle("verify", "no-verify"),
+                config.verify_checksums,
+                true,
+            ),
+            keep_dated: Setting::resolve(None, config.keep_dated.map(Some), None),
+            keep_backups: Setting::resolve(
+                None,
+                config.keep_backups,
+                gtav_helpers::DEFAULT_KEEP_BACKUPS,
+            ),
+            date_format: Setting::resolve(
+                matches.value_of("date-format").map(str::to_string),
+                config.date_format,
+                String::from(gtav_helpers::DATED_FORMAT),
+            ),
+            dated_name: Setting::resolve(
+                matches.value_of("dated-name").map(str::to_string),
+                config.dated_name,
+                String::from(gtav_helpers::DATED_NAME),
+            ),
+            slots_dir: Setting::resolve(
+                matches
+                    .value_of_os("slots-dir")
+                    .map(|d| Some(PathBuf::from(d))),
+                config.slots_dir.map(Some),
+                None,
+            ),
+            shared_slots: Setting::resolve(
+                matches.is_present("shared-slots").then_some(true),
+                config.shared_slots,
+                false,
+            ),
+            compress_dated: Setting::resolve(
+                toggle("compress", "no-compress"),
+                config.compress_dated,
+                false,
+            ),
+            compression_level: Setting::resolve(
+                matches
+                    .value_of("compression-level")
+                    .and_then(|l| l.parse().ok()),
+                config.compression_level,
+                gtav_helpers::DEFAULT_COMPRESSION_LEVEL,
+            ),
+            include_settings: Setting::resolve(
+                matches.is_present("include-settings").then_some(true),
+                config.include_settings,
+                false,
+            ),
+            min_save_size: Setting::resolve(
+                matches
+                    .value_of("min-save-size")
+                    .and_then(|s| s.parse().ok()),
+                config.min_save_size,
+                gtav_helpers::DEFAULT_MIN_SAVE_SIZE,
+            ),
+            hydrate_timeout: Setting::resolve(
+                matches
+                    .value_of("hydrate-timeout")
+                    .and_then(|s| s.parse().ok()),
+                config.hydrate_timeout,
+                gtav_helpers::DEFAULT_HYDRATE_TIMEOUT.as_secs(),
+            ),
+            include: Setting::resolve(
+                patterns("include"),
+                config.include,
+                vec![game.value.include()],
+            ),
+            exclude: Setting::resolve(patterns("exclude"), config.exclude, Vec::new()),
+            hotkeys: Setting::resolve(
+                None,
+                config.hotkeys,
+                hotkey::DEFAULT_HOTKEYS
+                    .iter()
+                    .map(|(a, h)| (a.to_string(), h.to_string()))
+                    .collect(),
+            ),
+            notify: Setting::resolve(
+                matches.is_present("no-notify").then_some(false),
+                config.notify,
+                true,
+            ),
+            edition: Setting::resolve(
+                matches.value_of("edition").map(|e| Some(e.to_string())),
+                config.edition.map(Some),
+                None,
+            ),
+            game_path: Setting::resolve(
+                matches
+                    .value_of_os("game-path")
+                    .map(|p| Some(PathBuf::from(p))),
+                config.game_path.map(Some),
+                None,
+            ),
+            game,
+            remote: Setting::resolve(None, config.remote.map(Some), None),
+        })
+    }
+}
+
+/// Print the effective settings and where each of them came from.
+fn print_config(settings: &Settings) {
+    use serde_json::{json, Value};
+
+    let rows = [
+        (
+            "base_path",
+            json!(settings.base_path.value),
+            settings.base_path.origin,
+        ),
+        (
+            "profile",
+            json!(settings.profile.value),
+            settings.profile.origin,
+        ),
+        (
+            "backup_before_load",
+            json!(settings.backup_before_load.value),
+            settings.backup_before_load.origin,
+        ),
+        (
+            "verify_checksums",
+            json!(settings.verify_checksums.value),
+            settings.verify_checksums.origin,
+        ),
+        (
+            "keep_dated",
+            json!(settings.keep_dated.value),
+            settings.keep_dated.origin,
+        ),
+        (
+            "keep_backups",
+            json!(settings.keep_backups.value),
+            settings.keep_backups.origin,
+        ),
+        (
+            "date_format",
+            json!(settings.date_format.value),
+            settings.date_format.origin,
+        ),
+        (
+            "dated_name",
+            json!(settings.dated_name.value),
+            settings.dated_name.origin,
+        ),
+        (
+            "slots_dir",
+            json!(settings.slots_dir.value),
+            settings.slots_dir.origin,
+        ),
+        (
+            "shared_slots",
+            json!(settings.shared_slots.value),
+            settings.shared_slots.origin,
+        ),
+        (
+            "compress_dated",
+            json!(settings.compress_dated.value),
+            settings.compress_dated.origin,
+        ),
+        (
+            "compression_level",
+            json!(settings.compression_level.value),
+            settings.compression_level.origin,
+        ),
+        (
+            "include_settings",
+            json!(settings.include_settings.value),
+            settings.include_settings.origin,
+        ),
+        (
+            "min_save_size",
+            json!(settings.min_save_size.value),
+            settings.min_save_size.origin,
+        ),
+        (
+            "hydrate_timeout",
+            json!(settings.hydrate_timeout.value),
+            settings.hydrate_timeout.origin,
+        ),
+        (
+            "include",
+            json!(settings.include.value),
+            settings.include.origin,
+        ),
+        (
+            "exclude",
+            json!(settings.exclude.value),
+            settings.exclude.origin,
+        ),
+        (
+            "hotkeys",
+            json!(settings.hotkeys.value),
+            settings.hotkeys.origin,
+        ),
+        (
+            "notify",
+            json!(settings.notify.value),
+            settings.notify.origin,
+        ),
+        (
+            "edition",
+            json!(settings.edition.value),
+            settings.edition.origin,
+        ),
+        (
+            "game_path",
+            json!(settings.game_path.value),
+            settings.game_path.origin,
+        ),
+        (
+            "game",
+            json!(settings.game.value.name),
+            settings.game.origin,
+        ),
+        // The credentials of the remote aren't printed.
+        (
+            "remote",
+            json!(settings.remote.value.as_ref().and_then(|r| r.url.as_ref())),
+            settings.remote.origin,
+        ),
+    ];
+
+    if output::capturing() {
+        let values = rows
+            .iter()
+            .map(|(key, value, origin)| {
+                let value = json!({ "value": value, "origin": origin.name() });
+                (key.to_string(), value)
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        output::result(json!({
+            "config_file": settings.config_path,
+            "settings": values,
+        }));
+
+        return;
+    }
+
+    match &settings.config_path {
+        Some(path) if path.is_file() => report!("config file: {}", path.display()),
+        Some(path) => report!("config file: {} (not found)", path.display()),
+        None => report!("config file: none"),
+    }
+
+    for (key, value, origin) in &rows {
+        match value {
+            Value::Null => report!("{} is not set", key),
+            Value::String(value) => report!("{} = {} ({})", key, value, origin),
+            value => report!("{} = {} ({})", key, value, origin),
+        }
+    }
+}
+
+/// The number of entries shown by `history` unless told otherwise.
+const DEFAULT_HISTORY: usize = 10;
+
+/// The hidden subcommand which completion scripts run to list slot names.
+const COMPLETE_SLOTS: &str = "__complete-slots";
+
+/// The arguments which name an existing slot, as the subcommand followed by the position of the
+/// argument after it, like `diff-slots:2`.
+const SLOT_ARGUMENTS: &[&str] = &[
+    "save:1",
+    "load:1",
+    "swap:1",
+    "verify:1",
+    "diff-slots:1",
+    "diff-slots:2",
+    "show-slot:1",
+    "pin:1",
+    "unpin:1",
+    "set-note:1",
+    "delete-slot:1",
+    "rename-slot:1",
+    "copy-slot:1",
+    "export-slot:1",
+    "bundle:1",
+    "push:1",
+    "repair:1",
+    "alias set:2",
+];
+
+/// Subcommands which have subcommands of their own.
+const PARENT_COMMANDS: &[&str] = &["alias"];
+
+/// Options which decide where the slots are, and are passed on to [COMPLETE_SLOTS] so that it
+/// lists the slots of the same profile as the command being completed.
+const FORWARDED_OPTIONS: &[&str] = &[
+    "--config",
+    "--base-path",
+    "--edition",
+    "--game",
+    "--profile",
+    "--slots-dir",
+];
+
+/// Options which take a value, which isn't counted as an argument when completing.
+const VALUE_OPTIONS: &[&str] = &[
+    "--format",
+    "--log-level",
+    "--map-profile",
+    "--compression-level",
+    "--date-format",
+    "--dated-name",
+    "--note",
+    "--tag",
+    "--pick",
+    "--load",
+    "--filter-tag",
+    "--search",
+    "--debounce",
+    "--watch-interval",
+    "--keep",
+    "--keep-days",
+    "--policy",
+    "--passphrase-file",
+    "--only",
+    "--min-save-size",
+    "--hydrate-timeout",
+    "--include",
+    "--exclude",
+    "--token",
+    "--as-slot",
+    "--edition",
+    "--game-path",
+    "--game-version",
+    "--game",
+    // The deprecated flags.
+    "--save",
+    "--load-save-file",
+    "--load-nth-newest-slot",
+    "--delete-nth-newest-slot",
+];
+
+/// Completes slot names for [SLOT_ARGUMENTS] and leaves everything else to the generated
+/// completions, which are registered under another name.
+const BASH_SLOTS: &str = r#"
+_gtav-saveload_slots() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" cmd="" n=0 i word
+    local -a forward=()
+
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        word="${COMP_WORDS[i]}"
+
+        case "${word}" in
+            @FORWARDED@)
+                forward+=("${word}" "${COMP_WORDS[i + 1]}")
+                i=$((i + 1)) ;;
+            --shared-slots)
+                forward+=("${word}") ;;
+            @VALUES@)
+                i=$((i + 1)) ;;
+            -*) ;;
+            *)
+                case "${cmd}" in
+                    "") cmd="${word}" ;;
+                    @PARENTS@) cmd="${cmd} ${word}" ;;
+                    *) n=$((n + 1)) ;;
+                esac ;;
+        esac
+    done
+
+    if [[ ${cur} != -* ]]; then
+        case "${cmd}:$((n + 1))" in
+            @SLOTS@)
+                local IFS=$'\n'
+                COMPREPLY=($(compgen -W "$("${COMP_WORDS[0]}" "${forward[@]}" @COMPLETE@ 2>/dev/null)" -- "${cur}"))
+                return 0 ;;
+        esac
+    fi
+
+    _gtav-saveload "$@"
+}
+
+complete -F _gtav-saveload_slots -o bashdefault -o default gtav-saveload
+"#;
+
+/// Like [BASH_SLOTS], but for zsh.
+const ZSH_SLOTS: &str = r#"
+_gtav-saveload_slots() {
+    local cmd="" n=0 i word
+    local -a forward slots
+
+    for ((i = 2; i < CURRENT; i++)); do
+        word="${words[i]}"
+
+        case "${word}" in
+            @FORWARDED@)
+                forward+=("${word}" "${words[i + 1]}")
+                i=$((i + 1)) ;;
+            --shared-slots)
+                forward+=("${word}") ;;
+            @VALUES@)
+                i=$((i + 1)) ;;
+            -*) ;;
+            *)
+                case "${cmd}" in
+                    "") cmd="${word}" ;;
+                    @PARENTS@) cmd="${cmd} ${word}" ;;
+                    *) n=$((n + 1)) ;;
+                esac ;;
+        esac
+    done
+
+    if [[ ${words[CURRENT]} != -* ]]; then
+        case "${cmd}:$((n + 1))" in
+            @SLOTS@)
+                slots=(${(f)"$(${words[1]} "${forward[@]}" @COMPLETE@ 2>/dev/null)"})
+                compadd -a slots
+                return ;;
+        esac
+    fi
+
+    _gtav-saveload "$@"
+}
+
+_gtav-saveload_slots "$@"
+"#;
+
+/// Like [BASH_SLOTS], but for PowerShell, where it runs before the generated completions in the
+/// same script block.
+const POWERSHELL_SLOTS: &str = r#"
+    $words = @($commandElements | ForEach-Object { $_.Extent.Text })
+
+    # Once something has been typed, the word being completed is the last element.
+    if ($wordToComplete -ne '') {
+        $words = @($words | Select-Object -SkipLast 1)
+    }
+
+    $cmd = ''
+    $n = 0
+    $forward = @()
+
+    for ($i = 1; $i -lt $words.Count; $i++) {
+        $word = $words[$i]
+
+        if (@(@FORWARDED@) -contains $word) {
+            $forward += $word, $words[$i + 1]
+            $i++
+        } elseif ($word -eq '--shared-slots') {
+            $forward += $word
+        } elseif (@(@VALUES@) -contains $word) {
+            $i++
+        } elseif ($word.StartsWith('-')) {
+        } elseif ($cmd -eq '') {
+            $cmd = $word
+        } elseif (@(@PARENTS@) -contains $cmd) {
+            $cmd = "$cmd $word"
+        } else {
+            $n++
+        }
+    }
+
+    if (-not $wordToComplete.StartsWith('-') -and @(@SLOTS@) -contains "${cmd}:$($n + 1)") {
+        & $words[0] @forward @COMPLETE@ 2>$null |
+            Where-Object { $_ -like "$wordToComplete*" } |
+            ForEach-Object {
+                $text = if ($_ -match '\s') { "'$_'" } else { $_ }
+                [CompletionResult]::new($text, $_, [CompletionResultType]::ParameterValue, $_)
+            }
+        return
+    }
+
+"#;
+
+/// The GTA V directory given with `--base-path`, `GTAV_SAVE_DIR` or the config file, along
+/// with where it was given.
+fn given_base(settings: &Settings) -> Option<(&Path, &'static str)> {
+    let base = settings.base_path.value.as_deref()?;
+
+    let method = match settings.base_path.origin {
+        Origin::CommandLine => "--base-path",
+        Origin::Environment => "GTAV_SAVE_DIR",
+        _ => "base_path in the config file",
+    };
+
+    Some((base, method))
+}
+
+/// The edition chosen with `--edition` or in the config file, where `Some(None)` is every
+/// edition and `None` is the first one found.
+fn edition_choice(settings: &Settings) -> Result<Option<Option<Edition>>, Error> {
+    settings
+        .edition
+        .value
+        .as_deref()
+        .map(gtav_helpers::parse_edition_choice)
+        .transpose()
+}
+
+/// The GTA V directories to use the profiles of, which is the one given with `--base-path`,
+/// or the ones of the editions chosen with `--edition`.
+///
+/// Unless an edition is chosen, the first one which is found is used, and the others are
+/// mentioned so that there's no doubt which one is used.
+fn game_dirs(settings: &Settings) -> Result<Vec<GameDir>, Error> {
+    let choice = edition_choice(settings)?;
+
+    if let Some((base, method)) = given_base(settings) {
+        if !base.join("Profiles").is_dir() {
+            return Err(Error::NoProfiles {
+                searched: vec![base.to_owned()],
+            });
+        }
+
+        return Ok(vec![GameDir {
+            edition: choice.flatten().unwrap_or_else(|| Edition::of(base)),
+            path: base.to_owned(),
+            method,
+        }]);
+    }
+
+    let mut dirs = gtav_helpers::find_game_dirs(&settings.game.value, choice.flatten())?;
+
+    if choice.is_none() {
+        for other in dirs.split_off(1) {
+            out!(
+                "using the {} edition in {}, use --edition {} for the one in {}",
+                dirs[0].edition,
+                dirs[0].path.display(),
+                other.edition,
+                other.path.display()
+            );
+        }
+    }
+
+    Ok(dirs)
+}
+
+/// List the profiles in every one of the given GTA V directories.
+///
+/// Errors if there are no profiles in any of them.
+fn list_game_profiles(dirs: &[GameDir]) -> Result<Vec<PathBuf>, Error> {
+    let mut profiles = Vec::new();
+    let mut first_error = None;
+
+    for dir in dirs {
+        match gtav_helpers::list_profiles(&dir.path) {
+            Ok(found) => profiles.extend(found),
+            Err(e) => {
+                debug!("{}", e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match first_error {
+        Some(e) if profiles.is_empty() => Err(e),
+        _ => Ok(profiles),
+    }
+}
+
+/// The edition of the given profile, which is in one of the given GTA V directories.
+fn edition_of(dirs: &[GameDir], profile: &Path) -> Edition {
+    dirs.iter()
+        .find(|dir| profile.starts_with(&dir.path))
+        .map_or(Edition::Legacy, |dir| dir.edition)
+}
+
+/// Check the environment and print the results, erroring with the worst of them.
+///
+/// This doesn't need the GTA V directory to be found, since explaining why it isn't is part of
+/// what it checks.
+fn doctor(settings: &Settings) -> Result<(), Error> {
+    let patterns = SaveFilePatterns::new(&settings.include.value, &settings.exclude.value)?;
+
+    let mut checks = Vec::new();
+    let edition = edition_choice(settings)?.flatten();
+    let (check, dirs) = doctor::check_base(&settings.game.value, given_base(settings), edition);
+    checks.push(check);
+
+    for dir in dirs {
+        let (check, profiles) = doctor::check_profiles(&dir.path);
+        checks.push(check);
+
+        for profile in profiles {
+            let manager = SaveManager::new(profile)
+                .game(&settings.game.value)
+                .edition(dir.edition)
+                .shared_slots(settings.shared_slots.value)
+                .save_file_patterns(patterns.clone());
+
+            let manager = match &settings.slots_dir.value {
+                Some(dir) => manager.slots_dir(dir),
+                None => manager,
+            };
+
+            checks.extend(manager.doctor());
+        }
+    }
+
+    // Where the game is installed is only looked up for GTA V.
+    if settings.game.value.is_default() {
+        checks.push(doctor::check_install());
+    }
+
+    checks.push(doctor::check_game());
+
+    for check in &checks {
+        if output::capturing() {
+            output::result(serde_json::json!({
+                "name": check.name,
+                "status": check.status.name(),
+                "message": check.message,
+            }));
+        } else {
+            report!("[{}] {}: {}", check.status, check.name, check.message);
+        }
+    }
+
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+
+    match checks.iter().map(|c| c.status).max() {
+        Some(Status::Fail) => Err(Error::ChecksFailed {
+            failed: count(Status::Fail),
+        }),
+        Some(Status::Warn) => Err(Error::ChecksWarned {
+            warned: count(Status::Warn),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Print the completion script for the given shell, which is one of the possible values of
+/// `completions`.
+fn print_completions(shell: &str) {
+    let script = completions(shell);
+    let _ = io::stdout().write_all(script.as_bytes());
+}
+
+/// Generate the completion script for the given shell, where the completions generated by clap
+/// are extended to complete slot names by running [COMPLETE_SLOTS].
+fn completions(shell: &str) -> String {
+    let (generated, slots) = match shell {
+        "zsh" => (clap::Shell::Zsh, ZSH_SLOTS),
+        "powershell" => (clap::Shell::PowerShell, POWERSHELL_SLOTS),
+        _ => (clap::Shell::Bash, BASH_SLOTS),
+    };
+
+    let mut script = Vec::new();
+    commands().gen_completions_to("gtav-saveload", generated, &mut script);
+    let script = String::from_utf8_lossy(&script).into_owned();
+
+    let (quote, separator) = match generated {
+        clap::Shell::PowerShell => ("'", ", "),
+        _ => ("\"", "|"),
+    };
+
+    let patterns = |words: &[&str]| {
+        words
+            .iter()
+            .map(|w| format!("{}{}{}", quote, w, quote))
+            .collect::<Vec<_>>()
+            .join(separator)
+    };
+
+    let slots = slots
+        .replace("@FORWARDED@", &patterns(FORWARDED_OPTIONS))
+        .replace("@VALUES@", &patterns(VALUE_OPTIONS))
+        .replace("@PARENTS@", &patterns(PARENT_COMMANDS))
+        .replace("@SLOTS@", &patterns(SLOT_ARGUMENTS))
+        .replace("@COMPLETE@", COMPLETE_SLOTS);
+
+    match generated {
+        clap::Shell::PowerShell => {
+            let start = "    $commandElements = $commandAst.CommandElements\n";
+            script.replacen(start, &format!("{}{}", start, slots), 1)
+        }
+        clap::Shell::Zsh => {
+            let end = script.trim_end().trim_end_matches("_gtav-saveload \"$@\"");
+            format!("{}{}", end, slots.trim_start())
+        }
+        _ => {
+            // The generated script matches subcommands with the program named `gtav__saveload`,
+            // but looks for them with it named `gtav-saveload`, so they'd never be completed.
+            let script = script.replace("gtav__saveload__", "gtav-saveload__");
+
+            let end = script.trim_end().trim_end_matches(
+                "complete -F _gtav-saveload -o bashdefault -o default gtav-saveload",
+            );
+            format!("{}{}", end, slots.trim_start())
+        }
+    }
+}
+
+/// Print the names of the slots in the selected profiles, one per line, for [COMPLETE_SLOTS].
+///
+/// Nothing is printed if the profiles can't be found, since errors would only get in the way
+/// of completing.
+fn print_slot_names(settings: &Settings) {
+    // Anything printed while selecting profiles, like the profiles when none of them match,
+    // would be taken for slot names.
+    output::capture();
+
+    let names = || -> Result<BTreeSet<String>, Error> {
+        let dirs = game_dirs(settings)?;
+
+        let profiles = select_profiles(
+            list_game_profiles(&dirs)?,
+            settings.profile.value.as_deref(),
+            false,
+            false,
+        )?;
+
+        let mut names = BTreeSet::new();
+
+        for profile in profiles {
+            let manager = SaveManager::new(&profile)
+                .game(&settings.game.value)
+                .edition(edition_of(&dirs, &profile))
+                .shared_slots(settings.shared_slots.value);
+
+            let manager = match &settings.slots_dir.value {
+                Some(dir) => manager.slots_dir(dir),
+                None => manager,
+            };
+
+            names.extend(manager.slot_names()?);
+        }
+
+        Ok(names)
+    };
+
+    let names = names().unwrap_or_default();
+    let _ = output::take_captured();
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    for name in names {
+        let _ = writeln!(stdout, "{}", name);
+    }
+}
+
+/// Set to stop watching or waiting for the game, when Ctrl+C is pressed or `stop-watch` is used.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Set to cancel what's being done at the next safe point, when Ctrl+C is pressed or the console
+/// is closed.
+static CANCEL: AtomicBool = AtomicBool::new(false);
+
+/// Set while watching or waiting for the game, so that Ctrl+C sets [STOP] instead of [CANCEL].
+static STOP_ON_CTRL_C: AtomicBool = AtomicBool::new(false);
+
+/// Set once the settings are read if a notification should say what was done, since there's no
+/// console to print it in.
+static NOTIFY: AtomicBool = AtomicBool::new(false);
+
+/// If an error is shown in a message box when there's no console and no notification is shown
+/// for it, which it isn't with both `--quiet` and `--no-notify`.
+static ERROR_BOX: AtomicBool = AtomicBool::new(true);
+
+/// The single action to perform for an invocation.
+enum Action<'a> {
+    Save(&'a str, Annotations<'a>),
+    Load(&'a str),
+    SaveQuick(&'a str),
+    LoadQuick(&'a str),
+    Swap(&'a str),
+    Verify(&'a str),
+    ShowSlot(&'a str, bool),
+    DiffSlots(&'a str, &'a str, bool),
+    LoadSaveFile(&'a str, Option<usize>),
+    ListSaveFiles,
+    SaveDated(Annotations<'a>),
+    SetNote(&'a str, &'a str),
+    Pin(&'a str, bool),
+    Clear,
+    Undo,
+    Stash(Option<&'a str>),
+    Pop,
+    StashList,
+    BackupTo(&'a OsStr, usize),
+    RestoreFrom(&'a OsStr, Option<&'a str>),
+    Watch(WatchOptions),
+    StopWatch,
+    Play(Option<&'a OsStr>, bool),
+    Hotkeys(Vec<(HotkeyAction, Hotkey)>),
+    Tray,
+    Serve(u16, Option<&'a str>),
+    Daemon,
+    List(Filter<'a>),
+    ListProfiles,
+    TransferProfile(&'a str, &'a str),
+    LoadNewest(usize),
+    LoadAt(SystemTime),
+    DeleteNewest(usize),
+    DeleteSlot(&'a str),
+    PruneDated(Keep),
+    RenameSlot(&'a str, &'a str),
+    CopySlot(&'a str, &'a str),
+    ExportSlot(&'a str, Option<&'a OsStr>),
+    ImportSlot(&'a OsStr, Option<&'a str>),
+    Bundle(&'a str, Option<&'a OsStr>),
+    Unbundle(&'a OsStr, Option<&'a str>),
+    LoadBundle(&'a OsStr),
+    LoadUrl(&'a str, Option<&'a str>),
+    Push(Remote, &'a str, Option<&'a str>),
+    Pull(Remote, &'a str, Option<&'a str>),
+    ExportAll(&'a OsStr),
+    ImportAll(&'a OsStr, Vec<(&'a str, &'a str)>, bool),
+    Sync(&'a OsStr),
+    ShowSpace,
+    Fsck(bool),
+    Repair(&'a str),
+    Dedup,
+    RecompressSlots,
+    SetAlias(&'a str, &'a str),
+    RemoveAlias(&'a str),
+    ListAliases,
+    History(usize),
+}
+
+impl<'a> Action<'a> {
+    /// Test if the action can remove or overwrite existing save files.
+    fn is_destructive(&self) -> bool {
+        !matches!(
+            self,
+            Action::List(..)
+                | Action::ListProfiles
+                | Action::ListSaveFiles
+                | Action::ExportAll(..)
+                | Action::ImportAll(..)
+                | Action::SaveDated(..)
+                | Action::SetNote(..)
+                | Action::Pin(..)
+                | Action::Watch(..)
+                | Action::StopWatch
+                | Action::Play(..)
+                | Action::Verify(..)
+                | Action::ShowSlot(..)
+                | Action::DiffSlots(..)
+                | Action::CopySlot(..)
+                | Action::ShowSpace
+                | Action::Fsck(..)
+                | Action::Repair(..)
+                | Action::Dedup
+                | Action::RecompressSlots
+                | Action::StashList
+                | Action::BackupTo(..)
+                | Action::SetAlias(..)
+                | Action::RemoveAlias(..)
+                | Action::ListAliases
+                | Action::History(..)
+        )
+    }
+
+    /// The command and the slot it names to record the action as in the journal, or `None` if
+    /// it only reads, or records what it does by itself.
+    fn journal(&self) -> Option<(&'static str, Option<&'a str>)> {
+        let entry = match *self {
+            Action::Save(slot, ..) => ("save", Some(slot)),
+            Action::Load(slot) => ("load", Some(slot)),
+            Action::SaveQuick(..) => ("save-quick", None),
+            Action::LoadQuick(..) => ("load-quick", None),
+            Action::Swap(slot) => ("swap", Some(slot)),
+            Action::LoadSaveFile(..) => ("load-save-file", None),
+            Action::SaveDated(..) => ("save-dated", None),
+            Action::SetNote(slot, ..) => ("set-note", Some(slot)),
+            Action::Pin(slot, true) => ("pin", Some(slot)),
+            Action::Pin(slot, false) => ("unpin", Some(slot)),
+            Action::Clear => ("clear", None),
+            Action::Undo => ("undo", None),
+            Action::Stash(load) => ("stash", load),
+            Action::Pop => ("pop", None),
+            Action::BackupTo(..) => ("backup-to", None),
+            Action::RestoreFrom(_, backup) => ("restore-from", backup),
+            Action::LoadNewest(..) => ("load-newest", None),
+            Action::LoadAt(..) => ("load-at", None),
+            Action::DeleteNewest(..) => ("delete-newest", None),
+            Action::DeleteSlot(slot) => ("delete-slot", Some(slot)),
+            Action::PruneDated(..) => ("prune-dated", None),
+            Action::RenameSlot(from, ..) => ("rename-slot", Some(from)),
+            Action::CopySlot(from, ..) => ("copy-slot", Some(from)),
+            Action::ExportSlot(slot, ..) => ("export-slot", Some(slot)),
+            Action::ImportSlot(_, slot) => ("import-slot", slot),
+            Action::Bundle(slot, ..) => ("bundle", Some(slot)),
+            Action::Unbundle(_, slot) => ("unbundle", slot),
+            Action::LoadBundle(..) => ("load-bundle", None),
+            Action::LoadUrl(_, None) => ("load-url", None),
+            Action::LoadUrl(_, slot) => ("import-url", slot),
+            Action::Push(_, slot, ..) => ("push", Some(slot)),
+            Action::Pull(_, name, slot) => ("pull", Some(slot.unwrap_or(name))),
+            Action::Sync(..) => ("sync", None),
+            Action::Fsck(true) => ("fsck", None),
+            Action::Repair(slot) => ("repair", Some(slot)),
+            Action::Dedup => ("dedup", None),
+            Action::RecompressSlots => ("recompress-slots", None),
+            Action::SetAlias(..) => ("alias set", None),
+            Action::RemoveAlias(..) => ("alias remove", None),
+            Action::TransferProfile(..) => ("transfer-profile", None),
+            Action::Verify(..)
+            | Action::ShowSlot(..)
+            | Action::DiffSlots(..)
+            | Action::StashList
+            | Action::Watch(..)
+            | Action::StopWatch
+            | Action::Play(..)
+            | Action::Hotkeys(..)
+            | Action::Tray
+            | Action::Serve(..)
+            | Action::Daemon
+            | Action::List(..)
+            | Action::ListProfiles
+            | Action::ListSaveFiles
+            | Action::ExportAll(..)
+            | Action::ImportAll(..)
+            | Action::ShowSpace
+            | Action::Fsck(false)
+            | Action::ListAliases
+            | Action::History(..) => return None,
+        };
+
+        Some(entry)
+    }
+
+    /// Test if the action replaces or removes the save files in the profile, or deletes slots,
+    /// which isn't safe while the game is running.
+    fn conflicts_with_game(&self) -> bool {
+        matches!(
+            self,
+            Action::Load(..)
+                | Action::LoadQuick(..)
+                | Action::Swap(..)
+                | Action::LoadSaveFile(..)
+                | Action::LoadNewest(..)
+                | Action::LoadAt(..)
+                | Action::LoadBundle(..)
+                | Action::LoadUrl(_, None)
+                | Action::Clear
+                | Action::Undo
+                | Action::Stash(..)
+                | Action::Pop
+                | Action::RestoreFrom(..)
+                | Action::TransferProfile(..)
+                | Action::DeleteSlot(..)
+                | Action::DeleteNewest(..)
+                | Action::PruneDated(..)
+        )
+    }
+
+    /// Parse the action from the given subcommand, or the deprecated flags if no subcommand
+    /// was used.
+    fn from_matches(
+        matches: &'a ArgMatches<'a>,
+        settings: &Settings,
+    ) -> Result<Option<Self>, Error> {
+        if matches.subcommand_name().is_some() && matches.is_present("legacy") {
+            clap::Error::with_description(
+                "deprecated flags cannot be combined with a subcommand",
+                clap::ErrorKind::ArgumentConflict,
+            )
+            .exit();
+        }
+
+        let action = match matches.subcommand() {
+            ("save", Some(m)) => Action::Save(
+                m.value_of("slot").unwrap_or_default(),
+                Annotations::from_matches(m)?,
+            ),
+            ("load", Some(m)) => Action::Load(m.value_of("slot").unwrap_or_default()),
+            ("save-quick", Some(m)) => Action::SaveQuick(m.value_of("alias").unwrap_or_default()),
+            ("load-quick", Some(m)) => Action::LoadQuick(m.value_of("alias").unwrap_or_default()),
+            ("swap", Some(m)) => Action::Swap(m.value_of("slot").unwrap_or_default()),
+            ("verify", Some(m)) => Action::Verify(m.value_of("slot").unwrap_or_default()),
+            ("show-slot", Some(m)) => Action::ShowSlot(
+                m.value_of("slot").unwrap_or_default(),
+                m.value_of("format") == Some("json"),
+            ),
+            ("diff-slots", Some(m)) => Action::DiffSlots(
+                m.value_of("a").unwrap_or_default(),
+                m.value_of("b").unwrap_or_default(),
+                m.is_present("identical-ok"),
+            ),
+            ("load-save-file", Some(m)) => {
+                let pick = match m.value_of("pick") {
+                    Some(pick) => Some(parse_index(pick)?),
+                    None => None,
+                };
+
+                Action::LoadSaveFile(m.value_of("name").unwrap_or_default(), pick)
+            }
+            ("save-dated", Some(m)) => Action::SaveDated(Annotations::from_matches(m)?),
+            ("pin", Some(m)) => Action::Pin(m.value_of("slot").unwrap_or_default(), true),
+            ("unpin", Some(m)) => Action::Pin(m.value_of("slot").unwrap_or_default(), false),
+            ("set-note", Some(m)) => Action::SetNote(
+                m.value_of("slot").unwrap_or_default(),
+                m.value_of("note").unwrap_or_default(),
+            ),
+            ("clear", Some(..)) => Action::Clear,
+            ("undo", Some(..)) => Action::Undo,
+            ("stash", Some(m)) => Action::Stash(m.value_of("load")),
+            ("pop", Some(..)) => Action::Pop,
+            ("stash-list", Some(..)) => Action::StashList,
+            ("backup-to", Some(m)) => Action::BackupTo(
+                m.value_of_os("dir").unwrap_or_default(),
+                m.value_of("keep")
+                    .and_then(|k| k.parse().ok())
+                    .unwrap_or(settings.keep_backups.value),
+            ),
+            ("restore-from", Some(m)) => Action::RestoreFrom(
+                m.value_of_os("dir").unwrap_or_default(),
+                m.value_of("timestamp"),
+            ),
+            ("watch", Some(m)) => Action::Watch(parse_watch_options(m)),
+            ("stop-watch", Some(..)) => Action::StopWatch,
+            ("run", Some(m)) => {
+                // Only GTA V can be launched without saying how.
+                if !m.is_present("executable") && !settings.game.value.is_default() {
+                    clap::Error::with_description(
+                        "the game or launcher to run must be given for games other than gtav",
+                        clap::ErrorKind::MissingRequiredArgument,
+                    )
+                    .exit();
+                }
+
+                Action::Play(m.value_of_os("executable"), m.is_present("snapshot-before"))
+            }
+            ("hotkeys", Some(..)) => Action::Hotkeys(hotkey::parse_hotkeys(
+                settings
+                    .hotkeys
+                    .value
+                    .iter()
+                    .map(|(a, h)| (a.as_str(), h.as_str())),
+            )?),
+            ("tray", Some(..)) => Action::Tray,
+            ("daemon", Some(..)) => Action::Daemon,
+            ("serve", Some(m)) => Action::Serve(
+                m.value_of("port")
+                    .and_then(|p| str::parse(p).ok())
+                    .unwrap_or(serve::DEFAULT_PORT),
+                m.value_of("token"),
+            ),
+            ("list", Some(m)) => Action::List(Filter {
+                tag: m.value_of("filter-tag"),
+                search: m.value_of("search").map(str::to_lowercase),
+                detail: m.is_present("detail"),
+            }),
+            ("list-profiles", Some(..)) => Action::ListProfiles,
+            ("list-save-files", Some(..)) => Action::ListSaveFiles,
+            ("transfer-profile", Some(m)) => Action::TransferProfile(
+                m.value_of("source").unwrap_or_default(),
+                m.value_of("destination").unwrap_or_default(),
+            ),
+            ("load-newest", Some(m)) => Action::LoadNewest(parse_nth(m)?),
+            ("load-at", Some(m)) => {
+                Action::LoadAt(parse_time(m.value_of("time").unwrap_or_default())?)
+            }
+            ("delete-newest", Some(m)) => Action::DeleteNewest(parse_nth(m)?),
+            ("prune-dated", Some(m)) => {
+                match parse_keep(m).or(settings.keep_dated.value.map(Keep::Newest)) {
+                    Some(keep) => Action::PruneDated(keep),
+                    None => clap::Error::with_description(
+                        "prune-dated needs --keep, --keep-days, --policy or keep_dated in the config file",
+                        clap::ErrorKind::MissingRequiredArgument,
+                    )
+                    .exit(),
+                }
+            }
+            ("delete-slot", Some(m)) => Action::DeleteSlot(m.value_of("slot").unwrap_or_default()),
+            ("rename-slot", Some(m)) => Action::RenameSlot(
+                m.value_of("from").unwrap_or_default(),
+                m.value_of("to").unwrap_or_default(),
+            ),
+            ("copy-slot", Some(m)) => Action::CopySlot(
+                m.value_of("from").unwrap_or_default(),
+                m.value_of("to").unwrap_or_default(),
+            ),
+            ("export-slot", Some(m)) => Action::ExportSlot(
+                m.value_of("slot").unwrap_or_default(),
+                m.value_of_os("file"),
+            ),
+            ("import-slot", Some(m)) => Action::ImportSlot(
+                m.value_of_os("file").unwrap_or_default(),
+                m.value_of("slot"),
+            ),
+            ("bundle", Some(m)) => Action::Bundle(
+                m.value_of("slot").unwrap_or_default(),
+                m.value_of_os("file"),
+            ),
+            ("unbundle", Some(m)) => Action::Unbundle(
+                m.value_of_os("file").unwrap_or_default(),
+                m.value_of("slot"),
+            ),
+            ("load-bundle", Some(m)) => {
+                Action::LoadBundle(m.value_of_os("file").unwrap_or_default())
+            }
+            ("load-url", Some(m)) => {
+                Action::LoadUrl(m.value_of("url").unwrap_or_default(), m.value_of("as-slot"))
+            }
+            ("push", Some(m)) => Action::Push(
+                remote(settings)?,
+                m.value_of("slot").unwrap_or_default(),
+                m.value_of("name"),
+            ),
+            ("pull", Some(m)) => Action::Pull(
+                remote(settings)?,
+                m.value_of("name").unwrap_or_default(),
+                m.value_of("slot"),
+            ),
+            ("export-all", Some(m)) => Action::ExportAll(m.value_of_os("file").unwrap_or_default()),
+            ("import-all", Some(m)) => Action::ImportAll(
+                m.value_of_os("file").unwrap_or_default(),
+                // Validated by clap.
+                m.values_of("map-profile")
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|map| map.split_once('='))
+                    .collect(),
+                m.is_present("overwrite"),
+            ),
+            ("show-space", Some(..)) => Action::ShowSpace,
+            ("fsck", Some(m)) => Action::Fsck(m.is_present("adopt")),
+            ("repair", Some(m)) => Action::Repair(m.value_of("slot").unwrap_or_default()),
+            ("dedup", Some(..)) => Action::Dedup,
+            ("recompress-slots", Some(..)) => Action::RecompressSlots,
+            ("alias", Some(m)) => match m.subcommand() {
+                ("set", Some(m)) => Action::SetAlias(
+                    m.value_of("alias").unwrap_or_default(),
+                    m.value_of("slot").unwrap_or_default(),
+                ),
+                ("remove", Some(m)) => Action::RemoveAlias(m.value_of("alias").unwrap_or_default()),
+                _ => Action::ListAliases,
+            },
+            ("history", Some(m)) => Action::History(
+                m.value_of("n")
+                    .and_then(|n| str::parse::<usize>(n).ok())
+                    .unwrap_or(DEFAULT_HISTORY),
+            ),
+            ("sync", Some(m)) => Action::Sync(m.value_of_os("remote").unwrap_or_default()),
+            _ => return Self::from_legacy_flags(matches),
+        };
+
+        Ok(Some(action))
+    }
+
+    /// Parse the action from the flags used before subcommands were introduced.
+    fn from_legacy_flags(matches: &'a ArgMatches<'a>) -> Result<Option<Self>, Error> {
+        let (flag, subcommand, action) = if let Some(slot) = matches.value_of("save") {
+            ("save", "save", Action::Save(slot, Annotations::default()))
+        } else if let Some(slot) = matches.value_of("load") {
+            ("load", "load", Action::Load(slot))
+        } else if let Some(name) = matches.value_of("load-save-file") {
+            (
+                "load-save-file",
+                "load-save-file",
+                Action::LoadSaveFile(name, None),
+            )
+        } else if matches.is_present("save-dated") {
+            (
+                "save-dated",
+                "save-dated",
+                Action::SaveDated(Annotations::default()),
+            )
+        } else if matches.is_present("clear-profile") {
+            ("clear-profile", "clear", Action::Clear)
+        } else if matches.is_present("list-slots") {
+            (
+                "list-slots",
+                "list",
+                Action::List(Filter {
+                    tag: None,
+                    search: None,
+                    detail: false,
+                }),
+            )
+        } else if let Some(nth) = matches.value_of("load-nth-newest-slot") {
+            let nth = parse_legacy_index(nth)?;
+            (
+                "load-nth-newest-slot",
+                "load-newest",
+                Action::LoadNewest(nth),
+            )
+        } else if let Some(nth) = matches.value_of("delete-nth-newest-slot") {
+            let nth = parse_legacy_index(nth)?;
+            (
+                "delete-nth-newest-slot",
+                "delete-newest",
+                Action::DeleteNewest(nth),
+            )
+        } else {
+            return Ok(None);
+        };
+
+        eout!(
+            "warning: --{} is deprecated, use the `{}` subcommand instead",
+            flag,
+            subcommand
+        );
+
+        Ok(Some(action))
+    }
+}
+
+/// Asks the user to confirm destructive actions.
+struct Confirm {
+    /// If a console is attached, otherwise a message box is used.
+    console: bool,
+    /// Skip asking and assume yes.
+    yes: bool,
+    /// Ask on stderr, since stdout is reserved for the JSON document.
+    json: bool,
+}
+
+impl Confirm {
+    /// Ask the user to confirm removing the given files, erroring with [Error::Aborted] unless
+    /// they answer yes.
+    fn ask(&self, question: &str, files: &[PathBuf]) -> Result<(), Error> {
+        if self.yes {
+            return Ok(());
+        }
+
+        let mut message = String::new();
+
+        for file in files {
+            message.push_str(&format!("{}\n", file.display()));
+        }
+
+        message.push_str(question);
+
+        let confirmed = if self.console && output::quiet() {
+            // Asking would print the question.
+            eout!(
+                "warning: can't ask to confirm with --quiet, use --yes: {}",
+                question
+            );
+            false
+        } else if self.console {
+            if self.json {
+                eprint!("{} [y/N] ", message);
+            } else {
+                print!("{} [y/N] ", message);
+                let _ = io::stdout().flush();
+            }
+
+            // Failing to read an answer counts as a no.
+            let mut answer = String::new();
+            let _ = io::stdin().read_line(&mut answer);
+
+            matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+        } else {
+            platform::confirm_box("GTA V SaveLoad Helper", &message)
+        };
+
+        if !confirmed {
+            return Err(Error::Aborted);
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse the optional `nth` argument of a subcommand, defaulting to the newest slot.
+fn parse_nth(matches: &ArgMatches<'_>) -> Result<usize, Error> {
+    match matches.value_of("nth") {
+        Some(nth) => parse_index(nth),
+        None => Ok(1),
+    }
+}
+
+/// Parse which dated slots to keep from the arguments of `prune-dated`, which clap has
+/// already checked to be numbers, if any were given.
+fn parse_keep(matches: &ArgMatches<'_>) -> Option<Keep> {
+    let number = |name| {
+        matches
+            .value_of(name)
+            .and_then(|n| str::parse::<u64>(n).ok())
+            .unwrap_or_default()
+    };
+
+    if matches.is_present("policy") {
+        Some(Keep::Policy)
+    } else if matches.is_present("keep") {
+        Some(Keep::Newest(number("keep") as usize))
+    } else if matches.is_present("keep-days") {
+        Some(Keep::Days(number("keep-days")))
+    } else {
+        None
+    }
+}
+
+/// Parse the arguments of `watch`, which clap has already checked to be numbers.
+fn parse_watch_options(matches: &ArgMatches<'_>) -> WatchOptions {
+    let mut options = WatchOptions::default();
+
+    let seconds = |name| {
+        matches
+            .value_of(name)
+            .and_then(|n| str::parse::<u64>(n).ok())
+            .map(Duration::from_secs)
+    };
+
+    if let Some(debounce) = seconds("debounce") {
+        options.debounce = debounce;
+    }
+
+    if let Some(interval) = seconds("watch-interval") {
+        options.interval = interval;
+    }
+
+    options
+}
+
+/// Validate that an argument is a non-negative number.
+fn is_number(value: String) -> Result<(), String> {
+    match str::parse::<u64>(&value) {
+        Ok(..) => Ok(()),
+        Err(..) => Err(format!("expected a number, but got '{}'", value)),
+    }
+}
+
+/// Validate that an argument is a port which can be listened on.
+fn is_port(value: String) -> Result<(), String> {
+    match str::parse::<u16>(&value) {
+        Ok(1..=65535) => Ok(()),
+        _ => Err(format!(
+            "expected a port from 1 to 65535, but got '{}'",
+            value
+        )),
+    }
+}
+
+/// Validate that an argument maps a profile in a bundle to another one, like `1234abcd=0`.
+fn is_profile_map(value: String) -> Result<(), String> {
+    match value.split_once('=') {
+        Some((old, new)) if !old.is_empty() && !new.is_empty() => Ok(()),
+        _ => Err(format!(
+            "expected the old and the new profile like 1234abcd=5678efgh, but got '{}'",
+            value
+        )),
+    }
+}
+
+/// Validate that an argument is the number of an in-game save slot.
+fn is_save_number(value: String) -> Result<(), String> {
+    match str::parse::<u32>(&value) {
+        Ok(0..=99) => Ok(()),
+        _ => Err(format!(
+            "expected a number from 0 to 99, but got '{}'",
+            value
+        )),
+    }
+}
+
+/// Validate that an argument is a supported zstd compression level.
+fn is_compression_level(value: String) -> Result<(), String> {
+    match str::parse::<i32>(&value) {
+        Ok(1..=22) => Ok(()),
+        _ => Err(format!(
+            "expected a compression level from 1 to 22, but got '{}'",
+            value
+        )),
+    }
+}
+
+/// Parse a slot index, where 1 is the newest slot.
+fn parse_index(value: &str) -> Result<usize, Error> {
+    match str::parse::<usize>(value) {
+        Ok(nth) if nth > 0 => Ok(nth),
+        _ => Err(Error::InvalidIndex {
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// Parse a slot index given to the deprecated flags, where 0 is the newest slot.
+fn parse_legacy_index(value: &str) -> Result<usize, Error> {
+    match str::parse::<usize>(value) {
+        Ok(nth) => Ok(nth + 1),
+        Err(..) => Err(Error::InvalidIndex {
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// Select which profiles to operate on.
+///
+/// The selector is either the index of a profile as printed by [print_profiles], or a part of
+/// its directory name which must match exactly one profile.
+///
+/// If neither a selector or `all` is specified, all profiles are selected unless there's more
+/// than one and the action is destructive.
+/// The level of output asked for, where `--log-level` takes precedence over `-q` and `-v`.
+fn log_level(matches: &ArgMatches<'_>) -> Level {
+    if let Some(level) = matches.value_of("log-level").and_then(Level::parse) {
+        return level;
+    }
+
+    if matches.is_present("quiet") {
+        Level::Warn
+    } else if matches.is_present("verbose") {
+        Level::Debug
+    } else {
+        Level::Info
+    }
+}
+
+fn select_profiles(
+    profiles: Vec<PathBuf>,
+    selector: Option<&str>,
+    all: bool,
+    destructive: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    let selector = match selector {
+        Some(selector) => selector,
+        None if all || profiles.len() <= 1 || !destructive => {
+            debug!(
+                "using all {} profiles, since none was selected",
+                profiles.len()
+            );
+            return Ok(profiles);
+        }
+        None => {
+            print_profiles(&profiles);
+            return Err(Error::MultipleProfiles);
+        }
+    };
+
+    if let Ok(index) = str::parse::<usize>(selector) {
+        if let Some(profile) = profiles.get(index) {
+            debug!(
+                "selected profile {}, which is number {}",
+                profile.display(),
+                index
+            );
+            return Ok(vec![profile.clone()]);
+        }
+    }
+
+    let mut matching = profiles
+        .iter()
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.contains(selector))
+                .unwrap_or_default()
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    match matching.len() {
+        0 => {
+            print_profiles(&profiles);
+
+            Err(Error::ProfileNotFound {
+                selector: selector.to_string(),
+            })
+        }
+        1 => {
+            let profile = matching.remove(0);
+            debug!(
+                "selected profile {}, which is the only one matching '{}'",
+                profile.display(),
+                selector
+            );
+            Ok(vec![profile])
+        }
+        _ => {
+            print_profiles(&matching);
+
+            Err(Error::AmbiguousProfile {
+                selector: selector.to_string(),
+            })
+        }
+    }
+}
+
+/// Print the given profiles with their index.
+fn print_profiles(profiles: &[PathBuf]) {
+    report!("Profiles:");
+
+    let mut table = Table::new().align_right(0);
+
+    for (index, profile) in profiles.iter().enumerate() {
+        table.row([format!("{:>3}:", index), profile.display().to_string()]);
+    }
+
+    table.report();
+}
+
+/// Print every profile with its index, along with when it was created, its save files and the
+/// in-game title of its newest save file, which helps telling the profiles of different accounts
+/// apart.
+fn print_profile_details(
+    profiles: &[PathBuf],
+    dirs: &[GameDir],
+    settings: &Settings,
+) -> Result<(), Error> {
+    let patterns = SaveFilePatterns::new(&settings.include.value, &settings.exclude.value)?;
+
+    if !output::capturing() {
+        report!("Profiles:");
+    }
+
+    // The details of every profile are aligned with each other.
+    let mut table = Table::new();
+    let detail = |table: &mut Table, label: &str, value: String| {
+        table.row([format!("       {}:", label), value]);
+    };
+
+    for (index, profile) in profiles.iter().enumerate() {
+        let edition = edition_of(dirs, profile);
+        let info = SaveManager::new(profile.clone())
+            .game(&settings.game.value)
+            .edition(edition)
+            .save_file_patterns(patterns.clone())
+            .slot(PROFILE)?;
+
+        // Not every filesystem records when a directory was created.
+        let created = fs::metadata(profile).and_then(|m| m.created()).ok();
+        let newest = (!info.is_empty()).then_some(info.modified);
+
+        if output::capturing() {
+            output::result(serde_json::json!({
+                "index": index,
+                "path": profile,
+                "edition": edition.name(),
+                "created": created.map(rfc3339),
+                "files": info.files,
+                "size": info.size,
+                "newest_save": newest.map(rfc3339),
+                "title": info.title,
+            }));
+
+            continue;
+        }
+
+        table.text(format!("{:>3}: {}", index, profile.display()));
+
+        if settings.game.value.has_editions() {
+            detail(&mut table, "edition", edition.to_string());
+        }
+
+        if let Some(created) = created {
+            detail(&mut table, "created", local_time(created).to_string());
+        }
+
+        match newest {
+            Some(newest) => {
+                let files = format!("{}, {}", info.files, output::size(info.size));
+                detail(&mut table, "save files", files);
+
+                let newest = match &info.title {
+                    Some(title) => format!("{} \"{}\"", local_time(newest), title),
+                    None => local_time(newest).to_string(),
+                };
+
+                detail(&mut table, "newest save", newest);
+            }
+            None => detail(&mut table, "save files", String::from("none")),
+        }
+    }
+
+    table.report();
+    Ok(())
+}
+
+/// Import the slots in the bundle at `file` into the profiles they were exported from, or the
+/// ones they're mapped to by `maps`, reporting the slots which were skipped since they already
+/// exist.
+///
+/// Where every profile goes is worked out before anything is imported. A profile which doesn't
+/// exist here and isn't mapped is imported into the only profile here after asking to confirm,
+/// and errors if there are several.
+fn import_all(
+    managers: &[SaveManager],
+    file: &Path,
+    maps: &[(&str, &str)],
+    overwrite: bool,
+    confirm: &Confirm,
+) -> Result<(), Error> {
+    let bundle = Bundle::read(file)?;
+    let profiles = managers
+        .iter()
+        .map(|m| m.profile().to_owned())
+        .collect::<Vec<_>>();
+
+    for (old, _) in maps {
+        if !bundle.profiles.iter().any(|p| p.id == *old) {
+            eout!(
+                "warning: there's no profile '{}' in the archive to map",
+                old
+            );
+        }
+    }
+
+    let mut targets = Vec::new();
+
+    for profile in &bundle.profiles {
+        let target = match maps.iter().find(|(old, _)| *old == profile.id) {
+            Some((_, new)) => {
+                let path = select_profiles(profiles.clone(), Some(new), false, true)?.remove(0);
+                managers.iter().find(|m| m.profile() == path)
+            }
+            None => managers
+                .iter()
+                .find(|m| m.profile().file_name() == Some(OsStr::new(&profile.id))),
+        };
+
+        let manager = match target {
+            Some(manager) => manager,
+            None if managers.len() == 1 => {
+                let manager = &managers[0];
+
+                confirm.ask(
+                    &format!(
+                        "Profile '{}' in the archive doesn't exist here, import its slots into this profile?",
+                        profile.id
+                    ),
+                    &[manager.profile().to_owned()],
+                )?;
+
+                manager
+            }
+            None => {
+                return Err(Error::UnmappedProfile {
+                    id: profile.id.clone(),
+                })
+            }
+        };
+
+        targets.push((profile, manager));
+    }
+
+    for (profile, manager) in targets {
+        let import =
+            manager.journaled("import-all", None, |m| m.import_bundled(profile, overwrite))?;
+
+        if output::capturing() {
+            output::result(serde_json::json!({
+                "profile": manager.profile(),
+                "from": profile.id,
+                "imported": import.imported,
+                "skipped": import.skipped,
+            }));
+        }
+
+        out!(
+            "{}: imported {} from profile '{}'",
+            manager.profile().display(),
+            output::count(import.imported.len(), "slot"),
+            profile.id
+        );
+
+        if !import.skipped.is_empty() {
+            eout!(
+                "warning: skipped {} which already exist, use --overwrite to replace them: {}",
+                output::count(import.skipped.len(), "slot"),
+                import.skipped.join(", ")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace the save files in the profile of `manager` with the ones in the profile matching
+/// `source`, after printing both and asking to confirm.
+fn transfer_profile(manager: &SaveManager, source: &str, confirm: &Confirm) -> Result<(), Error> {
+    let destination = manager.profile();
+
+    // The profiles next to the destination, which is in the `Profiles` directory.
+    let profiles = match destination.parent().and_then(Path::parent) {
+        Some(base) => gtav_helpers::list_profiles(base)?,
+        None => vec![destination.to_owned()],
+    };
+
+    let source = select_profiles(profiles, Some(source), false, true)?.remove(0);
+
+    if source == destination {
+        return Err(Error::SameProfile { path: source });
+    }
+
+    // Both are listed when asking to confirm.
+    if confirm.yes {
+        out!("from: {}", source.display());
+        out!("to: {}", destination.display());
+    }
+
+    confirm.ask(
+        "Replace the save files in the second profile with the ones in the first?",
+        &[source.clone(), destination.to_owned()],
+    )?;
+
+    manager.transfer_from(&source)?;
+    out!("transferred the save files from {}", source.display());
+    Ok(())
+}
+
+/// Print the slots in the given profile matching `filter`, one per line and newest first,
+/// followed by the in-game title of their newest save file, and their tags and note if they
+/// have any. Pinned slots are marked as such.
+///
+/// The index printed next to each slot is the one accepted by `load-newest`, so it's counted
+/// before the slots are filtered.
+fn print_slots(manager: &SaveManager, filter: &Filter<'_>) -> Result<(), Error> {
+    if output::capturing() {
+        for (nth, slot) in (1..).zip(manager.list_slots()?) {
+            if filter.matches(&slot) {
+                output::result(slot_json(manager, nth, &slot));
+            }
+        }
+
+        return Ok(());
+    }
+
+    report!("{}:", manager.profile().display());
+
+    let slots = manager.list_slots()?;
+
+    if slots.is_empty() {
+        report!("  no slots yet");
+    }
+
+    let mut table = Table::new().align_right(0);
+
+    for (nth, slot) in (1..).zip(slots) {
+        if !filter.matches(&slot) {
+            continue;
+        }
+
+        let modified = local_time(slot.modified);
+
+        let name = if slot.pinned {
+            format!("{} (pinned)", slot.name)
+        } else {
+            slot.name.clone()
+        };
+
+        let mut contents = if slot.is_empty() {
+            String::from("(empty)")
+        } else {
+            let numbers = slot
+                .save_numbers
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>();
+
+            format!(
+                "{} (in-game slots {}), {}",
+                output::count(slot.files, "file"),
+                numbers.join(", "),
+                output::size(slot.size)
+            )
+        };
+
+        if !slot.suspicious.is_empty() {
+            contents.push_str(&format!(
+                ", possibly truncated: {}",
+                slot.suspicious.join(", ")
+            ));
+        }
+
+        if let Some(title) = &slot.title {
+            contents.push_str(&format!(" \"{}\"", title));
+        }
+
+        if let Some(version) = &slot.game_version {
+            contents.push_str(&format!(", version {}", version));
+        }
+
+        if !slot.tags.is_empty() {
+            contents.push_str(&format!(" [{}]", slot.tags.join(", ")));
+        }
+
+        if let Some(note) = &slot.note {
+            contents.push_str(&format!(" - {}", note));
+        }
+
+        table.row([
+            format!("{:>3}:", nth),
+            name,
+            format!("({})", modified),
+            contents,
+        ]);
+
+        if filter.detail {
+            for (name, header) in &slot.headers {
+                let title = header.title.as_deref().unwrap_or("unknown");
+
+                match header.saved {
+                    Some(saved) => table.text(format!(
+                        "       {}: {} (saved {})",
+                        name,
+                        title,
+                        local_time(saved)
+                    )),
+                    None => table.text(format!("       {}: {}", name, title)),
+                }
+            }
+        }
+    }
+
+    table.report();
+    Ok(())
+}
+
+/// Print how much space the slots of the given profiles take up, largest first, followed by the
+/// total across all of them. The size on disk counts save files which are hard linked into
+/// several slots only once, and is only printed if it differs.
+fn print_space(managers: &[SaveManager]) -> Result<(), Error> {
+    let profiles = SaveManager::space_usage(managers)?;
+    let apparent = profiles.iter().map(|p| p.apparent).sum::<u64>();
+    let actual = profiles.iter().map(|p| p.actual).sum::<u64>();
+
+    if output::capturing() {
+        let profiles = profiles
+            .iter()
+            .map(|profile| {
+                let slots = profile
+                    .slots
+                    .iter()
+                    .map(|slot| {
+                        serde_json::json!({
+                            "name": slot.name,
+                            "path": slot.path,
+                            "apparent": slot.apparent,
+                            "actual": slot.actual,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                serde_json::json!({
+                    "profile": profile.profile,
+                    "apparent": profile.apparent,
+                    "actual": profile.actual,
+                    "slots": slots,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        output::result(serde_json::json!({
+            "profiles": profiles,
+            "apparent": apparent,
+            "actual": actual,
+        }));
+
+        return Ok(());
+    }
+
+    let describe = |apparent: u64, actual: u64| {
+        if apparent == actual {
+            output::size(apparent)
+        } else {
+            format!(
+                "{}, {} on disk",
+                output::size(apparent),
+                output::size(actual)
+            )
+        }
+    };
+
+    for profile in &profiles {
+        report!(
+            "{}: {}",
+            profile.profile.display(),
+            describe(profile.apparent, profile.actual)
+        );
+
+        if profile.slots.is_empty() {
+            report!("  no slots yet");
+        }
+
+        for slot in &profile.slots {
+            report!("  {}: {}", slot.name, describe(slot.apparent, slot.actual));
+        }
+    }
+
+    report!("total: {}", describe(apparent, actual));
+    Ok(())
+}
+
+/// Print what's wrong with each slot in the profile which doesn't match its manifest, followed
+/// by how many slots were checked, erroring if any of them had problems.
+fn print_fsck(manager: &SaveManager, adopt: bool) -> Result<(), Error> {
+    let checks = manager.fsck(adopt)?;
+
+    let count = |f: fn(&CheckStatus) -> bool| checks.iter().filter(|c| f(&c.status)).count();
+    let ok = count(|s| matches!(s, CheckStatus::Ok));
+    let unverifiable = count(|s| matches!(s, CheckStatus::Unverifiable));
+    let adopted = count(|s| matches!(s, CheckStatus::Adopted));
+    let corrupt = count(|s| matches!(s, CheckStatus::Problems(..)));
+
+    let problems = checks
+        .iter()
+        .map(|c| match &c.status {
+            CheckStatus::Problems(problems) => problems.len(),
+            _ => 0,
+        })
+        .sum();
+
+    if output::capturing() {
+        for check in &checks {
+            let (status, problems) = match &check.status {
+                CheckStatus::Ok => ("ok", &[][..]),
+                CheckStatus::Unverifiable => ("unverifiable", &[][..]),
+                CheckStatus::Adopted => ("adopted", &[][..]),
+                CheckStatus::Problems(problems) => ("corrupt", &problems[..]),
+            };
+
+            output::result(serde_json::json!({
+                "profile": manager.profile(),
+                "name": check.name,
+                "path": check.path,
+                "status": status,
+                "problems": problems,
+            }));
+        }
+    } else {
+        report!("{}:", manager.profile().display());
+
+        for check in &checks {
+            match &check.status {
+                CheckStatus::Ok => out!("  {}: ok", check.name),
+                CheckStatus::Unverifiable => {
+                    report!("  {}: unverifiable, it has no manifest", check.name)
+                }
+                CheckStatus::Adopted => report!("  {}: adopted, wrote a manifest", check.name),
+                CheckStatus::Problems(problems) => {
+                    for problem in problems {
+                        report!("  {}: {}", check.name, problem);
+                    }
+                }
+            }
+        }
+
+        report!(
+            "checked {}: {} ok, {} with problems, {} unverifiable, {} adopted",
+            output::count(checks.len(), "slot"),
+            ok,
+            corrupt,
+            unverifiable,
+            adopted
+        );
+    }
+
+    if corrupt > 0 {
+        return Err(Error::SlotsCorrupt {
+            slots: corrupt,
+            problems,
+        });
+    }
+
+    Ok(())
+}
+
+/// Repair the given slot, printing which slot each save file was copied from and the save files
+/// which are still broken, erroring if there are any.
+fn print_repair(manager: &SaveManager, slot: &str) -> Result<(), Error> {
+    let repair = manager.repair(slot)?;
+
+    if output::capturing() {
+        let repaired = repair
+            .repaired
+            .iter()
+            .map(|f| serde_json::json!({ "name": f.name, "donor": f.donor }))
+            .collect::<Vec<_>>();
+
+        output::result(serde_json::json!({
+            "slot": slot,
+            "repaired": repaired,
+            "unrepaired": repair.unrepaired,
+            "problems": repair.problems,
+        }));
+    } else {
+        for file in &repair.repaired {
+            report!("{}: copied from slot '{}'", file.name, file.donor);
+        }
+
+        for name in &repair.unrepaired {
+            report!("{}: no other slot has an identical copy", name);
+        }
+
+        for problem in &repair.problems {
+            report!("{}", problem);
+        }
+
+        if repair.repaired.is_empty() && repair.unrepaired.is_empty() {
+            report!("slot '{}' has no broken save files", slot);
+        }
+    }
+
+    let problems = repair.unrepaired.len() + repair.problems.len();
+
+    if problems > 0 {
+        return Err(Error::SlotsCorrupt { slots: 1, problems });
+    }
+
+    Ok(())
+}
+
+/// Print the entries on the stash, most recently stashed first.
+fn print_stash(manager: &SaveManager) -> Result<(), Error> {
+    let entries = manager.stash_list()?;
+
+    if output::capturing() {
+        for entry in entries {
+            output::result(serde_json::json!({
+                "profile": manager.profile(),
+                "name": entry.name,
+                "path": entry.path,
+                "stashed": rfc3339(entry.stashed),
+                "files": entry.files,
+            }));
+        }
+
+        return Ok(());
+    }
+
+    report!("{}:", manager.profile().display());
+
+    if entries.is_empty() {
+        report!("  the stash is empty");
+    }
+
+    for entry in entries {
+        report!(
+            "  {}: stashed {}, {}",
+            entry.name,
+            local_time(entry.stashed),
+            output::count(entry.files, "file")
+        );
+    }
+
+    Ok(())
+}
+
+/// The remote in the config file.
+fn remote(settings: &Settings) -> Result<Remote, Error> {
+    Remote::new(settings.remote.value.as_ref().ok_or(Error::NoRemote)?)
+}
+
+/// Print the slot bundles on the remote.
+fn print_remote(remote: &Remote) -> Result<(), Error> {
+    let bundles = remote.list()?;
+
+    if output::capturing() {
+        for bundle in bundles {
+            output::result(serde_json::json!({
+                "name": bundle.name,
+                "url": remote.url(&bundle.name),
+                "size": bundle.size,
+                "modified": bundle.modified.map(rfc3339),
+            }));
+        }
+
+        return Ok(());
+    }
+
+    if bundles.is_empty() {
+        report!("the remote has no slot bundles");
+    }
+
+    for bundle in bundles {
+        match bundle.modified {
+            Some(modified) => report!(
+                "{}: {}, uploaded {}",
+                bundle.name,
+                output::size(bundle.size),
+                local_time(modified)
+            ),
+            None => report!("{}: {}", bundle.name, output::size(bundle.size)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the aliases of the slots in the profile of the given manager, noting the ones which
+/// refer to slots that don't exist.
+fn print_aliases(manager: &SaveManager) -> Result<(), Error> {
+    let aliases = manager.aliases()?;
+
+    let slots = manager
+        .list_slots()?
+        .into_iter()
+        .map(|slot| slot.name)
+        .collect::<HashSet<_>>();
+
+    if output::capturing() {
+        for (alias, slot) in aliases {
+            output::result(serde_json::json!({
+                "profile": manager.profile(),
+                "alias": alias,
+                "slot": slot,
+                "exists": slots.contains(&slot),
+            }));
+        }
+
+        return Ok(());
+    }
+
+    report!("{}:", manager.profile().display());
+
+    if aliases.is_empty() {
+        report!("  no aliases");
+    }
+
+    for (alias, slot) in aliases {
+        if slots.contains(&slot) {
+            report!("  @{} -> {}", alias, slot);
+        } else {
+            report!("  @{} -> {} (missing)", alias, slot);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the last `n` entries in the journal of the given profile, most recent first.
+fn print_history(manager: &SaveManager, n: usize) -> Result<(), Error> {
+    let entries = manager.history()?;
+
+    if output::capturing() {
+        for entry in entries.iter().rev().take(n) {
+            output::result(serde_json::to_value(entry).unwrap_or_default());
+        }
+
+        return Ok(());
+    }
+
+    report!("{}:", manager.profile().display());
+
+    if entries.is_empty() {
+        report!("  no history yet");
+    }
+
+    for entry in entries.iter().rev().take(n) {
+        let time = chrono::DateTime::parse_from_rfc3339(&entry.time)
+            .map(|t| local_time(t.into()).to_string())
+            .unwrap_or_else(|_| entry.time.clone());
+
+        let command = match &entry.slot {
+            Some(slot) => format!("{} {}", entry.command, slot),
+            None => entry.command.clone(),
+        };
+
+        match &entry.error {
+            Some(error) => report!("  {} {}: failed - {}", time, command, error),
+            None => report!(
+                "  {} {}: {}",
+                time,
+                command,
+                output::count(entry.files.len(), "operation")
+            ),
+        }
+
+        for file in &entry.files {
+            let path = match &file.to {
+                Some(to) => format!("{} -> {}", file.path.display(), to.display()),
+                None => file.path.display().to_string(),
+            };
+
+            match (file.size, &file.sha256) {
+                (Some(size), Some(sha256)) => report!(
+                    "    {} {} ({}, {})",
+                    file.op,
+                    path,
+                    output::size(size),
+                    &sha256[..sha256.len().min(12)]
+                ),
+                _ => report!("    {} {}", file.op, path),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Describe a slot listed by `list` for `--format json`.
+fn slot_json(manager: &SaveManager, nth: usize, slot: &Slot) -> serde_json::Value {
+    serde_json::json!({
+        "profile": manager.profile(),
+        "nth": nth,
+        "name": slot.name,
+        "path": slot.path,
+        "modified": rfc3339(slot.modified),
+        "files": slot.files,
+        "size": slot.size,
+        "note": slot.note,
+        "tags": slot.tags,
+        "title": slot.title,
+        "pinned": slot.pinned,
+        "save_numbers": slot.save_numbers,
+        "suspicious": slot.suspicious,
+        "dated": slot.dated,
+        "game_version": slot.game_version,
+        "headers": slot.headers.iter().map(|(name, header)| {
+            serde_json::json!({
+                "name": name,
+                "title": header.title,
+                "saved": header.saved.map(rfc3339),
+                "completion": header.completion(),
+            })
+        }).collect::<Vec<_>>(),
+    })
+}
+
+/// Print the note, tags and size of a slot, followed by the size, modification time, digest
+/// and in-game title of every save file in it.
+fn print_slot(manager: &SaveManager, slot: &str, json: bool) -> Result<(), Error> {
+    let info = manager.slot(slot)?;
+    let save_files = manager.show_slot(slot)?;
+
+    if json {
+        let save_files = save_files
+            .iter()
+            .map(|f| {
+                serde_json::json!({
+                    "name": f.name,
+                    "size": f.size,
+                    "modified": rfc3339(f.modified),
+                    "sha256": f.sha256,
+                    "title": f.header.title,
+                    "saved": f.header.saved.map(rfc3339),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let value = serde_json::json!({
+            "profile": manager.profile(),
+            "name": info.name,
+            "path": info.path,
+            "note": info.note,
+            "tags": info.tags,
+            "files": info.files,
+            "size": info.size,
+            "save_files": save_files,
+        });
+
+        output::result(value);
+        return Ok(());
+    }
+
+    report!(
+        "{}: {}, {}",
+        slot,
+        output::count(info.files, "file"),
+        output::size(info.size)
+    );
+
+    if let Some(note) = &info.note {
+        report!("  note: {}", note);
+    }
+
+    if !info.tags.is_empty() {
+        report!("  tags: {}", info.tags.join(", "));
+    }
+
+    for save_file in save_files {
+        let header = &save_file.header;
+        let title = header.title.as_deref().unwrap_or("unknown");
+
+        match header.saved {
+            Some(saved) => report!(
+                "  {}: {} (saved {})",
+                save_file.name,
+                title,
+                local_time(saved)
+            ),
+            None => report!("  {}: {}", save_file.name, title),
+        }
+
+        report!("    size: {}", output::size(save_file.size));
+        report!("    modified: {}", local_time(save_file.modified));
+        report!("    sha256: {}", save_file.sha256);
+    }
+
+    Ok(())
+}
+
+/// Format a point in time as local time, like `2019-10-30 18:04:12`.
+fn local_time(time: SystemTime) -> impl fmt::Display {
+    chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d %H:%M:%S")
+}
+
+/// Format a duration in the largest whole unit, like `25 minutes`.
+fn display_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    let (n, unit) = match seconds {
+        0..=59 => (seconds, "second"),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+
+    output::count(n as usize, unit)
+}
+
+/// Format a point in time as local time in RFC 3339 format.
+fn rfc3339(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Local>::from(time).to_rfc3339()
+}
+
+/// Print the differences between the profile and a slot, erroring if there are any.
+fn print_differences(manager: &SaveManager, slot: &str) -> Result<(), Error> {
+    let differences = manager.compare(slot)?;
+
+    if differences.is_empty() {
+        report!("{}: matches slot '{}'", manager.profile().display(), slot);
+        return Ok(());
+    }
+
+    for difference in &differences {
+        let what = describe_difference(difference, "profile", "slot");
+        report!("{}: {}", difference.name(), what);
+    }
+
+    Err(Error::SlotDiffers {
+        name: slot.to_string(),
+    })
+}
+
+/// Print the differences between two slots, along with when each side of a differing save file
+/// was modified.
+///
+/// If `identical_ok` is set, this errors if there are any differences.
+fn print_slot_differences(
+    manager: &SaveManager,
+    left: &str,
+    right: &str,
+    identical_ok: bool,
+) -> Result<(), Error> {
+    let differences = manager.diff_slots(left, right)?;
+
+    if differences.is_empty() {
+        report!("slot '{}' matches slot '{}'", left, right);
+        return Ok(());
+    }
+
+    let left_dir = manager.slot(left)?.path;
+    let right_dir = manager.slot(right)?.path;
+
+    for difference in &differences {
+        let name = difference.name();
+        let what = describe_difference(difference, left, right);
+
+        match difference {
+            Difference::Size { .. } | Difference::Contents { .. } => report!(
+                "{}: {}, modified {} vs {}",
+                name,
+                what,
+                modified(&left_dir.join(name)),
+                modified(&right_dir.join(name))
+            ),
+            _ => report!("{}: {}", name, what),
+        }
+    }
+
+    if identical_ok {
+        return Err(Error::SlotsDiffer {
+            left: left.to_string(),
+            right: right.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Describe a difference, where `left` and `right` name the two sides compared.
+fn describe_difference(difference: &Difference, left: &str, right: &str) -> String {
+    match difference {
+        Difference::OnlyLeft { .. } => format!("only in {}", left),
+        Difference::OnlyRight { .. } => format!("only in {}", right),
+        Difference::Size { left, right, .. } => format!(
+            "differs ({} vs {} bytes)",
+            thousands(*left),
+            thousands(*right)
+        ),
+        Difference::Contents { .. } => String::from("differs (contents)"),
+    }
+}
+
+/// Format when the given file was modified, or `unknown` if it can't be read.
+fn modified(path: &Path) -> String {
+    match fs::metadata(path).and_then(|m| m.modified()) {
+        Ok(modified) => local_time(modified).to_string(),
+        Err(..) => String::from("unknown"),
+    }
+}
+
+/// Format a number with thousands separators, like `12,482`.
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+
+        out.push(c);
+    }
+
+    out
+}
+
+/// Delete the named slot after asking for confirmation.
+fn delete_slot(manager: &SaveManager, slot: &str, confirm: &Confirm) -> Result<(), Error> {
+    let files = manager.slot_files_to_delete(slot)?;
+    let question = format!(
+        "Delete slot '{}' with {} from {}?",
+        slot,
+        output::count(files.len(), "file"),
+        manager.profile().display()
+    );
+
+    confirm.ask(&question, &files)?;
+    manager.delete_slot(slot)
+}
+
+/// Print the directories matching `name` if there's more than one, and which one is picked.
+fn print_save_file_candidates(
+    manager: &SaveManager,
+    name: &str,
+    pick: Option<usize>,
+) -> Result<(), Error> {
+    // Indexes name a single directory.
+    if name.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(());
+    }
+
+    let candidates = manager.find_save_files(name)?;
+
+    if candidates.len() <= 1 {
+        return Ok(());
+    }
+
+    report!("Directories matching '{}':", name);
+
+    for (n, candidate) in (1..).zip(&candidates) {
+        let marker = if Some(n) == pick { " (picked)" } else { "" };
+        report!("{:>3}: {}{}", n, candidate.display(), marker);
+    }
+
+    Ok(())
+}
+
+/// Print the directories in the `Save Files` folder of the profile, with the index
+/// `load-save-file` accepts.
+fn print_save_files_dirs(manager: &SaveManager) -> Result<(), Error> {
+    let dirs = manager.list_save_files_dirs()?;
+
+    if output::capturing() {
+        for (index, dir) in (1..).zip(dirs) {
+            output::result(serde_json::json!({
+                "profile": manager.profile(),
+                "index": index,
+                "name": dir.name,
+                "path": dir.path,
+                "modified": rfc3339(dir.modified),
+                "files": dir.files,
+            }));
+        }
+
+        return Ok(());
+    }
+
+    report!("{}:", manager.profile().display());
+
+    if dirs.is_empty() {
+        report!("  no directories in Save Files");
+    }
+
+    let mut table = Table::new().align_right(0);
+
+    for (index, dir) in (1..).zip(dirs) {
+        table.row([
+            format!("{:>3}:", index),
+            dir.name,
+            format!("({})", local_time(dir.modified)),
+            output::count(dir.files, "file"),
+        ]);
+    }
+
+    table.report();
+    Ok(())
+}
+
+/// The file a slot is exported to if none is specified, like `foo-2019-10-30.zip`.
+fn default_export_path(slot: &str) -> PathBuf {
+    let name = slot.trim_start_matches('@');
+    let date = chrono::Local::now().format("%Y-%m-%d");
+    PathBuf::from(format!("{}-{}.zip", name, date))
+}
+
+/// Read the passphrase from `--passphrase-file`, or ask for it without echoing it, twice if
+/// `repeat` is set so that a typo doesn't lock away an archive.
+fn read_passphrase(matches: &ArgMatches<'_>, console: bool, repeat: bool) -> Result<String, Error> {
+    let invalid = |reason| Error::InvalidPassphrase { reason };
+
+    let passphrase = if let Some(path) = matches.value_of_os("passphrase-file") {
+        let path = Path::new(path);
+
+        let contents = fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+
+        contents.lines().next().unwrap_or_default().to_string()
+    } else {
+        if !console {
+            clap::Error::with_description(
+                "a passphrase can only be asked for with --console, use --passphrase-file instead",
+                clap::ErrorKind::MissingRequiredArgument,
+            )
+            .exit();
+        }
+
+        let prompt = |prompt| {
+            rpassword::prompt_password(prompt).map_err(|_| invalid("it could not be read"))
+        };
+
+        let passphrase = prompt("Passphrase: ")?;
+
+        if repeat && prompt("Repeat passphrase: ")? != passphrase {
+            return Err(invalid("the passphrases don't match"));
+        }
+
+        passphrase
+    };
+
+    if passphrase.is_empty() {
+        return Err(invalid("it can't be empty"));
+    }
+
+    Ok(passphrase)
+}
+
+/// Test if the picker should be shown instead of running a command, which it is with
+/// `--interactive`, or when run from a terminal without any arguments but `--console`.
+fn wants_picker(matches: &ArgMatches<'_>, console: bool) -> bool {
+    let command = matches.subcommand_name().is_some() || matches.is_present("legacy");
+
+    if !matches.is_present("interactive") {
+        // The picked command is run with the arguments of the program, which it mustn't be
+        // picked again for.
+        return console
+            && !command
+            && env::args_os().skip(1).all(|arg| arg == "--console")
+            && io::stdin().is_terminal()
+            && io::stderr().is_terminal();
+    }
+
+    if command {
+        clap::Error::with_description(
+            "--interactive cannot be combined with a command",
+            clap::ErrorKind::ArgumentConflict,
+        )
+        .exit();
+    }
+
+    if !console {
+        clap::Error::with_description(
+            "--interactive can only be used with --console",
+            clap::ErrorKind::MissingRequiredArgument,
+        )
+        .exit();
+    }
+
+    true
+}
+
+/// Let the user pick a profile, an action and a slot in the terminal, confirming before
+/// anything is changed, and return the arguments which run the picked command.
+///
+/// The command is then parsed and run like any other, so that it behaves exactly like it does
+/// when it's given as arguments.
+fn pick_command(settings: &Settings, matches: &ArgMatches<'_>) -> Result<Vec<String>, Error> {
+    let dirs = game_dirs(settings)?;
+    let profiles = list_game_profiles(&dirs)?;
+    let all = matches.is_present("all-profiles");
+
+    let profile = match &settings.profile.value {
+        None if !all && profiles.len() > 1 => {
+            let items = profiles
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>();
+
+            let index = picked(
+                dialoguer::Select::new()
+                    .with_prompt("Profile")
+                    .items(&items)
+                    .default(0)
+                    .interact_opt(),
+            )?;
+
+            Some(index)
+        }
+        _ => None,
+    };
+
+    let selected = select_profiles(
+        profiles,
+        profile
+            .map(|index| index.to_string())
+            .as_deref()
+            .or(settings.profile.value.as_deref()),
+        all,
+        true,
+    )?;
+
+    let path = &selected[0];
+    let manager = SaveManager::new(path)
+        .game(&settings.game.value)
+        .edition(edition_of(&dirs, path))
+        .shared_slots(settings.shared_slots.value);
+
+    let manager = match &settings.slots_dir.value {
+        Some(dir) => manager.slots_dir(dir),
+        None => manager,
+    };
+
+    let slots = manager.list_slots()?;
+
+    // Actions on slots are only offered once there are slots to pick from.
+    let actions = PICKER_ACTIONS
+        .iter()
+        .filter(|(_, _, on_slot)| !on_slot || !slots.is_empty())
+        .collect::<Vec<_>>();
+
+    let items = actions.iter().map(|(label, ..)| *label).collect::<Vec<_>>();
+
+    let index = picked(
+        dialoguer::Select::new()
+            .with_prompt("Action")
+            .items(&items)
+            .default(0)
+            .interact_opt(),
+    )?;
+
+    let (_, subcommand, on_slot) = *actions[index];
+    let mut command = vec![subcommand.to_string()];
+
+    if on_slot {
+        let items = slots
+            .iter()
+            .map(|slot| {
+                let modified = local_time(slot.modified);
+
+                match &slot.note {
+                    Some(note) => format!("{} ({}) - {}", slot.name, modified, note),
+                    None => format!("{} ({})", slot.name, modified),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let index = picked(
+            dialoguer::Select::new()
+                .with_prompt("Slot")
+                .items(&items)
+                .default(0)
+                .max_length(PICKER_SLOTS)
+                .interact_opt(),
+        )?;
+
+        command.push(slots[index].name.clone());
+    }
+
+    let args = picked_args(env::args().skip(1), profile, &command, false);
+    let matches = app()
+        .get_matches_from_safe(&args)
+        .unwrap_or_else(|e| e.exit());
+
+    let destructive = match Action::from_matches(&matches, settings)? {
+        Some(action) => action.is_destructive(),
+        None => false,
+    };
+
+    if !destructive {
+        return Ok(args);
+    }
+
+    let question = match selected.len() {
+        1 => format!("Run '{}' in {}?", command.join(" "), path.display()),
+        n => format!("Run '{}' in all {} profiles?", command.join(" "), n),
+    };
+
+    let confirmed = picked(
+        dialoguer::Confirm::new()
+            .with_prompt(question)
+            .default(false)
+            .interact_opt(),
+    )?;
+
+    if !confirmed {
+        return Err(Error::Aborted);
+    }
+
+    // Answered already, so the command doesn't ask again.
+    Ok(picked_args(env::args().skip(1), profile, &command, true))
+}
+
+/// The arguments which run `command` with the options given to the program, in the profile
+/// with the given index if one was picked, and without asking before removing files if `yes` is
+/// set.
+fn picked_args<I>(given: I, profile: Option<usize>, command: &[String], yes: bool) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = vec![env!("CARGO_BIN_NAME").to_string()];
+    args.extend(given.into_iter().filter(|arg| arg != "--interactive"));
+
+    if let Some(profile) = profile {
+        args.extend(["--profile".to_string(), profile.to_string()]);
+    }
+
+    if yes {
+        args.push("--yes".to_string());
+    }
+
+    args.extend(command.iter().cloned());
+    args
+}
+
+/// The answer to a prompt of the picker, where Esc aborts and Ctrl+C cancels.
+fn picked<T>(answer: dialoguer::Result<Option<T>>) -> Result<T, Error> {
+    let answer = answer.map_err(|dialoguer::Error::IO(e)| {
+        // The prompt is left behind when it's interrupted.
+        let _ = dialoguer::console::Term::stderr().show_cursor();
+
+        if e.kind() == io::ErrorKind::Interrupted {
+            Error::Cancelled
+        } else {
+            Error::Terminal { source: e }
+        }
+    })?;
+
+    answer.ok_or(Error::Aborted)
+}
+
+/// Run the given action against a single profile, recording it in the journal unless it only
+/// reads.
+fn run(manager: &SaveManager, action: &Action<'_>, confirm: &Confirm) -> Result<(), Error> {
+    match action.journal() {
+        Some((command, slot)) => manager.journaled(command, slot, |manager| {
+            run_action(manager, action, confirm)
+        }),
+        None => run_action(manager, action, confirm),
+    }
+}
+
+/// Run the given action against a single profile.
+fn run_action(manager: &SaveManager, action: &Action<'_>, confirm: &Confirm) -> Result<(), Error> {
+    match *action {
+        Action::List(ref filter) => {
+            print_slots(manager, filter)?;
+        }
+        Action::Save(slot, ref annotations) => {
+            manager.save(slot)?;
+            annotations.apply(manager, slot)?;
+        }
+        Action::Load(slot) => {
+            manager.load(slot)?;
+        }
+        Action::SaveQuick(alias) => {
+            manager.save(&format!("@{}", alias))?;
+        }
+        Action::LoadQuick(alias) => {
+            manager.load(&format!("@{}", alias))?;
+        }
+        Action::Swap(slot) => {
+            manager.swap(slot)?;
+        }
+        Action::Verify(slot) => {
+            print_differences(manager, slot)?;
+        }
+        Action::ShowSlot(slot, json) => {
+            print_slot(manager, slot, json)?;
+        }
+        Action::DiffSlots(left, right, identical_ok) => {
+            print_slot_differences(manager, left, right, identical_ok)?;
+        }
+        Action::LoadSaveFile(name, pick) => {
+            print_save_file_candidates(manager, name, pick)?;
+            manager.load_save_file(name, pick)?;
+        }
+        Action::ListSaveFiles => {
+            print_save_files_dirs(manager)?;
+        }
+        Action::SaveDated(ref annotations) => {
+            let slot = manager.save_dated_with_note(annotations.note)?;
+            annotations.apply(manager, &slot)?;
+        }
+        Action::SetNote(slot, note) => {
+            manager.annotate(slot, Some(note), &[])?;
+        }
+        Action::Pin(slot, pinned) => {
+            manager.pin(slot, pinned)?;
+        }
+        Action::Clear => {
+            let files = manager.save_files()?;
+
+            if !files.is_empty() {
+                let question = format!(
+                    "Delete {} from {}?",
+                    output::count(files.len(), "save file"),
+                    manager.profile().display()
+                );
+
+                confirm.ask(&question, &files)?;
+            }
+
+            manager.clear()?;
+        }
+        Action::Undo => {
+            manager.undo()?;
+        }
+        Action::Stash(load) => {
+            manager.stash(load)?;
+        }
+        Action::Pop => {
+            manager.pop()?;
+        }
+        Action::StashList => {
+            print_stash(manager)?;
+        }
+        Action::BackupTo(dir, keep) => {
+            manager.backup_to(dir.as_ref(), keep)?;
+        }
+        Action::RestoreFrom(dir, backup) => {
+            manager.restore_from(dir.as_ref(), backup)?;
+        }
+        Action::Watch(options) => {
+            manager.watch(options, &STOP)?;
+        }
+        Action::StopWatch => {
+            if !platform::stop_watching() {
+                out!("no running `watch` to stop");
+            }
+        }
+        Action::Play(..) => {
+            // The game is launched once for all profiles by `play`.
+        }
+        Action::Hotkeys(..) => {
+            // The hotkeys are listened for once for all profiles by `hotkeys`.
+        }
+        Action::Tray => {
+            // The tray icon is shown once for all profiles by `tray`.
+        }
+        Action::Serve(..) => {
+            // Requests are served once for all profiles by `serve`.
+        }
+        Action::Daemon => {
+            // Commands are answered once for all profiles by `daemon`.
+        }
+        Action::ListProfiles => {
+            // Every profile is listed before one is selected by `list-profiles`.
+        }
+        Action::TransferProfile(source, _) => {
+            transfer_profile(manager, source, confirm)?;
+        }
+        Action::LoadNewest(nth) => {
+            let slot = manager.nth_newest_slot(nth)?;
+            manager.load(&slot.name)?;
+        }
+        Action::LoadAt(time) => {
+            let (slot, saved) = manager.dated_slot_at(time)?;
+            let before = time.duration_since(saved).unwrap_or_default();
+
+            out!(
+                "selected slot '{}' saved {}, {} before the requested time",
+                slot.name,
+                local_time(saved),
+                display_duration(before)
+            );
+
+            manager.load(&slot.name)?;
+        }
+        Action::DeleteNewest(nth) => {
+            let slot = manager.nth_newest_slot(nth)?;
+            delete_slot(manager, &slot.name, confirm)?;
+        }
+        Action::DeleteSlot(slot) => {
+            delete_slot(manager, slot, confirm)?;
+        }
+        Action::PruneDated(keep) => {
+            let slots = manager.prunable_slots(keep)?;
+
+            if !slots.is_empty() {
+                let paths = slots.iter().map(|s| s.path.clone()).collect::<Vec<_>>();
+                let question = format!(
+                    "Delete {} dated slots from {}?",
+                    slots.len(),
+                    manager.profile().display()
+                );
+
+                confirm.ask(&question, &paths)?;
+            }
+
+            manager.prune(&slots)?;
+        }
+        Action::RenameSlot(from, to) => {
+            manager.rename_slot(from, to)?;
+        }
+        Action::CopySlot(from, to) => {
+            manager.copy_slot(from, to)?;
+        }
+        Action::ExportSlot(slot, file) => {
+            let file = match file {
+                Some(file) => PathBuf::from(file),
+                None => default_export_path(slot),
+            };
+
+            manager.export_slot(slot, &file)?;
+        }
+        Action::ImportSlot(file, slot) => {
+            manager.import_slot(file.as_ref(), slot)?;
+        }
+        Action::Bundle(slot, file) => {
+            let file = match file {
+                Some(file) => PathBuf::from(file),
+                None => PathBuf::from(format!("{}.{}", slot, SLOT_BUNDLE_EXTENSION)),
+            };
+
+            manager.bundle_slot(slot, &file)?;
+        }
+        Action::Unbundle(file, slot) => {
+            manager.unbundle(file.as_ref(), slot)?;
+        }
+        Action::LoadBundle(file) => {
+            manager.load_bundle(file.as_ref())?;
+        }
+        Action::LoadUrl(url, slot) => {
+            manager.load_url(url, slot)?;
+        }
+        Action::Push(ref remote, slot, name) => {
+            manager.push(remote, slot, name)?;
+        }
+        Action::Pull(ref remote, name, slot) => {
+            manager.pull(remote, name, slot)?;
+        }
+        Action::ExportAll(..) | Action::ImportAll(..) => {
+            // Every profile is exported into and imported from a single bundle.
+        }
+        Action::ShowSpace => {
+            // The space used is added up across every profile.
+        }
+        Action::Sync(remote) => {
+            manager.sync(remote.as_ref())?;
+        }
+        Action::Fsck(adopt) => {
+            print_fsck(manager, adopt)?;
+        }
+        Action::Repair(slot) => {
+            print_repair(manager, slot)?;
+        }
+        Action::Dedup => {
+            manager.dedup()?;
+        }
+        Action::RecompressSlots => {
+            manager.recompress_slots()?;
+        }
+        Action::SetAlias(alias, slot) => {
+            manager.set_alias(alias, slot)?;
+        }
+        Action::RemoveAlias(alias) => {
+            manager.remove_alias(alias)?;
+        }
+        Action::ListAliases => {
+            print_aliases(manager)?;
+        }
+        Action::History(n) => {
+            print_history(manager, n)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn app() -> App<'static, 'static> {
+    commands().subcommand(
+        SubCommand::with_name(COMPLETE_SLOTS)
+            .setting(AppSettings::Hidden)
+            .about("Lists the names of the slots, for completion scripts."),
+    )
+}
+
+/// The app without the hidden subcommands, which completions are generated from.
+fn commands() -> App<'static, 'static> {
+    let slot = Arg::with_name("slot")
+        .value_name("slot")
+        .required(true)
+        .help("The name of the slot.");
+
+    let alias = Arg::with_name("alias")
+        .value_name("alias")
+        .required(true)
+        .help("The alias, like 1.");
+
+    let nth = Arg::with_name("nth")
+        .value_name("nth")
+        .help("Index of the slot, where 1 is the newest. Defaults to 1.");
+
+    let from = Arg::with_name("from")
+        .value_name("from")
+        .required(true)
+        .help("The name of the existing slot.");
+
+    let to = Arg::with_name("to")
+        .value_name("to")
+        .required(true)
+        .help("The name of the new slot.");
+
+    let note = Arg::with_name("note")
+        .long("note")
+        .value_name("text")
+        .takes_value(true)
+        .help("A note describing the slot.");
+
+    let passphrase_file = Arg::with_name("passphrase-file")
+        .long("passphrase-file")
+        .value_name("file")
+        .takes_value(true)
+        .help("Reads the passphrase from the first line of this file instead of asking for it.");
+
+    let tag = Arg::with_name("tag")
+        .long("tag")
+        .value_name("tag")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help("A label to attach to the slot. Can be used multiple times.");
+
+    App::new("GTA V SaveLoad Helper")
+        .version(env!("CARGO_PKG_VERSION"))
+        .author("John-John Tedro")
+        .about("Manages GTA V Save Files")
+        .global_setting(AppSettings::UnifiedHelpMessage)
+        .after_help(EXIT_CODES)
+        .setting(AppSettings::VersionlessSubcommands)
+        .subcommand(
+            SubCommand::with_name("save")
+                .about("Saves the current save files in the given slot.")
+                .arg(slot.clone())
+                .arg(note.clone())
+                .arg(tag.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("load")
+                .about("Loads the current save files from the given slot.")
+                .arg(slot.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("save-quick")
+                .about("Saves the current save files in the slot which an alias refers to.")
+                .arg(alias.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("load-quick")
+                .about("Loads the current save files from the slot which an alias refers to.")
+                .arg(alias.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("swap")
+                .about("Exchanges the current save files with the ones in the given slot.")
+                .arg(slot.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Compares the current save files against the given slot.")
+                .arg(slot.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("diff-slots")
+                .about("Compares the save files in two slots, either of which can be @profile.")
+                .arg(
+                    Arg::with_name("a")
+                        .value_name("a")
+                        .required(true)
+                        .help("The first slot."),
+                )
+                .arg(
+                    Arg::with_name("b")
+                        .value_name("b")
+                        .required(true)
+                        .help("The second slot."),
+                )
+                .arg(
+                    Arg::with_name("identical-ok")
+                        .long("identical-ok")
+                        .help("Exits with 1 if the slots differ, and 0 if they're identical."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("show-slot")
+                .about("Shows the save files in the given slot, or @profile.")
+                .arg(slot.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("load-save-file")
+                .about("Loads a directory in the Save Files folder by its index or name.")
+                .arg(
+                    Arg::with_name("name")
+                        .value_name("name")
+                        .required(true)
+                        .help("The index of the directory to load in list-save-files, or part of its name, ignoring case."),
+                )
+                .arg(
+                    Arg::with_name("pick")
+                        .long("pick")
+                        .value_name("n")
+                        .takes_value(true)
+                        .help("Which of the directories matching the name to load, if several do."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list-save-files")
+                .about("Lists the directories in the Save Files folder with the index load-save-file accepts."),
+        )
+        .subcommand(
+            SubCommand::with_name("save-dated")
+                .about("Saves the current save files in a dated slot.")
+                .arg(note)
+                .arg(tag),
+        )
+        .subcommand(
+            SubCommand::with_name("pin")
+                .about("Protects a slot from being deleted or replaced.")
+                .arg(slot.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("unpin")
+                .about("Removes the protection added by pin.")
+                .arg(slot.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("set-note")
+                .about("Sets the note of a slot.")
+                .arg(slot.clone())
+                .arg(
+                    Arg::with_name("note")
+                        .value_name("note")
+                        .required(true)
+                        .help("The new note."),
+                ),
+        )
+        .subcommand(SubCommand::with_name("clear").about("Removes the current save files."))
+        .subcommand(
+            SubCommand::with_name("undo").about(
+                "Undoes the last load, clear or deletion of slots, as recorded in the history.",
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("stash")
+                .about("Moves the current save files onto the stash, leaving the profile empty.")
+                .arg(
+                    Arg::with_name("load")
+                        .long("load")
+                        .value_name("slot")
+                        .takes_value(true)
+                        .help("Loads this slot into the profile once the save files are stashed."),
+                ),
+        )
+        .subcommand(SubCommand::with_name("pop").about(
+            "Restores the most recently stashed save files and removes them from the stash.",
+        ))
+        .subcommand(
+            SubCommand::with_name("stash-list")
+                .about("Lists the stash, most recently stashed first."),
+        )
+        .subcommand(
+            SubCommand::with_name("backup-to")
+                .about(
+                    "Backs up the save files into a new dated directory outside of the GTA V directory.",
+                )
+                .arg(
+                    Arg::with_name("dir")
+                        .value_name("dir")
+                        .required(true)
+                        .help("The directory to keep the backups in, like on another drive."),
+                )
+                .arg(
+                    Arg::with_name("keep")
+                        .long("keep")
+                        .value_name("n")
+                        .takes_value(true)
+                        .validator(is_number)
+                        .help("The number of backups to keep, removing the oldest ones [default: 10]."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("restore-from")
+                .about("Restores the save files from a backup made by backup-to.")
+                .arg(
+                    Arg::with_name("dir")
+                        .value_name("dir")
+                        .required(true)
+                        .help("The directory the backups are kept in."),
+                )
+                .arg(
+                    Arg::with_name("timestamp")
+                        .help("The backup to restore, like 2019-10-30_180412, or the newest one."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("Lists all slots, newest first.")
+                .arg(
+                    Arg::with_name("filter-tag")
+                        .long("filter-tag")
+                        .value_name("tag")
+                        .takes_value(true)
+                        .help("Only lists slots with the given tag."),
+                )
+                .arg(
+                    Arg::with_name("search")
+                        .long("search")
+                        .value_name("term")
+                        .takes_value(true)
+                        .help("Only lists slots whose name, note or in-game titles contain a term."),
+                )
+                .arg(
+                    Arg::with_name("detail")
+                        .long("detail")
+                        .help("Lists the in-game title and save time of every save file, as recorded when the slot was saved."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list-profiles")
+                .about("Lists every profile with the index --profile accepts, and details which tell them apart."),
+        )
+        .subcommand(
+            SubCommand::with_name("transfer-profile")
+                .about("Replaces the save files in a profile with the ones in another, like when moving to a new account.")
+                .arg(
+                    Arg::with_name("source")
+                        .value_name("source")
+                        .required(true)
+                        .help("The index or part of the name of the profile to copy the save files from."),
+                )
+                .arg(
+                    Arg::with_name("destination")
+                        .value_name("destination")
+                        .required(true)
+                        .help("The index or part of the name of the profile to copy the save files to, which are backed up first."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("watch")
+                .about("Saves dated slots whenever the game writes its save files.")
+                .arg(
+                    Arg::with_name("debounce")
+                        .long("debounce")
+                        .value_name("seconds")
+                        .takes_value(true)
+                        .validator(is_number)
+                        .help("How long to wait for the game to finish writing. Defaults to 5."),
+                )
+                .arg(
+                    Arg::with_name("watch-interval")
+                        .long("watch-interval")
+                        .value_name("seconds")
+                        .takes_value(true)
+                        .validator(is_number)
+                        .help("The minimum time between two saved slots. Defaults to 60."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Launches the game and saves a dated slot when it exits.")
+                .arg(
+                    Arg::with_name("executable")
+                        .value_name("executable")
+                        .help("The game or launcher to run. Defaults to the launcher of GTA V where it's installed, or Steam."),
+                )
+                .arg(
+                    Arg::with_name("snapshot-before")
+                        .long("snapshot-before")
+                        .help("Also saves a dated slot before launching the game."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("hotkeys")
+                .about("Saves and loads when the hotkeys in the config file are pressed, on Windows."),
+        )
+        .subcommand(
+            SubCommand::with_name("tray")
+                .about("Shows an icon in the notification area with a menu to save and load, on Windows."),
+        )
+        .subcommand(
+            SubCommand::with_name("daemon").about(
+                "Runs commands sent with --send from other instances, on Windows.",
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Serves HTTP requests to save and load on 127.0.0.1, for Stream Deck or OBS.")
+                .arg(
+                    Arg::with_name("port")
+                        .value_name("port")
+                        .validator(is_port)
+                        .help("The port to listen on. Defaults to 7373."),
+                )
+                .arg(
+                    Arg::with_name("token")
+                        .long("token")
+                        .value_name("token")
+                        .takes_value(true)
+                        .help("Refuses requests which don't have this in their X-Token header."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stop-watch")
+                .about("Stops a running watch, hotkeys, tray, serve or daemon on Windows."),
+        )
+        .subcommand(
+            SubCommand::with_name("load-newest")
+                .about("Loads the nth newest slot.")
+                .arg(nth.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("load-at")
+                .about("Loads the dated slot saved last at or before the given time.")
+                .arg(
+                    Arg::with_name("time")
+                        .required(true)
+                        .help("A local time like '2024-03-01 21:00', '2024-03-01', '21:00', 'yesterday', or how long ago like '2h'."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("delete-newest")
+                .about("Deletes the nth newest slot.")
+                .arg(nth),
+        )
+        .subcommand(
+            SubCommand::with_name("delete-slot")
+                .about("Deletes the given slot.")
+                .arg(slot.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("prune-dated")
+                .about("Deletes all but the newest dated slots.")
+                .arg(
+                    Arg::with_name("keep")
+                        .long("keep")
+                        .value_name("n")
+                        .takes_value(true)
+                        .validator(is_number)
+                        .help("The number of dated slots to keep."),
+                )
+                .arg(
+                    Arg::with_name("keep-days")
+                        .long("keep-days")
+                        .value_name("days")
+                        .takes_value(true)
+                        .validator(is_number)
+                        .help("Keeps the dated slots modified within this many days."),
+                )
+                .arg(
+                    Arg::with_name("policy")
+                        .long("policy")
+                        .help("Keeps fewer dated slots the older they are."),
+                )
+                .group(ArgGroup::with_name("retention").args(&["keep", "keep-days", "policy"])),
+        )
+        .subcommand(
+            SubCommand::with_name("rename-slot")
+                .about("Renames a slot.")
+                .arg(from.clone())
+                .arg(to.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("copy-slot")
+                .about("Copies a slot into a new slot.")
+                .arg(from)
+                .arg(to),
+        )
+        .subcommand(
+            SubCommand::with_name("export-slot")
+                .about("Exports a slot, or @profile, to a zip archive.")
+                .arg(slot.clone())
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("file")
+                        .help("The archive to write. Defaults to <slot>-<date>.zip."),
+                )
+                .arg(
+                    Arg::with_name("encrypt")
+                        .long("encrypt")
+                        .help("Encrypts the archive with a passphrase, which is asked for."),
+                )
+                .arg(passphrase_file.clone().requires("encrypt")),
+        )
+        .subcommand(
+            SubCommand::with_name("import-slot")
+                .about("Imports a zip archive into a new slot.")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("file")
+                        .required(true)
+                        .help("The archive to import."),
+                )
+                .arg(
+                    Arg::with_name("slot")
+                        .value_name("slot")
+                        .help("The name of the new slot. Defaults to the name of the archive."),
+                )
+                .arg(passphrase_file),
+        )
+        .subcommand(
+            SubCommand::with_name("bundle")
+                .about("Writes a slot into a single .gtavslot file to share it, along with its note and tags.")
+                .arg(slot.clone())
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("file")
+                        .help("The bundle to write. Defaults to <slot>.gtavslot."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("unbundle")
+                .about("Imports a .gtavslot file written by bundle into a new slot.")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("file")
+                        .required(true)
+                        .help("The bundle to import."),
+                )
+                .arg(
+                    Arg::with_name("slot")
+                        .value_name("slot")
+                        .help("The name of the new slot. Defaults to the name of the bundled slot."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("load-bundle")
+                .about("Loads the save files in a .gtavslot file written by bundle into the profile, backing up the current ones first.")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("file")
+                        .required(true)
+                        .help("The bundle to load."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("load-url")
+                .about("Downloads a .gtavslot file or a zip archive of save files, and loads it into the profile after backing up the current ones.")
+                .arg(
+                    Arg::with_name("url")
+                        .value_name("url")
+                        .required(true)
+                        .help("The https:// URL to download."),
+                )
+                .arg(
+                    Arg::with_name("as-slot")
+                        .long("as-slot")
+                        .value_name("slot")
+                        .takes_value(true)
+                        .help("Imports the download into a new slot with this name instead of loading it."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("push")
+                .about("Uploads a slot as a .gtavslot file to the remote in the config file.")
+                .arg(slot.clone())
+                .arg(
+                    Arg::with_name("name")
+                        .value_name("name")
+                        .help("The name to upload it as. Defaults to the name of the slot."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("pull")
+                .about("Downloads a .gtavslot file from the remote in the config file into a new slot.")
+                .arg(
+                    Arg::with_name("name")
+                        .value_name("name")
+                        .required(true)
+                        .help("The name it was uploaded as."),
+                )
+                .arg(
+                    Arg::with_name("slot")
+                        .value_name("slot")
+                        .help("The name of the new slot. Defaults to the name it was uploaded as."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("remote-list")
+                .about("Lists the .gtavslot files on the remote in the config file, with their sizes and when they were uploaded."),
+        )
+        .subcommand(
+            SubCommand::with_name("export-all")
+                .about("Exports every slot of every profile into a single zip archive.")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("file")
+                        .required(true)
+                        .help("The archive to write."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import-all")
+                .about("Imports the slots in an archive written by export-all into the profiles they were exported from.")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("file")
+                        .required(true)
+                        .help("The archive to import."),
+                )
+                .arg(
+                    Arg::with_name("map-profile")
+                        .long("map-profile")
+                        .value_name("old=new")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .validator(is_profile_map)
+                        .help("Imports the slots of the profile named old in the archive into the profile matching new, which is an index or part of its name. Can be used more than once."),
+                )
+                .arg(
+                    Arg::with_name("overwrite")
+                        .long("overwrite")
+                        .help("Replaces slots which already exist, instead of skipping them."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("show-space")
+                .about("Prints how much space the slots of every profile take up, largest first."),
+        )
+        .subcommand(
+            SubCommand::with_name("fsck")
+                .about("Checks every file in every slot against the manifest of the slot.")
+                .arg(
+                    Arg::with_name("adopt")
+                        .long("adopt")
+                        .help("Writes a manifest for slots which don't have one, describing the files in them now."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("repair")
+                .about("Replaces save files in the given slot which don't match its manifest with identical copies from other slots.")
+                .arg(slot.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("dedup")
+                .about("Replaces identical save files in different slots with hard links."),
+        )
+        .subcommand(
+            SubCommand::with_name("recompress-slots")
+                .about("Compresses the save files in every slot which aren't compressed yet."),
+        )
+        .subcommand(
+            SubCommand::with_name("alias")
+                .about("Manages aliases, which can be used as @alias in place of a slot name.")
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Makes an alias refer to a slot, which doesn't have to exist yet.")
+                        .arg(alias.clone())
+                        .arg(slot),
+                )
+                .subcommand(SubCommand::with_name("list").about("Lists all aliases."))
+                .subcommand(
+                    SubCommand::with_name("remove")
+                        .about("Removes an alias.")
+                        .arg(alias),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sync")
+                .about("Syncs the slots with another directory in both directions.")
+                .arg(
+                    Arg::with_name("remote")
+                        .value_name("remote-dir")
+                        .required(true)
+                        .help(
+                            "The directory to sync with, like a cloud folder or a network share.",
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("Shows what was done in the profile, most recent first.")
+                .arg(
+                    Arg::with_name("n")
+                        .value_name("n")
+                        .validator(is_number)
+                        .help("The number of entries to show. Defaults to 10."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Checks that the save files can be found and saved, and explains what's wrong if they can't."),
+        )
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("Prints a script which completes commands and slot names in a shell.")
+                .arg(
+                    Arg::with_name("shell")
+                        .value_name("shell")
+                        .required(true)
+                        .possible_values(&["bash", "zsh", "powershell"])
+                        .help("The shell to complete in."),
+                ),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .global(true)
+                .help("Prints what would be done without touching any files."),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .global(true)
+                .conflicts_with("no-verify")
+                .help("Verifies that copied files match their source. Enabled by default."),
+        )
+        .arg(
+            Arg::with_name("no-verify")
+                .long("no-verify")
+                .global(true)
+                .help("Skips verifying that copied files match their source."),
+        )
+        .arg(
+            Arg::with_name("no-preserve-times")
+                .long("no-preserve-times")
+                .global(true)
+                .help("Gives copied files the current time as their modification time."),
+        )
+        .arg(
+            Arg::with_name("no-retry")
+                .long("no-retry")
+                .global(true)
+                .help("Fails right away if another process has a save file open."),
+        )
+        .arg(
+            Arg::with_name("no-cache")
+                .long("no-cache")
+                .global(true)
+                .help("Reads every save file when comparing or deduplicating slots, instead of using cached hashes."),
+        )
+        .arg(
+            Arg::with_name("respect-readonly")
+                .long("respect-readonly")
+                .global(true)
+                .help("Leaves read-only save files alone instead of replacing or deleting them."),
+        )
+        .arg(
+            Arg::with_name("only")
+                .long("only")
+                .value_name("n")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(is_save_number)
+                .global(true)
+                .help("Only saves, loads or clears the save files of this in-game save slot, like 0 for SGTA00000. Can be used multiple times."),
+        )
+        .arg(
+            Arg::with_name("min-save-size")
+                .long("min-save-size")
+                .value_name("bytes")
+                .takes_value(true)
+                .validator(is_number)
+                .global(true)
+                .help("Warns about save files smaller than this when saving and verifying, since they might be truncated. Defaults to 65536."),
+        )
+        .arg(
+            Arg::with_name("hydrate-timeout")
+                .long("hydrate-timeout")
+                .value_name("seconds")
+                .takes_value(true)
+                .validator(is_number)
+                .global(true)
+                .help("How long to wait for save files which are only stored in OneDrive to be downloaded. Defaults to 120."),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .global(true)
+                .help("Refuses to save if any of the save files are smaller than --min-save-size."),
+        )
+        .arg(
+            Arg::with_name("strict-version")
+                .long("strict-version")
+                .global(true)
+                .help("Refuses to load slots saved with another version of the game, instead of warning about it."),
+        )
+        .arg(
+            Arg::with_name("include")
+                .long("include")
+                .value_name("pattern")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help("Counts the files matching this glob pattern as save files, instead of SGTA* or the save files of the game picked with --game. Can be used multiple times."),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .long("exclude")
+                .value_name("pattern")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help("Doesn't count the files matching this glob pattern as save files, like '*.bak'. Can be used multiple times."),
+        )
+        .arg(
+            Arg::with_name("include-settings")
+                .long("include-settings")
+                .global(true)
+                .help("Also saves, loads and verifies the settings in the profile, like pc_settings.bin. Clearing never removes them."),
+        )
+        .arg(
+            Arg::with_name("permanent")
+                .long("permanent")
+                .global(true)
+                .help("Deletes files permanently instead of moving them to the Recycle Bin."),
+        )
+        .arg(
+            Arg::with_name("backup")
+                .long("backup")
+                .global(true)
+                .conflicts_with("no-backup")
+                .help("Backs up the current save files before replacing them. Enabled by default."),
+        )
+        .arg(
+            Arg::with_name("no-backup")
+                .long("no-backup")
+                .global(true)
+                .help("Skips backing up the current save files before replacing them."),
+        )
+        .arg(Arg::with_name("force").long("force").global(true).help(
+            "Allows replacing non-empty slots or changed save files, or deleting unexpected files.",
+        ))
+        .arg(
+            Arg::with_name("force-unpin")
+                .long("force-unpin")
+                .global(true)
+                .help("Allows deleting and replacing pinned slots."),
+        )
+        .arg(
+            Arg::with_name("fuzzy")
+                .long("fuzzy")
+                .global(true)
+                .help("Uses the slot closest to a slot name which doesn't exist, if only one is close to it."),
+        )
+        .arg(
+            Arg::with_name("ignore-running")
+                .long("ignore-running")
+                .global(true)
+                .help("Allows loading, clearing and deleting while the game is running."),
+        )
+        .arg(
+            Arg::with_name("fail-fast")
+                .long("fail-fast")
+                .global(true)
+                .help("Stops at the first profile which fails, instead of continuing."),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .long("yes")
+                .short("y")
+                .global(true)
+                .help("Deletes files without asking for confirmation."),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .long("verbose")
+                .short("v")
+                .global(true)
+                .help("Prints additional details about what's being done, and sets --log-level to debug."),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .long("quiet")
+                .short("q")
+                .global(true)
+                .conflicts_with_all(&["verbose", "format"])
+                .help("Prints nothing, not even errors, so that the exit code says what happened. Everything is still written to the log file. Without a console, errors are still shown in a message box unless --no-notify is given."),
+        )
+        .arg(
+            Arg::with_name("log-level")
+                .long("log-level")
+                .value_name("level")
+                .takes_value(true)
+                .global(true)
+                .possible_values(&["error", "warn", "info", "debug", "trace"])
+                .help("How much is printed, from only errors to every file looked at. At debug, every filesystem call is printed. The log file always gets what was done. Defaults to info."),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("format")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .global(true)
+                .help("Prints a single JSON document describing what was done. Defaults to text."),
+        )
+        .arg(
+            Arg::with_name("no-color")
+                .long("no-color")
+                .global(true)
+                .help("Prints without colors, which are otherwise used in a terminal unless NO_COLOR is set. JSON is never colored."),
+        )
+        .arg(
+            Arg::with_name("no-notify")
+                .long("no-notify")
+                .global(true)
+                .help("Skips the notification saying what was done when there's no console."),
+        )
+        .arg(
+            Arg::with_name("send")
+                .long("send")
+                .global(true)
+                .help("Sends the command to a running daemon, or runs it here if none is running."),
+        )
+        .arg(
+            Arg::with_name("console")
+                .long("console")
+                .global(true)
+                .help("Attaches to or opens a console to show output in."),
+        )
+        .arg(
+            Arg::with_name("interactive")
+                .long("interactive")
+                .conflicts_with("quiet")
+                .help("Picks the profile, command and slot to run it on in the terminal. Used when run from a terminal without arguments."),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .value_name("file")
+                .takes_value(true)
+                .global(true)
+                .help("The config file to read defaults from."),
+        )
+        .arg(
+            Arg::with_name("print-config")
+                .long("print-config")
+                .global(true)
+                .help("Prints the settings in effect and where each of them came from."),
+        )
+        .arg(
+            Arg::with_name("base-path")
+                .long("base-path")
+                .value_name("dir")
+                .takes_value(true)
+                .global(true)
+                .env("GTAV_SAVE_DIR")
+                .help("The GTA V directory containing Profiles."),
+        )
+        .arg(
+            Arg::with_name("edition")
+                .long("edition")
+                .value_name("edition")
+                .takes_value(true)
+                .global(true)
+                .possible_values(&["legacy", "enhanced", "all"])
+                .help("The edition of the game to use the profiles of. Defaults to the first one found, legacy before enhanced."),
+        )
+        .arg(
+            Arg::with_name("game")
+                .long("game")
+                .value_name("name")
+                .takes_value(true)
+                .global(true)
+                .help("The game to manage the save files of, gtav, rdr2 or one from the config file. Defaults to gtav."),
+        )
+        .arg(
+            Arg::with_name("game-path")
+                .long("game-path")
+                .value_name("dir")
+                .takes_value(true)
+                .global(true)
+                .help("The directory the game is installed in. Defaults to looking it up in the registry and Steam."),
+        )
+        .arg(
+            Arg::with_name("game-version")
+                .long("game-version")
+                .value_name("version")
+                .takes_value(true)
+                .global(true)
+                .help("The version of the game, which is recorded in the slots saved. Defaults to the version of GTA5.exe where the game is installed."),
+        )
+        .arg(
+            Arg::with_name("slots-dir")
+                .long("slots-dir")
+                .value_name("dir")
+                .takes_value(true)
+                .global(true)
+                .help("Stores slots in this directory instead of in each profile."),
+        )
+        .arg(
+            Arg::with_name("shared-slots")
+                .long("shared-slots")
+                .global(true)
+                .help("Shares the slots in --slots-dir between all profiles."),
+        )
+        .arg(
+            Arg::with_name("compress")
+                .long("compress")
+                .global(true)
+                .conflicts_with("no-compress")
+                .help(
+                    "Compresses the save files in dated slots, including the ones saved by watch.",
+                ),
+        )
+        .arg(
+            Arg::with_name("no-compress")
+                .long("no-compress")
+                .global(true)
+                .help("Stores the save files in dated slots uncompressed. The default."),
+        )
+        .arg(
+            Arg::with_name("compression-level")
+                .long("compression-level")
+                .value_name("n")
+                .takes_value(true)
+                .global(true)
+                .validator(is_compression_level)
+                .help(
+                    "The zstd compression level from 1 to 22 used when compressing. Defaults to 3.",
+                ),
+        )
+        .arg(
+            Arg::with_name("date-format")
+                .long("date-format")
+                .value_name("format")
+                .takes_value(true)
+                .global(true)
+                .help("The strftime format of {datetime} in the names of dated slots. Defaults to %Y-%m-%d_%H%M%S."),
+        )
+        .arg(
+            Arg::with_name("dated-name")
+                .long("dated-name")
+                .value_name("template")
+                .takes_value(true)
+                .global(true)
+                .help("The names of dated slots, with {datetime}, {date}, {time}, {note} and {seq} replaced. Defaults to dated-{datetime}."),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .value_name("name-or-index")
+                .takes_value(true)
+                .global(true)
+                .help("Only operate on the profile with the given index or name."),
+        )
+        .arg(
+            Arg::with_name("all-profiles")
+                .long("all-profiles")
+                .global(true)
+                .conflicts_with("profile")
+                .help("Operate on all profiles."),
+        )
+        .args(&legacy_flags())
+        .group(
+            ArgGroup::with_name("legacy")
+                .args(&[
+                    "save",
+                    "load",
+                    "load-save-file",
+                    "save-dated",
+                    "clear-profile",
+                    "list-slots",
+                    "load-nth-newest-slot",
+                    "delete-nth-newest-slot",
+                ])
+                .multiple(false),
+        )
+}
+
+/// Flags used before subcommands were introduced, which are kept hidden for compatibility.
+fn legacy_flags() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("save")
+            .long("save")
+            .value_name("slot")
+            .takes_value(true),
+        Arg::with_name("load")
+            .long("load")
+            .value_name("slot")
+            .takes_value(true),
+        Arg::with_name("load-save-file")
+            .long("load-save-file")
+            .value_name("slot")
+            .takes_value(true),
+        Arg::with_name("save-dated").long("save-dated"),
+        Arg::with_name("clear-profile").long("clear-profile"),
+        Arg::with_name("list-slots").long("list-slots"),
+        Arg::with_name("load-nth-newest-slot")
+            .long("load-nth-newest-slot")
+            .value_name("nth")
+            .takes_value(true),
+        Arg::with_name("delete-nth-newest-slot")
+            .long("delete-nth-newest-slot")
+            .value_name("nth")
+            .takes_value(true),
+    ]
+    .into_iter()
+    .map(|arg| arg.hidden(true))
+    .collect()
+}
+
+fn main() {
+    // Since we're built for the windows subsystem, there's no console to write to unless we
+    // explicitly attach to one. This has to happen before arguments are parsed so that usage
+    // errors are visible.
+    let console = platform::attach_console(env::args_os().any(|a| a == "--console"));
+
+    // Without a console, progress is only written to the log file.
+    output::show_progress(if !console {
+        ProgressStyle::Log
+    } else if io::stderr().is_terminal() {
+        ProgressStyle::Bar
+    } else {
+        ProgressStyle::Hidden
+    });
+
+    cancel_on_ctrl_c();
+
+    let result = try_main(console, app().get_matches());
+
+    if let Some(captured) = output::take_captured() {
+        let document = Document::new(captured, result.as_ref().err());
+
+        match serde_json::to_string_pretty(&document) {
+            Ok(json) => println!("{}", json),
+            Err(e) => error!("error: failed to serialize output: {}", e),
+        }
+    }
+
+    if let Err(e) = result {
+        error!("error: {}", e);
+
+        // The user already saw a message box when they aborted.
+        if !console && !matches!(e, Error::Aborted) {
+            if NOTIFY.load(Ordering::SeqCst) {
+                notify("GTA V SaveLoad Helper", &e.to_string(), true);
+            } else if ERROR_BOX.load(Ordering::SeqCst) {
+                platform::error_box("GTA V SaveLoad Helper", &e.to_string());
+            }
+        }
+
+        process::exit(e.exit_code());
+    }
+}
+
+/// The single document printed to stdout for `--format json`, describing what the command did.
+#[derive(Debug, Serialize)]
+struct Document {
+    /// Either `ok` or `error`.
+    status: &'static str,
+    /// Structured results, like the slots listed by `list`.
+    results: Vec<serde_json::Value>,
+    /// The filesystem operations which were performed, or would have been in a dry run.
+    operations: Vec<FileOperation>,
+    /// The lines which would have been printed without `--format json`.
+    messages: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorDocument>,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorDocument {
+    /// A stable name for the kind of error, like `slot_not_found`.
+    kind: &'static str,
+    message: String,
+    exit_code: i32,
+}
+
+impl Document {
+    fn new(captured: Captured, error: Option<&Error>) -> Self {
+        Self {
+            status: if error.is_some() { "error" } else { "ok" },
+            results: captured.results,
+            operations: captured.operations,
+            messages: captured.messages,
+            error: error.map(|e| ErrorDocument {
+                kind: e.kind(),
+                message: e.to_string(),
+                exit_code: e.exit_code(),
+            }),
+        }
+    }
+}
+
+fn try_main(console: bool, matches: ArgMatches<'static>) -> Result<(), Error> {
+    output::set_level(log_level(&matches));
+
+    let quiet = matches.is_present("quiet");
+    output::set_quiet(quiet);
+    ERROR_BOX.store(!quiet || !matches.is_present("no-notify"), Ordering::SeqCst);
+
+    // The progress bar would be drawn on stderr, so progress is written to the log instead.
+    if quiet {
+        output::show_progress(ProgressStyle::Log);
+    }
+
+    let json = matches.value_of("format") == Some("json");
+    output::set_color(console && !json && !matches.is_present("no-color"));
+
+    if json {
+        output::capture();
+    }
+
+    if matches.is_present("send") && send_to_daemon(&matches)? {
+        return Ok(());
+    }
+
+    let settings = Settings::load(&matches)?;
+    NOTIFY.store(
+        !console && !quiet && settings.notify.value,
+        Ordering::SeqCst,
+    );
+    ERROR_BOX.store(!quiet || settings.notify.value, Ordering::SeqCst);
+
+    if let Some(path) = &settings.game_path.value {
+        game::set_game_path(path);
+    }
+
+    if let Some(version) = matches.value_of("game-version") {
+        game::set_game_version(version);
+    }
+
+    game::set_process(&settings.game.value.process);
+
+    if matches.is_present("print-config") {
+        print_config(&settings);
+        return Ok(());
+    }
+
+    if wants_picker(&matches, console) {
+        let args = pick_command(&settings, &matches)?;
+        return try_main(console, app().get_matches_from(args));
+    }
+
+    match matches.subcommand() {
+        ("completions", Some(m)) => {
+            print_completions(m.value_of("shell").unwrap_or_default());
+            return Ok(());
+        }
+        (COMPLETE_SLOTS, Some(..)) => {
+            print_slot_names(&settings);
+            return Ok(());
+        }
+        ("doctor", Some(..)) => return doctor(&settings),
+        ("remote-list", Some(..)) => return print_remote(&remote(&settings)?),
+        _ => (),
+    }
+
+    let action = match Action::from_matches(&matches, &settings)? {
+        Some(action) => action,
+        None => {
+            report!("{}", matches.usage());
+            return Ok(());
+        }
+    };
+
+    let dirs = game_dirs(&settings)?;
+
+    // The log and the locks are kept in the first directory, whichever profiles are selected.
+    let base = dirs[0].path.clone();
+
+    if let Err(e) = gtav_helpers::open_log(&base) {
+        eout!("Failed to open log file: {}", e);
+    }
+
+    let existing_profiles = list_game_profiles(&dirs)?;
+
+    // The indexes printed have to be the ones of every profile, whichever is selected.
+    if let Action::ListProfiles = action {
+        return print_profile_details(&existing_profiles, &dirs, &settings);
+    }
+
+    // Transferring runs in the destination, whichever profile is selected otherwise.
+    let selector = match action {
+        Action::TransferProfile(_, destination) => Some(destination),
+        _ => settings.profile.value.as_deref(),
+    };
+
+    let existing_profiles = select_profiles(
+        existing_profiles,
+        selector,
+        matches.is_present("all-profiles"),
+        action.is_destructive(),
+    )?;
+
+    // Commands which run for a long time only lock while they're saving.
+    let _lock = match action {
+        Action::Watch(..)
+        | Action::Play(..)
+        | Action::Hotkeys(..)
+        | Action::Tray
+        | Action::Serve(..)
+        | Action::Daemon
+        | Action::StopWatch => None,
+        _ => Some(gtav_helpers::lock_instance(&base, LOCK_WAIT)?),
+    };
+
+    check_game(&action, matches.is_present("ignore-running"))?;
+
+    let confirm = Confirm {
+        console,
+        yes: matches.is_present("yes") || matches.is_present("dry-run"),
+        json: output::capturing(),
+    };
+
+    let passphrase = match matches.subcommand() {
+        _ if matches.is_present("dry-run") => None,
+        ("export-slot", Some(m)) if m.is_present("encrypt") => {
+            Some(read_passphrase(m, console, true)?)
+        }
+        ("import-slot", Some(m)) => {
+            let file = Path::new(m.value_of_os("file").unwrap_or_default());
+
+            if gtav_helpers::is_encrypted_archive(file)? {
+                Some(read_passphrase(m, console, false)?)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+
+    // Validated by clap.
+    let save_numbers = matches
+        .values_of("only")
+        .into_iter()
+        .flatten()
+        .flat_map(str::parse::<u32>)
+        .collect::<Vec<_>>();
+
+    let patterns = SaveFilePatterns::new(&settings.include.value, &settings.exclude.value)?;
+
+    let managers = existing_profiles.into_iter().map(|profile| {
+        let manager = SaveManager::new(&profile)
+            .game(&settings.game.value)
+            .edition(edition_of(&dirs, &profile))
+            .dry_run(matches.is_present("dry-run"))
+            .verify(settings.verify_checksums.value)
+            .preserve_times(!matches.is_present("no-preserve-times"))
+            .backup(settings.backup_before_load.value)
+            .force(matches.is_present("force"))
+            .force_unpin(matches.is_present("force-unpin"))
+            .fuzzy(matches.is_present("fuzzy"))
+            .retry(!matches.is_present("no-retry"))
+            .hash_cache(!matches.is_present("no-cache"))
+            .respect_readonly(matches.is_present("respect-readonly"))
+            .permanent(matches.is_present("permanent"))
+            .only(save_numbers.iter().copied())
+            .include_settings(settings.include_settings.value)
+            .min_save_size(settings.min_save_size.value)
+            .strict(matches.is_present("strict"))
+            .strict_version(matches.is_present("strict-version"))
+            .hydrate_timeout(Duration::from_secs(settings.hydrate_timeout.value))
+            .cancel(&CANCEL)
+            .save_file_patterns(patterns.clone())
+            .verbose(matches.is_present("verbose"))
+            .date_format(settings.date_format.value.as_str())
+            .dated_name(settings.dated_name.value.as_str())
+            .shared_slots(settings.shared_slots.value)
+            .compress(settings.compress_dated.value)
+            .compression_level(settings.compression_level.value);
+
+        let manager = match &passphrase {
+            Some(passphrase) => manager.passphrase(passphrase.as_str()),
+            None => manager,
+        };
+
+        let manager = match game::detect_game_version() {
+            Some(version) => manager.game_version(version),
+            None => manager,
+        };
+
+        match &settings.slots_dir.value {
+            Some(dir) => manager.slots_dir(dir),
+            None => manager,
+        }
+    });
+
+    match action {
+        Action::Watch(..) => return watch(managers.collect(), &action, &confirm),
+        Action::Play(..) => return play(&base, &managers.collect::<Vec<_>>(), &action),
+        Action::Hotkeys(ref hotkeys) => {
+            let managers = managers.collect::<Vec<_>>();
+            let ignore_running = matches.is_present("ignore-running");
+            return listen_for_hotkeys(&base, &managers, hotkeys, &confirm, ignore_running);
+        }
+        Action::Tray => {
+            let managers = managers.collect::<Vec<_>>();
+            let ignore_running = matches.is_present("ignore-running");
+            return show_tray(&base, &managers, &confirm, ignore_running);
+        }
+        Action::Serve(port, token) => {
+            let managers = managers.collect::<Vec<_>>();
+            let ignore_running = matches.is_present("ignore-running");
+            return serve_requests(&base, &managers, port, token, &confirm, ignore_running);
+        }
+        Action::Daemon => {
+            let managers = managers.collect::<Vec<_>>();
+            let ignore_running = matches.is_present("ignore-running");
+            return run_daemon(&base, &managers, &settings, &confirm, ignore_running);
+        }
+        Action::ExportAll(file) => {
+            SaveManager::export_all(&managers.collect::<Vec<_>>(), file.as_ref())?;
+            return Ok(());
+        }
+        Action::ShowSpace => return print_space(&managers.collect::<Vec<_>>()),
+        Action::ImportAll(file, ref maps, overwrite) => {
+            let managers = managers.collect::<Vec<_>>();
+            return import_all(&managers, file.as_ref(), maps, overwrite, &confirm);
+        }
+        _ => (),
+    }
+
+    let managers = managers.collect::<Vec<_>>();
+
+    if matches.is_present("fail-fast") || managers.len() == 1 {
+        for manager in &managers {
+            run(manager, &action, &confirm)?;
+        }
+    } else {
+        run_all(&managers, &action, &confirm)?;
+    }
+
+    if NOTIFY.load(Ordering::SeqCst) {
+        if let Some(message) = describe_done(&managers, &action) {
+            notify("GTA V SaveLoad Helper", &message, false);
+        }
+    }
+
+    Ok(())
 }
 
-/// List files that contains the given name.
-fn list_name_contains(path: &Path, name: &str) -> Result<Vec<(String, PathBuf)>, Error> {
-    return find_matching(path, |p| p.is_dir(), |n| n.contains(name));
+/// Describe what the action did for a notification, like `Loaded slot 'foo' - 3 files, 1.2 MiB`,
+/// or `None` if it only reads.
+fn describe_done(managers: &[SaveManager], action: &Action<'_>) -> Option<String> {
+    let (command, name) = action.journal()?;
+    let manager = managers.first()?;
+
+    // The slot which was saved or loaded, which is read again for how big it is.
+    let slot = match *action {
+        Action::Save(slot, ..) => Some(("Saved", manager.slot(slot))),
+        Action::SaveDated(..) => Some(("Saved", manager.nth_newest_slot(1))),
+        Action::Load(slot) => Some(("Loaded", manager.slot(slot))),
+        Action::LoadNewest(nth) => Some(("Loaded", manager.nth_newest_slot(nth))),
+        Action::LoadAt(time) => Some(("Loaded", manager.dated_slot_at(time).map(|(s, _)| s))),
+        _ => None,
+    };
+
+    let message = match (slot, name) {
+        (Some((verb, Ok(slot))), _) => format!(
+            "{} slot '{}' \u{2014} {}, {}",
+            verb,
+            slot.name,
+            output::count(slot.files, "file"),
+            output::size(slot.size)
+        ),
+        (_, Some(name)) => format!("Ran {} on '{}'", command, name),
+        (_, None) => format!("Ran {}", command),
+    };
+
+    match managers.len() {
+        1 => Some(message),
+        n => Some(format!("{} in {} profiles", message, n)),
+    }
 }
 
-/// Find files matching the given predicate.
-fn find_matching<P, F>(path: &Path, p: P, m: F) -> Result<Vec<(String, PathBuf)>, Error>
-where
-    P: Copy + Fn(&Path) -> bool,
-    F: Copy + Fn(&str) -> bool,
-{
-    let mut out = Vec::new();
+/// Show a notification, warning about it if it can't be shown.
+fn notify(title: &str, message: &str, error: bool) {
+    if let Err(e) = tray::notify(title, message, error) {
+        eout!("warning: {}", e);
+    }
+}
 
-    for entry in fs::read_dir(&path)? {
-        let entry = entry?;
-        let path = entry.path();
+/// Run the action in every profile, continuing with the next profile if it fails, and print a
+/// summary of how it went in each of them at the end.
+fn run_all(managers: &[SaveManager], action: &Action<'_>, confirm: &Confirm) -> Result<(), Error> {
+    let mut results = Vec::new();
 
-        let name = match path.file_name().and_then(|n| n.to_str()) {
-            Some(name) => name.to_string(),
-            None => continue,
-        };
+    for manager in managers {
+        let result = run(manager, action, confirm);
+
+        if let Err(e) = &result {
+            error!("error: {}: {}", manager.profile().display(), e);
+        }
+
+        results.push((manager.profile(), result));
+    }
+
+    let mut errors = Vec::new();
 
-        if p(&path) && m(&name) {
-            out.push((name, path))
+    for (profile, result) in results {
+        let id = profile.file_name().unwrap_or(profile.as_os_str());
+
+        match result {
+            Ok(()) => out!("profile {}: ok", id.to_string_lossy()),
+            Err(e) => {
+                out!("profile {}: failed - {}", id.to_string_lossy(), e);
+                errors.push(e);
+            }
         }
     }
 
-    Ok(out)
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    Err(Error::ProfilesFailed {
+        total: managers.len(),
+        errors,
+    })
 }
 
-/// Ensure that the Slots directory exists and return it.
-fn ensure_slot(path: &Path) -> Result<PathBuf, Error> {
-    let slots = path.join("Slots");
+/// Refuse actions which conflict with the game while it's running, unless `ignore` is set.
+///
+/// Saving is allowed, but since the game might have progress which it hasn't saved yet, a
+/// warning is printed.
+fn check_game(action: &Action<'_>, ignore: bool) -> Result<(), Error> {
+    let saves = matches!(action, Action::Save(..) | Action::SaveDated(..));
+
+    if (!saves && !action.conflicts_with_game()) || !game::is_running() {
+        return Ok(());
+    }
+
+    if saves {
+        eout!(
+            "warning: {} is running and may have progress which isn't saved yet",
+            game::process()
+        );
+        return Ok(());
+    }
 
-    if !slots.is_dir() {
-        fs::create_dir(&slots)?;
+    if ignore {
+        eout!("warning: {} is running", game::process());
+        return Ok(());
     }
 
-    Ok(slots)
+    Err(Error::GameRunning {
+        process: game::process().to_string(),
+    })
+}
+
+/// Watch every profile at the same time until Ctrl+C is pressed or `stop-watch` is used.
+fn watch(managers: Vec<SaveManager>, action: &Action<'_>, confirm: &Confirm) -> Result<(), Error> {
+    stop_on_ctrl_c();
+    platform::listen_for_stop(&STOP);
+
+    thread::scope(|s| {
+        let threads = managers
+            .iter()
+            .map(|manager| s.spawn(move || run(manager, action, confirm)))
+            .collect::<Vec<_>>();
+
+        let mut result = Ok(());
+
+        for thread in threads {
+            let thread_result = thread.join().unwrap_or(Err(Error::Aborted));
+
+            // Stop watching the other profiles if one of them fails.
+            if thread_result.is_err() {
+                STOP.store(true, Ordering::SeqCst);
+            }
+
+            result = result.and(thread_result);
+        }
+
+        result
+    })
 }
 
-/// Copy save files from `from`, to `to`, deleting any existing save files in `to` in the process.
-fn copy_save_files(from: &Path, to: &Path) -> Result<(), Error> {
-    delete_save_files(&to)?;
+/// Launch the game once, and save a dated slot in every profile when it exits. If requested,
+/// a dated slot is saved before the game is launched as well.
+///
+/// If waiting is interrupted with Ctrl+C, the dated slot is saved right away.
+fn play(base: &Path, managers: &[SaveManager], action: &Action<'_>) -> Result<(), Error> {
+    let (game, before) = match *action {
+        Action::Play(game, before) => (game, before),
+        _ => return Ok(()),
+    };
+
+    if before {
+        let _lock = gtav_helpers::lock_instance(base, LOCK_WAIT)?;
+
+        for manager in managers {
+            snapshot(manager, "session started")?;
+        }
+    }
+
+    stop_on_ctrl_c();
+    let child = game::launch(game.map(Path::new))?;
+    out!("waiting for {} to exit", game::process());
 
-    for (_, save_file) in list_save_files(&from)? {
-        if let Some(file_name) = save_file.file_name() {
-            let dest = to.join(file_name);
-            println!("{} -> {}", save_file.display(), dest.display());
-            fs::copy(save_file, &dest)?;
+    let what = match game::wait_for_exit(child, &STOP) {
+        SessionEnd::Exited => "session ended",
+        SessionEnd::Interrupted => "session interrupted",
+        SessionEnd::NeverStarted => {
+            eout!("warning: {} was never started", game::process());
+            return Ok(());
         }
+    };
+
+    let _lock = gtav_helpers::lock_instance(base, LOCK_WAIT)?;
+
+    for manager in managers {
+        snapshot(manager, what)?;
     }
 
     Ok(())
 }
 
-/// Find the nth newest slot.
-fn find_newest_slot(profile: &Path, nth: usize) -> Result<Option<PathBuf>, Error> {
-    let slots = ensure_slot(&profile)?;
-    let slots = find_matching(&slots, |p| p.is_dir(), |_| true)?;
+/// Listen for the hotkeys until Ctrl+C is pressed or `stop-watch` is used, running the action of
+/// each one in every profile when it's pressed.
+///
+/// Since the game has focus while they're pressed, a sound tells if the action succeeded or
+/// failed, and what happened is written to the log.
+fn listen_for_hotkeys(
+    base: &Path,
+    managers: &[SaveManager],
+    hotkeys: &[(HotkeyAction, Hotkey)],
+    confirm: &Confirm,
+    ignore_running: bool,
+) -> Result<(), Error> {
+    let _lock = gtav_helpers::lock_hotkeys(base)?;
 
-    let mut slots_and_meta = slots
-        .into_iter()
-        .map(|s| {
-            let meta = fs::metadata(&s.1)?;
-            Ok((s.1, meta.modified()?))
+    stop_on_ctrl_c();
+    platform::listen_for_stop(&STOP);
+
+    let keys = hotkeys.iter().map(|(_, h)| h.clone()).collect::<Vec<_>>();
+
+    let listed = hotkeys
+        .iter()
+        .map(|(action, hotkey)| format!("{} ({})", hotkey, action))
+        .collect::<Vec<_>>();
+
+    out!("listening for hotkeys: {}", listed.join(", "));
+
+    hotkey::listen(&keys, &STOP, |index| {
+        let (action, hotkey) = &hotkeys[index];
+        out!("{} pressed, running {}", hotkey, action);
+
+        let action = match action {
+            HotkeyAction::SaveDated => Action::SaveDated(Annotations::default()),
+            HotkeyAction::LoadNewest => Action::LoadNewest(1),
+        };
+
+        let result = check_game(&action, ignore_running).and_then(|()| {
+            let _lock = gtav_helpers::lock_instance(base, LOCK_WAIT)?;
+            managers.iter().try_for_each(|m| run(m, &action, confirm))
+        });
+
+        match result {
+            Ok(()) => platform::beep(true),
+            Err(e) => {
+                error!("error: {}", e);
+                platform::beep(false);
+            }
+        }
+    })
+}
+
+/// Show the tray icon until "Exit" is picked in its menu, Ctrl+C is pressed or `stop-watch` is
+/// used, running what's picked in every profile.
+///
+/// The menu lists the slots of the first profile, and what happened is shown as a notification as
+/// well as written to the log.
+fn show_tray(
+    base: &Path,
+    managers: &[SaveManager],
+    confirm: &Confirm,
+    ignore_running: bool,
+) -> Result<(), Error> {
+    stop_on_ctrl_c();
+    platform::listen_for_stop(&STOP);
+
+    let slots = || {
+        let slots = match managers.first().map(SaveManager::list_slots) {
+            Some(Ok(slots)) => slots,
+            Some(Err(e)) => {
+                error!("error: {}", e);
+                return Vec::new();
+            }
+            None => return Vec::new(),
+        };
+
+        slots
+            .into_iter()
+            .take(tray::MENU_SLOTS)
+            .map(|s| s.name)
+            .collect()
+    };
+
+    out!("showing the tray icon");
+
+    tray::run(TRAY_TOOLTIP, &STOP, slots, |command| {
+        let (action, done) = match command {
+            TrayCommand::SaveDated => (
+                Action::SaveDated(Annotations::default()),
+                String::from("saved a dated slot"),
+            ),
+            TrayCommand::LoadNewest => (
+                Action::LoadNewest(1),
+                String::from("loaded the newest slot"),
+            ),
+            TrayCommand::Load(slot) => (Action::Load(slot), format!("loaded {}", slot)),
+        };
+
+        let result = check_game(&action, ignore_running).and_then(|()| {
+            let _lock = gtav_helpers::lock_instance(base, LOCK_WAIT)?;
+            managers.iter().try_for_each(|m| run(m, &action, confirm))
+        });
+
+        if let Err(e) = &result {
+            error!("error: {}", e);
+        }
+
+        result.map(|()| done)
+    })
+}
+
+/// Serve requests on 127.0.0.1 until Ctrl+C is pressed or `stop-watch` is used, running what
+/// each one asks for in every profile.
+///
+/// Each response is the document `--format json` prints for the same command, with a status code
+/// which tells how it went.
+fn serve_requests(
+    base: &Path,
+    managers: &[SaveManager],
+    port: u16,
+    token: Option<&str>,
+    confirm: &Confirm,
+    ignore_running: bool,
+) -> Result<(), Error> {
+    stop_on_ctrl_c();
+    platform::listen_for_stop(&STOP);
+
+    out!("serving requests on http://127.0.0.1:{}", port);
+
+    serve::serve(port, token, &STOP, |request| {
+        let action = match route(request) {
+            Ok(action) => action,
+            Err(response) => return response,
+        };
+
+        output::capture();
+
+        let result = check_game(&action, ignore_running).and_then(|()| {
+            let _lock = gtav_helpers::lock_instance(base, LOCK_WAIT)?;
+            managers.iter().try_for_each(|m| run(m, &action, confirm))
+        });
+
+        let captured = output::take_captured().unwrap_or_default();
+        let document = Document::new(captured, result.as_ref().err());
+        let status = result.as_ref().map_or_else(serve::status_code, |()| 200);
+
+        match serde_json::to_string(&document) {
+            Ok(body) => Response::json(status, body),
+            Err(e) => Response::error(500, "serialize", &e.to_string()),
+        }
+    })
+}
+
+/// Pick the action a request to `serve` asks for, or the response which refuses it.
+fn route(request: &Request) -> Result<Action<'_>, Response> {
+    let slot = || match request.param("slot") {
+        Some(slot) => Ok(slot),
+        None => Err(Response::error(
+            400,
+            "missing_parameter",
+            "the slot parameter is missing",
+        )),
+    };
+
+    let action = match request.path() {
+        "/slots" => Action::List(Filter {
+            tag: None,
+            search: None,
+            detail: false,
+        }),
+        "/save" => Action::Save(slot()?, Annotations::default()),
+        "/save-dated" => Action::SaveDated(Annotations::default()),
+        "/load" => Action::Load(slot()?),
+        "/load-newest" => match request.param("nth").map(parse_index) {
+            Some(Ok(nth)) => Action::LoadNewest(nth),
+            Some(Err(e)) => return Err(Response::error(400, e.kind(), &e.to_string())),
+            None => Action::LoadNewest(1),
+        },
+        path => {
+            let message = format!("there's nothing at {}", path);
+            return Err(Response::error(404, "unknown_endpoint", &message));
+        }
+    };
+
+    let method = match action {
+        Action::List(..) => "GET",
+        _ => "POST",
+    };
+
+    if request.method() != method {
+        let message = format!("{} expects {}", request.path(), method);
+        return Err(Response::error(405, "method_not_allowed", &message));
+    }
+
+    Ok(action)
+}
+
+/// A command sent to the daemon, as a line of JSON.
+#[derive(Debug, Serialize, Deserialize)]
+struct SentCommand {
+    /// The arguments from the name of the command on, like `["load", "my-slot"]`.
+    args: Vec<String>,
+}
+
+/// Commands which can't be sent to the daemon, since they run for a long time, ask for a
+/// passphrase, or don't run in the selected profiles.
+const UNSENDABLE: &[&str] = &[
+    "watch",
+    "stop-watch",
+    "run",
+    "hotkeys",
+    "tray",
+    "serve",
+    "daemon",
+    "export-slot",
+    "import-slot",
+    "bundle",
+    "unbundle",
+    "load-bundle",
+    "export-all",
+    "import-all",
+    "doctor",
+    "remote-list",
+    "list-profiles",
+    "transfer-profile",
+    "completions",
+    COMPLETE_SLOTS,
+];
+
+/// Send the command to a running daemon and print what it did, returning `false` if it should
+/// be run here instead, because no daemon is running or the command can't be sent to it.
+fn send_to_daemon(matches: &ArgMatches<'_>) -> Result<bool, Error> {
+    let name = match matches.subcommand_name() {
+        Some(name) if !UNSENDABLE.contains(&name) => name,
+        _ => return Ok(false),
+    };
+
+    // Options before the command only apply if it's run here, the daemon uses its own.
+    let args = env::args_os()
+        .skip(1)
+        .skip_while(|arg| arg.as_os_str() != name)
+        .map(|arg| arg.into_string())
+        .collect::<Result<Vec<_>, _>>();
+
+    let args = match args {
+        Ok(args) => args,
+        Err(..) => return Ok(false),
+    };
+
+    let line = serde_json::json!(SentCommand { args }).to_string();
+
+    let reply = match pipe::send(&line)? {
+        Some(reply) => reply,
+        None => return Ok(false),
+    };
+
+    let document = serde_json::from_str::<serde_json::Value>(&reply).map_err(|e| Error::Pipe {
+        reason: format!("the daemon answered with invalid JSON: {}", e),
+    })?;
+
+    if output::take_captured().is_some() {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&document).unwrap_or(reply)
+        );
+    } else {
+        let messages = document["messages"].as_array().into_iter().flatten();
+
+        for message in messages.flat_map(serde_json::Value::as_str) {
+            out!("{}", message);
+        }
+    }
+
+    match document.get("error") {
+        Some(error) => Err(Error::DaemonFailed {
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+            exit_code: error["exit_code"].as_i64().map_or(1, |code| code as i32),
+        }),
+        None => Ok(true),
+    }
+}
+
+/// Answer the commands sent with `--send` until Ctrl+C is pressed or `stop-watch` is used,
+/// running each one in every profile.
+///
+/// The profiles are only found once, and commands run with the options the daemon was started
+/// with. Each one is answered with the document `--format json` prints for it.
+fn run_daemon(
+    base: &Path,
+    managers: &[SaveManager],
+    settings: &Settings,
+    confirm: &Confirm,
+    ignore_running: bool,
+) -> Result<(), Error> {
+    stop_on_ctrl_c();
+    platform::listen_for_stop(&STOP);
+
+    out!("listening for commands on {}", pipe::PIPE_NAME);
+
+    pipe::listen(&STOP, |line| {
+        output::capture();
+        let result = run_sent(line, base, managers, settings, confirm, ignore_running);
+        let captured = output::take_captured().unwrap_or_default();
+
+        match &result {
+            Ok(()) => out!("ran {}", line),
+            Err(e) => error!("error: {}: {}", line, e),
+        }
+
+        let document = Document::new(captured, result.as_ref().err());
+
+        serde_json::to_string(&document).unwrap_or_else(|e| {
+            let error = Error::Pipe {
+                reason: format!("failed to serialize the answer: {}", e),
+            };
+
+            serde_json::json!({
+                "status": "error",
+                "error": { "kind": error.kind(), "message": error.to_string(), "exit_code": 1 },
+            })
+            .to_string()
         })
-        .collect::<Result<Vec<_>, Error>>()?;
+    })
+}
+
+/// Parse and run a command sent to the daemon.
+fn run_sent(
+    line: &str,
+    base: &Path,
+    managers: &[SaveManager],
+    settings: &Settings,
+    confirm: &Confirm,
+    ignore_running: bool,
+) -> Result<(), Error> {
+    let invalid = |reason: String| Error::InvalidCommand { reason };
+
+    let command = serde_json::from_str::<SentCommand>(line).map_err(|e| invalid(e.to_string()))?;
+    let args = iter::once(String::from("gtav-saveload")).chain(command.args);
+
+    let matches = app()
+        .setting(AppSettings::ColorNever)
+        .get_matches_from_safe(args)
+        .map_err(|e| {
+            let message = e.message.lines().next().unwrap_or_default();
+            invalid(message.trim_start_matches("error: ").to_string())
+        })?;
+
+    match matches.subcommand_name() {
+        Some(name) if UNSENDABLE.contains(&name) => {
+            return Err(invalid(format!("'{}' can't be sent to the daemon", name)));
+        }
+        Some(..) => (),
+        None => return Err(invalid(String::from("there's no command"))),
+    }
+
+    let action = match Action::from_matches(&matches, settings)? {
+        Some(action) => action,
+        None => return Err(invalid(String::from("there's no command"))),
+    };
+
+    let confirm = Confirm {
+        console: confirm.console,
+        yes: confirm.yes || matches.is_present("yes"),
+        json: true,
+    };
+
+    check_game(
+        &action,
+        ignore_running || matches.is_present("ignore-running"),
+    )?;
 
-    slots_and_meta.sort_by(|a, b| b.1.cmp(&a.1));
+    let _lock = gtav_helpers::lock_instance(base, LOCK_WAIT)?;
+    managers.iter().try_for_each(|m| run(m, &action, &confirm))
+}
 
-    Ok(slots_and_meta.get(nth).map(|n| n.0.clone()))
+/// Save a dated slot with a note saying what happened when.
+fn snapshot(manager: &SaveManager, what: &str) -> Result<(), Error> {
+    manager.journaled("run", None, |manager| {
+        let note = format!("{} {}", what, local_time(SystemTime::now()));
+        let slot = manager.save_dated_with_note(Some(&note))?;
+        manager.annotate(&slot, Some(&note), &[])
+    })
 }
 
-/// Delete save files in the given path.
-fn delete_save_files(path: &Path) -> Result<(), Error> {
-    for (_, save_file) in list_save_files(path)? {
-        println!("delete: {}", save_file.display());
-        fs::remove_file(&save_file)?;
+/// Set [CANCEL] when Ctrl+C is pressed or the console is closed, instead of exiting part of the
+/// way through replacing save files. Pressing Ctrl+C again exits right away.
+fn cancel_on_ctrl_c() {
+    let handler = || {
+        if STOP_ON_CTRL_C.load(Ordering::SeqCst) {
+            STOP.store(true, Ordering::SeqCst);
+            return;
+        }
+
+        if CANCEL.swap(true, Ordering::SeqCst) {
+            process::exit(Error::Cancelled.exit_code());
+        }
+
+        eout!("cancelling, press Ctrl+C again to exit right away");
+    };
+
+    if let Err(e) = ctrlc::set_handler(handler) {
+        eout!("warning: Ctrl+C won't cancel cleanly: {}", e);
     }
 
-    Ok(())
+    platform::cancel_on_close(&CANCEL);
 }
 
-fn main() -> Result<(), Error> {
-    let matches = App::new("GTA V SaveLoad Helper")
-        .version(env!("CARGO_PKG_VERSION"))
-        .author("John-John Tedro")
-        .about("Manages GTA V Save Files")
-        .arg(
-            Arg::with_name("save")
-                .long("save")
-                .value_name("slot")
-                .help("Saves the current save files in the given slot.")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("load")
-                .long("load")
-                .value_name("slot")
-                .help("Loads the current save files in the given slot.")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("load-save-file")
-                .long("load-save-file")
-                .value_name("slot")
-                .help("Loads the current save file from the Save Files folder.")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("save-dated")
-                .long("save-dated")
-                .help("Removes the current save files, and saves them in a dated folder."),
-        )
-        .arg(
-            Arg::with_name("clear-profile")
-                .long("clear-profile")
-                .help("Removes the current save files."),
-        )
-        .arg(
-            Arg::with_name("load-nth-newest-slot")
-                .long("load-nth-newest-slot")
-                .value_name("nth")
-                .help("Load the nth newest slot.")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("delete-nth-newest-slot")
-                .long("delete-nth-newest-slot")
-                .value_name("nth")
-                .help("Delete the nth newest slot.")
-                .takes_value(true),
-        )
-        .get_matches();
+/// Set [STOP] when Ctrl+C is pressed from now on, instead of [CANCEL].
+fn stop_on_ctrl_c() {
+    STOP_ON_CTRL_C.store(true, Ordering::SeqCst);
+}
 
-    let base = PathBuf::from(env::var("USERPROFILE")?)
-        .join("Documents")
-        .join("Rockstar Games")
-        .join("GTA V");
+#[cfg(windows)]
+mod platform {
+    use std::{
+        ffi::OsStr,
+        iter,
+        os::windows::ffi::OsStrExt as _,
+        ptr,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            OnceLock,
+        },
+        thread,
+        time::Duration,
+    };
+    use winapi::{
+        shared::minwindef::{BOOL, DWORD, FALSE, TRUE},
+        um::{
+            consoleapi::{AllocConsole, SetConsoleCtrlHandler},
+            handleapi::CloseHandle,
+            synchapi::{CreateEventW, OpenEventW, SetEvent, WaitForSingleObject},
+            winbase::INFINITE,
+            wincon::{AttachConsole, ATTACH_PARENT_PROCESS, CTRL_CLOSE_EVENT},
+            winnt::{EVENT_MODIFY_STATE, HANDLE},
+            winuser::{
+                MessageBeep, MessageBoxW, IDYES, MB_ICONASTERISK, MB_ICONERROR, MB_ICONHAND,
+                MB_ICONWARNING, MB_OK, MB_YESNO,
+            },
+        },
+    };
 
-    let profiles = base.join("Profiles");
+    /// Name of the event which is signalled to stop watching.
+    const STOP_EVENT: &str = "Local\\gtav-saveload-stop-watch";
 
-    if !profiles.is_dir() {
-        println!("Missing profile directory: {}", base.display());
-        return Ok(());
+    /// How long closing the console waits for what's being done to be cancelled, since Windows
+    /// ends the process 5 seconds after it's closed.
+    const CLOSE_GRACE: Duration = Duration::from_millis(4500);
+
+    /// What [cancel_on_close] sets, since the handler can't capture it.
+    static CLOSE_CANCEL: OnceLock<&'static AtomicBool> = OnceLock::new();
+
+    fn wide(s: &str) -> Vec<u16> {
+        OsStr::new(s).encode_wide().chain(iter::once(0)).collect()
     }
 
-    let mut existing_profiles = Vec::new();
+    /// Attach to the console of the parent process, or allocate a new one if requested.
+    pub fn attach_console(requested: bool) -> bool {
+        requested && unsafe { AttachConsole(ATTACH_PARENT_PROCESS) != 0 || AllocConsole() != 0 }
+    }
 
-    for entry in fs::read_dir(&profiles)? {
-        let entry = entry?;
-        let path = entry.path();
+    /// Show a native message box with the given error.
+    pub fn error_box(title: &str, message: &str) {
+        let title = wide(title);
+        let message = wide(message);
 
-        if path.is_dir() {
-            existing_profiles.push(path.to_owned());
+        unsafe {
+            MessageBoxW(
+                ptr::null_mut(),
+                message.as_ptr(),
+                title.as_ptr(),
+                MB_OK | MB_ICONERROR,
+            );
         }
     }
 
-    for profile in &existing_profiles {
-        if let Some(slot) = matches.value_of("save") {
-            let slot = ensure_slot(&profile)?.join(slot);
+    /// Ask a yes or no question with a native message box.
+    pub fn confirm_box(title: &str, message: &str) -> bool {
+        let title = wide(title);
+        let message = wide(message);
 
-            if !slot.is_dir() {
-                fs::create_dir(&slot)?;
-            }
+        unsafe {
+            MessageBoxW(
+                ptr::null_mut(),
+                message.as_ptr(),
+                title.as_ptr(),
+                MB_YESNO | MB_ICONWARNING,
+            ) == IDYES
+        }
+    }
 
-            copy_save_files(profile, &slot)?;
+    /// Play the sound of an information or an error message box, depending on `success`.
+    pub fn beep(success: bool) {
+        let sound = if success {
+            MB_ICONASTERISK
+        } else {
+            MB_ICONHAND
+        };
+        unsafe { MessageBeep(sound) };
+    }
+
+    /// Set `stop` once the named stop event is signalled by [stop_watching], since there's no
+    /// way to press Ctrl+C without a console.
+    pub fn listen_for_stop(stop: &'static AtomicBool) {
+        let name = wide(STOP_EVENT);
+
+        // Manual reset, so that every running watch sees it.
+        let event = unsafe { CreateEventW(ptr::null_mut(), TRUE, FALSE, name.as_ptr()) };
+
+        if event.is_null() {
+            return;
         }
 
-        if let Some(slot) = matches.value_of("load") {
-            let slot = ensure_slot(&profile)?.join(slot);
+        // Handles can be used from any thread, but aren't marked as such.
+        let event = event as usize;
+
+        thread::spawn(move || {
+            unsafe { WaitForSingleObject(event as HANDLE, INFINITE) };
+            stop.store(true, Ordering::SeqCst);
+        });
+    }
+
+    /// Set `cancel` when the console is closed, and give what's being done a few seconds to
+    /// stop at a safe point before the process is ended.
+    ///
+    /// This has to be called after the Ctrl+C handler is set, since the handler set last is
+    /// called first.
+    pub fn cancel_on_close(cancel: &'static AtomicBool) {
+        unsafe extern "system" fn handler(event: DWORD) -> BOOL {
+            if event != CTRL_CLOSE_EVENT {
+                return FALSE;
+            }
 
-            if !slot.is_dir() {
-                fs::create_dir(&slot)?;
+            if let Some(cancel) = CLOSE_CANCEL.get() {
+                cancel.store(true, Ordering::SeqCst);
             }
 
-            copy_save_files(&slot, profile)?;
+            // The process exits on its own once the cancelled operation has been rolled back.
+            thread::sleep(CLOSE_GRACE);
+            TRUE
         }
 
-        if let Some(name) = matches.value_of("load-save-file") {
-            let mut matches = list_name_contains(&profile.join("Save Files"), name)?;
-            matches.sort_by(|a, b| b.0.cmp(&a.0));
+        if CLOSE_CANCEL.set(cancel).is_ok() {
+            unsafe { SetConsoleCtrlHandler(Some(handler), TRUE) };
+        }
+    }
 
-            if let Some((_, from)) = matches.first() {
-                copy_save_files(&from, profile)?;
-            }
+    /// Signal every running watch to stop, returning `false` if there were none.
+    pub fn stop_watching() -> bool {
+        let name = wide(STOP_EVENT);
+        let event = unsafe { OpenEventW(EVENT_MODIFY_STATE, FALSE, name.as_ptr()) };
+
+        if event.is_null() {
+            return false;
         }
 
-        if matches.is_present("save-dated") {
-            let when = chrono::Local::now();
-            let when = format!("dated-{}", when.format("%Y-%m-%d_%H%M%S"));
-            let slot = ensure_slot(&profile)?.join(when);
+        unsafe {
+            SetEvent(event);
+            CloseHandle(event);
+        }
 
-            if !slot.is_dir() {
-                fs::create_dir(&slot)?;
-            }
+        true
+    }
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use std::sync::atomic::AtomicBool;
+
+    /// Other platforms always run with whatever terminal they were started from.
+    pub fn attach_console(_: bool) -> bool {
+        true
+    }
+
+    /// Errors are only written to stderr on other platforms.
+    pub fn error_box(_: &str, _: &str) {}
+
+    /// Never used, since there's always a console to prompt in.
+    pub fn confirm_box(_: &str, _: &str) -> bool {
+        false
+    }
+
+    /// Never used, since hotkeys are only supported on Windows.
+    pub fn beep(_: bool) {}
+
+    /// Watching is stopped with Ctrl+C or by signals on other platforms.
+    pub fn listen_for_stop(_: &'static AtomicBool) {}
+
+    /// Closing the terminal sends a signal which isn't handled on other platforms.
+    pub fn cancel_on_close(_: &'static AtomicBool) {}
+
+    /// There's no named event to signal on other platforms.
+    pub fn stop_watching() -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_document_schema() -> Result<(), Box<dyn std::error::Error>> {
+        use serde::Deserialize;
+
+        #[derive(Deserialize)]
+        struct TestDocument {
+            status: String,
+            results: Vec<TestSlot>,
+            operations: Vec<TestOperation>,
+            messages: Vec<String>,
+            error: Option<TestError>,
+        }
+
+        #[derive(Deserialize)]
+        struct TestSlot {
+            profile: PathBuf,
+            nth: usize,
+            name: String,
+            path: PathBuf,
+            modified: String,
+            files: usize,
+            size: u64,
+            note: Option<String>,
+            tags: Vec<String>,
+            title: Option<String>,
+            pinned: bool,
+            save_numbers: Vec<u32>,
+            suspicious: Vec<String>,
+            dated: bool,
+            game_version: Option<String>,
+            headers: Vec<TestHeader>,
+        }
+
+        #[derive(Deserialize)]
+        struct TestHeader {
+            name: String,
+            title: Option<String>,
+            saved: Option<String>,
+            completion: Option<f64>,
+        }
 
-            copy_save_files(profile, &slot)?;
+        #[derive(Deserialize)]
+        struct TestOperation {
+            op: String,
+            path: PathBuf,
+            to: Option<PathBuf>,
         }
 
-        if matches.is_present("clear-profile") {
-            delete_save_files(&profile)?;
+        #[derive(Deserialize)]
+        struct TestError {
+            kind: String,
+            message: String,
+            exit_code: i32,
         }
 
-        if let Some(nth) = matches.value_of("load-nth-newest-slot") {
-            let nth = str::parse::<usize>(nth)?;
+        let manager = SaveManager::new("abc");
+        let slot = Slot {
+            name: "jewel".to_string(),
+            path: PathBuf::from("abc").join("Slots").join("jewel"),
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(1_400_000_000),
+            files: 2,
+            size: 1024,
+            note: Some("before the heist".to_string()),
+            tags: vec!["heist".to_string()],
+            title: None,
+            pinned: true,
+            save_numbers: vec![0, 1],
+            suspicious: vec!["SGTA00001".to_string()],
+            dated: false,
+            game_version: Some("1.0.3258.0".to_string()),
+            headers: vec![(
+                "SGTA00000".to_string(),
+                gtav_helpers::SaveHeader {
+                    title: Some("The Jewel Store Job - 12.53%".to_string()),
+                    saved: None,
+                },
+            )],
+        };
+
+        let captured = Captured {
+            messages: vec!["abc -> def".to_string()],
+            operations: vec![FileOperation {
+                op: "copy",
+                path: PathBuf::from("abc"),
+                to: Some(PathBuf::from("def")),
+            }],
+            results: vec![slot_json(&manager, 1, &slot)],
+        };
+
+        let error = Error::SlotNotFound {
+            name: "foo".to_string(),
+        };
+        let json = serde_json::to_string(&Document::new(captured, Some(&error)))?;
+        let document = serde_json::from_str::<TestDocument>(&json)?;
+
+        assert_eq!(document.status, "error");
+        assert_eq!(document.messages, vec!["abc -> def"]);
+
+        let slot = &document.results[0];
+        assert_eq!(slot.profile, PathBuf::from("abc"));
+        assert_eq!((slot.nth, slot.name.as_str()), (1, "jewel"));
+        assert_eq!(slot.path, PathBuf::from("abc").join("Slots").join("jewel"));
+        assert!(slot.modified.starts_with("2014-05-13T"));
+        assert_eq!((slot.files, slot.size), (2, 1024));
+        assert_eq!(slot.note.as_deref(), Some("before the heist"));
+        assert_eq!(slot.tags, vec!["heist"]);
+        assert_eq!(slot.title, None);
+        assert!(slot.pinned);
+        assert_eq!(slot.save_numbers, vec![0, 1]);
+        assert_eq!(slot.suspicious, vec!["SGTA00001"]);
+        assert!(!slot.dated);
+        assert_eq!(slot.game_version.as_deref(), Some("1.0.3258.0"));
+        assert_eq!(slot.headers.len(), 1);
+        assert_eq!(slot.headers[0].name, "SGTA00000");
+        assert_eq!(
+            slot.headers[0].title.as_deref(),
+            Some("The Jewel Store Job - 12.53%")
+        );
+        assert_eq!(slot.headers[0].saved, None);
+        assert_eq!(slot.headers[0].completion, Some(12.53));
+
+        let operation = &document.operations[0];
+        assert_eq!(operation.op, "copy");
+        assert_eq!(operation.path, PathBuf::from("abc"));
+        assert_eq!(operation.to, Some(PathBuf::from("def")));
+
+        let error = document.error.expect("an error");
+        assert_eq!(error.kind, "slot_not_found");
+        assert_eq!(error.message, "slot 'foo' does not exist");
+        assert_eq!(error.exit_code, 3);
+
+        let json = serde_json::to_string(&Document::new(Captured::default(), None))?;
+        let document = serde_json::from_str::<TestDocument>(&json)?;
+        assert_eq!(document.status, "ok");
+        assert!(document.error.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn settings_override_config() -> Result<(), Error> {
+        let config = Config {
+            profile: Some(String::from("abc")),
+            backup_before_load: Some(false),
+            verify_checksums: Some(false),
+            keep_dated: Some(3),
+            ..Config::default()
+        };
+
+        let matches = app().get_matches_from(["gtav-saveload", "--verify", "list"]);
+        let settings = Settings::new(&matches, config.clone(), None)?;
+
+        assert_eq!(settings.profile.value.as_deref(), Some("abc"));
+        assert_eq!(settings.profile.origin, Origin::ConfigFile);
+        assert!(!settings.backup_before_load.value);
+        assert!(settings.verify_checksums.value);
+        assert_eq!(settings.verify_checksums.origin, Origin::CommandLine);
+        assert_eq!(settings.keep_dated.value, Some(3));
+        assert_eq!(settings.date_format.value, gtav_helpers::DATED_FORMAT);
+        assert_eq!(settings.date_format.origin, Origin::Default);
+
+        let matches = app().get_matches_from([
+            "gtav-saveload",
+            "--base-path",
+            "gta",
+            "--all-profiles",
+            "--no-backup",
+            "list",
+        ]);
+        let settings = Settings::new(&matches, config.clone(), None)?;
+
+        assert_eq!(settings.base_path.value, Some(PathBuf::from("gta")));
+        assert_eq!(settings.base_path.origin, Origin::CommandLine);
+        assert_eq!(settings.profile.value, None);
+        assert_eq!(settings.backup_before_load.origin, Origin::CommandLine);
+        assert!(!settings.verify_checksums.value);
+        assert_eq!(settings.verify_checksums.origin, Origin::ConfigFile);
+        assert_eq!(settings.game.value.name, gtav_helpers::DEFAULT_GAME);
+        assert_eq!(settings.include.value, vec![String::from("SGTA*")]);
+
+        // The save files of the game are matched unless others are given.
+        let matches = app().get_matches_from(["gtav-saveload", "--game", "rdr2", "list"]);
+        let settings = Settings::new(&matches, config.clone(), None)?;
+        assert_eq!(settings.game.value.name, "rdr2");
+        assert_eq!(settings.game.origin, Origin::CommandLine);
+        assert_eq!(settings.include.value, vec![String::from("SRDR*")]);
+
+        let matches = app().get_matches_from(["gtav-saveload", "--game", "gta6", "list"]);
+        assert!(matches!(
+            Settings::new(&matches, config, None),
+            Err(Error::UnknownGame { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn log_levels() {
+        let level = |args: &[&str]| {
+            let args = iter::once("gtav-saveload").chain(args.iter().copied());
+            log_level(&app().get_matches_from(args))
+        };
+
+        assert_eq!(level(&["list"]), Level::Info);
+        assert_eq!(level(&["-q", "list"]), Level::Warn);
+        assert_eq!(level(&["list", "-v"]), Level::Debug);
+        assert_eq!(level(&["--log-level", "trace", "list"]), Level::Trace);
+        assert_eq!(level(&["-v", "--log-level", "error", "list"]), Level::Error);
+        assert!(app()
+            .get_matches_from_safe(["gtav-saveload", "-q", "-v", "list"])
+            .is_err());
+        assert!(app()
+            .get_matches_from_safe(["gtav-saveload", "--quiet", "--format", "json", "list"])
+            .is_err());
+    }
+
+    #[test]
+    fn sent_commands() -> Result<(), Box<dyn std::error::Error>> {
+        let base = tempfile::tempdir()?;
+        let profile = base.path().join("Profiles").join("abc");
+        fs::create_dir_all(&profile)?;
+        fs::write(profile.join("SGTA00000"), b"save")?;
+
+        let matches = app().get_matches_from(["gtav-saveload", "daemon"]);
+        let settings = Settings::new(&matches, Config::default(), None)?;
+        let managers = [SaveManager::new(profile.clone())];
+
+        let confirm = Confirm {
+            console: true,
+            yes: false,
+            json: true,
+        };
+
+        let run = |line| run_sent(line, base.path(), &managers, &settings, &confirm, false);
 
-            if let Some(path) = find_newest_slot(&profile, nth)? {
-                copy_save_files(&path, &profile)?;
+        run(r#"{"args":["save","foo"]}"#)?;
+        assert!(profile
+            .join("Slots")
+            .join("foo")
+            .join("SGTA00000")
+            .is_file());
+
+        for (line, reason) in [
+            (
+                r#"{"args":["watch"]}"#,
+                "'watch' can't be sent to the daemon",
+            ),
+            (r#"{"args":["--yes"]}"#, "there's no command"),
+            (
+                r#"{"args":["nope"]}"#,
+                "Found argument 'nope' which wasn't expected, or isn't valid in this context",
+            ),
+        ] {
+            match run(line) {
+                Err(Error::InvalidCommand { reason: r }) => assert_eq!(r, reason),
+                result => panic!("{}: {:?}", line, result),
             }
         }
 
-        if let Some(nth) = matches.value_of("delete-nth-newest-slot") {
-            let nth = str::parse::<usize>(nth)?;
+        assert!(matches!(run("load"), Err(Error::InvalidCommand { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn describe_what_was_done() -> Result<(), Box<dyn std::error::Error>> {
+        let base = tempfile::tempdir()?;
+        let profile = base.path().join("Profiles").join("abc");
+        fs::create_dir_all(&profile)?;
+        fs::write(profile.join("SGTA00000"), vec![0; 1_500_000])?;
 
-            if let Some(path) = find_newest_slot(&profile, nth)? {
-                delete_save_files(&path)?;
+        let managers = [SaveManager::new(profile)];
+        managers[0].save("foo")?;
 
-                if let Err(e) = fs::remove_dir(&path) {
-                    println!("Failed to remove directory: {}", e);
-                }
+        let saved = Action::Save("foo", Annotations::default());
+        assert_eq!(
+            describe_done(&managers, &saved).as_deref(),
+            Some("Saved slot 'foo' \u{2014} 1 file, 1.4 MiB")
+        );
+
+        let pinned = Action::Pin("foo", true);
+        assert_eq!(
+            describe_done(&managers, &pinned).as_deref(),
+            Some("Ran pin on 'foo'")
+        );
+
+        assert_eq!(describe_done(&managers, &Action::StashList), None);
+        Ok(())
+    }
+
+    #[test]
+    fn select_profiles_by_index_and_name() -> Result<(), Error> {
+        let profiles = vec![PathBuf::from("1234abcd"), PathBuf::from("5678efgh")];
+
+        let selected = select_profiles(profiles.clone(), Some("1"), false, true)?;
+        assert_eq!(selected, vec![PathBuf::from("5678efgh")]);
+
+        let selected = select_profiles(profiles.clone(), Some("abc"), false, true)?;
+        assert_eq!(selected, vec![PathBuf::from("1234abcd")]);
+
+        assert!(select_profiles(profiles.clone(), Some("zzz"), false, true).is_err());
+        assert!(select_profiles(profiles.clone(), None, false, true).is_err());
+        assert_eq!(
+            select_profiles(profiles.clone(), None, false, false)?.len(),
+            2
+        );
+        assert_eq!(select_profiles(profiles, None, true, true)?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn picked_commands_run_like_given_ones() -> Result<(), Box<dyn std::error::Error>> {
+        let given = || {
+            ["--interactive", "--game", "rdr2"]
+                .iter()
+                .map(|a| a.to_string())
+        };
+        let command = ["load".to_string(), "foo".to_string()];
+
+        let args = picked_args(given(), Some(1), &command, false);
+        assert_eq!(
+            args,
+            [
+                "gtav-saveload",
+                "--game",
+                "rdr2",
+                "--profile",
+                "1",
+                "load",
+                "foo"
+            ]
+        );
+
+        let matches = app().get_matches_from_safe(&args)?;
+        let settings = Settings::new(&matches, Config::default(), None)?;
+        assert!(!matches.is_present("interactive"));
+        assert_eq!(settings.profile.value.as_deref(), Some("1"));
+
+        match Action::from_matches(&matches, &settings)? {
+            Some(action @ Action::Load(..)) => assert!(action.is_destructive()),
+            _ => panic!("expected the load action"),
+        }
+
+        let args = picked_args(given(), None, &command, true);
+        assert_eq!(
+            args,
+            ["gtav-saveload", "--game", "rdr2", "--yes", "load", "foo"]
+        );
+        assert!(app().get_matches_from_safe(&args)?.is_present("yes"));
+        Ok(())
+    }
+
+    #[test]
+    fn completions_complete_slot_names() {
+        for shell in &["bash", "zsh", "powershell"] {
+            let script = completions(shell);
+            assert!(script.contains(COMPLETE_SLOTS), "{}", shell);
+            assert!(script.contains("\"diff-slots:2\"") || script.contains("'diff-slots:2'"));
+            assert!(!script.contains("@SLOTS@"), "{}", shell);
+        }
+
+        let bash = completions("bash");
+        assert!(bash.contains("gtav-saveload__load)"));
+        assert!(bash
+            .trim_end()
+            .ends_with("-F _gtav-saveload_slots -o bashdefault -o default gtav-saveload"));
+
+        // The generated completions complete the value of every option which takes one, all of
+        // which have to be skipped when counting arguments.
+        let (generated, _) = bash.split_at(bash.find("_gtav-saveload_slots()").unwrap());
+
+        for line in generated.lines().map(str::trim) {
+            if let Some(option) = line.strip_suffix(')').filter(|o| o.starts_with("--")) {
+                assert!(
+                    VALUE_OPTIONS.contains(&option) || FORWARDED_OPTIONS.contains(&option),
+                    "{} is missing",
+                    option
+                );
             }
         }
     }
 
-    Ok(())
+    #[test]
+    fn slot_indexes_start_at_one() {
+        assert_eq!(parse_index("1").ok(), Some(1));
+        assert_eq!(parse_index("12").ok(), Some(12));
+        assert!(parse_index("0").is_err());
+        assert!(parse_index("-1").is_err());
+        assert!(parse_index("newest").is_err());
+
+        // The deprecated flags have always counted from 0.
+        assert_eq!(parse_legacy_index("0").ok(), Some(1));
+        assert!(parse_legacy_index("newest").is_err());
+    }
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(999), "999");
+        assert_eq!(thousands(12482), "12,482");
+        assert_eq!(thousands(1234567), "1,234,567");
+    }
 }
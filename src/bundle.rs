@@ -0,0 +1,495 @@
+use crate::{
+    archive::{system_time, zip_time, EntryReader},
+    files::{self, check_file_name, to_extended_path},
+    hash,
+    manifest::ManifestFile,
+    ops::{FsOp, Ops},
+    space, Error,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Cursor, Seek, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use zip::write::FileOptions;
+
+/// Name of the manifest at the top of a bundle, which lists every file in it.
+const BUNDLE_MANIFEST: &str = "bundle.json";
+
+/// Describes the contents of a bundle.
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleManifest {
+    /// When the bundle was created, in RFC 3339 format.
+    exported: String,
+    /// The profiles in the bundle.
+    profiles: Vec<ProfileManifest>,
+}
+
+/// Describes the slots of a single profile in a bundle.
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileManifest {
+    id: String,
+    slots: Vec<SlotFiles>,
+}
+
+/// Describes the files of a single slot in a bundle.
+#[derive(Debug, Serialize, Deserialize)]
+struct SlotFiles {
+    name: String,
+    files: Vec<ManifestFile>,
+}
+
+/// A zip archive of every slot in one or more profiles, as written by
+/// [SaveManager::export_all](crate::SaveManager::export_all).
+///
+/// Each slot is stored with all of its files, including its manifest, under a directory named
+/// after the profile and the slot, like `1234abcd/before-heist/SGTA00000`.
+#[derive(Debug)]
+pub struct Bundle {
+    /// The profiles in the bundle, sorted by their id.
+    pub profiles: Vec<BundledProfile>,
+}
+
+/// The slots of a single profile in a [Bundle].
+#[derive(Debug)]
+pub struct BundledProfile {
+    /// The name of the profile directory the slots were exported from, like `1234abcd`.
+    pub id: String,
+    /// The slots, sorted by name.
+    pub slots: Vec<BundledSlot>,
+}
+
+/// A single slot in a [Bundle].
+#[derive(Debug)]
+pub struct BundledSlot {
+    /// The name of the slot.
+    pub name: String,
+    files: Vec<BundledFile>,
+}
+
+/// A single file of a slot in a [Bundle] or a [SlotBundle](crate::SlotBundle).
+#[derive(Debug)]
+pub(crate) struct BundledFile {
+    /// The name of the entry in the archive, for errors.
+    pub(crate) entry: PathBuf,
+    pub(crate) name: String,
+    pub(crate) contents: Vec<u8>,
+    pub(crate) modified: Option<SystemTime>,
+}
+
+/// The slots imported from a [BundledProfile].
+#[derive(Debug, Default)]
+pub struct BundleImport {
+    /// The slots which were imported.
+    pub imported: Vec<String>,
+    /// The slots which were skipped, since a slot with the same name already exists.
+    pub skipped: Vec<String>,
+}
+
+impl Bundle {
+    /// Read the bundle at `path`, checking every file in it against the SHA-256 digest in its
+    /// manifest.
+    ///
+    /// Errors with [Error::UnsafeArchiveEntry] if an entry isn't a file in the directory of a
+    /// slot in the directory of a profile, since it could otherwise be used to write outside of
+    /// the slots directory, and with [Error::InvalidBundle] if the manifest is missing or
+    /// doesn't match the files.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let zip_error = |source| Error::Zip {
+            path: path.to_owned(),
+            source,
+        };
+
+        let invalid = |reason: String| Error::InvalidBundle {
+            path: path.to_owned(),
+            reason,
+        };
+
+        let contents = fs::read(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+        let mut zip = zip::ZipArchive::new(Cursor::new(contents)).map_err(zip_error)?;
+
+        let mut manifest = None;
+        let mut found = BTreeMap::<(String, String), Vec<BundledFile>>::new();
+        let mut reader = EntryReader::new(path);
+
+        for index in 0..zip.len() {
+            let mut entry = zip.by_index(index).map_err(zip_error)?;
+            let name = entry.name().to_string();
+
+            let contents = reader.read(&mut entry, &name)?;
+
+            if name == BUNDLE_MANIFEST {
+                let parsed = serde_json::from_slice::<BundleManifest>(&contents)
+                    .map_err(|e| invalid(format!("{} can't be read: {}", BUNDLE_MANIFEST, e)))?;
+                manifest = Some(parsed);
+                continue;
+            }
+
+            let parts = name.split('/').collect::<Vec<_>>();
+
+            let (id, slot, file) = match parts[..] {
+                [id, slot, file] if parts.iter().all(|p| check_file_name(p).is_ok()) => {
+                    (id, slot, file)
+                }
+                _ => {
+                    return Err(Error::UnsafeArchiveEntry {
+                        path: path.to_owned(),
+                        name,
+                    })
+                }
+            };
+
+            found
+                .entry((id.to_string(), slot.to_string()))
+                .or_default()
+                .push(BundledFile {
+                    entry: path.join(&name),
+                    name: file.to_string(),
+                    contents,
+                    modified: system_time(entry.last_modified()),
+                });
+        }
+
+        let manifest = match manifest {
+            Some(manifest) => manifest,
+            None => return Err(invalid(format!("{} is missing", BUNDLE_MANIFEST))),
+        };
+
+        let mut profiles = Vec::new();
+
+        for profile in manifest.profiles {
+            let mut slots = Vec::new();
+
+            for slot in profile.slots {
+                // Entries have been checked already, but slots without any files haven't.
+                if check_file_name(&profile.id).is_err() || check_file_name(&slot.name).is_err() {
+                    return Err(Error::UnsafeArchiveEntry {
+                        path: path.to_owned(),
+                        name: format!("{}/{}", profile.id, slot.name),
+                    });
+                }
+
+                let key = (profile.id.clone(), slot.name.clone());
+                let mut files = found.remove(&key).unwrap_or_default();
+
+                for expected in &slot.files {
+                    let file = match files.iter().find(|f| f.name == expected.name) {
+                        Some(file) => file,
+                        None => {
+                            return Err(invalid(format!(
+                                "{}/{}/{} is missing",
+                                profile.id, slot.name, expected.name
+                            )))
+                        }
+                    };
+
+                    let actual = hash::hash_bytes(&file.contents).to_string();
+
+                    if actual != expected.sha256 {
+                        return Err(invalid(format!(
+                            "{}/{}/{} is corrupt, expected SHA-256 {}, got {}",
+                            profile.id, slot.name, expected.name, expected.sha256, actual
+                        )));
+                    }
+                }
+
+                if let Some(extra) = files
+                    .iter()
+                    .find(|f| !slot.files.iter().any(|e| e.name == f.name))
+                {
+                    return Err(invalid(format!(
+                        "{}/{}/{} isn't in the manifest",
+                        profile.id, slot.name, extra.name
+                    )));
+                }
+
+                files.sort_by(|a, b| a.name.cmp(&b.name));
+
+                slots.push(BundledSlot {
+                    name: slot.name,
+                    files,
+                });
+            }
+
+            slots.sort_by(|a, b| a.name.cmp(&b.name));
+
+            profiles.push(BundledProfile {
+                id: profile.id,
+                slots,
+            });
+        }
+
+        if let Some((id, slot)) = found.into_keys().next() {
+            return Err(invalid(format!("{}/{} isn't in the manifest", id, slot)));
+        }
+
+        profiles.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(Self { profiles })
+    }
+}
+
+/// Write every file in the given slots, sorted by profile id, into a new bundle at `path`,
+/// returning the number of slots written.
+///
+/// Each profile is given by its id and the names and directories of its slots. The bundle is
+/// read back and checked once it's written, and removed again if anything fails. Errors before
+/// writing anything if the files wouldn't fit uncompressed.
+pub(crate) fn write(
+    path: &Path,
+    profiles: &[(String, Vec<(String, PathBuf)>)],
+) -> Result<usize, Error> {
+    let mut listed = Vec::new();
+    let mut size = 0;
+
+    for (id, slots) in profiles {
+        let mut slot_files = Vec::new();
+
+        for (name, dir) in slots {
+            let mut files = files::find_matching(dir, |p| p.is_file(), |_| true)?;
+            files.sort();
+            size += files::total_size(&files)?;
+            slot_files.push((name.as_str(), files));
+        }
+
+        listed.push((id.as_str(), slot_files));
+    }
+
+    space::check(path, size)?;
+
+    let result = fs::File::create(to_extended_path(path))
+        .map_err(|e| Error::io(path, e))
+        .and_then(|file| write_bundle(file, path, &listed))
+        .and_then(|_| Bundle::read(path));
+
+    match result {
+        Ok(bundle) => Ok(bundle.profiles.iter().map(|p| p.slots.len()).sum()),
+        Err(e) => {
+            let _ = fs::remove_file(to_extended_path(path));
+            Err(e)
+        }
+    }
+}
+
+/// The files of each slot of each profile to write into a bundle.
+type Listed<'a> = [(&'a str, Vec<(&'a str, Vec<(std::ffi::OsString, PathBuf)>)>)];
+
+fn write_bundle<W>(file: W, path: &Path, profiles: &Listed<'_>) -> Result<W, Error>
+where
+    W: Write + Seek,
+{
+    let zip_error = |source| Error::Zip {
+        path: path.to_owned(),
+        source,
+    };
+
+    let mut zip = zip::ZipWriter::new(file);
+    let mut manifest = BundleManifest {
+        exported: chrono::Local::now().to_rfc3339(),
+        profiles: Vec::new(),
+    };
+
+    for (id, slots) in profiles {
+        let mut profile = ProfileManifest {
+            id: id.to_string(),
+            slots: Vec::new(),
+        };
+
+        for (name, slot_files) in slots {
+            let mut slot = SlotFiles {
+                name: name.to_string(),
+                files: Vec::new(),
+            };
+
+            for (file_name, file) in slot_files {
+                // The files are stored as they are, so compressed save files stay compressed.
+                let contents = fs::read(to_extended_path(file)).map_err(|e| Error::io(file, e))?;
+                let modified = fs::metadata(to_extended_path(file))
+                    .and_then(|m| m.modified())
+                    .map_err(|e| Error::io(file, e))?;
+
+                let file_name = files::display_name(file_name);
+                let entry = format!("{}/{}/{}", id, name, file_name);
+                let options = FileOptions::default().last_modified_time(zip_time(modified));
+                zip.start_file(entry, options).map_err(zip_error)?;
+                zip.write_all(&contents).map_err(|e| Error::io(path, e))?;
+
+                slot.files
+                    .push(ManifestFile::new(file_name, hash::hash_bytes(&contents)));
+            }
+
+            profile.slots.push(slot);
+        }
+
+        manifest.profiles.push(profile);
+    }
+
+    let options = FileOptions::default().last_modified_time(zip_time(SystemTime::now()));
+    zip.start_file(BUNDLE_MANIFEST, options)
+        .map_err(zip_error)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest)
+        .map_err(|e| Error::io(path, io::Error::from(e)))?;
+    zip.finish().map_err(zip_error)
+}
+
+/// Write the files of a bundled slot into `dir`, giving them the modification times they had
+/// when they were exported, and checking that each one was written correctly.
+pub(crate) fn write_slot(ops: &Ops, slot: &BundledSlot, dir: &Path) -> Result<(), Error> {
+    write_files(ops, &slot.files, dir)
+}
+
+/// Write the given bundled files into `dir` like [write_slot].
+pub(crate) fn write_files(ops: &Ops, files: &[BundledFile], dir: &Path) -> Result<(), Error> {
+    for file in files {
+        let dest = dir.join(&file.name);
+        ops.run(FsOp::Write(&dest, &file.contents))?;
+
+        if ops.dry_run {
+            continue;
+        }
+
+        if let Some(modified) = file.modified {
+            files::set_modified(&dest, modified)?;
+        }
+
+        let written = fs::read(to_extended_path(&dest)).map_err(|e| Error::io(&dest, e))?;
+        let (expected, actual) = (hash::hash_bytes(&file.contents), hash::hash_bytes(&written));
+
+        if expected != actual {
+            return Err(Error::Corrupt {
+                from: file.entry.clone(),
+                to: dest,
+                reason: format!("expected SHA-256 {}, got {}", expected, actual),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_zip;
+    use crate::SaveManager;
+
+    #[test]
+    fn export_and_import_every_slot() -> Result<(), Box<dyn std::error::Error>> {
+        let base = tempfile::tempdir()?;
+        let first = base.path().join("1234abcd");
+        let second = base.path().join("5678efgh");
+
+        for profile in [&first, &second] {
+            fs::create_dir(profile)?;
+            fs::write(
+                profile.join("SGTA00000"),
+                profile.to_string_lossy().as_bytes(),
+            )?;
+        }
+
+        let managers = [SaveManager::new(&first), SaveManager::new(&second)];
+        managers[0].save("a")?;
+        managers[0].save("b")?;
+        managers[1].save("c")?;
+
+        let out = tempfile::tempdir()?;
+        let path = out.path().join("all.zip");
+        assert_eq!(SaveManager::export_all(&managers, &path)?, 3);
+
+        let bundle = Bundle::read(&path)?;
+        let ids = bundle
+            .profiles
+            .iter()
+            .map(|p| p.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["1234abcd", "5678efgh"]);
+        assert_eq!(bundle.profiles[0].slots[1].name, "b");
+
+        managers[0].delete_slot("a")?;
+        let import = managers[0].import_bundled(&bundle.profiles[0], false)?;
+        assert_eq!(import.imported, vec!["a"]);
+        assert_eq!(import.skipped, vec!["b"]);
+        assert_eq!(
+            fs::read(first.join("Slots").join("a").join("SGTA00000"))?,
+            first.to_string_lossy().as_bytes()
+        );
+
+        // Slots can be imported into another profile than they were exported from.
+        let import = managers[1].import_bundled(&bundle.profiles[0], true)?;
+        assert_eq!(import.imported, vec!["a", "b"]);
+        assert_eq!(managers[1].list_slots()?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn unsafe_and_corrupt_bundles_are_refused() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("all.zip");
+
+        let manifest = |sha256: &str| {
+            serde_json::json!({
+                "exported": "2019-10-30T18:04:12+01:00",
+                "profiles": [{
+                    "id": "1234abcd",
+                    "slots": [{
+                        "name": "a",
+                        "files": [{ "name": "SGTA00000", "size": 4, "sha256": sha256 }],
+                    }],
+                }],
+            })
+            .to_string()
+        };
+
+        let digest = hash::hash_bytes(b"save").to_string();
+
+        for name in &[
+            "1234abcd/../SGTA00000",
+            "1234abcd/a/b/SGTA00000",
+            "SGTA00000",
+        ] {
+            write_zip(
+                &path,
+                &[
+                    (name, b"save"),
+                    (BUNDLE_MANIFEST, manifest(&digest).as_bytes()),
+                ],
+            )?;
+
+            assert!(
+                matches!(Bundle::read(&path), Err(Error::UnsafeArchiveEntry { .. })),
+                "{}",
+                name
+            );
+        }
+
+        write_zip(
+            &path,
+            &[
+                ("1234abcd/a/SGTA00000", b"save"),
+                (BUNDLE_MANIFEST, manifest(&digest).as_bytes()),
+            ],
+        )?;
+        assert_eq!(Bundle::read(&path)?.profiles[0].slots[0].files.len(), 1);
+
+        write_zip(
+            &path,
+            &[
+                ("1234abcd/a/SGTA00000", b"evil"),
+                (BUNDLE_MANIFEST, manifest(&digest).as_bytes()),
+            ],
+        )?;
+        assert!(matches!(
+            Bundle::read(&path),
+            Err(Error::InvalidBundle { .. })
+        ));
+
+        write_zip(&path, &[("1234abcd/a/SGTA00000", b"save")])?;
+        assert!(matches!(
+            Bundle::read(&path),
+            Err(Error::InvalidBundle { .. })
+        ));
+        Ok(())
+    }
+}
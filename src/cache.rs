@@ -0,0 +1,201 @@
+use crate::{
+    files::to_extended_path,
+    hash::{self, FileHash},
+    Error,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom as _,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Name of the file in the slots directory which caches the hashes of save files, so that
+/// unchanged ones don't have to be read again.
+pub(crate) const HASH_CACHE: &str = ".hash-cache.json";
+
+/// The hash of a file, as of when it had the given length and modification time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedHash {
+    /// The length of the file, which is smaller than the hashed contents if it's compressed.
+    len: u64,
+    /// The modification time in nanoseconds since the Unix epoch.
+    modified: u64,
+    /// The size of the hashed contents.
+    size: u64,
+    sha256: String,
+}
+
+/// Hashes of files by their path, which are only used while the length and modification time
+/// of the file stay the same.
+///
+/// The cache is only advisory. Nothing in it is trusted without the file looking the same as
+/// when it was hashed, and anything wrong with it only means that files are read again.
+#[derive(Debug)]
+pub(crate) struct HashCache {
+    /// Where the cache is stored, or `None` if it isn't.
+    path: Option<PathBuf>,
+    entries: BTreeMap<String, CachedHash>,
+    changed: bool,
+}
+
+impl HashCache {
+    /// Load the cache stored at the given path.
+    ///
+    /// A missing cache is the same as an empty one, and an unreadable one is warned about and
+    /// rebuilt. If `use_cached` isn't set the stored cache is ignored, so every file is read
+    /// again and the cache is replaced with what was read.
+    pub(crate) fn load(path: PathBuf, use_cached: bool) -> Self {
+        let mut changed = !use_cached;
+
+        let entries = match fs::read(to_extended_path(&path)) {
+            Ok(..) if !use_cached => BTreeMap::new(),
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                eout!(
+                    "warning: {}: ignoring invalid hash cache: {}",
+                    path.display(),
+                    e
+                );
+                changed = true;
+                BTreeMap::new()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                eout!(
+                    "warning: {}: ignoring unreadable hash cache: {}",
+                    path.display(),
+                    e
+                );
+                changed = true;
+                BTreeMap::new()
+            }
+        };
+
+        Self {
+            path: Some(path),
+            entries,
+            changed,
+        }
+    }
+
+    /// A cache which isn't stored anywhere, for when every file has to be read.
+    pub(crate) fn none() -> Self {
+        Self {
+            path: None,
+            entries: BTreeMap::new(),
+            changed: false,
+        }
+    }
+
+    /// Hash the file at the given path like [hash::hash_file], reading it only if its length
+    /// or modification time has changed since it was last hashed.
+    pub(crate) fn hash(&mut self, path: &Path) -> Result<FileHash, Error> {
+        if self.path.is_none() {
+            return hash::hash_file(path);
+        }
+
+        let meta = fs::metadata(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+        let modified = nanos(meta.modified().map_err(|e| Error::io(path, e))?);
+        let key = path.to_string_lossy().into_owned();
+
+        if let Some(cached) = self.entries.get(&key) {
+            if cached.len == meta.len() && cached.modified == modified {
+                if let Some(digest) = hash::parse_digest(&cached.sha256) {
+                    trace!("cached hash of {}", path.display());
+                    return Ok(FileHash {
+                        size: cached.size,
+                        digest,
+                    });
+                }
+            }
+        }
+
+        let hash = hash::hash_file(path)?;
+
+        self.entries.insert(
+            key,
+            CachedHash {
+                len: meta.len(),
+                modified,
+                size: hash.size,
+                sha256: hash.to_string(),
+            },
+        );
+
+        self.changed = true;
+        Ok(hash)
+    }
+
+    /// Write the cache if anything was hashed, forgetting the files which no longer exist.
+    ///
+    /// Nothing is written if the directory of the cache doesn't exist. The cache isn't written
+    /// through [crate::ops::Ops] since it's never part of what a command changes, so that it
+    /// isn't recorded in the journal or reported as an operation.
+    pub(crate) fn save(&mut self) -> Result<(), Error> {
+        let path = match &self.path {
+            Some(path) if path.parent().is_some_and(Path::is_dir) => path,
+            _ => return Ok(()),
+        };
+
+        let before = self.entries.len();
+        self.entries.retain(|path, _| Path::new(path).is_file());
+
+        if !self.changed && self.entries.len() == before {
+            return Ok(());
+        }
+
+        let bytes =
+            serde_json::to_vec_pretty(&self.entries).map_err(|e| Error::io(path, e.into()))?;
+        fs::write(to_extended_path(path), &bytes).map_err(|e| Error::io(path, e))?;
+        debug!(bytes = bytes.len(), "write {}", path.display());
+        self.changed = false;
+        Ok(())
+    }
+}
+
+/// The given time in nanoseconds since the Unix epoch, or 0 if it's before it.
+fn nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached_hashes_are_advisory() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let cache_path = dir.path().join(HASH_CACHE);
+        let file = dir.path().join("SGTA00000");
+        fs::write(&file, b"save")?;
+
+        let mut cache = HashCache::load(cache_path.clone(), true);
+        let expected = cache.hash(&file)?;
+        cache.save()?;
+
+        let reads = hash::files_read();
+        let mut cache = HashCache::load(cache_path.clone(), true);
+        assert_eq!(cache.hash(&file)?, expected);
+        assert_eq!(hash::files_read(), reads);
+
+        // Not using the cache reads the file again.
+        let mut cache = HashCache::load(cache_path.clone(), false);
+        assert_eq!(cache.hash(&file)?, expected);
+        assert_eq!(hash::files_read(), reads + 1);
+
+        // A corrupt cache is ignored, and rebuilt.
+        fs::write(&cache_path, b"{ not json")?;
+        let mut cache = HashCache::load(cache_path.clone(), true);
+        assert_eq!(cache.hash(&file)?, expected);
+        assert_eq!(hash::files_read(), reads + 2);
+        cache.save()?;
+
+        let mut cache = HashCache::load(cache_path, true);
+        assert_eq!(cache.hash(&file)?, expected);
+        assert_eq!(hash::files_read(), reads + 2);
+        Ok(())
+    }
+}
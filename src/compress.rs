@@ -0,0 +1,155 @@
+use crate::{
+    files::{self, to_extended_path as ext},
+    hash,
+    ops::{FsOp, Ops},
+    Error,
+};
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    io::{self, Read},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+/// Extension of compressed save files in slots, like `SGTA00000.zst`.
+const EXTENSION: &str = "zst";
+
+/// The compression level used unless another one is specified, which favors speed.
+pub const DEFAULT_LEVEL: i32 = 3;
+
+/// The compression levels which are accepted.
+pub(crate) const LEVELS: RangeInclusive<i32> = 1..=22;
+
+/// Test if the save file at the given path is compressed.
+pub(crate) fn is_compressed(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new(EXTENSION))
+}
+
+/// The name of a save file with the given file name once it's decompressed.
+pub(crate) fn save_file_name(name: OsString) -> OsString {
+    let path = Path::new(&name);
+
+    match path.file_stem() {
+        Some(stem) if is_compressed(path) => stem.to_owned(),
+        _ => name,
+    }
+}
+
+/// Error if the given compression level isn't supported.
+pub(crate) fn check_level(level: i32) -> Result<(), Error> {
+    if !LEVELS.contains(&level) {
+        return Err(Error::InvalidCompressionLevel { level });
+    }
+
+    Ok(())
+}
+
+/// Open the save file at the given path for reading, decompressing it if it's compressed.
+pub(crate) fn open(path: &Path) -> Result<Box<dyn Read>, Error> {
+    let file = fs::File::open(ext(path)).map_err(|e| Error::io(path, e))?;
+
+    if !is_compressed(path) {
+        return Ok(Box::new(file));
+    }
+
+    let decoder = zstd::Decoder::new(file).map_err(|e| Error::io(path, e))?;
+    Ok(Box::new(decoder))
+}
+
+/// Read the contents of the save file at the given path, decompressing it if it's compressed.
+pub(crate) fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    let mut contents = Vec::new();
+
+    open(path)?
+        .read_to_end(&mut contents)
+        .map_err(|e| Error::io(path, e))?;
+
+    Ok(contents)
+}
+
+/// The size of the save file at the given path once it's decompressed.
+pub(crate) fn size(path: &Path) -> Result<u64, Error> {
+    if !is_compressed(path) {
+        let meta = fs::metadata(ext(path)).map_err(|e| Error::io(path, e))?;
+        return Ok(meta.len());
+    }
+
+    io::copy(&mut open(path)?, &mut io::sink()).map_err(|e| Error::io(path, e))
+}
+
+/// Replace the uncompressed save file at `path` with a compressed one next to it, which has the
+/// same modification time, returning the size of the compressed file.
+///
+/// The original is only removed once the compressed file has been written, and verified unless
+/// verifying is disabled.
+pub(crate) fn compress_file(ops: &Ops, path: &Path, level: i32) -> Result<u64, Error> {
+    check_level(level)?;
+
+    let mut dest = path.as_os_str().to_owned();
+    dest.push(".");
+    dest.push(EXTENSION);
+    let dest = PathBuf::from(dest);
+
+    let contents = fs::read(ext(path)).map_err(|e| Error::io(path, e))?;
+    let compressed = zstd::bulk::compress(&contents, level).map_err(|e| Error::io(path, e))?;
+
+    let result = ops.run(FsOp::Write(&dest, &compressed)).and_then(|()| {
+        if ops.dry_run {
+            return Ok(());
+        }
+
+        files::copy_times(path, &dest)?;
+
+        if ops.verify {
+            hash::verify_copy(path, &dest)?;
+        }
+
+        Ok(())
+    });
+
+    if let Err(e) = result {
+        if dest.is_file() {
+            let _ = ops.run(FsOp::RemoveFile(&dest));
+        }
+
+        return Err(e);
+    }
+
+    ops.run(FsOp::RemoveFile(path))?;
+    Ok(compressed.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compress_and_read_back() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("SGTA00000");
+        let contents = b"SGTA".repeat(1000);
+        fs::write(&path, &contents)?;
+
+        let size = compress_file(&Ops::default(), &path, DEFAULT_LEVEL)?;
+        let compressed = dir.path().join("SGTA00000.zst");
+
+        assert!(!path.exists());
+        assert_eq!(fs::metadata(&compressed)?.len(), size);
+        assert!(size < contents.len() as u64);
+        assert_eq!(read(&compressed)?, contents);
+        assert_eq!(self::size(&compressed)?, contents.len() as u64);
+
+        assert_eq!(
+            save_file_name(compressed.file_name().unwrap().into()),
+            "SGTA00000"
+        );
+        assert_eq!(save_file_name("SGTA00000".into()), "SGTA00000");
+
+        assert!(matches!(
+            compress_file(&Ops::default(), &path, 0),
+            Err(Error::InvalidCompressionLevel { level: 0 })
+        ));
+        Ok(())
+    }
+}
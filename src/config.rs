@@ -0,0 +1,365 @@
+use crate::{
+    compress, hotkey, profiles, prune, remote, template, Error, GameDef, RemoteConfig,
+    SaveFilePatterns, DATED_FORMAT,
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Name of the directory containing the config file, in `%APPDATA%` on Windows.
+const CONFIG_DIR: &str = "gtav-saveload";
+
+/// Name of the config file.
+const CONFIG_FILE: &str = "config.toml";
+
+/// The keys which are understood in the config file, others are warned about.
+const KEYS: &[&str] = &[
+    "base_path",
+    "profile",
+    "backup_before_load",
+    "verify_checksums",
+    "keep_dated",
+    "keep_backups",
+    "date_format",
+    "dated_name",
+    "slots_dir",
+    "shared_slots",
+    "compress_dated",
+    "compression_level",
+    "include_settings",
+    "min_save_size",
+    "hydrate_timeout",
+    "include",
+    "exclude",
+    "hotkeys",
+    "notify",
+    "edition",
+    "game_path",
+    "game",
+    "games",
+    "remote",
+];
+
+/// Defaults for the command line tool, read from a TOML file.
+///
+/// Every key is optional, and is overridden by the corresponding command line flag.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The GTA V directory containing Profiles, like `--base-path`.
+    pub base_path: Option<PathBuf>,
+    /// The profile to operate on, like `--profile`.
+    pub profile: Option<String>,
+    /// Back up the save files in the profile before replacing them, unlike `--no-backup`.
+    pub backup_before_load: Option<bool>,
+    /// Verify that copied files match their source, unlike `--no-verify`.
+    pub verify_checksums: Option<bool>,
+    /// The number of dated slots kept by `prune-dated`, like `--keep`.
+    pub keep_dated: Option<usize>,
+    /// The number of backups kept by `backup-to`, like `--keep`.
+    pub keep_backups: Option<usize>,
+    /// The `strftime` format of the time in the names of dated slots, like `--date-format`.
+    pub date_format: Option<String>,
+    /// The template of the names of dated slots, like `--dated-name`.
+    pub dated_name: Option<String>,
+    /// The directory to store slots in instead of the profile, like `--slots-dir`.
+    pub slots_dir: Option<PathBuf>,
+    /// Share the slots in `slots_dir` between profiles, like `--shared-slots`.
+    pub shared_slots: Option<bool>,
+    /// Compress the save files in dated slots, like `--compress`.
+    pub compress_dated: Option<bool>,
+    /// The zstd compression level from 1 to 22, like `--compression-level`.
+    pub compression_level: Option<i32>,
+    /// Save and load the settings files in the profile along with the save files, like
+    /// `--include-settings`.
+    pub include_settings: Option<bool>,
+    /// Save files smaller than this many bytes are warned about, like `--min-save-size`.
+    pub min_save_size: Option<u64>,
+    /// How many seconds to wait for save files in OneDrive to be downloaded, like
+    /// `--hydrate-timeout`.
+    pub hydrate_timeout: Option<u64>,
+    /// The glob patterns of the names of save files, like `--include`.
+    pub include: Option<Vec<String>>,
+    /// The glob patterns of the names of files which aren't save files, like `--exclude`.
+    pub exclude: Option<Vec<String>>,
+    /// The key combination of each action of `hotkeys`, like `save-dated = "Ctrl+Alt+F5"`.
+    pub hotkeys: Option<BTreeMap<String, String>>,
+    /// Show a notification saying what was done when there's no console, unlike `--no-notify`.
+    pub notify: Option<bool>,
+    /// The edition of the game to use the profiles of, `legacy`, `enhanced` or `all`, like
+    /// `--edition`.
+    pub edition: Option<String>,
+    /// The directory the game is installed in, like `--game-path`.
+    pub game_path: Option<PathBuf>,
+    /// The game to manage the save files of, like `--game`.
+    pub game: Option<String>,
+    /// Games other than the built-in ones, in `[games.{name}]` tables.
+    pub games: Option<BTreeMap<String, GameDef>>,
+    /// Where `push` uploads slots to and `pull` downloads them from, in a `[remote]` table.
+    pub remote: Option<RemoteConfig>,
+}
+
+impl Config {
+    /// Load the config file at the given path.
+    ///
+    /// A missing file is the same as an empty one unless `required` is set. Unknown keys are
+    /// warned about rather than rejected, so that a config file written for a newer version
+    /// still works.
+    pub fn load(path: &Path, required: bool) -> Result<Self, Error> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(Error::io(path, e)),
+        };
+
+        Self::parse(path, &contents)
+    }
+
+    /// Parse the contents of the config file at the given path.
+    fn parse(path: &Path, contents: &str) -> Result<Self, Error> {
+        let error = |source| Error::InvalidConfig {
+            path: path.to_owned(),
+            source,
+        };
+
+        let table = toml::from_str::<toml::Table>(contents).map_err(error)?;
+
+        for key in table.keys().filter(|k| !KEYS.contains(&k.as_str())) {
+            eout!("warning: {}: unknown key '{}'", path.display(), key);
+        }
+
+        if let Some(toml::Value::Table(remote)) = table.get("remote") {
+            for key in remote
+                .keys()
+                .filter(|k| !remote::KEYS.contains(&k.as_str()))
+            {
+                eout!("warning: {}: unknown key 'remote.{}'", path.display(), key);
+            }
+        }
+
+        if let Some(toml::Value::Table(games)) = table.get("games") {
+            for (name, game) in games {
+                let keys = game.as_table().into_iter().flat_map(|t| t.keys());
+
+                for key in keys.filter(|k| !profiles::GAME_KEYS.contains(&k.as_str())) {
+                    eout!(
+                        "warning: {}: unknown key 'games.{}.{}'",
+                        path.display(),
+                        name,
+                        key
+                    );
+                }
+            }
+        }
+
+        let config = toml::from_str::<Self>(contents).map_err(error)?;
+
+        if let Some(format) = &config.date_format {
+            prune::check_date_format(format)?;
+        }
+
+        if let Some(template) = &config.dated_name {
+            let format = config.date_format.as_deref().unwrap_or(DATED_FORMAT);
+            template::check(template, format)?;
+        }
+
+        if let Some(level) = config.compression_level {
+            compress::check_level(level)?;
+        }
+
+        for patterns in config.include.iter().chain(&config.exclude) {
+            SaveFilePatterns::new(patterns, &[] as &[&str])?;
+        }
+
+        if let Some(edition) = &config.edition {
+            profiles::parse_edition_choice(edition)?;
+        }
+
+        let games = config.games.clone().unwrap_or_default();
+
+        for name in games.keys() {
+            GameDef::find(name, &games)?.check()?;
+        }
+
+        if let Some(game) = &config.game {
+            GameDef::find(game, &games)?;
+        }
+
+        if let Some(hotkeys) = &config.hotkeys {
+            hotkey::parse_hotkeys(hotkeys.iter().map(|(a, h)| (a.as_str(), h.as_str())))?;
+        }
+
+        Ok(config)
+    }
+}
+
+/// The config file which is used unless another one is specified, like
+/// `%APPDATA%\gtav-saveload\config.toml` on Windows.
+pub fn default_config_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_known_and_unknown_keys() -> Result<(), Error> {
+        let path = Path::new("config.toml");
+
+        let config = Config::parse(
+            path,
+            r#"
+            base_path = 'D:\GTA V'
+            profile = "abc"
+            verify_checksums = false
+            keep_dated = 5
+            keep_backups = 3
+            date_format = "%Y%m%d-%H%M"
+            dated_name = "{date}_{time}_{note}"
+            slots_dir = 'D:\Dropbox\GTA V'
+            compress_dated = true
+            include_settings = true
+            min_save_size = 1024
+            exclude = ["*.bak"]
+            notify = false
+            edition = "enhanced"
+            game_path = 'D:\Games\Grand Theft Auto V'
+            game = "mp3"
+            from_the_future = true
+
+            [games.mp3]
+            documents = 'Rockstar Games\Max Payne 3'
+            prefix = "SMP3"
+            process = "MaxPayne3.exe"
+
+            [hotkeys]
+            save-dated = "Ctrl+Shift+S"
+
+            [remote]
+            url = "s3://saves/gtav"
+            endpoint = "https://s3.eu-central-1.amazonaws.com"
+            region = "eu-central-1"
+            "#,
+        )?;
+
+        assert_eq!(config.base_path, Some(PathBuf::from(r"D:\GTA V")));
+        assert_eq!(config.profile.as_deref(), Some("abc"));
+        assert_eq!(config.backup_before_load, None);
+        assert_eq!(config.verify_checksums, Some(false));
+        assert_eq!(config.keep_dated, Some(5));
+        assert_eq!(config.keep_backups, Some(3));
+        assert_eq!(config.date_format.as_deref(), Some("%Y%m%d-%H%M"));
+        assert_eq!(config.dated_name.as_deref(), Some("{date}_{time}_{note}"));
+        assert_eq!(config.slots_dir, Some(PathBuf::from(r"D:\Dropbox\GTA V")));
+        assert_eq!(config.shared_slots, None);
+        assert_eq!(config.compress_dated, Some(true));
+        assert_eq!(config.compression_level, None);
+        assert_eq!(config.include_settings, Some(true));
+        assert_eq!(config.min_save_size, Some(1024));
+        assert_eq!(config.include, None);
+        assert_eq!(config.exclude, Some(vec![String::from("*.bak")]));
+        assert_eq!(config.notify, Some(false));
+        assert_eq!(config.edition.as_deref(), Some("enhanced"));
+        assert_eq!(config.game.as_deref(), Some("mp3"));
+        assert_eq!(
+            config
+                .games
+                .as_ref()
+                .and_then(|g| g.get("mp3"))
+                .map(|g| g.prefix.as_str()),
+            Some("SMP3")
+        );
+        assert_eq!(
+            config.game_path,
+            Some(PathBuf::from(r"D:\Games\Grand Theft Auto V"))
+        );
+        assert_eq!(
+            config.hotkeys,
+            Some(BTreeMap::from([(
+                String::from("save-dated"),
+                String::from("Ctrl+Shift+S")
+            )]))
+        );
+
+        assert_eq!(
+            config.remote,
+            Some(RemoteConfig {
+                url: Some(String::from("s3://saves/gtav")),
+                endpoint: Some(String::from("https://s3.eu-central-1.amazonaws.com")),
+                region: Some(String::from("eu-central-1")),
+                ..RemoteConfig::default()
+            })
+        );
+
+        assert_eq!(Config::parse(path, "")?, Config::default());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_errors() {
+        let path = Path::new("config.toml");
+
+        let error = Config::parse(path, "keep_dated = \"five\"").unwrap_err();
+        assert!(matches!(error, Error::InvalidConfig { .. }));
+        assert!(error.to_string().starts_with("config.toml: "));
+
+        assert!(Config::parse(path, "profile = ").is_err());
+
+        assert!(matches!(
+            Config::parse(path, "date_format = \"%Q\""),
+            Err(Error::InvalidDateFormat { .. })
+        ));
+
+        assert!(matches!(
+            Config::parse(path, "dated_name = \"{date}\""),
+            Err(Error::InvalidDatedName { .. })
+        ));
+
+        assert!(matches!(
+            Config::parse(path, "edition = \"next-gen\""),
+            Err(Error::InvalidEdition { .. })
+        ));
+
+        assert!(matches!(
+            Config::parse(path, "game = \"gta6\""),
+            Err(Error::UnknownGame { .. })
+        ));
+
+        assert!(matches!(
+            Config::parse(
+                path,
+                "[games.mp3]\ndocuments = 'C:\\Max Payne 3'\nprefix = \"SMP3\"\nprocess = \"MaxPayne3.exe\""
+            ),
+            Err(Error::InvalidGame { .. })
+        ));
+
+        assert!(matches!(
+            Config::parse(path, "compression_level = 23"),
+            Err(Error::InvalidCompressionLevel { level: 23 })
+        ));
+
+        assert!(matches!(
+            Config::parse(path, "[hotkeys]\nsave-dated = \"Ctrl+Hyper+S\""),
+            Err(Error::InvalidHotkey { .. })
+        ));
+    }
+
+    #[test]
+    fn load_missing_file() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(CONFIG_FILE);
+
+        assert_eq!(Config::load(&path, false)?, Config::default());
+        assert!(matches!(Config::load(&path, true), Err(Error::Io { .. })));
+
+        fs::write(&path, "keep_dated = 3")?;
+        assert_eq!(Config::load(&path, true)?.keep_dated, Some(3));
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{files::to_extended_path, Error};
+use aes_gcm::{
+    aead::{rand_core::RngCore as _, Aead as _, AeadCore as _, KeyInit as _, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use std::{fmt, fs, io::Read as _, path::Path};
+
+/// Marks the start of an encrypted archive, and the version of its format.
+const MAGIC: &[u8; 8] = b"GTAVENC1";
+
+/// Identifies Argon2id as the key derivation function in the header.
+const KDF_ARGON2ID: u8 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Length of the header, which is the magic followed by the key derivation function and its
+/// parameters as little-endian memory cost in KiB, iterations and parallelism, the salt and the
+/// nonce.
+const HEADER_LEN: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+
+/// The most memory in KiB that the key derivation may use when decrypting, so that a corrupt
+/// or malicious header can't exhaust it.
+const MAX_M_COST: u32 = 1024 * 1024;
+
+/// The most iterations that the key derivation may use when decrypting.
+const MAX_T_COST: u32 = 64;
+
+/// A passphrase used to encrypt or decrypt archives, which is never printed.
+#[derive(Clone)]
+pub(crate) struct Passphrase(String);
+
+impl Passphrase {
+    pub(crate) fn new(passphrase: String) -> Self {
+        Self(passphrase)
+    }
+}
+
+impl fmt::Debug for Passphrase {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "Passphrase(..)")
+    }
+}
+
+/// Test if the file at the given path is an encrypted archive.
+pub fn is_encrypted_archive(path: &Path) -> Result<bool, Error> {
+    let file = fs::File::open(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+
+    let mut magic = Vec::with_capacity(MAGIC.len());
+    file.take(MAGIC.len() as u64)
+        .read_to_end(&mut magic)
+        .map_err(|e| Error::io(path, e))?;
+
+    Ok(is_encrypted(&magic))
+}
+
+/// Test if the given contents are encrypted.
+pub(crate) fn is_encrypted(contents: &[u8]) -> bool {
+    contents.starts_with(MAGIC)
+}
+
+/// Encrypt the given contents with a key derived from the passphrase, for the file at `path`.
+///
+/// The key derivation parameters, salt and nonce are stored in a header in front of the
+/// encrypted contents, which is also authenticated.
+pub(crate) fn encrypt(
+    contents: &[u8],
+    passphrase: &Passphrase,
+    path: &Path,
+) -> Result<Vec<u8>, Error> {
+    let (m_cost, t_cost, p_cost) = (
+        Params::DEFAULT_M_COST,
+        Params::DEFAULT_T_COST,
+        Params::DEFAULT_P_COST,
+    );
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(KDF_ARGON2ID);
+    header.extend_from_slice(&m_cost.to_le_bytes());
+    header.extend_from_slice(&t_cost.to_le_bytes());
+    header.extend_from_slice(&p_cost.to_le_bytes());
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let failed = |reason: String| Error::io(path, std::io::Error::other(reason));
+
+    let key = derive_key(passphrase, &salt, m_cost, t_cost, p_cost)
+        .ok_or_else(|| failed(String::from("key derivation failed")))?;
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let payload = Payload {
+        msg: contents,
+        aad: &header,
+    };
+
+    let encrypted = cipher
+        .encrypt(&nonce, payload)
+        .map_err(|e| failed(e.to_string()))?;
+
+    header.extend_from_slice(&encrypted);
+    Ok(header)
+}
+
+/// Decrypt the contents of the encrypted file at `path`.
+///
+/// Errors with [Error::DecryptionFailed] if the passphrase is wrong, or the file has been
+/// tampered with or is corrupt, which can't be told apart.
+pub(crate) fn decrypt(
+    contents: &[u8],
+    passphrase: &Passphrase,
+    path: &Path,
+) -> Result<Vec<u8>, Error> {
+    let failed = || Error::DecryptionFailed {
+        path: path.to_owned(),
+    };
+
+    if contents.len() < HEADER_LEN || !is_encrypted(contents) {
+        return Err(failed());
+    }
+
+    let (header, encrypted) = contents.split_at(HEADER_LEN);
+    let mut rest = &header[MAGIC.len()..];
+
+    let mut take = |n: usize| {
+        let (taken, tail) = rest.split_at(n);
+        rest = tail;
+        taken
+    };
+
+    let kdf = take(1)[0];
+    let mut number = || {
+        let bytes = take(4);
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    let (m_cost, t_cost, p_cost) = (number(), number(), number());
+    let salt = take(SALT_LEN);
+    let nonce = Nonce::from_slice(take(NONCE_LEN));
+
+    if kdf != KDF_ARGON2ID || m_cost > MAX_M_COST || t_cost > MAX_T_COST {
+        return Err(failed());
+    }
+
+    let key = derive_key(passphrase, salt, m_cost, t_cost, p_cost).ok_or_else(failed)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let payload = Payload {
+        msg: encrypted,
+        aad: header,
+    };
+
+    cipher.decrypt(nonce, payload).map_err(|_| failed())
+}
+
+/// Derive a key from the passphrase with Argon2id, or `None` if the parameters are invalid.
+fn derive_key(
+    passphrase: &Passphrase,
+    salt: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+) -> Option<[u8; 32]> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32)).ok()?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+    let mut key = [0u8; 32];
+    argon2
+        .hash_password_into(passphrase.0.as_bytes(), salt, &mut key)
+        .ok()?;
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_and_decrypt() -> Result<(), Error> {
+        let path = Path::new("foo.zip");
+        let passphrase = Passphrase::new(String::from("hunter2"));
+
+        let encrypted = encrypt(b"secret save files", &passphrase, path)?;
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.windows(b"secret".len()).any(|w| w == b"secret"));
+
+        assert_eq!(
+            decrypt(&encrypted, &passphrase, path)?,
+            b"secret save files"
+        );
+
+        let wrong = Passphrase::new(String::from("hunter3"));
+        assert!(matches!(
+            decrypt(&encrypted, &wrong, path),
+            Err(Error::DecryptionFailed { .. })
+        ));
+
+        // The header is authenticated along with the contents.
+        let mut tampered = encrypted.clone();
+        tampered[HEADER_LEN - 1] ^= 1;
+        assert!(decrypt(&tampered, &passphrase, path).is_err());
+
+        assert!(decrypt(&encrypted[..HEADER_LEN - 1], &passphrase, path).is_err());
+        Ok(())
+    }
+}
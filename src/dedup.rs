@@ -0,0 +1,131 @@
+use crate::{
+    cache::HashCache,
+    compress,
+    files::{list_save_files, to_extended_path},
+    hash::FileHash,
+    links,
+    ops::{FsOp, Ops},
+    output, Error,
+};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Prefix of the temporary link which replaces a duplicate, which mustn't look like a save file
+/// if it's left behind.
+const TEMPORARY_PREFIX: &str = ".dedup-";
+
+/// Replace every save file in the given slots which is identical to one before it, including
+/// when it was modified, with a hard link to that one, printing how many bytes were reclaimed.
+///
+/// Since the modification time of a slot is the one of its newest save file, files which only
+/// differ in when they were modified are left alone so that slots keep their order. Files on
+/// another volume than the one they'd be linked to are skipped.
+pub(crate) fn dedup(ops: &Ops, cache: &mut HashCache, slots: &[PathBuf]) -> Result<(), Error> {
+    let mut groups = Vec::<Vec<(OsString, PathBuf, FileHash)>>::new();
+    let mut index = HashMap::<(FileHash, SystemTime, bool), usize>::new();
+
+    for slot in slots {
+        let mut save_files = list_save_files(slot, &ops.patterns)?;
+        save_files.sort();
+
+        for (name, save_file) in save_files {
+            let hash = cache.hash(&save_file)?;
+            let modified = fs::metadata(to_extended_path(&save_file))
+                .and_then(|m| m.modified())
+                .map_err(|e| Error::io(&save_file, e))?;
+
+            // Compressed files are never linked to uncompressed ones with the same contents.
+            let compressed = compress::is_compressed(&save_file);
+
+            let group = *index
+                .entry((hash, modified, compressed))
+                .or_insert_with(|| {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                });
+
+            groups[group].push((name, save_file, hash));
+        }
+    }
+
+    let mut linked = 0;
+    let mut reclaimed = 0;
+    let mut elsewhere = 0;
+
+    for group in groups {
+        let mut group = group.into_iter();
+
+        let (_, canonical, _) = match group.next() {
+            Some(first) => first,
+            None => continue,
+        };
+
+        let canonical_id = links::file_id(&canonical);
+
+        for (name, duplicate, hash) in group {
+            let (a, b) = match (canonical_id, links::file_id(&duplicate)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+
+            if a.volume != b.volume {
+                elsewhere += 1;
+                continue;
+            }
+
+            // Already linked by an earlier run.
+            if a.index == b.index {
+                continue;
+            }
+
+            link(ops, &canonical, &duplicate, &name)?;
+            linked += 1;
+            reclaimed += hash.size;
+        }
+    }
+
+    if elsewhere > 0 {
+        out!(
+            "skipped {} on another volume",
+            output::count(elsewhere, "duplicate save file")
+        );
+    }
+
+    let verb = if ops.dry_run { "would link" } else { "linked" };
+    out!(
+        "{} {}, reclaiming {}",
+        verb,
+        output::count(linked, "duplicate save file"),
+        output::size(reclaimed)
+    );
+
+    Ok(())
+}
+
+/// Replace `duplicate` with a hard link to `canonical`.
+///
+/// The link is created next to the duplicate and renamed over it, so that the duplicate is
+/// never missing.
+fn link(ops: &Ops, canonical: &Path, duplicate: &Path, name: &OsString) -> Result<(), Error> {
+    let mut temporary = OsString::from(TEMPORARY_PREFIX);
+    temporary.push(name);
+    let temporary = duplicate.with_file_name(temporary);
+
+    if temporary.is_file() {
+        ops.run(FsOp::RemoveFile(&temporary))?;
+    }
+
+    ops.run(FsOp::HardLink(canonical, &temporary))?;
+    ops.run(FsOp::Rename(&temporary, duplicate))?;
+
+    if !ops.dry_run {
+        out!("{} -> {}", duplicate.display(), canonical.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use crate::{
+    cache::HashCache,
+    compress,
+    files::{display_name, list_save_files, list_settings_files},
+    Error, SaveFilePatterns,
+};
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+/// A difference between the save files in two directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// The save file only exists on the left-hand side.
+    OnlyLeft { name: String },
+    /// The save file only exists on the right-hand side.
+    OnlyRight { name: String },
+    /// The save files have different sizes.
+    Size { name: String, left: u64, right: u64 },
+    /// The save files have the same size, but different contents.
+    Contents { name: String },
+}
+
+impl Difference {
+    /// The name of the save file which differs.
+    pub fn name(&self) -> &str {
+        match self {
+            Difference::OnlyLeft { name }
+            | Difference::OnlyRight { name }
+            | Difference::Size { name, .. }
+            | Difference::Contents { name } => name,
+        }
+    }
+}
+
+/// Compare the save files in `left` and `right`, returning the differences sorted by name.
+///
+/// Save files with the same size are hashed through `cache`.
+pub(crate) fn compare_save_files(
+    cache: &mut HashCache,
+    left: &Path,
+    right: &Path,
+    patterns: &SaveFilePatterns,
+) -> Result<Vec<Difference>, Error> {
+    compare_files(
+        cache,
+        list_save_files(left, patterns)?,
+        list_save_files(right, patterns)?,
+    )
+}
+
+/// Compare the settings files in `left` and `right`, like [compare_save_files]. Only the
+/// settings files in `right` are compared, since not every slot has them.
+pub(crate) fn compare_settings_files(
+    cache: &mut HashCache,
+    left: &Path,
+    right: &Path,
+) -> Result<Vec<Difference>, Error> {
+    let right = list_settings_files(right)?;
+    let mut left = list_settings_files(left)?;
+    left.retain(|(name, _)| right.iter().any(|(n, _)| n == name));
+    compare_files(cache, left, right)
+}
+
+fn compare_files(
+    cache: &mut HashCache,
+    left: Vec<(OsString, PathBuf)>,
+    right: Vec<(OsString, PathBuf)>,
+) -> Result<Vec<Difference>, Error> {
+    let mut files = BTreeMap::new();
+
+    for (name, path) in left {
+        files.entry(name).or_insert((None, None)).0 = Some(path);
+    }
+
+    for (name, path) in right {
+        files.entry(name).or_insert((None, None)).1 = Some(path);
+    }
+
+    let mut out = Vec::new();
+
+    for (name, sides) in files {
+        let name = display_name(&name);
+        let (left, right) = match sides {
+            (Some(left), Some(right)) => (left, right),
+            (Some(..), None) => {
+                out.push(Difference::OnlyLeft { name });
+                continue;
+            }
+            (None, _) => {
+                out.push(Difference::OnlyRight { name });
+                continue;
+            }
+        };
+
+        let left_size = compress::size(&left)?;
+        let right_size = compress::size(&right)?;
+
+        if left_size != right_size {
+            out.push(Difference::Size {
+                name,
+                left: left_size,
+                right: right_size,
+            });
+            continue;
+        }
+
+        if cache.hash(&left)? != cache.hash(&right)? {
+            out.push(Difference::Contents { name });
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn compare_reports_all_kinds_of_differences() -> Result<(), Box<dyn std::error::Error>> {
+        let left = tempfile::tempdir()?;
+        let right = tempfile::tempdir()?;
+
+        fs::write(left.path().join("SGTA00000"), b"same")?;
+        fs::write(right.path().join("SGTA00000"), b"same")?;
+        fs::write(left.path().join("SGTA00001"), b"longer")?;
+        fs::write(right.path().join("SGTA00001"), b"short")?;
+        fs::write(left.path().join("SGTA00002"), b"abc")?;
+        fs::write(right.path().join("SGTA00002"), b"xyz")?;
+        fs::write(left.path().join("SGTA00003"), b"left")?;
+        fs::write(right.path().join("SGTA00004"), b"right")?;
+
+        let differences = compare_save_files(
+            &mut HashCache::none(),
+            left.path(),
+            right.path(),
+            &SaveFilePatterns::default(),
+        )?;
+
+        assert_eq!(
+            differences,
+            vec![
+                Difference::Size {
+                    name: "SGTA00001".to_string(),
+                    left: 6,
+                    right: 5
+                },
+                Difference::Contents {
+                    name: "SGTA00002".to_string()
+                },
+                Difference::OnlyLeft {
+                    name: "SGTA00003".to_string()
+                },
+                Difference::OnlyRight {
+                    name: "SGTA00004".to_string()
+                },
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,397 @@
+use crate::{
+    files::{display_name, list_save_files, to_extended_path},
+    game, hydrate, output,
+    profiles::{self, GameDir},
+    space, Edition, Error, GameDef, SaveFilePatterns,
+};
+use std::{
+    ffi::OsString,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Name of the file created in the slots directory to test if it's writable.
+const PROBE: &str = ".gtav-saveload-doctor";
+
+/// The number of saves there should be room for before free space is warned about.
+const ROOM_FOR_SAVES: u64 = 10;
+
+/// How a check went, ordered from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    /// Nothing is wrong.
+    Pass,
+    /// Something might cause problems.
+    Warn,
+    /// Something prevents saving or loading.
+    Fail,
+}
+
+impl Status {
+    /// A stable name for the status, used in `--format json` output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+/// The result of checking one thing about the environment.
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// What was checked, like `base directory`.
+    pub name: String,
+    /// How the check went.
+    pub status: Status,
+    /// What was found, and what to do about it unless the check passed.
+    pub message: String,
+}
+
+impl Check {
+    fn new<N, M>(name: N, status: Status, message: M) -> Self
+    where
+        N: Into<String>,
+        M: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// Check that the directory of the game exists, returning it along with its edition if it does.
+///
+/// If `given` is set, it's the directory which was given along with where it was given, like
+/// `GTAV_SAVE_DIR`, and its edition is `edition` or the one it's named like. Otherwise the
+/// directories of every edition of `game`, or only the one of `edition`, are looked for like
+/// [crate::find_game_dirs] does.
+pub fn check_base(
+    game: &GameDef,
+    given: Option<(&Path, &'static str)>,
+    edition: Option<Edition>,
+) -> (Check, Vec<GameDir>) {
+    const NAME: &str = "base directory";
+
+    let dirs = match given {
+        Some((base, method)) => vec![GameDir {
+            edition: edition.unwrap_or_else(|| Edition::of(base)),
+            path: base.to_owned(),
+            method,
+        }],
+        None => match profiles::find_game_dirs(game, edition) {
+            Ok(found) => found,
+            Err(e) => {
+                let message = format!("{}, set GTAV_SAVE_DIR or use --base-path", e);
+                return (Check::new(NAME, Status::Fail, message), Vec::new());
+            }
+        },
+    };
+
+    for dir in &dirs {
+        if !dir.path.join("Profiles").is_dir() {
+            let message = format!(
+                "{} from {} doesn't contain a Profiles directory",
+                dir.path.display(),
+                dir.method
+            );
+            return (Check::new(NAME, Status::Fail, message), Vec::new());
+        }
+    }
+
+    let found = dirs
+        .iter()
+        .map(|dir| {
+            if game.has_editions() {
+                format!(
+                    "found the {} edition in {} through {}",
+                    dir.edition,
+                    dir.path.display(),
+                    dir.method
+                )
+            } else {
+                format!("found {} through {}", dir.path.display(), dir.method)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    (Check::new(NAME, Status::Pass, found.join(", ")), dirs)
+}
+
+/// Check that there are profiles in the given GTA V directory, returning them.
+pub fn check_profiles(base: &Path) -> (Check, Vec<PathBuf>) {
+    const NAME: &str = "profiles";
+
+    match profiles::list_profiles(base) {
+        Ok(profiles) => {
+            let names = profiles
+                .iter()
+                .map(|p| display_name(p.file_name().unwrap_or_default()))
+                .collect::<Vec<_>>();
+
+            let message = format!("found {}: {}", names.len(), names.join(", "));
+            (Check::new(NAME, Status::Pass, message), profiles)
+        }
+        Err(e) => {
+            let message = format!("{}, start the game and save once", e);
+            (Check::new(NAME, Status::Fail, message), Vec::new())
+        }
+    }
+}
+
+/// Check where the game is installed, which is only used to launch it and to tell it apart from
+/// other programs with the same name, so not finding it is fine.
+pub fn check_install() -> Check {
+    const NAME: &str = "game install";
+
+    match game::detect_game_install() {
+        Some(install) if !install.path.join(game::GAME_PROCESS).is_file() => {
+            let message = format!(
+                "{} isn't in {} given with {}",
+                game::GAME_PROCESS,
+                install.path.display(),
+                install.method
+            );
+            Check::new(NAME, Status::Warn, message)
+        }
+        Some(install) => {
+            let message = format!(
+                "found in {} through {}",
+                install.path.display(),
+                install.method
+            );
+            Check::new(NAME, Status::Pass, message)
+        }
+        None => {
+            let message = "unknown, pass --game-path to say where it's installed";
+            Check::new(NAME, Status::Pass, message)
+        }
+    }
+}
+
+/// Check that the game isn't running, which would overwrite the save files which are loaded.
+pub fn check_game() -> Check {
+    const NAME: &str = "game";
+
+    if game::is_running() {
+        let message = format!(
+            "{} is running, close it before loading or clearing save files",
+            game::process()
+        );
+        return Check::new(NAME, Status::Warn, message);
+    }
+
+    let message = format!("{} isn't running", game::process());
+    Check::new(NAME, Status::Pass, message)
+}
+
+/// Check the save files in the given profile, and that its slots directory can be written to.
+pub(crate) fn check_profile(
+    profile: &Path,
+    slots: &Path,
+    patterns: &SaveFilePatterns,
+) -> Vec<Check> {
+    let name = display_name(profile.file_name().unwrap_or_default());
+
+    let save_files = match list_save_files(profile, patterns) {
+        Ok(save_files) => save_files,
+        Err(e) => {
+            let name = format!("{} save files", name);
+            return vec![Check::new(name, Status::Fail, e.to_string())];
+        }
+    };
+
+    let size = save_files
+        .iter()
+        .flat_map(|(_, path)| fs::metadata(to_extended_path(path)))
+        .map(|m| m.len())
+        .sum();
+
+    vec![
+        check_save_files(&name, save_files.len()),
+        check_writable(&name, slots),
+        check_placeholders(&name, &save_files),
+        check_space(&name, slots, size),
+    ]
+}
+
+fn check_save_files(profile: &str, count: usize) -> Check {
+    let name = format!("{} save files", profile);
+
+    if count == 0 {
+        let message =
+            "there are no save files, the game hasn't saved in this profile or --include doesn't match them";
+        return Check::new(name, Status::Warn, message);
+    }
+
+    Check::new(name, Status::Pass, format!("found {}", count))
+}
+
+/// Check that files can be created in the slots directory, or in the directory it would be
+/// created in if it doesn't exist yet.
+fn check_writable(profile: &str, slots: &Path) -> Check {
+    let name = format!("{} slots", profile);
+
+    let dir = match slots.ancestors().find(|p| p.is_dir()) {
+        Some(dir) => dir,
+        None => {
+            let message = format!("{} can't be created", slots.display());
+            return Check::new(name, Status::Fail, message);
+        }
+    };
+
+    let probe = dir.join(PROBE);
+
+    let created = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to_extended_path(&probe));
+
+    if let Err(e) = created {
+        let message = format!("can't create files in {}: {}", dir.display(), e);
+        return Check::new(name, Status::Fail, message);
+    }
+
+    if let Err(e) = fs::remove_file(to_extended_path(&probe)) {
+        let error = Error::io(&probe, e);
+        let message = format!("{}, remove it by hand", error);
+        return Check::new(name, Status::Warn, message);
+    }
+
+    let message = if dir == slots {
+        format!("{} is writable", slots.display())
+    } else {
+        format!(
+            "{} doesn't exist yet, but can be created in {}",
+            slots.display(),
+            dir.display()
+        )
+    };
+
+    Check::new(name, Status::Pass, message)
+}
+
+/// Check that none of the save files are placeholders of files which are stored in the cloud,
+/// like OneDrive makes with Files On Demand, which have to be downloaded before they're copied.
+fn check_placeholders(profile: &str, save_files: &[(OsString, PathBuf)]) -> Check {
+    let name = format!("{} placeholders", profile);
+
+    let placeholders = save_files
+        .iter()
+        .filter(|(_, path)| hydrate::is_placeholder(path))
+        .map(|(name, _)| display_name(name))
+        .collect::<Vec<_>>();
+
+    if !placeholders.is_empty() {
+        let message = format!(
+            "{} are only stored in the cloud, make the folder 'Always keep on this device'",
+            placeholders.join(", ")
+        );
+        return Check::new(name, Status::Warn, message);
+    }
+
+    Check::new(
+        name,
+        Status::Pass,
+        "the save files are stored on this device",
+    )
+}
+
+/// Check that there's room for saving the save files of the given size a number of times.
+fn check_space(profile: &str, slots: &Path, size: u64) -> Check {
+    let name = format!("{} disk space", profile);
+
+    let available = match space::available_space(slots) {
+        Some(available) => available,
+        None => {
+            let message = format!(
+                "couldn't tell how much space is free for {}",
+                slots.display()
+            );
+            return Check::new(name, Status::Warn, message);
+        }
+    };
+
+    if let Err(e) = space::check(slots, size) {
+        return Check::new(name, Status::Fail, e.to_string());
+    }
+
+    if available < size.saturating_mul(ROOM_FOR_SAVES) {
+        let message = format!(
+            "only {} free for {}, which is room for fewer than {} saves",
+            output::size(available),
+            slots.display(),
+            ROOM_FOR_SAVES
+        );
+        return Check::new(name, Status::Warn, message);
+    }
+
+    let message = format!("{} free for {}", output::size(available), slots.display());
+    Check::new(name, Status::Pass, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_checks() -> Result<(), Box<dyn std::error::Error>> {
+        let base = tempfile::tempdir()?;
+        let profile = base.path().join("Profiles").join("abc");
+        fs::create_dir_all(&profile)?;
+
+        let games = Default::default();
+        let gtav = GameDef::find(crate::DEFAULT_GAME, &games)?;
+        let rdr2 = GameDef::find("rdr2", &games)?;
+
+        let (check, found) = check_base(&gtav, Some((base.path(), "--base-path")), None);
+        assert_eq!(check.status, Status::Pass);
+        assert!(check.message.starts_with("found the legacy edition in "));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, base.path());
+        assert_eq!(found[0].edition, Edition::Legacy);
+
+        let given = Some((base.path(), "--base-path"));
+        let (_, found) = check_base(&gtav, given, Some(Edition::Enhanced));
+        assert_eq!(found[0].edition, Edition::Enhanced);
+
+        let (check, _) = check_base(&rdr2, given, None);
+        assert!(check.message.starts_with("found "));
+        assert!(!check.message.contains("edition"));
+
+        let (check, found) = check_base(&gtav, Some((&profile, "--base-path")), None);
+        assert_eq!(check.status, Status::Fail);
+        assert!(found.is_empty());
+
+        let (check, profiles) = check_profiles(base.path());
+        assert_eq!(check.message, "found 1: abc");
+        assert_eq!(profiles, vec![profile.clone()]);
+
+        let slots = profile.join("Slots");
+        let patterns = SaveFilePatterns::default();
+
+        let checks = check_profile(&profile, &slots, &patterns);
+        assert_eq!(checks[0].status, Status::Warn);
+        assert_eq!(checks[1].status, Status::Pass);
+        assert!(checks[1].message.contains("doesn't exist yet"));
+
+        fs::write(profile.join("SGTA00000"), b"save")?;
+        fs::create_dir(&slots)?;
+
+        let checks = check_profile(&profile, &slots, &patterns);
+        assert_eq!(checks[0].message, "found 1");
+        assert_eq!(checks[1].status, Status::Pass);
+        assert!(!slots.join(PROBE).exists());
+        assert_eq!(checks[2].status, Status::Pass);
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+use crate::{
+    crypto,
+    files::{check_file_name, to_extended_path},
+    ops::Ops,
+    output,
+    progress::Progress,
+    share::SLOT_BUNDLE_MANIFEST,
+    Error,
+};
+use std::{
+    env, fs,
+    io::{Cursor, Read as _, Write as _},
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+
+/// The largest file which is downloaded, which is far more than the save files of a profile
+/// ever take up.
+pub(crate) const MAX_DOWNLOAD_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The number of redirects which are followed before giving up.
+const MAX_REDIRECTS: usize = 10;
+
+/// How long connecting to the server may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the whole download may take.
+const TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The size of the chunks the download is read in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// What a downloaded file turned out to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DownloadKind {
+    /// A slot bundle written by [crate::SaveManager::bundle_slot].
+    SlotBundle,
+    /// A zip archive with save files in it, like the ones written by
+    /// [crate::SaveManager::export_slot], which might be encrypted.
+    Archive,
+}
+
+/// A directory of its own in the temporary directory, which is removed along with everything in
+/// it once it's dropped.
+#[derive(Debug)]
+pub(crate) struct Scratch {
+    dir: PathBuf,
+}
+
+impl Scratch {
+    /// Create the directory, named after what it's for like `download` and this process, so
+    /// that whatever an earlier process with the same id left behind is removed.
+    pub(crate) fn new(purpose: &str) -> Result<Self, Error> {
+        let dir = env::temp_dir().join(format!("gtav-saveload-{}-{}", purpose, process::id()));
+
+        if dir.is_dir() {
+            fs::remove_dir_all(to_extended_path(&dir)).map_err(|e| Error::io(&dir, e))?;
+        }
+
+        fs::create_dir(to_extended_path(&dir)).map_err(|e| Error::io(&dir, e))?;
+        Ok(Self { dir })
+    }
+
+    /// The directory.
+    pub(crate) fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(to_extended_path(&self.dir)) {
+            debug!("failed to remove {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+/// A downloaded file in a [Scratch] directory, which is removed once it's dropped.
+#[derive(Debug)]
+pub(crate) struct Download {
+    _scratch: Scratch,
+    path: PathBuf,
+    pub(crate) kind: DownloadKind,
+}
+
+impl Download {
+    /// The downloaded file, which is named like the last part of the path of the URL so that
+    /// errors about it make sense.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Download the file at the given HTTPS URL into the temporary directory, following redirects
+/// as long as they stay on HTTPS and showing progress while doing so.
+///
+/// Errors with [Error::InvalidUrl] if the URL isn't an HTTPS URL, and with [Error::Download]
+/// if the server doesn't respond with success, the file is larger than
+/// [MAX_DOWNLOAD_SIZE], or it isn't a zip archive. Whatever was downloaded is removed again if
+/// anything fails.
+pub(crate) fn download(ops: &Ops, url: &str) -> Result<Download, Error> {
+    let name = file_name(url)?;
+    let scratch = Scratch::new("download")?;
+
+    let mut download = Download {
+        path: scratch.path().join(name),
+        _scratch: scratch,
+        kind: DownloadKind::Archive,
+    };
+
+    fetch(ops, url, &download.path)?;
+    download.kind = detect(url, &download.path)?;
+    Ok(download)
+}
+
+/// Check that the URL can be downloaded, returning the name to store the download under.
+fn file_name(url: &str) -> Result<String, Error> {
+    let invalid = |reason: String| Error::InvalidUrl {
+        url: url.to_string(),
+        reason,
+    };
+
+    let parsed = reqwest::Url::parse(url).map_err(|e| invalid(e.to_string()))?;
+
+    if parsed.scheme() != "https" {
+        return Err(invalid(String::from("only https:// URLs are supported")));
+    }
+
+    let name = parsed
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| check_file_name(name).is_ok())
+        .unwrap_or("download");
+
+    Ok(name.to_string())
+}
+
+fn fetch(ops: &Ops, url: &str, path: &Path) -> Result<(), Error> {
+    let failed = |reason: String| Error::Download {
+        url: url.to_string(),
+        reason,
+    };
+
+    let client = client(true).map_err(|e| failed(describe(&e)))?;
+    let response = client.get(url).send().map_err(|e| failed(describe(&e)))?;
+    let status = response.status();
+
+    if !status.is_success() {
+        return Err(failed(format!("the server responded with {}", status)));
+    }
+
+    write_body(ops, url, response, path)
+}
+
+/// The client to download with, which only follows redirects to HTTPS URLs if `https_only` is
+/// set.
+pub(crate) fn client(https_only: bool) -> Result<reqwest::blocking::Client, reqwest::Error> {
+    reqwest::blocking::Client::builder()
+        .https_only(https_only)
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(TIMEOUT)
+        .user_agent(concat!("gtav-saveload/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+/// Write the body of a successful response to `url` into the file at `path`, showing progress
+/// while doing so.
+///
+/// Errors with [Error::Download] if the body is larger than [MAX_DOWNLOAD_SIZE].
+pub(crate) fn write_body(
+    ops: &Ops,
+    url: &str,
+    mut response: reqwest::blocking::Response,
+    path: &Path,
+) -> Result<(), Error> {
+    let failed = |reason: String| Error::Download {
+        url: url.to_string(),
+        reason,
+    };
+
+    let too_large = || {
+        failed(format!(
+            "it's larger than the limit of {}",
+            output::size(MAX_DOWNLOAD_SIZE)
+        ))
+    };
+
+    let length = response.content_length();
+
+    if length.is_some_and(|length| length > MAX_DOWNLOAD_SIZE) {
+        return Err(too_large());
+    }
+
+    let mut file = fs::File::create(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+    let progress = Progress::start("downloaded", 1, length.unwrap_or_default());
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut total = 0;
+
+    let result = loop {
+        if let Err(e) = ops.check_cancelled() {
+            break Err(e);
+        }
+
+        let n = match response.read(&mut buf) {
+            Ok(0) => break Ok(()),
+            Ok(n) => n,
+            Err(e) => break Err(failed(describe(&e))),
+        };
+
+        total += n as u64;
+
+        // The length the server sends can't be trusted.
+        if total > MAX_DOWNLOAD_SIZE {
+            break Err(too_large());
+        }
+
+        if let Err(e) = file.write_all(&buf[..n]) {
+            break Err(Error::io(path, e));
+        }
+
+        if let Some(progress) = &progress {
+            progress.add(n as u64);
+        }
+    };
+
+    if let Some(progress) = &progress {
+        progress.file_done();
+        progress.finish();
+    }
+
+    result?;
+    debug!(bytes = total, "download {} -> {}", url, path.display());
+    Ok(())
+}
+
+/// Tell what the downloaded file is, erroring if it isn't anything which can be imported.
+fn detect(url: &str, path: &Path) -> Result<DownloadKind, Error> {
+    let contents = fs::read(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+
+    // Encrypted archives can only be told apart once they're decrypted.
+    if crypto::is_encrypted(&contents) {
+        return Ok(DownloadKind::Archive);
+    }
+
+    match zip::ZipArchive::new(Cursor::new(contents)) {
+        Ok(zip) if zip.file_names().any(|n| n == SLOT_BUNDLE_MANIFEST) => {
+            Ok(DownloadKind::SlotBundle)
+        }
+        Ok(..) => Ok(DownloadKind::Archive),
+        Err(..) => Err(Error::Download {
+            url: url.to_string(),
+            reason: String::from("it isn't a slot bundle or a zip archive with save files"),
+        }),
+    }
+}
+
+/// Describe an error along with what caused it, since the errors of requests only say which
+/// step failed.
+pub(crate) fn describe(error: &dyn std::error::Error) -> String {
+    let mut out = error.to_string();
+    let mut source = error.source();
+
+    while let Some(error) = source {
+        out.push_str(": ");
+        out.push_str(&error.to_string());
+        source = error.source();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_https_urls_are_downloaded() {
+        assert_eq!(
+            file_name("https://example.com/saves/heist.gtavslot").ok(),
+            Some(String::from("heist.gtavslot"))
+        );
+        assert_eq!(
+            file_name("https://example.com/").ok(),
+            Some(String::from("download"))
+        );
+        assert_eq!(
+            file_name("https://example.com/get?id=..").ok(),
+            Some(String::from("get"))
+        );
+
+        for url in &[
+            "http://example.com/heist.zip",
+            "file:///heist.zip",
+            "heist.zip",
+        ] {
+            assert!(
+                matches!(file_name(url), Err(Error::InvalidUrl { .. })),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn downloads_are_detected() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("download");
+        let url = "https://example.com/download";
+
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path)?);
+        zip.start_file(SLOT_BUNDLE_MANIFEST, Default::default())?;
+        zip.finish()?;
+        assert_eq!(detect(url, &path)?, DownloadKind::SlotBundle);
+
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path)?);
+        zip.start_file("SGTA00000", Default::default())?;
+        zip.finish()?;
+        assert_eq!(detect(url, &path)?, DownloadKind::Archive);
+
+        fs::write(&path, b"<html>Not Found</html>")?;
+        assert!(matches!(detect(url, &path), Err(Error::Download { .. })));
+        Ok(())
+    }
+}
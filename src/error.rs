@@ -0,0 +1,569 @@
+use crate::{output, Edition};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Errors raised while managing save files.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("could not determine the location of the Documents folder")]
+    NoDocuments,
+    #[error("no profiles found, searched: {}", display_paths(.searched))]
+    NoProfiles { searched: Vec<PathBuf> },
+    #[error("found multiple profiles, use --profile to select one, or --all-profiles")]
+    MultipleProfiles,
+    #[error("no profile matching '{selector}'")]
+    ProfileNotFound { selector: String },
+    #[error("more than one profile matching '{selector}'")]
+    AmbiguousProfile { selector: String },
+    #[error("slot '{name}' does not exist")]
+    SlotNotFound { name: String },
+    #[error(
+        "slot '{name}' does not exist, did you mean {}?",
+        .similar.iter().map(|s| format!("'{}'", s)).collect::<Vec<_>>().join(" or ")
+    )]
+    SimilarSlotNotFound { name: String, similar: Vec<String> },
+    #[error("slot '{name}' does not exist, which alias '{alias}' refers to")]
+    AliasedSlotNotFound { alias: String, name: String },
+    #[error("alias '@{alias}' does not exist")]
+    AliasNotFound { alias: String },
+    #[error("invalid alias '{alias}', aliases other than 'profile' can only contain letters, digits, '-' and '_'")]
+    InvalidAlias { alias: String },
+    #[error("slot '{name}' does not contain any save files")]
+    EmptySlot { name: String },
+    #[error(
+        "slot '{name}' already contains {files} save files from {}, use --force to overwrite it",
+        display_time(.modified)
+    )]
+    SlotExists {
+        name: String,
+        files: usize,
+        modified: SystemTime,
+    },
+    #[error("slot '{name}' already exists")]
+    SlotNameTaken { name: String },
+    #[error(
+        "slot '{name}' contains files which aren't save files: {}, use --force to delete them anyway",
+        display_paths(.files)
+    )]
+    UnexpectedFiles { name: String, files: Vec<PathBuf> },
+    #[error("{process} is running, close the game first or use --ignore-running")]
+    GameRunning { process: String },
+    #[error(
+        "another instance is already running, {} is locked",
+        .path.display()
+    )]
+    InstanceLocked { path: PathBuf },
+    #[error("aborted")]
+    Aborted,
+    #[error("cancelled")]
+    Cancelled,
+    #[error("the terminal could not be used: {source}")]
+    Terminal { source: io::Error },
+    #[error("there is no backup to restore")]
+    NoBackup,
+    #[error("the last operation, '{command}', can't be undone")]
+    NotUndoable { command: String },
+    #[error(
+        "the save files in the profile have changed since '{command}', use --force to undo it anyway"
+    )]
+    ProfileModified { command: String },
+    #[error("there are no save files in the profile to stash")]
+    NothingToStash,
+    #[error("there are no save files in {} to transfer", .path.display())]
+    NothingToTransfer { path: PathBuf },
+    #[error("{} is both the profile to transfer from and to", .path.display())]
+    SameProfile { path: PathBuf },
+    #[error("the stash is empty")]
+    EmptyStash,
+    #[error("there are no save files in the profile to back up")]
+    NothingToBackUp,
+    #[error("there are no backups in {}", .dir.display())]
+    NoBackups { dir: PathBuf },
+    #[error("backup '{name}' does not exist in {}", .dir.display())]
+    BackupNotFound { dir: PathBuf, name: String },
+    #[error("backup {} does not contain any save files", .path.display())]
+    EmptyBackup { path: PathBuf },
+    #[error(
+        "the save files in the profile aren't saved in any slot, save them first or use --force to replace them anyway"
+    )]
+    UnsavedChanges,
+    #[error("profile differs from slot '{name}'")]
+    SlotDiffers { name: String },
+    #[error("slot '{left}' differs from slot '{right}'")]
+    SlotsDiffer { left: String, right: String },
+    #[error("{problems} problems found in {slots} slots")]
+    SlotsCorrupt { slots: usize, problems: usize },
+    #[error("slot '{name}' has no manifest to check its save files against")]
+    NoManifest { name: String },
+    #[error("slot '{name}' is pinned, unpin it or use --force-unpin to remove it")]
+    SlotPinned { name: String },
+    #[error("invalid tag '{tag}', tags can't be empty or contain whitespace or commas")]
+    InvalidTag { tag: String },
+    #[error("invalid date format '{format}'")]
+    InvalidDateFormat { format: String },
+    #[error("invalid dated slot name template '{template}', {reason}")]
+    InvalidDatedName { template: String, reason: String },
+    #[error("invalid compression level {level}, expected 1 to 22")]
+    InvalidCompressionLevel { level: i32 },
+    #[error("invalid save file pattern '{pattern}', {reason}")]
+    InvalidPattern { pattern: String, reason: String },
+    #[error("invalid in-game save slot {number}, expected 0 to 99")]
+    InvalidSaveNumber { number: u32 },
+    #[error("slot '{name}' doesn't contain save files of in-game save slots {only}")]
+    NoSelectedSaveFiles { name: String, only: String },
+    #[error(
+        "refusing to save, save files smaller than {min_size} bytes might be truncated: {}",
+        .files.join(", ")
+    )]
+    SmallSaveFiles { files: Vec<String>, min_size: u64 },
+    #[error("invalid slot name '{name}', {reason}")]
+    InvalidSlotName { name: String, reason: String },
+    #[error(
+        "invalid slot index '{value}', expected a positive integer where 1 is the newest slot"
+    )]
+    InvalidIndex { value: String },
+    #[error(
+        "invalid time '{value}', expected a time like '2024-03-01 21:00', '2024-03-01', '21:00', 'yesterday' or '2h'"
+    )]
+    InvalidTime { value: String },
+    #[error("no dated slot was saved at or before {}", display_time(.time))]
+    NoSlotBefore { time: SystemTime },
+    #[error("{}", display_out_of_range(*.nth, .slots))]
+    OutOfRange { nth: usize, slots: Vec<String> },
+    #[error(
+        "only {} directories match, pick {pick} is out of range: {}",
+        .matches.len(),
+        .matches.join(", ")
+    )]
+    PickOutOfRange { pick: usize, matches: Vec<String> },
+    #[error(
+        "'{name}' matches {} directories in Save Files, use --pick to choose one: {}",
+        .matches.len(),
+        .matches.join(", ")
+    )]
+    AmbiguousSaveFiles { name: String, matches: Vec<String> },
+    #[error("{}: {source}", .path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("failed to copy {} to {}: {source}", .from.display(), .to.display())]
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+        source: io::Error,
+    },
+    #[error("copy of {} to {} is corrupt: {reason}", .from.display(), .to.display())]
+    Corrupt {
+        from: PathBuf,
+        to: PathBuf,
+        reason: String,
+    },
+    #[error(
+        "failed to download {} from OneDrive, {reason}, make its folder 'Always keep on this device'",
+        .path.display()
+    )]
+    Hydrate { path: PathBuf, reason: String },
+    #[error(
+        "not enough space for {}, need {}, have {}",
+        .path.display(),
+        output::size(*.needed),
+        output::size(*.available)
+    )]
+    InsufficientSpace {
+        path: PathBuf,
+        needed: u64,
+        available: u64,
+    },
+    #[error("failed to watch {}: {source}", .path.display())]
+    Watch {
+        path: PathBuf,
+        source: notify::Error,
+    },
+    #[error("failed to move {} to the recycle bin: {reason}", .path.display())]
+    Recycle { path: PathBuf, reason: String },
+    #[error(
+        "skipped {} slots which changed on both sides since the last sync: {}",
+        .slots.len(),
+        .slots.join(", ")
+    )]
+    SyncConflicts { slots: Vec<String> },
+    #[error("{} save files failed: {}", .errors.len(), display_errors(.errors))]
+    Multiple { errors: Vec<Error> },
+    #[error("{} of {total} profiles failed", .errors.len())]
+    ProfilesFailed { total: usize, errors: Vec<Error> },
+    #[error("{failed} of the checks failed")]
+    ChecksFailed { failed: usize },
+    #[error("{warned} of the checks found possible problems")]
+    ChecksWarned { warned: usize },
+    #[error("{}: {source}", .path.display())]
+    Zip {
+        path: PathBuf,
+        source: zip::result::ZipError,
+    },
+    #[error("{}: refusing to extract '{name}', which isn't a plain file name", .path.display())]
+    UnsafeArchiveEntry { path: PathBuf, name: String },
+    #[error("{}: refusing to extract '{name}', which is larger than {}", .path.display(), crate::output::size(*.limit))]
+    ArchiveEntryTooLarge {
+        path: PathBuf,
+        name: String,
+        limit: u64,
+    },
+    #[error("{}: refusing to extract more than {} from it", .path.display(), crate::output::size(*.limit))]
+    ArchiveTooLarge { path: PathBuf, limit: u64 },
+    #[error("{} does not contain any save files", .path.display())]
+    EmptyArchive { path: PathBuf },
+    #[error("{} isn't a valid bundle of slots, {reason}", .path.display())]
+    InvalidBundle { path: PathBuf, reason: String },
+    #[error("{} isn't a valid slot bundle, {reason}", .path.display())]
+    InvalidSlotBundle { path: PathBuf, reason: String },
+    #[error("can't download '{url}', {reason}")]
+    InvalidUrl { url: String, reason: String },
+    #[error("failed to download {url}, {reason}")]
+    Download { url: String, reason: String },
+    #[error("'{name}' is from the {from} edition of the game, refusing to load it into a profile of the {to} edition, use --force to load it anyway")]
+    EditionMismatch {
+        name: String,
+        from: Edition,
+        to: Edition,
+    },
+    #[error("'{name}' was saved with version {saved} of the game, refusing to load it into version {current} because of --strict-version")]
+    GameVersionMismatch {
+        name: String,
+        saved: String,
+        current: String,
+    },
+    #[error("unknown edition '{name}', expected legacy, enhanced or all")]
+    InvalidEdition { name: String },
+    #[error("unknown game '{name}', expected one of {}", .known.join(", "))]
+    UnknownGame { name: String, known: Vec<String> },
+    #[error("the definition of the game '{name}' can't be used, {reason}")]
+    InvalidGame { name: String, reason: String },
+    #[error("no remote is configured, add a [remote] table with a url to the config file")]
+    NoRemote,
+    #[error("the remote in the config file can't be used, {reason}")]
+    InvalidRemote { reason: String },
+    #[error("the remote refused the credentials for {url}, {reason}")]
+    RemoteAuth { url: String, reason: String },
+    #[error("the remote already has a slot bundle named '{name}', use --force to replace it")]
+    RemoteNameTaken { name: String },
+    #[error("the remote has no slot bundle named '{name}'")]
+    RemoteBundleNotFound { name: String },
+    #[error("request to {url} failed, {reason}")]
+    Remote { url: String, reason: String },
+    #[error("profile '{id}' in the bundle doesn't exist here, use --map-profile {id}=<profile> to pick where its slots go")]
+    UnmappedProfile { id: String },
+    #[error("{}: {source}", .path.display())]
+    InvalidManifest {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("{}: {source}", .path.display())]
+    InvalidConfig {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("{}: decryption failed, the passphrase is wrong or the file is corrupt", .path.display())]
+    DecryptionFailed { path: PathBuf },
+    #[error("{} is encrypted, a passphrase is needed to import it", .path.display())]
+    PassphraseRequired { path: PathBuf },
+    #[error("invalid passphrase, {reason}")]
+    InvalidPassphrase { reason: &'static str },
+    #[error("{} already exists, use --force to replace it", .path.display())]
+    FileExists { path: PathBuf },
+    #[error("invalid hotkey '{value}', {reason}")]
+    InvalidHotkey { value: String, reason: String },
+    #[error(
+        "hotkeys which are already used by another program: {}",
+        .hotkeys.join(", ")
+    )]
+    HotkeysTaken { hotkeys: Vec<String> },
+    #[error("hotkeys are only supported on Windows")]
+    HotkeysUnsupported,
+    #[error("failed to show the tray icon: {reason}")]
+    Tray { reason: String },
+    #[error("the tray icon is only supported on Windows")]
+    TrayUnsupported,
+    #[error("failed to serve on 127.0.0.1:{port}: {source}")]
+    Serve { port: u16, source: io::Error },
+    #[error("failed to talk over the pipe of the daemon: {reason}")]
+    Pipe { reason: String },
+    #[error("the daemon is only supported on Windows")]
+    DaemonUnsupported,
+    #[error("invalid command, {reason}")]
+    InvalidCommand { reason: String },
+    #[error("{message}")]
+    DaemonFailed { message: String, exit_code: i32 },
+    #[error("failed to link {} to {}: {source}", .to.display(), .from.display())]
+    HardLink {
+        from: PathBuf,
+        to: PathBuf,
+        source: io::Error,
+    },
+    #[error("failed to rename {} to {}: {source}", .from.display(), .to.display())]
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// Construct an I/O error for the given path.
+    pub(crate) fn io(path: &Path, source: io::Error) -> Self {
+        Error::Io {
+            path: path.to_owned(),
+            source,
+        }
+    }
+
+    /// A stable name for the kind of error, used in `--format json` output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::NoDocuments => "no_documents",
+            Error::NoProfiles { .. } => "no_profiles",
+            Error::MultipleProfiles => "multiple_profiles",
+            Error::ProfileNotFound { .. } => "profile_not_found",
+            Error::AmbiguousProfile { .. } => "ambiguous_profile",
+            Error::SlotNotFound { .. }
+            | Error::SimilarSlotNotFound { .. }
+            | Error::AliasedSlotNotFound { .. } => "slot_not_found",
+            Error::AliasNotFound { .. } => "alias_not_found",
+            Error::InvalidAlias { .. } => "invalid_alias",
+            Error::EmptySlot { .. } => "empty_slot",
+            Error::SlotExists { .. } => "slot_exists",
+            Error::SlotNameTaken { .. } => "slot_name_taken",
+            Error::UnexpectedFiles { .. } => "unexpected_files",
+            Error::GameRunning { .. } => "game_running",
+            Error::InstanceLocked { .. } => "instance_locked",
+            Error::Aborted => "aborted",
+            Error::Cancelled => "cancelled",
+            Error::Terminal { .. } => "terminal",
+            Error::NoBackup => "no_backup",
+            Error::NotUndoable { .. } => "not_undoable",
+            Error::ProfileModified { .. } => "profile_modified",
+            Error::NothingToStash => "nothing_to_stash",
+            Error::NothingToTransfer { .. } => "nothing_to_transfer",
+            Error::SameProfile { .. } => "same_profile",
+            Error::EmptyStash => "empty_stash",
+            Error::NothingToBackUp => "nothing_to_back_up",
+            Error::NoBackups { .. } => "no_backups",
+            Error::BackupNotFound { .. } => "backup_not_found",
+            Error::EmptyBackup { .. } => "empty_backup",
+            Error::UnsavedChanges => "unsaved_changes",
+            Error::SlotDiffers { .. } => "slot_differs",
+            Error::SlotsDiffer { .. } => "slots_differ",
+            Error::SlotsCorrupt { .. } => "slots_corrupt",
+            Error::NoManifest { .. } => "no_manifest",
+            Error::SlotPinned { .. } => "slot_pinned",
+            Error::InvalidTag { .. } => "invalid_tag",
+            Error::InvalidDateFormat { .. } => "invalid_date_format",
+            Error::InvalidDatedName { .. } => "invalid_dated_name",
+            Error::InvalidCompressionLevel { .. } => "invalid_compression_level",
+            Error::InvalidPattern { .. } => "invalid_pattern",
+            Error::InvalidSaveNumber { .. } => "invalid_save_number",
+            Error::NoSelectedSaveFiles { .. } => "no_selected_save_files",
+            Error::SmallSaveFiles { .. } => "small_save_files",
+            Error::InvalidSlotName { .. } => "invalid_slot_name",
+            Error::InvalidIndex { .. } => "invalid_index",
+            Error::InvalidTime { .. } => "invalid_time",
+            Error::NoSlotBefore { .. } => "no_slot_before",
+            Error::OutOfRange { .. } => "out_of_range",
+            Error::PickOutOfRange { .. } => "pick_out_of_range",
+            Error::AmbiguousSaveFiles { .. } => "ambiguous_save_files",
+            Error::Io { .. } => "io",
+            Error::Copy { .. } => "copy",
+            Error::Corrupt { .. } => "corrupt",
+            Error::Hydrate { .. } => "hydrate",
+            Error::InsufficientSpace { .. } => "insufficient_space",
+            Error::Watch { .. } => "watch",
+            Error::Recycle { .. } => "recycle",
+            Error::SyncConflicts { .. } => "sync_conflicts",
+            Error::Multiple { .. } => "multiple",
+            Error::ProfilesFailed { .. } => "profiles_failed",
+            Error::ChecksFailed { .. } => "checks_failed",
+            Error::ChecksWarned { .. } => "checks_warned",
+            Error::Zip { .. } => "zip",
+            Error::UnsafeArchiveEntry { .. } => "unsafe_archive_entry",
+            Error::ArchiveEntryTooLarge { .. } => "archive_entry_too_large",
+            Error::ArchiveTooLarge { .. } => "archive_too_large",
+            Error::EmptyArchive { .. } => "empty_archive",
+            Error::InvalidBundle { .. } => "invalid_bundle",
+            Error::InvalidSlotBundle { .. } => "invalid_slot_bundle",
+            Error::InvalidUrl { .. } => "invalid_url",
+            Error::Download { .. } => "download",
+            Error::EditionMismatch { .. } => "edition_mismatch",
+            Error::GameVersionMismatch { .. } => "game_version_mismatch",
+            Error::InvalidEdition { .. } => "invalid_edition",
+            Error::UnknownGame { .. } => "unknown_game",
+            Error::InvalidGame { .. } => "invalid_game",
+            Error::NoRemote => "no_remote",
+            Error::InvalidRemote { .. } => "invalid_remote",
+            Error::RemoteAuth { .. } => "remote_auth",
+            Error::RemoteNameTaken { .. } => "remote_name_taken",
+            Error::RemoteBundleNotFound { .. } => "remote_bundle_not_found",
+            Error::Remote { .. } => "remote",
+            Error::UnmappedProfile { .. } => "unmapped_profile",
+            Error::InvalidManifest { .. } => "invalid_manifest",
+            Error::InvalidConfig { .. } => "invalid_config",
+            Error::DecryptionFailed { .. } => "decryption_failed",
+            Error::PassphraseRequired { .. } => "passphrase_required",
+            Error::InvalidPassphrase { .. } => "invalid_passphrase",
+            Error::FileExists { .. } => "file_exists",
+            Error::InvalidHotkey { .. } => "invalid_hotkey",
+            Error::HotkeysTaken { .. } => "hotkeys_taken",
+            Error::HotkeysUnsupported => "hotkeys_unsupported",
+            Error::Tray { .. } => "tray",
+            Error::TrayUnsupported => "tray_unsupported",
+            Error::Serve { .. } => "serve",
+            Error::Pipe { .. } => "pipe",
+            Error::DaemonUnsupported => "daemon_unsupported",
+            Error::InvalidCommand { .. } => "invalid_command",
+            Error::DaemonFailed { .. } => "daemon_failed",
+            Error::HardLink { .. } => "hard_link",
+            Error::Rename { .. } => "rename",
+        }
+    }
+
+    /// The exit code used by the command line tool for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::NoDocuments | Error::NoProfiles { .. } => 2,
+            Error::SlotNotFound { .. }
+            | Error::SimilarSlotNotFound { .. }
+            | Error::AliasedSlotNotFound { .. }
+            | Error::AliasNotFound { .. }
+            | Error::EmptySlot { .. }
+            | Error::NoSelectedSaveFiles { .. }
+            | Error::NoSlotBefore { .. }
+            | Error::NoBackup
+            | Error::NotUndoable { .. }
+            | Error::NothingToStash
+            | Error::NothingToTransfer { .. }
+            | Error::EmptyStash
+            | Error::NothingToBackUp
+            | Error::NoBackups { .. }
+            | Error::BackupNotFound { .. }
+            | Error::EmptyBackup { .. }
+            | Error::RemoteBundleNotFound { .. } => 3,
+            Error::Io { .. }
+            | Error::Copy { .. }
+            | Error::Corrupt { .. }
+            | Error::Zip { .. }
+            | Error::Download { .. }
+            | Error::RemoteAuth { .. }
+            | Error::Remote { .. }
+            | Error::HardLink { .. }
+            | Error::Rename { .. }
+            | Error::Hydrate { .. }
+            | Error::Terminal { .. }
+            | Error::InsufficientSpace { .. } => 4,
+            Error::OutOfRange { .. } | Error::PickOutOfRange { .. } => 5,
+            Error::InstanceLocked { .. } => 6,
+            Error::ChecksWarned { .. } => 7,
+            Error::ChecksFailed { .. } => 8,
+            Error::Cancelled => 9,
+            Error::AmbiguousSaveFiles { .. } => 10,
+            Error::DaemonFailed { exit_code, .. } => *exit_code,
+            Error::Multiple { errors } | Error::ProfilesFailed { errors, .. } => {
+                errors.first().map_or(1, Error::exit_code)
+            }
+            _ => 1,
+        }
+    }
+}
+
+/// Format a point in time for display in an error.
+fn display_time(time: &SystemTime) -> String {
+    let time = chrono::DateTime::<chrono::Local>::from(*time);
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Format the names of the available slots for display in an error.
+fn display_out_of_range(nth: usize, slots: &[String]) -> String {
+    if slots.is_empty() {
+        return format!("there are no slots yet, index {} is out of range", nth);
+    }
+
+    format!(
+        "only {} slots exist, index {} is out of range, available slots: {}",
+        slots.len(),
+        nth,
+        slots.join(", ")
+    )
+}
+
+/// Format a list of errors for display in an error.
+fn display_errors(errors: &[Error]) -> String {
+    let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    errors.join("; ")
+}
+
+/// Format a list of paths for display in an error.
+fn display_paths(paths: &[PathBuf]) -> String {
+    let paths = paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>();
+
+    paths.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        let error = Error::SlotNotFound {
+            name: "foo".to_string(),
+        };
+        assert_eq!(error.exit_code(), 3);
+
+        let error = Error::OutOfRange {
+            nth: 4,
+            slots: vec!["foo".to_string(), "bar".to_string()],
+        };
+        assert_eq!(
+            error.to_string(),
+            "only 2 slots exist, index 4 is out of range, available slots: foo, bar"
+        );
+        assert_eq!(error.exit_code(), 5);
+
+        let error = Error::OutOfRange {
+            nth: 1,
+            slots: Vec::new(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "there are no slots yet, index 1 is out of range"
+        );
+
+        let error = Error::io(
+            Path::new("SGTA00000"),
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        assert_eq!(error.exit_code(), 4);
+
+        let error = Error::AmbiguousSaveFiles {
+            name: "chop".to_string(),
+            matches: vec!["Chop10".to_string(), "Chop9".to_string()],
+        };
+        assert_eq!(
+            error.to_string(),
+            "'chop' matches 2 directories in Save Files, use --pick to choose one: Chop10, Chop9"
+        );
+        assert_eq!(error.exit_code(), 10);
+
+        let error = Error::MultipleProfiles;
+        assert_eq!(error.exit_code(), 1);
+        assert_eq!(error.kind(), "multiple_profiles");
+
+        let error = Error::ProfilesFailed {
+            total: 3,
+            errors: vec![Error::NoBackup, Error::MultipleProfiles],
+        };
+        assert_eq!(error.to_string(), "2 of 3 profiles failed");
+        assert_eq!(error.exit_code(), 3);
+    }
+}
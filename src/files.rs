@@ -0,0 +1,1055 @@
+use crate::{
+    compress, hash, hydrate,
+    manifest::MANIFEST,
+    ops::{is_readonly, FsOp, Ops},
+    output::Style,
+    progress::Progress,
+    space, Error, SaveFilePatterns,
+};
+use std::{
+    cmp::Ordering,
+    ffi::{OsStr, OsString},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{self, AtomicUsize},
+    thread,
+    time::{Instant, SystemTime},
+};
+
+/// Name of the directory used to stage files before they are swapped into place.
+pub(crate) const STAGING: &str = ".gtav-saveload-staging";
+
+/// Settings files in the profile, which are saved and loaded along with the save files if
+/// [Ops::settings] is set.
+pub(crate) const SETTINGS_FILES: &[&str] = &["pc_settings.bin", "cfg.dat"];
+
+/// Save files smaller than this many bytes are warned about as probably truncated, unless
+/// another size is given. Even the save of a new game is several hundred KB.
+pub const DEFAULT_MIN_SAVE_SIZE: u64 = 64 * 1024;
+
+/// The most save files which are copied and verified at the same time.
+const STAGING_THREADS: usize = 4;
+
+/// The longest name of a directory created by the user, in UTF-16 code units like Windows
+/// counts them. File names can be up to 255, which leaves room for suffixes like `-2`.
+pub(crate) const MAX_NAME: usize = 200;
+
+/// Characters which can't be used in file names on Windows.
+const INVALID_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names which can't be used as file names on Windows, even with an extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// List all save files in the given directory, which are the files matched by `patterns`.
+///
+/// Compressed save files are listed by the name they have once they're decompressed, like
+/// `SGTA00000` for `SGTA00000.zst`, while the path is the one of the compressed file.
+pub(crate) fn list_save_files(
+    path: &Path,
+    patterns: &SaveFilePatterns,
+) -> Result<Vec<(OsString, PathBuf)>, Error> {
+    let files = find_matching(path, |p| p.is_file(), |n| is_save_file_name(n, patterns))?;
+
+    Ok(files
+        .into_iter()
+        .map(|(name, path)| (compress::save_file_name(name), path))
+        .collect())
+}
+
+/// List the settings files in the given directory, see [SETTINGS_FILES].
+pub(crate) fn list_settings_files(path: &Path) -> Result<Vec<(OsString, PathBuf)>, Error> {
+    find_matching(path, |p| p.is_file(), is_settings_file_name)
+}
+
+/// List everything in the given directory which isn't a save file, a settings file or a
+/// manifest.
+pub(crate) fn list_other_files(
+    path: &Path,
+    patterns: &SaveFilePatterns,
+) -> Result<Vec<(OsString, PathBuf)>, Error> {
+    let mut files = find_matching(path, |_| true, |_| true)?;
+    files.retain(|(name, path)| {
+        !(path.is_file()
+            && (is_save_file_name(name, patterns)
+                || is_settings_file_name(name)
+                || name == MANIFEST))
+    });
+    Ok(files)
+}
+
+/// List directories whose name contains the given name, ignoring case.
+pub(crate) fn list_name_contains(
+    path: &Path,
+    name: &str,
+) -> Result<Vec<(OsString, PathBuf)>, Error> {
+    let name = name.to_lowercase();
+    find_matching(
+        path,
+        |p| p.is_dir(),
+        |n| n.to_string_lossy().to_lowercase().contains(&name),
+    )
+}
+
+/// Test if the file name is the name of a save file, even if it isn't valid UTF-8.
+///
+/// Manifests and settings files are never save files, whatever the patterns say.
+pub(crate) fn is_save_file_name(name: &OsStr, patterns: &SaveFilePatterns) -> bool {
+    name != MANIFEST && !is_settings_file_name(name) && patterns.matches(name)
+}
+
+/// Test if the file name is one of [SETTINGS_FILES], ignoring case like Windows does.
+fn is_settings_file_name(name: &OsStr) -> bool {
+    match name.to_str() {
+        Some(name) => SETTINGS_FILES.iter().any(|s| s.eq_ignore_ascii_case(name)),
+        None => false,
+    }
+}
+
+/// The number of the in-game save slot of a save file, which is the last two digits after the
+/// letters its name starts with, like 3 for `SGTA00003` or `SRDR30003`. Anything after the
+/// digits, like an extension, is ignored.
+pub(crate) fn save_number(name: &OsStr) -> Option<u32> {
+    let name = name.to_str()?;
+    let rest = name.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+
+    if rest.len() == name.len() {
+        return None;
+    }
+
+    let digits = &rest[..rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len())];
+
+    if digits.len() < 2 {
+        return None;
+    }
+
+    digits[digits.len() - 2..].parse().ok()
+}
+
+/// A selection of in-game save slots, see [save_number].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Selection(u128);
+
+impl Selection {
+    /// Select the given in-game save slots, or `None` to select every save file if there are
+    /// none.
+    pub(crate) fn new(numbers: &[u32]) -> Result<Option<Self>, Error> {
+        let mut bits = 0u128;
+
+        for &number in numbers {
+            if number > 99 {
+                return Err(Error::InvalidSaveNumber { number });
+            }
+
+            bits |= 1 << number;
+        }
+
+        Ok(if bits == 0 { None } else { Some(Self(bits)) })
+    }
+
+    /// Test if the save file with the given name is selected.
+    pub(crate) fn contains(self, name: &OsStr) -> bool {
+        matches!(save_number(name), Some(n) if self.0 & (1 << n) != 0)
+    }
+}
+
+/// List the save files in the given directory which are selected by [Ops::only].
+pub(crate) fn list_selected(ops: &Ops, path: &Path) -> Result<Vec<(OsString, PathBuf)>, Error> {
+    let mut files = list_save_files(path, &ops.patterns)?;
+
+    if let Some(only) = ops.only {
+        files.retain(|(name, _)| only.contains(name));
+    }
+
+    Ok(files)
+}
+
+/// List the files in `from` which are copied, which are the selected save files and the
+/// settings files if [Ops::settings] is set.
+fn list_copied(ops: &Ops, from: &Path) -> Result<Vec<(OsString, PathBuf)>, Error> {
+    let mut files = list_selected(ops, from)?;
+
+    if ops.settings {
+        files.extend(list_settings_files(from)?);
+    }
+
+    Ok(files)
+}
+
+/// List the files in `to` which are replaced by the given copied files, which are the selected
+/// save files and the settings files which are copied. Settings files are never removed.
+fn list_replaced(
+    ops: &Ops,
+    to: &Path,
+    copied: &[(OsString, PathBuf)],
+) -> Result<Vec<(OsString, PathBuf)>, Error> {
+    let mut files = list_selected(ops, to)?;
+
+    if ops.settings {
+        for (name, path) in list_settings_files(to)? {
+            if copied.iter().any(|(n, _)| *n == name) {
+                files.push((name, path));
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Check that the name can be used as a file name on Windows, returning why it can't.
+///
+/// Names are checked on every platform, so that slots can be synced to and from Windows.
+pub(crate) fn check_file_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err(String::from("it's empty"));
+    }
+
+    if let Some(c) = name.chars().find(|c| INVALID_CHARS.contains(c)) {
+        return Err(format!(
+            "it contains '{}', which can't be used in file names",
+            c
+        ));
+    }
+
+    if name.chars().any(char::is_control) {
+        return Err(String::from("it contains a control character"));
+    }
+
+    if name.ends_with(&['.', ' '][..]) {
+        return Err(String::from("it ends with a dot or a space"));
+    }
+
+    // `con.txt` and `CON ` are reserved as well.
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+
+    if let Some(reserved) = RESERVED_NAMES.iter().find(|r| r.eq_ignore_ascii_case(stem)) {
+        return Err(format!(
+            "'{}' is a reserved device name on Windows",
+            reserved
+        ));
+    }
+
+    if name.encode_utf16().count() > MAX_NAME {
+        return Err(format!("it's longer than {} characters", MAX_NAME));
+    }
+
+    Ok(())
+}
+
+/// List the given save files which are smaller than `min_size` bytes once decompressed, like
+/// the empty ones a crash can leave behind, by name along with their size.
+pub(crate) fn small_save_files(
+    save_files: &[(OsString, PathBuf)],
+    min_size: u64,
+) -> Result<Vec<(String, u64)>, Error> {
+    let mut small = Vec::new();
+
+    for (name, path) in save_files {
+        let meta = fs::metadata(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+
+        // Decompressing doesn't make a file smaller, so only small files need to be.
+        if meta.len() >= min_size {
+            continue;
+        }
+
+        let size = compress::size(path)?;
+
+        if size < min_size {
+            small.push((display_name(name), size));
+        }
+    }
+
+    small.sort();
+    Ok(small)
+}
+
+/// Convert a file name for display, marking names which aren't valid UTF-8 since they can't be
+/// shown exactly.
+pub(crate) fn display_name(name: &OsStr) -> String {
+    match name.to_str() {
+        Some(name) => name.to_string(),
+        None => format!("{} (invalid UTF-8)", name.to_string_lossy()),
+    }
+}
+
+/// Compare two names the way a person would, ignoring case and comparing runs of digits by
+/// their numeric value, so that `save9` sorts before `Save10`.
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_runs = runs(a);
+    let mut b_runs = runs(b);
+
+    loop {
+        let (a, b) = match (a_runs.next(), b_runs.next()) {
+            (Some(a), Some(b)) => (a, b),
+            (None, None) => break,
+            (None, Some(..)) => return Ordering::Less,
+            (Some(..), None) => return Ordering::Greater,
+        };
+
+        let a_digits = a.starts_with(|c: char| c.is_ascii_digit());
+        let b_digits = b.starts_with(|c: char| c.is_ascii_digit());
+
+        let ordering = if a_digits && b_digits {
+            let a = a.trim_start_matches('0');
+            let b = b.trim_start_matches('0');
+            a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+        } else {
+            a.to_lowercase().cmp(&b.to_lowercase())
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    // Fall back to an exact comparison so that the order is total.
+    a.cmp(b)
+}
+
+/// Split a name into alternating runs of digits and non-digits.
+fn runs(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(end);
+        rest = tail;
+        Some(run)
+    })
+}
+
+/// Convert the path into an extended-length path, prefixed with `\\?\`, which isn't limited
+/// to `MAX_PATH` (260 characters) even if long paths aren't enabled for the system.
+///
+/// Deep OneDrive folders can push slots in the Documents folder past that limit. Relative
+/// paths are made absolute first, since extended-length paths are used as is. Paths are left
+/// as they are on other platforms.
+pub(crate) fn to_extended_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        use std::{
+            ffi::OsString,
+            os::windows::ffi::{OsStrExt as _, OsStringExt as _},
+        };
+
+        let absolute = match std::path::absolute(path) {
+            Ok(absolute) => absolute,
+            Err(..) => return path.to_owned(),
+        };
+
+        let wide = absolute.as_os_str().encode_wide().collect::<Vec<_>>();
+        let starts_with =
+            |prefix: &str| wide.starts_with(&prefix.encode_utf16().collect::<Vec<_>>());
+
+        let extended = if starts_with(r"\\?\") || starts_with(r"\\.\") {
+            return absolute;
+        } else if starts_with(r"\\") {
+            // A network share like `\\server\share`.
+            r"\\?\UNC\"
+                .encode_utf16()
+                .chain(wide[2..].iter().copied())
+                .collect::<Vec<_>>()
+        } else {
+            r"\\?\".encode_utf16().chain(wide).collect::<Vec<_>>()
+        };
+
+        PathBuf::from(OsString::from_wide(&extended))
+    }
+
+    #[cfg(not(windows))]
+    {
+        path.to_owned()
+    }
+}
+
+/// Find files matching the given predicate.
+///
+/// Names are matched as they are, so that entries which aren't valid UTF-8 aren't skipped.
+pub(crate) fn find_matching<P, F>(
+    path: &Path,
+    p: P,
+    m: F,
+) -> Result<Vec<(OsString, PathBuf)>, Error>
+where
+    P: Copy + Fn(&Path) -> bool,
+    F: Copy + Fn(&OsStr) -> bool,
+{
+    let mut out = Vec::new();
+
+    debug!("read directory {}", path.display());
+
+    for entry in fs::read_dir(to_extended_path(path)).map_err(|e| Error::io(path, e))? {
+        let entry = entry.map_err(|e| Error::io(path, e))?;
+        // Joined onto the original path, so that it's displayed without the extended prefix.
+        let name = entry.file_name();
+        let path = path.join(&name);
+
+        if p(&path) && m(&name) {
+            trace!("matched {}", path.display());
+            out.push((name, path))
+        } else {
+            trace!("skipped {}", path.display());
+        }
+    }
+
+    Ok(out)
+}
+
+/// Copy save files from `from`, to `to`, replacing any existing save files in `to` in the process.
+///
+/// If [Ops::only] is set, only the selected save files are copied and replaced, and the others
+/// in `to` are left alone. Settings files are copied if [Ops::settings] is set, but only ever
+/// replaced and not removed.
+///
+/// This happens in two phases. All source files are first copied into a staging directory
+/// inside of `to`, and only once that has succeeded are the existing save files moved out of
+/// the way and the new ones renamed into place. If anything fails, the original save files in
+/// `to` are left untouched.
+///
+/// Errors before anything is copied if there isn't enough space for the new save files, and
+/// with [Error::Cancelled] if cancelled before the save files are swapped, which is done
+/// without stopping.
+///
+/// In a dry run, the staging is skipped and the effective deletes and copies are printed.
+pub(crate) fn copy_save_files(ops: &Ops, from: &Path, to: &Path) -> Result<(), Error> {
+    if ops.dry_run {
+        let mut skipped = Vec::new();
+        let mut kept = Vec::new();
+        let copied = list_copied(ops, from)?;
+
+        if to.is_dir() {
+            for (name, save_file) in list_replaced(ops, to, &copied)? {
+                if ops.respect_readonly && is_readonly(&save_file) {
+                    skipped.push(save_file);
+                    kept.push(name);
+                    continue;
+                }
+
+                ops.run(FsOp::RemoveFile(&save_file))?;
+            }
+        }
+
+        for (name, save_file) in copied {
+            if !kept.contains(&name) {
+                ops.run(FsOp::Copy(&save_file, &to.join(name)))?;
+            }
+        }
+
+        report_readonly(&skipped);
+        return Ok(());
+    }
+
+    let copied = list_copied(ops, from)?;
+    let total = total_size(&copied)?;
+    space::check(to, total)?;
+
+    let staging = to.join(STAGING);
+
+    if staging.is_dir() {
+        ops.run(FsOp::RemoveDirAll(&staging))?;
+    }
+
+    // Files are only copied in chunks if progress is shown, since copying them whole is faster.
+    let progress = Progress::start("copied", copied.len(), total);
+
+    let staging_ops = Ops {
+        progress: progress.clone(),
+        ..ops.clone()
+    };
+
+    let result = stage_save_files(&staging_ops, from, &staging);
+
+    if let Some(progress) = &progress {
+        progress.finish();
+    }
+
+    // The last point where cancelling leaves `to` as it was, since the swap isn't stopped.
+    let result = result.and_then(|staged| {
+        ops.check_cancelled()?;
+        swap_save_files(ops, &staging, staged, to)
+    });
+
+    if let Err(e) = ops.run(FsOp::RemoveDirAll(&staging)) {
+        out!("Failed to remove staging directory: {}", e);
+    }
+
+    result
+}
+
+/// The total size of the given files once they're decompressed.
+pub(crate) fn total_size(files: &[(OsString, PathBuf)]) -> Result<u64, Error> {
+    let mut total = 0;
+
+    for (_, path) in files {
+        total += compress::size(path)?;
+    }
+
+    Ok(total)
+}
+
+/// Copy all save files in `from` into `staging`, returning the names and original paths of the
+/// staged files sorted by name.
+///
+/// The files are copied and verified in parallel, since that's mostly waiting on the disk. Every
+/// file is attempted, and all of the errors are returned together.
+fn stage_save_files(
+    ops: &Ops,
+    from: &Path,
+    staging: &Path,
+) -> Result<Vec<(OsString, PathBuf)>, Error> {
+    let new = staging.join("new");
+    ops.run(FsOp::CreateDir(&new))?;
+
+    let mut save_files = list_copied(ops, from)?;
+    save_files.sort();
+
+    // Downloaded one at a time up front, so that it's clear which one is being waited on.
+    for (_, save_file) in &save_files {
+        ops.check_cancelled()?;
+        hydrate::ensure_local(ops, save_file)?;
+    }
+
+    let started = Instant::now();
+    let next = AtomicUsize::new(0);
+
+    let stage = || {
+        let mut done = Vec::new();
+
+        loop {
+            if ops.is_cancelled() {
+                break done;
+            }
+
+            let index = next.fetch_add(1, atomic::Ordering::SeqCst);
+
+            let (name, save_file) = match save_files.get(index) {
+                Some(file) => file,
+                None => break done,
+            };
+
+            done.push((index, stage_save_file(ops, save_file, &new.join(name))));
+
+            if let Some(progress) = &ops.progress {
+                progress.file_done();
+            }
+        }
+    };
+
+    let mut results = save_files.iter().map(|_| None).collect::<Vec<_>>();
+
+    thread::scope(|s| {
+        let threads = (0..STAGING_THREADS.min(save_files.len()))
+            .map(|_| s.spawn(stage))
+            .collect::<Vec<_>>();
+
+        // A thread which panics leaves the files it was working on without a result.
+        for done in threads.into_iter().flat_map(|t| t.join()) {
+            for (index, result) in done {
+                results[index] = Some(result);
+            }
+        }
+    });
+
+    // What was staged is thrown away, so the files which failed along the way don't matter.
+    ops.check_cancelled()?;
+
+    let mut errors = Vec::new();
+
+    for ((name, save_file), result) in save_files.iter().zip(results) {
+        match result {
+            Some(Ok(bytes)) => {
+                if let (true, Some(bytes)) = (ops.verbose, bytes) {
+                    out!("verified: {} ({} bytes)", save_file.display(), bytes);
+                }
+            }
+            Some(Err(e)) => errors.push(e),
+            None => errors.push(Error::Copy {
+                from: save_file.clone(),
+                to: new.join(name),
+                source: io::Error::other("copying was interrupted"),
+            }),
+        }
+    }
+
+    if ops.verbose {
+        out!(
+            "staged {} save files in {}ms",
+            save_files.len(),
+            started.elapsed().as_millis()
+        );
+    }
+
+    match errors.len() {
+        0 => Ok(save_files),
+        1 => Err(errors.swap_remove(0)),
+        _ => Err(Error::Multiple { errors }),
+    }
+}
+
+/// Copy a single save file into staging, decompressing it if it's compressed, returning the
+/// number of bytes verified if it was.
+pub(crate) fn stage_save_file(
+    ops: &Ops,
+    save_file: &Path,
+    dest: &Path,
+) -> Result<Option<u64>, Error> {
+    if compress::is_compressed(save_file) {
+        let contents = compress::read(save_file)?;
+        ops.run(FsOp::Write(dest, &contents))?;
+
+        if let Some(progress) = &ops.progress {
+            progress.add(contents.len() as u64);
+        }
+    } else {
+        ops.run(FsOp::Copy(save_file, dest))?;
+    }
+
+    if ops.preserve_times {
+        copy_times(save_file, dest)?;
+    }
+
+    if !ops.verify {
+        return Ok(None);
+    }
+
+    hash::verify_copy(save_file, dest).map(Some)
+}
+
+/// Set when the file at `path` was modified, like for files extracted from an archive which
+/// records when they were.
+pub(crate) fn set_modified(path: &Path, modified: SystemTime) -> Result<(), Error> {
+    let modified = filetime::FileTime::from_system_time(modified);
+    filetime::set_file_mtime(to_extended_path(path), modified).map_err(|e| Error::io(path, e))
+}
+
+/// Give `to` the same access and modification times as `from`, and on Windows also the same
+/// creation time.
+pub(crate) fn copy_times(from: &Path, to: &Path) -> Result<(), Error> {
+    let meta = fs::metadata(to_extended_path(from)).map_err(|e| Error::io(from, e))?;
+    let atime = filetime::FileTime::from_last_access_time(&meta);
+    let mtime = filetime::FileTime::from_last_modification_time(&meta);
+    filetime::set_file_times(to_extended_path(to), atime, mtime).map_err(|e| Error::io(to, e))?;
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileTimesExt as _;
+
+        let created = meta.created().map_err(|e| Error::io(from, e))?;
+
+        fs::OpenOptions::new()
+            .write(true)
+            .open(to_extended_path(to))
+            .and_then(|f| f.set_times(fs::FileTimes::new().set_created(created)))
+            .map_err(|e| Error::io(to, e))?;
+    }
+
+    Ok(())
+}
+
+/// Move the existing save files in `to` into `staging`, and the staged files into `to`.
+///
+/// Rolls back to the original save files if any step fails.
+fn swap_save_files(
+    ops: &Ops,
+    staging: &Path,
+    staged: Vec<(OsString, PathBuf)>,
+    to: &Path,
+) -> Result<(), Error> {
+    let new = staging.join("new");
+    let old = staging.join("old");
+    ops.run(FsOp::CreateDir(&old))?;
+
+    let mut moved = Vec::new();
+    let mut placed = Vec::new();
+    let mut skipped = Vec::new();
+    let mut kept = Vec::new();
+
+    let result = (|| -> Result<(), Error> {
+        for (name, save_file) in list_replaced(ops, to, &staged)? {
+            if ops.respect_readonly && is_readonly(&save_file) {
+                skipped.push(save_file);
+                kept.push(name);
+                continue;
+            }
+
+            // Moved by the name on disk, so that compressed save files are restored as they were.
+            let file_name = save_file.file_name().map_or(name.clone(), OsStr::to_owned);
+            ops.run(FsOp::Rename(&save_file, &old.join(&file_name)))?;
+            moved.push((name, file_name));
+        }
+
+        for (name, source) in &staged {
+            if kept.contains(name) {
+                continue;
+            }
+
+            let dest = to.join(name);
+
+            ops.run(FsOp::Rename(&new.join(name), &dest))?;
+            out_styled!(Style::Copied, "{} -> {}", source.display(), dest.display());
+            placed.push(name.clone());
+        }
+
+        Ok(())
+    })();
+
+    if result.is_err() {
+        for name in placed {
+            let _ = ops.run(FsOp::RemoveFile(&to.join(name)));
+        }
+
+        for (_, file_name) in moved {
+            let _ = ops.run(FsOp::Rename(&old.join(&file_name), &to.join(&file_name)));
+        }
+    } else {
+        // Only mention the old save files which weren't replaced by a new one.
+        for (name, file_name) in moved {
+            if !staged.iter().any(|(n, _)| *n == name) {
+                out!("delete: {}", to.join(file_name).display());
+            }
+        }
+
+        report_readonly(&skipped);
+    }
+
+    result
+}
+
+/// Delete save files in the given path, moving them to the Recycle Bin unless
+/// [Ops::permanent] is set.
+///
+/// Read-only save files are skipped and reported if [Ops::respect_readonly] is set, and only the
+/// selected save files are deleted if [Ops::only] is. Cancelling stops it between files, with
+/// the ones before already deleted.
+pub(crate) fn delete_save_files(ops: &Ops, path: &Path) -> Result<(), Error> {
+    let mut skipped = Vec::new();
+
+    for (_, save_file) in list_selected(ops, path)? {
+        ops.check_cancelled()?;
+
+        if ops.respect_readonly && is_readonly(&save_file) {
+            skipped.push(save_file);
+            continue;
+        }
+
+        ops.remove(&save_file)?;
+    }
+
+    report_readonly(&skipped);
+    Ok(())
+}
+
+/// Report the read-only save files which were left alone.
+fn report_readonly(skipped: &[PathBuf]) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    eout!(
+        "warning: left {} read-only save files alone: {}",
+        skipped.len(),
+        skipped
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_save_files_cleans_up_staging() -> Result<(), Box<dyn std::error::Error>> {
+        let profile = tempfile::tempdir()?;
+        fs::write(profile.path().join("SGTA00000"), b"current")?;
+        fs::write(profile.path().join("SGTA00001"), b"current")?;
+
+        let slot = tempfile::tempdir()?;
+        fs::write(slot.path().join("SGTA00000"), b"old")?;
+        fs::write(slot.path().join("SGTA00005"), b"old")?;
+
+        copy_save_files(&Ops::default(), profile.path(), slot.path())?;
+
+        let mut files = list_save_files(slot.path(), &SaveFilePatterns::default())?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        files.sort();
+
+        assert_eq!(files, vec!["SGTA00000", "SGTA00001"]);
+        assert_eq!(fs::read(slot.path().join("SGTA00000"))?, b"current");
+        assert!(!slot.path().join(STAGING).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn respect_readonly_save_files() -> Result<(), Box<dyn std::error::Error>> {
+        let readonly = |path: &Path| -> std::io::Result<()> {
+            let mut permissions = fs::metadata(path)?.permissions();
+            permissions.set_readonly(true);
+            fs::set_permissions(path, permissions)
+        };
+
+        let ops = Ops {
+            respect_readonly: true,
+            ..Ops::default()
+        };
+
+        let slot = tempfile::tempdir()?;
+        fs::write(slot.path().join("SGTA00000"), b"new")?;
+        fs::write(slot.path().join("SGTA00001"), b"new")?;
+
+        let profile = tempfile::tempdir()?;
+        fs::write(profile.path().join("SGTA00000"), b"protected")?;
+        fs::write(profile.path().join("SGTA00001"), b"current")?;
+        readonly(&profile.path().join("SGTA00000"))?;
+
+        copy_save_files(&ops, slot.path(), profile.path())?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"protected");
+        assert_eq!(fs::read(profile.path().join("SGTA00001"))?, b"new");
+
+        delete_save_files(&ops, profile.path())?;
+        let files = list_save_files(profile.path(), &SaveFilePatterns::default())?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "SGTA00000");
+
+        // Deleted anyway by default.
+        delete_save_files(&Ops::default(), profile.path())?;
+        assert!(list_save_files(profile.path(), &SaveFilePatterns::default())?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn copy_and_delete_long_paths() -> Result<(), Box<dyn std::error::Error>> {
+        let root = tempfile::tempdir()?;
+        let deep = (0..4).fold(root.path().to_owned(), |path, n| {
+            path.join(format!("{}{}", n, "OneDrive - Documents ".repeat(4)))
+        });
+
+        let profile = deep.join("Profiles").join("abcd1234");
+        let slot = profile.join("Slots").join("dated-2024-01-01_120000");
+        assert!(slot.join("SGTA00000").as_os_str().len() > 260);
+
+        let ops = Ops::default();
+        ops.run(FsOp::CreateDir(&profile))?;
+        ops.run(FsOp::Write(&profile.join("SGTA00000"), b"current"))?;
+        ops.run(FsOp::CreateDir(&slot))?;
+
+        copy_save_files(&ops, &profile, &slot)?;
+        let files = list_save_files(&slot, &SaveFilePatterns::default())?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].1, slot.join("SGTA00000"));
+        assert_eq!(hash::hash_file(&files[0].1)?.size, 7);
+
+        delete_save_files(&ops, &slot)?;
+        assert!(list_save_files(&slot, &SaveFilePatterns::default())?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn extended_paths() {
+        let extended = |path: &str| to_extended_path(Path::new(path));
+
+        assert_eq!(extended(r"C:\Users\Me"), PathBuf::from(r"\\?\C:\Users\Me"));
+        assert_eq!(
+            extended(r"\\server\share\GTA V"),
+            PathBuf::from(r"\\?\UNC\server\share\GTA V")
+        );
+        assert_eq!(extended(r"\\?\C:\GTA V"), PathBuf::from(r"\\?\C:\GTA V"));
+        assert!(extended("Profiles").is_absolute());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn non_utf8_names_are_matched() -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::ffi::OsStrExt as _;
+
+        let name = OsStr::from_bytes(b"SGTA0000\xff");
+        let other = OsStr::from_bytes(b"notes\xff.txt");
+
+        let slot = tempfile::tempdir()?;
+        fs::write(slot.path().join("SGTA00000"), b"current")?;
+        fs::write(slot.path().join(name), b"current")?;
+        fs::write(slot.path().join(other), b"notes")?;
+
+        let mut files = list_save_files(slot.path(), &SaveFilePatterns::default())?;
+        files.sort();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].0, name);
+        assert_eq!(
+            display_name(&files[1].0),
+            "SGTA0000\u{fffd} (invalid UTF-8)"
+        );
+        assert_eq!(display_name(&files[0].0), "SGTA00000");
+
+        let others = list_other_files(slot.path(), &SaveFilePatterns::default())?;
+        assert_eq!(others.len(), 1);
+        assert_eq!(others[0].0, other);
+
+        delete_save_files(&Ops::default(), slot.path())?;
+        assert!(list_save_files(slot.path(), &SaveFilePatterns::default())?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn stage_reports_every_failure() -> Result<(), Box<dyn std::error::Error>> {
+        let from = tempfile::tempdir()?;
+
+        for n in 0..10 {
+            fs::write(from.path().join(format!("SGTA0000{}", n)), [n; 64])?;
+        }
+
+        let staging = tempfile::tempdir()?;
+        let staged = stage_save_files(&Ops::default(), from.path(), staging.path())?;
+        let names = staged.iter().map(|(n, _)| n.clone()).collect::<Vec<_>>();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names.len(), 10);
+        assert_eq!(names, sorted);
+
+        // Directories in the way of two of the copies.
+        let staging = tempfile::tempdir()?;
+        fs::create_dir_all(staging.path().join("new").join("SGTA00003"))?;
+        fs::create_dir_all(staging.path().join("new").join("SGTA00007"))?;
+
+        match stage_save_files(&Ops::default(), from.path(), staging.path()) {
+            Err(Error::Multiple { errors }) => {
+                assert_eq!(errors.len(), 2);
+                assert!(errors[0].to_string().contains("SGTA00003"));
+                assert!(errors[1].to_string().contains("SGTA00007"));
+            }
+            other => panic!("expected both copies to fail: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn save_numbers() -> Result<(), Error> {
+        let number = |name: &str| save_number(OsStr::new(name));
+
+        assert_eq!(number("SGTA00000"), Some(0));
+        assert_eq!(number("SGTA00012"), Some(12));
+        assert_eq!(number("SGTA00003.bak"), Some(3));
+        assert_eq!(number("SGTA"), None);
+        assert_eq!(number("SGTA1"), None);
+        assert_eq!(number("SGTAxx"), None);
+        assert_eq!(number("SRDR30011"), Some(11));
+        assert_eq!(number("00001"), None);
+        assert_eq!(number("manifest.json"), None);
+
+        let only = Selection::new(&[0, 12])?.expect("a selection");
+        assert!(only.contains(OsStr::new("SGTA00000")));
+        assert!(only.contains(OsStr::new("SGTA00012")));
+        assert!(!only.contains(OsStr::new("SGTA00001")));
+        assert!(!only.contains(OsStr::new("SGTA")));
+
+        assert_eq!(Selection::new(&[])?, None);
+        assert!(matches!(
+            Selection::new(&[100]),
+            Err(Error::InvalidSaveNumber { number: 100 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn check_file_names() {
+        for valid in &[
+            "foo",
+            "before heist",
+            "dated-2024-03-01_210405",
+            "console",
+            "COM10",
+        ] {
+            assert!(check_file_name(valid).is_ok(), "{:?}", valid);
+        }
+
+        for invalid in &[
+            "",
+            "a:b",
+            "a<b",
+            "a\"b",
+            "a|b",
+            "a?",
+            "a*",
+            "a\u{7}",
+            "foo.",
+            "foo ",
+            "con",
+            "CON.txt",
+            "Nul ",
+            "lpt9.tar.gz",
+            "com1",
+        ] {
+            assert!(check_file_name(invalid).is_err(), "{:?}", invalid);
+        }
+
+        assert!(check_file_name(&"a".repeat(MAX_NAME)).is_ok());
+        assert!(check_file_name(&"a".repeat(MAX_NAME + 1)).is_err());
+        assert_eq!(
+            check_file_name("foo:bar"),
+            Err(String::from(
+                "it contains ':', which can't be used in file names"
+            ))
+        );
+    }
+
+    #[test]
+    fn natural_ordering() {
+        let mut names = vec![
+            "SaveGames10",
+            "savegames9",
+            "SaveGames009b",
+            "Chop",
+            "chop2",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec![
+                "Chop",
+                "chop2",
+                "savegames9",
+                "SaveGames009b",
+                "SaveGames10"
+            ]
+        );
+    }
+
+    #[test]
+    fn copy_save_files_preserves_times() -> Result<(), Box<dyn std::error::Error>> {
+        let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+
+        let from = tempfile::tempdir()?;
+        fs::write(from.path().join("SGTA00000"), b"current")?;
+        filetime::set_file_mtime(from.path().join("SGTA00000"), old)?;
+
+        let mtime = |ops: &Ops| -> Result<filetime::FileTime, Box<dyn std::error::Error>> {
+            let to = tempfile::tempdir()?;
+            copy_save_files(ops, from.path(), to.path())?;
+            let meta = fs::metadata(to.path().join("SGTA00000"))?;
+            Ok(filetime::FileTime::from_last_modification_time(&meta))
+        };
+
+        assert_eq!(mtime(&Ops::default())?, old);
+
+        let ops = Ops {
+            preserve_times: false,
+            ..Ops::default()
+        };
+
+        assert_ne!(mtime(&ops)?, old);
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    files::{list_save_files, list_settings_files, to_extended_path},
+    hash,
+    manifest::{self, DATED_KIND, MANIFEST},
+    ops::Ops,
+    progress::Progress,
+    Error, Slot,
+};
+use std::{fs, path::PathBuf};
+
+/// The result of checking a slot against its manifest with [crate::SaveManager::fsck].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotCheck {
+    /// The name of the slot.
+    pub name: String,
+    /// The directory of the slot.
+    pub path: PathBuf,
+    /// What was found.
+    pub status: CheckStatus,
+}
+
+/// What was found when checking a slot against its manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// Every file in the slot matches its manifest.
+    Ok,
+    /// The slot doesn't have a manifest to check it against.
+    Unverifiable,
+    /// The slot didn't have a manifest, so one was written describing the files in it.
+    Adopted,
+    /// A description of every file which is missing, isn't in the manifest, or has a different
+    /// size or contents than the manifest says, or why the manifest couldn't be read.
+    Problems(Vec<String>),
+}
+
+/// Check every file in the given slots against their manifests, reading all of them again.
+///
+/// If `adopt` is set, slots without a manifest get one written describing the files they
+/// contain now.
+pub(crate) fn check_slots(ops: &Ops, slots: &[Slot], adopt: bool) -> Result<Vec<SlotCheck>, Error> {
+    let mut count = 0;
+    let mut total = 0;
+
+    // Slots without a manifest have nothing to check their files against.
+    for slot in slots.iter().filter(|s| s.path.join(MANIFEST).is_file()) {
+        let files = list_save_files(&slot.path, &ops.patterns)?
+            .into_iter()
+            .chain(list_settings_files(&slot.path)?);
+
+        for (_, path) in files {
+            count += 1;
+            total += fs::metadata(to_extended_path(&path))
+                .map_err(|e| Error::io(&path, e))?
+                .len();
+        }
+    }
+
+    let progress = Progress::start("checked", count, total);
+    let result = check_all(ops, slots, adopt, progress.as_deref());
+
+    if let Some(progress) = &progress {
+        progress.finish();
+    }
+
+    result
+}
+
+fn check_all(
+    ops: &Ops,
+    slots: &[Slot],
+    adopt: bool,
+    progress: Option<&Progress>,
+) -> Result<Vec<SlotCheck>, Error> {
+    let mut out = Vec::new();
+
+    for slot in slots {
+        ops.check_cancelled()?;
+
+        let status = match manifest::read(&slot.path) {
+            Ok(Some(manifest)) => {
+                let problems = manifest::check_with(&slot.path, &manifest, &ops.patterns, |p| {
+                    let hash = hash::hash_file(p)?;
+
+                    if let Some(progress) = progress {
+                        progress.add(fs::metadata(to_extended_path(p)).map_or(0, |m| m.len()));
+                        progress.file_done();
+                    }
+
+                    Ok(hash)
+                })?;
+
+                if problems.is_empty() {
+                    CheckStatus::Ok
+                } else {
+                    CheckStatus::Problems(problems)
+                }
+            }
+            Ok(None) if adopt => {
+                let kind = slot.dated.then_some(DATED_KIND);
+                manifest::write(ops, &slot.path, None, Vec::new(), kind)?;
+                CheckStatus::Adopted
+            }
+            Ok(None) => CheckStatus::Unverifiable,
+            Err(e) => CheckStatus::Problems(vec![e.to_string()]),
+        };
+
+        out.push(SlotCheck {
+            name: slot.name.clone(),
+            path: slot.path.clone(),
+            status,
+        });
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,115 @@
+/// The shortest name which is matched against the start or the middle of a slot name, so that a
+/// single letter doesn't match most slots.
+const MIN_PART: usize = 3;
+
+/// How many edits a name can be from a slot name to be similar, which is one for every four
+/// characters in the name, between one and three.
+fn max_distance(name: &str) -> usize {
+    (name.chars().count() / 4).clamp(1, 3)
+}
+
+/// How different `candidate` is from `name` ignoring case, as the number of characters which
+/// have to be inserted, removed or replaced to turn one into the other, or `None` if they're too
+/// different for `candidate` to be the name that was meant.
+///
+/// A name which is the start or part of the candidate counts as a single edit, like `before` for
+/// `before-heist`.
+pub(crate) fn distance(name: &str, candidate: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+    let candidate = candidate.to_lowercase();
+
+    if name == candidate {
+        return Some(0);
+    }
+
+    if name.chars().count() >= MIN_PART && candidate.contains(&name) {
+        return Some(1);
+    }
+
+    let distance = levenshtein(&name, &candidate);
+    (distance <= max_distance(&name)).then_some(distance)
+}
+
+/// The candidates which are closest to `name`, which are all as close to it as each other, in
+/// the order they're given in. Empty if no candidate is similar to `name`.
+pub(crate) fn best_matches<'a, I>(name: &str, candidates: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut best = None;
+    let mut out = Vec::new();
+
+    for candidate in candidates {
+        let distance = match distance(name, candidate) {
+            Some(distance) => distance,
+            None => continue,
+        };
+
+        if best.is_none_or(|best| distance < best) {
+            best = Some(distance);
+            out.clear();
+        }
+
+        if best == Some(distance) {
+            out.push(candidate);
+        }
+    }
+
+    out
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, b) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("åsa", "asa"), 1);
+
+        assert_eq!(distance("Before-Heist", "before-heist"), Some(0));
+        assert_eq!(distance("befor-heist", "before-heist"), Some(1));
+        assert_eq!(distance("bfore-haist", "before-heist"), Some(2));
+        assert_eq!(distance("before", "before-heist"), Some(1));
+        assert_eq!(distance("heist", "before-heist"), Some(1));
+        assert_eq!(distance("bar", "foo"), None);
+        assert_eq!(distance("b", "before-heist"), None);
+        assert_eq!(distance("fo", "foo"), Some(1));
+    }
+
+    #[test]
+    fn closest_matches() {
+        let slots = ["before-heist", "after-heist", "before-heist-2", "golden"];
+
+        assert_eq!(best_matches("befor-heist", slots), ["before-heist"]);
+        assert_eq!(best_matches("goldn", slots), ["golden"]);
+        assert_eq!(
+            best_matches("before", slots),
+            ["before-heist", "before-heist-2"]
+        );
+        assert_eq!(
+            best_matches("heist", slots),
+            ["before-heist", "after-heist", "before-heist-2"]
+        );
+        assert!(best_matches("franklin", slots).is_empty());
+    }
+}
@@ -0,0 +1,500 @@
+use crate::{pe, steam, Edition, Error};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Name of the game process, as opposed to the launchers which start it.
+pub const GAME_PROCESS: &str = "GTA5.exe";
+
+/// The launcher next to the game, which starts it through the Rockstar Games Launcher.
+const LAUNCHER: &str = "PlayGTAV.exe";
+
+/// The Steam URL which launches GTA V.
+const STEAM_URL: &str = "steam://rungameid/271590";
+
+/// The registry keys and values the Rockstar Games Launcher stores where the game is installed
+/// in, for copies bought from Rockstar, Steam and Epic, and if it's a Steam copy.
+#[cfg(windows)]
+const ROCKSTAR_KEYS: &[(&str, &str, bool)] = &[
+    (
+        r"SOFTWARE\WOW6432Node\Rockstar Games\Grand Theft Auto V",
+        "InstallFolder",
+        false,
+    ),
+    (
+        r"SOFTWARE\WOW6432Node\Rockstar Games\GTAV",
+        "InstallFolderSteam",
+        true,
+    ),
+    (
+        r"SOFTWARE\WOW6432Node\Rockstar Games\GTAV",
+        "InstallFolderEpic",
+        false,
+    ),
+];
+
+/// The registry key Steam stores where the game is installed in, for uninstalling it.
+#[cfg(windows)]
+const STEAM_UNINSTALL_KEY: &str =
+    r"SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\Steam App 271590";
+
+/// Where the game is installed, given with [set_game_path] or found by
+/// [detect_game_install].
+static INSTALL: OnceLock<Option<GameInstall>> = OnceLock::new();
+
+/// The name of the process of the game, given with [set_process].
+static PROCESS: OnceLock<String> = OnceLock::new();
+
+/// The version of the game, given with [set_game_version] or found by
+/// [detect_game_version].
+static VERSION: OnceLock<Option<String>> = OnceLock::new();
+
+/// How often to check if the game is running.
+const POLL: Duration = Duration::from_secs(1);
+
+/// How long the launcher gets to start the game before giving up on it.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How a session started by [launch] ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEnd {
+    /// The game was started and has exited.
+    Exited,
+    /// The launcher exited without the game being started.
+    NeverStarted,
+    /// Waiting was stopped before the game exited.
+    Interrupted,
+}
+
+/// Where the game is installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameInstall {
+    /// The directory [GAME_PROCESS] is in.
+    pub path: PathBuf,
+    /// How it was found, like `the Steam app manifest`.
+    pub method: &'static str,
+    /// If it was installed through Steam, which it's then launched through.
+    pub steam: bool,
+}
+
+/// Use the given directory as where the game is installed, instead of looking for it. A path to
+/// [GAME_PROCESS] or any other file in the directory works as well.
+///
+/// This has no effect once [detect_game_install] has been called.
+pub fn set_game_path(path: &Path) {
+    let path = if path.is_file() {
+        path.parent().unwrap_or(path)
+    } else {
+        path
+    };
+
+    let install = GameInstall {
+        path: path.to_owned(),
+        method: "--game-path",
+        steam: false,
+    };
+
+    if INSTALL.set(Some(install)).is_err() {
+        debug!("the game install was already detected, ignoring --game-path");
+    }
+}
+
+/// Find where the game is installed, which is looked for once and then reused.
+///
+/// On Windows, this looks in the registry keys of the Rockstar Games Launcher and Steam, and
+/// elsewhere in the app manifests of the Steam libraries. Entries which don't point to a
+/// directory with [GAME_PROCESS] in it are skipped, since they're left behind when the game is
+/// moved or uninstalled. If it can't be found, this is `None` and it's up to [set_game_path] to
+/// say where it is.
+pub fn detect_game_install() -> Option<&'static GameInstall> {
+    INSTALL
+        .get_or_init(|| {
+            for (path, method, steam) in candidates() {
+                if path.join(GAME_PROCESS).is_file() {
+                    debug!("found the game in {} through {}", path.display(), method);
+                    return Some(GameInstall {
+                        path,
+                        method,
+                        steam,
+                    });
+                }
+
+                debug!(
+                    "ignoring {} from {}, {} isn't in it",
+                    path.display(),
+                    method,
+                    GAME_PROCESS
+                );
+            }
+
+            None
+        })
+        .as_ref()
+}
+
+/// Use the given version of the game, like `1.0.3258.0`, instead of reading it from
+/// [GAME_PROCESS].
+///
+/// This has no effect once [detect_game_version] has been called.
+pub fn set_game_version(version: &str) {
+    if VERSION.set(Some(version.to_string())).is_err() {
+        debug!(
+            "the version of the game was already detected, ignoring {}",
+            version
+        );
+    }
+}
+
+/// Find the version of the game, which is looked for once and then reused.
+///
+/// This is the file version of [GAME_PROCESS] where [detect_game_install] found it, so it's
+/// only known for GTA V unless it's given with [set_game_version].
+pub fn detect_game_version() -> Option<&'static str> {
+    VERSION
+        .get_or_init(|| {
+            if process() != GAME_PROCESS {
+                return None;
+            }
+
+            let path = detect_game_install()?.path.join(GAME_PROCESS);
+
+            match pe::file_version(&path) {
+                Ok(Some(version)) => {
+                    debug!("{} is version {}", path.display(), version);
+                    Some(version)
+                }
+                Ok(None) => {
+                    debug!("{} has no version", path.display());
+                    None
+                }
+                Err(e) => {
+                    debug!("{}", e);
+                    None
+                }
+            }
+        })
+        .as_deref()
+}
+
+/// The directories the game might be installed in, with how they were found and if they're
+/// Steam installs.
+#[cfg(windows)]
+fn candidates() -> Vec<(PathBuf, &'static str, bool)> {
+    use winapi::um::winreg::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+
+    let mut out = Vec::new();
+
+    for (key, value, steam) in ROCKSTAR_KEYS {
+        if let Some(path) = registry_path(HKEY_LOCAL_MACHINE, key, value) {
+            out.push((
+                path,
+                "the registry key of the Rockstar Games Launcher",
+                *steam,
+            ));
+        }
+    }
+
+    if let Some(path) = registry_path(HKEY_LOCAL_MACHINE, STEAM_UNINSTALL_KEY, "InstallLocation") {
+        out.push((path, "the registry key of Steam", true));
+    }
+
+    let roots = vec![
+        registry_path(HKEY_CURRENT_USER, r"Software\Valve\Steam", "SteamPath"),
+        registry_path(
+            HKEY_LOCAL_MACHINE,
+            r"SOFTWARE\WOW6432Node\Valve\Steam",
+            "InstallPath",
+        ),
+    ];
+
+    let libraries = steam::libraries(roots.into_iter().flatten());
+
+    if let Some(path) = steam::app_dir(&libraries, steam::app_id(Edition::Legacy)) {
+        out.push((path, "the Steam app manifest", true));
+    }
+
+    out
+}
+
+/// The directories the game might be installed in, which is only in the Steam libraries in the
+/// home directory on other platforms.
+#[cfg(not(windows))]
+fn candidates() -> Vec<(PathBuf, &'static str, bool)> {
+    let libraries = match dirs::home_dir() {
+        Some(home) => steam::home_libraries(&home),
+        None => return Vec::new(),
+    };
+
+    steam::app_dir(&libraries, steam::app_id(Edition::Legacy))
+        .map(|path| (path, "the Steam app manifest", true))
+        .into_iter()
+        .collect()
+}
+
+/// Read a string value from the registry as a path, which is `None` if the key or value
+/// doesn't exist or is empty.
+#[cfg(windows)]
+fn registry_path(
+    key: winapi::shared::minwindef::HKEY,
+    subkey: &str,
+    value: &str,
+) -> Option<PathBuf> {
+    use std::{
+        ffi::{OsStr, OsString},
+        iter,
+        os::windows::ffi::{OsStrExt as _, OsStringExt as _},
+        ptr,
+    };
+    use winapi::{
+        shared::winerror::ERROR_SUCCESS,
+        um::winreg::{RegGetValueW, RRF_RT_REG_SZ},
+    };
+
+    let wide = |s: &str| {
+        OsStr::new(s)
+            .encode_wide()
+            .chain(iter::once(0))
+            .collect::<Vec<_>>()
+    };
+    let subkey_wide = wide(subkey);
+    let value_wide = wide(value);
+
+    let mut len = 0;
+
+    let status = unsafe {
+        RegGetValueW(
+            key,
+            subkey_wide.as_ptr(),
+            value_wide.as_ptr(),
+            RRF_RT_REG_SZ,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut len,
+        )
+    };
+
+    if status as u32 != ERROR_SUCCESS {
+        trace!("no registry value {} in {}: {}", value, subkey, status);
+        return None;
+    }
+
+    // The length is in bytes, including the terminating nul.
+    let mut buf = vec![0u16; (len as usize).div_ceil(2)];
+
+    let status = unsafe {
+        RegGetValueW(
+            key,
+            subkey_wide.as_ptr(),
+            value_wide.as_ptr(),
+            RRF_RT_REG_SZ,
+            ptr::null_mut(),
+            buf.as_mut_ptr().cast(),
+            &mut len,
+        )
+    };
+
+    if status as u32 != ERROR_SUCCESS {
+        debug!(
+            "failed to read registry value {} in {}: {}",
+            value, subkey, status
+        );
+        return None;
+    }
+
+    buf.truncate(len as usize / 2);
+
+    while buf.last() == Some(&0) {
+        buf.pop();
+    }
+
+    if buf.is_empty() {
+        return None;
+    }
+
+    Some(PathBuf::from(OsString::from_wide(&buf)))
+}
+
+/// Check if the given process is running instead of [GAME_PROCESS], for games other than
+/// GTA V.
+///
+/// This has no effect once [process] has been called.
+pub fn set_process(process: &str) {
+    if PROCESS.set(process.to_string()).is_err() {
+        debug!(
+            "the process of the game was already used, ignoring {}",
+            process
+        );
+    }
+}
+
+/// The name of the process of the game, which is [GAME_PROCESS] unless [set_process] says
+/// otherwise.
+pub fn process() -> &'static str {
+    PROCESS.get_or_init(|| String::from(GAME_PROCESS))
+}
+
+/// Test if the game is running.
+///
+/// If [detect_game_install] knows where GTA V is installed, processes named like it in other
+/// directories are ignored.
+pub fn is_running() -> bool {
+    let process = process();
+
+    let install = if process == GAME_PROCESS {
+        detect_game_install()
+    } else {
+        None
+    };
+
+    let mut system = sysinfo::System::new();
+    system.refresh_processes();
+
+    system
+        .processes()
+        .values()
+        .filter(|p| p.name().eq_ignore_ascii_case(process))
+        .any(|p| is_installed_game(p.exe(), install))
+}
+
+/// Test if a process named like the game with the given executable is the installed game.
+///
+/// If either isn't known, like for processes of other users or ones running through Proton
+/// where the executable is Wine, it's taken to be the game.
+fn is_installed_game(exe: Option<&Path>, install: Option<&GameInstall>) -> bool {
+    let (exe, install) = match (exe, install) {
+        (Some(exe), Some(install)) => (exe, install),
+        _ => return true,
+    };
+
+    let is_game = exe
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(GAME_PROCESS));
+
+    if !is_game {
+        return true;
+    }
+
+    let same = exe.parent().is_some_and(|dir| same_dir(dir, &install.path));
+
+    if !same {
+        trace!("ignoring {}, which isn't the installed game", exe.display());
+    }
+
+    same
+}
+
+/// Test if two paths are the same directory, ignoring case on Windows.
+fn same_dir(a: &Path, b: &Path) -> bool {
+    let a = fs::canonicalize(a).unwrap_or_else(|_| a.to_owned());
+    let b = fs::canonicalize(b).unwrap_or_else(|_| b.to_owned());
+
+    if cfg!(windows) {
+        a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+    } else {
+        a == b
+    }
+}
+
+/// Launch the game with the given executable or launcher.
+///
+/// If none is specified, the launcher next to the game is used on Windows unless the game was
+/// installed through Steam, and otherwise the game is launched through Steam.
+pub fn launch(path: Option<&Path>) -> Result<Child, Error> {
+    let launcher = detect_game_install()
+        .filter(|install| cfg!(windows) && !install.steam)
+        .map(|install| install.path.join(LAUNCHER))
+        .filter(|launcher| launcher.is_file());
+
+    let mut command = match path.or(launcher.as_deref()) {
+        Some(path) => Command::new(path),
+        None if cfg!(windows) => {
+            let mut command = Command::new("cmd");
+            command.args(["/C", "start", "", STEAM_URL]);
+            command
+        }
+        None => {
+            let mut command = Command::new("steam");
+            command.arg(STEAM_URL);
+            command
+        }
+    };
+
+    let program = Path::new(command.get_program()).to_owned();
+    command.spawn().map_err(|e| Error::io(&program, e))
+}
+
+/// Wait until the game started by `child` exits, or `stop` is set.
+///
+/// Since launchers like Steam or `PlayGTAV.exe` exit or keep running independently of the
+/// game, this waits for the game process itself to appear and then disappear.
+pub fn wait_for_exit(mut child: Child, stop: &AtomicBool) -> SessionEnd {
+    let started = Instant::now();
+    let mut seen = false;
+
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return SessionEnd::Interrupted;
+        }
+
+        if is_running() {
+            seen = true;
+        } else if seen {
+            break;
+        } else {
+            // A launcher which can't be waited for counts as exited.
+            let exited = !matches!(child.try_wait(), Ok(None));
+
+            if exited && started.elapsed() > STARTUP_TIMEOUT {
+                return SessionEnd::NeverStarted;
+            }
+        }
+
+        thread::sleep(POLL);
+    }
+
+    // Reap the launcher if it's done, without waiting for launchers which keep running.
+    let _ = child.try_wait();
+    SessionEnd::Exited
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn processes_are_told_apart_by_path() -> Result<(), Box<dyn std::error::Error>> {
+        let installed = tempfile::tempdir()?;
+        let other = tempfile::tempdir()?;
+
+        let install = GameInstall {
+            path: installed.path().to_owned(),
+            method: "--game-path",
+            steam: false,
+        };
+
+        let game = installed.path().join(GAME_PROCESS);
+        let impostor = other.path().join(GAME_PROCESS);
+        fs::write(&game, b"")?;
+        fs::write(&impostor, b"")?;
+
+        assert!(is_installed_game(Some(&game), Some(&install)));
+        assert!(!is_installed_game(Some(&impostor), Some(&install)));
+
+        // Without knowing either, anything named like the game counts.
+        assert!(is_installed_game(Some(&impostor), None));
+        assert!(is_installed_game(None, Some(&install)));
+        assert!(is_installed_game(
+            Some(Path::new("/usr/bin/wine64-preloader")),
+            Some(&install)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{compress, Error};
+use sha2::{Digest as _, Sha256};
+use std::{
+    fmt,
+    io::{self, Read as _},
+    path::Path,
+};
+
+/// The SHA-256 digest of a file, and its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct FileHash {
+    pub(crate) size: u64,
+    pub(crate) digest: [u8; 32],
+}
+
+impl fmt::Display for FileHash {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in &self.digest {
+            write!(fmt, "{:02x}", b)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash the given bytes.
+pub(crate) fn hash_bytes(bytes: &[u8]) -> FileHash {
+    FileHash {
+        size: bytes.len() as u64,
+        digest: Sha256::digest(bytes).into(),
+    }
+}
+
+/// Parse a SHA-256 digest written out like it's displayed, in lowercase hex.
+pub(crate) fn parse_digest(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut digest = [0u8; 32];
+
+    for (b, pair) in digest.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *b = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+
+    Some(digest)
+}
+
+#[cfg(test)]
+thread_local! {
+    static FILES_READ: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// The number of files which have been read by [hash_file] on this thread, so that tests can
+/// tell when hashing was avoided.
+#[cfg(test)]
+pub(crate) fn files_read() -> usize {
+    FILES_READ.with(|n| n.get())
+}
+
+/// Hash the file at the given path, after decompressing it if it's compressed.
+pub(crate) fn hash_file(path: &Path) -> Result<FileHash, Error> {
+    #[cfg(test)]
+    FILES_READ.with(|n| n.set(n.get() + 1));
+
+    let mut file = compress::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    let mut size = 0;
+
+    loop {
+        let n = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Error::io(path, e)),
+        };
+
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+
+    Ok(FileHash {
+        size,
+        digest: hasher.finalize().into(),
+    })
+}
+
+/// Verify that `to` is an identical copy of `from`, returning the number of bytes verified.
+pub(crate) fn verify_copy(from: &Path, to: &Path) -> Result<u64, Error> {
+    let expected = hash_file(from)?;
+    let actual = hash_file(to)?;
+
+    let reason = if expected.size != actual.size {
+        format!("expected {} bytes, got {}", expected.size, actual.size)
+    } else if expected.digest != actual.digest {
+        format!("expected SHA-256 {}, got {}", expected, actual)
+    } else {
+        return Ok(actual.size);
+    };
+
+    Err(Error::Corrupt {
+        from: from.to_owned(),
+        to: to.to_owned(),
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn verify_detects_differences() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+
+        fs::write(&a, b"hello")?;
+        fs::write(&b, b"hello")?;
+        assert_eq!(verify_copy(&a, &b)?, 5);
+
+        fs::write(&b, b"hell")?;
+        assert!(matches!(verify_copy(&a, &b), Err(Error::Corrupt { .. })));
+
+        fs::write(&b, b"jello")?;
+        assert!(matches!(verify_copy(&a, &b), Err(Error::Corrupt { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_displayed_digests() {
+        let hash = hash_bytes(b"hello");
+        assert_eq!(parse_digest(&hash.to_string()), Some(hash.digest));
+        assert_eq!(parse_digest("00"), None);
+        assert_eq!(parse_digest(&"zz".repeat(32)), None);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{compress, Error};
+use std::{
+    io::Read as _,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Offset of the save title, which is stored as null-terminated UTF-16LE.
+const TITLE_OFFSET: usize = 0x4;
+/// Space reserved for the save title in bytes.
+const TITLE_LEN: usize = 0x100;
+/// Offset of the save time, stored as 32-bit little-endian seconds since the Unix epoch.
+const TIME_OFFSET: usize = TITLE_OFFSET + TITLE_LEN;
+/// The number of bytes read from the start of a save file to parse its header.
+const HEADER_LEN: usize = TIME_OFFSET + 4;
+
+/// The in-game metadata stored at the start of a save file.
+///
+/// Either field is `None` if the header doesn't follow the expected layout, since the rest of
+/// the save file is encrypted and can't be used to recover them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveHeader {
+    /// The title shown in the in-game save list, like `Franklin - The Jewel Store Job`.
+    pub title: Option<String>,
+    /// When the game says the file was saved.
+    pub saved: Option<SystemTime>,
+}
+
+impl SaveHeader {
+    /// The completion percentage of the story, like `12.53` for a title ending in
+    /// ` - 12.53%`, if the title has one.
+    pub fn completion(&self) -> Option<f64> {
+        let title = self.title.as_deref()?;
+        let number = title[..title.rfind('%')?]
+            .rsplit(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()?;
+
+        number.parse().ok().filter(|n| (0.0..=100.0).contains(n))
+    }
+}
+
+/// Read the header of the save file at the given path, without modifying it.
+pub(crate) fn read(path: &Path) -> Result<SaveHeader, Error> {
+    let file = compress::open(path)?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    file.take(HEADER_LEN as u64)
+        .read_to_end(&mut bytes)
+        .map_err(|e| Error::io(path, e))?;
+
+    Ok(parse(&bytes))
+}
+
+/// Parse the header from the first bytes of a save file.
+pub(crate) fn parse(bytes: &[u8]) -> SaveHeader {
+    SaveHeader {
+        title: bytes.get(TITLE_OFFSET..TIME_OFFSET).and_then(parse_title),
+        saved: bytes.get(TIME_OFFSET..HEADER_LEN).and_then(parse_time),
+    }
+}
+
+fn parse_title(bytes: &[u8]) -> Option<String> {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&u| u != 0)
+        .collect::<Vec<_>>();
+
+    // A title filling the whole field is missing its terminator.
+    if units.is_empty() || units.len() == TITLE_LEN / 2 {
+        return None;
+    }
+
+    let title = String::from_utf16(&units).ok()?;
+
+    if title.chars().any(char::is_control) {
+        return None;
+    }
+
+    Some(title)
+}
+
+fn parse_time(bytes: &[u8]) -> Option<SystemTime> {
+    let seconds = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    if seconds == 0 {
+        return None;
+    }
+
+    Some(UNIX_EPOCH + Duration::from_secs(seconds.into()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Build the start of a save file with the given title and save time.
+    pub(crate) fn fixture(title: &str, saved: u32) -> Vec<u8> {
+        let mut bytes = vec![0u8; HEADER_LEN + 16];
+        bytes[..TITLE_OFFSET].copy_from_slice(&[0x00, 0x00, 0x00, 0x01]);
+
+        for (i, unit) in title.encode_utf16().enumerate() {
+            let at = TITLE_OFFSET + i * 2;
+            bytes[at..at + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+
+        bytes[TIME_OFFSET..HEADER_LEN].copy_from_slice(&saved.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn parse_expected_layout() {
+        let header = parse(&fixture("Franklin - The Jewel Store Job", 1_400_000_000));
+        assert_eq!(
+            header.title.as_deref(),
+            Some("Franklin - The Jewel Store Job")
+        );
+        assert_eq!(
+            header.saved,
+            Some(UNIX_EPOCH + Duration::from_secs(1_400_000_000))
+        );
+
+        let header = parse(&fixture("Trevor - Åsa's Ärende", 0));
+        assert_eq!(header.title.as_deref(), Some("Trevor - Åsa's Ärende"));
+        assert_eq!(header.saved, None);
+    }
+
+    #[test]
+    fn parse_unrecognized_data() {
+        assert_eq!(parse(b""), SaveHeader::default());
+        assert_eq!(parse(b"current\n"), SaveHeader::default());
+
+        // Not terminated before the end of the title field.
+        let mut bytes = fixture("", 1);
+        bytes[TITLE_OFFSET..TIME_OFFSET]
+            .iter_mut()
+            .for_each(|b| *b = b'a');
+        assert_eq!(parse(&bytes).title, None);
+
+        // An unpaired surrogate.
+        let mut bytes = fixture("", 1);
+        bytes[TITLE_OFFSET..TITLE_OFFSET + 2].copy_from_slice(&0xd800u16.to_le_bytes());
+        assert_eq!(parse(&bytes).title, None);
+
+        // Control characters.
+        assert_eq!(parse(&fixture("a\nb", 1)).title, None);
+
+        // Truncated before the save time.
+        let bytes = fixture("Michael", 1);
+        let header = parse(&bytes[..TIME_OFFSET + 2]);
+        assert_eq!(header.title.as_deref(), Some("Michael"));
+        assert_eq!(header.saved, None);
+    }
+
+    #[test]
+    fn parse_completion() {
+        let completion = |title: &str| parse(&fixture(title, 1)).completion();
+        assert_eq!(completion("The Jewel Store Job - 12.53%"), Some(12.53));
+        assert_eq!(completion("Prologue - 0%"), Some(0.0));
+        assert_eq!(completion("Franklin - The Jewel Store Job"), None);
+        assert_eq!(completion("Something - %"), None);
+        assert_eq!(completion("Something - 1.2.3%"), None);
+        assert_eq!(completion("Something - 250%"), None);
+        assert_eq!(SaveHeader::default().completion(), None);
+    }
+
+    #[test]
+    fn parse_game_header() {
+        // The start of a save file laid out like the game writes it, followed by part of the
+        // encrypted rest of the file.
+        let header = parse(include_bytes!("../tests/fixtures/SGTA00000.header"));
+        assert_eq!(
+            header.title.as_deref(),
+            Some("The Jewel Store Job - 12.53%")
+        );
+        assert_eq!(
+            header.saved,
+            Some(UNIX_EPOCH + Duration::from_secs(1_400_016_000))
+        );
+        assert_eq!(header.completion(), Some(12.53));
+    }
+
+    #[test]
+    fn read_leaves_file_untouched() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("SGTA00000");
+        let bytes = fixture("Michael - Prologue", 1_400_000_000);
+        fs::write(&path, &bytes)?;
+        let before = fs::metadata(&path)?.modified()?;
+
+        let header = read(&path)?;
+        assert_eq!(header.title.as_deref(), Some("Michael - Prologue"));
+        assert_eq!(fs::read(&path)?, bytes);
+        assert_eq!(fs::metadata(&path)?.modified()?, before);
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+use crate::Error;
+use std::{fmt, sync::atomic::AtomicBool};
+
+/// The hotkeys used unless others are configured, as pairs of the action and the key
+/// combination which runs it.
+pub const DEFAULT_HOTKEYS: &[(&str, &str)] = &[
+    ("save-dated", "Ctrl+Alt+F5"),
+    ("load-newest", "Ctrl+Alt+F9"),
+];
+
+// The modifiers understood by `RegisterHotKey`.
+const MOD_ALT: u32 = 0x0001;
+const MOD_CONTROL: u32 = 0x0002;
+const MOD_SHIFT: u32 = 0x0004;
+const MOD_WIN: u32 = 0x0008;
+
+/// The names of the modifiers, in the order they're displayed in.
+const MODIFIERS: &[(&str, u32)] = &[
+    ("Ctrl", MOD_CONTROL),
+    ("Alt", MOD_ALT),
+    ("Shift", MOD_SHIFT),
+    ("Win", MOD_WIN),
+];
+
+/// The names of the keys which aren't letters, digits or function keys, and their virtual-key
+/// codes.
+const KEYS: &[(&str, u32)] = &[
+    ("Pause", 0x13),
+    ("Space", 0x20),
+    ("PageUp", 0x21),
+    ("PageDown", 0x22),
+    ("End", 0x23),
+    ("Home", 0x24),
+    ("Left", 0x25),
+    ("Up", 0x26),
+    ("Right", 0x27),
+    ("Down", 0x28),
+    ("Insert", 0x2d),
+    ("Delete", 0x2e),
+];
+
+/// What pressing a hotkey does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    /// Save the save files into a new dated slot, like `save-dated`.
+    SaveDated,
+    /// Load the newest slot, like `load-newest`.
+    LoadNewest,
+}
+
+impl HotkeyAction {
+    /// Every action, in the order they're listed in.
+    const ALL: &'static [HotkeyAction] = &[HotkeyAction::SaveDated, HotkeyAction::LoadNewest];
+
+    /// The name of the action in the config file, which is the command it runs.
+    pub fn name(self) -> &'static str {
+        match self {
+            HotkeyAction::SaveDated => "save-dated",
+            HotkeyAction::LoadNewest => "load-newest",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|a| a.name() == name)
+    }
+}
+
+impl fmt::Display for HotkeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+/// A key combination which can be registered as a global hotkey, like `Ctrl+Alt+F5`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hotkey {
+    modifiers: u32,
+    key: u32,
+    name: String,
+}
+
+impl Hotkey {
+    /// Parse a key combination like `Ctrl+Alt+F5`, ignoring case.
+    ///
+    /// It's any number of the modifiers `Ctrl`, `Alt`, `Shift` and `Win` followed by a key,
+    /// which is a letter, a digit, a function key from `F1` to `F24`, a number pad key from
+    /// `Num0` to `Num9`, or one of `Space`, `Insert`, `Delete`, `Home`, `End`, `PageUp`,
+    /// `PageDown`, `Pause`, `Left`, `Right`, `Up` and `Down`.
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let invalid = |reason: String| Error::InvalidHotkey {
+            value: value.to_string(),
+            reason,
+        };
+
+        let mut parts = value.split('+').map(str::trim).collect::<Vec<_>>();
+
+        let key = match parts.pop() {
+            Some(key) if !key.is_empty() => key,
+            _ => return Err(invalid(String::from("it doesn't end with a key"))),
+        };
+
+        let mut modifiers = 0;
+
+        for part in parts {
+            let modifier = match part.to_lowercase().as_str() {
+                "ctrl" | "control" => MOD_CONTROL,
+                "alt" => MOD_ALT,
+                "shift" => MOD_SHIFT,
+                "win" => MOD_WIN,
+                _ => return Err(invalid(format!("'{}' isn't a modifier", part))),
+            };
+
+            if modifiers & modifier != 0 {
+                return Err(invalid(format!("'{}' is given more than once", part)));
+            }
+
+            modifiers |= modifier;
+        }
+
+        let (key, key_name) = match parse_key(key) {
+            Some(key) => key,
+            None => return Err(invalid(format!("'{}' isn't a key", key))),
+        };
+
+        let mut name = MODIFIERS
+            .iter()
+            .filter(|(_, m)| modifiers & m != 0)
+            .map(|(n, _)| *n)
+            .collect::<Vec<_>>();
+
+        name.push(&key_name);
+
+        Ok(Self {
+            modifiers,
+            key,
+            name: name.join("+"),
+        })
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name.fmt(f)
+    }
+}
+
+/// Parse the name of a key into its virtual-key code and how it's displayed.
+fn parse_key(key: &str) -> Option<(u32, String)> {
+    let upper = key.to_uppercase();
+
+    if let [c] = upper.as_bytes() {
+        if c.is_ascii_uppercase() || c.is_ascii_digit() {
+            return Some((u32::from(*c), upper));
+        }
+    }
+
+    if let Some(n) = upper.strip_prefix('F').and_then(|n| n.parse::<u32>().ok()) {
+        if (1..=24).contains(&n) {
+            return Some((0x6f + n, upper));
+        }
+    }
+
+    if let Some(n) = upper
+        .strip_prefix("NUM")
+        .and_then(|n| n.parse::<u32>().ok())
+    {
+        if n <= 9 {
+            return Some((0x60 + n, format!("Num{}", n)));
+        }
+    }
+
+    KEYS.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(name, code)| (*code, name.to_string()))
+}
+
+/// Parse pairs of an action and the key combination which runs it, like the ones in
+/// [DEFAULT_HOTKEYS].
+///
+/// Errors with [Error::InvalidHotkey] if an action isn't known, a key combination is invalid,
+/// or the same key combination is given for more than one action.
+pub fn parse_hotkeys<'a, I>(hotkeys: I) -> Result<Vec<(HotkeyAction, Hotkey)>, Error>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut parsed = Vec::<(HotkeyAction, Hotkey)>::new();
+
+    for (action, value) in hotkeys {
+        let hotkey = Hotkey::parse(value)?;
+
+        let action = match HotkeyAction::from_name(action) {
+            Some(action) => action,
+            None => {
+                let names = HotkeyAction::ALL
+                    .iter()
+                    .map(|a| a.name())
+                    .collect::<Vec<_>>();
+
+                return Err(Error::InvalidHotkey {
+                    value: value.to_string(),
+                    reason: format!(
+                        "'{}' isn't an action, expected one of {}",
+                        action,
+                        names.join(", ")
+                    ),
+                });
+            }
+        };
+
+        if let Some((other, _)) = parsed.iter().find(|(_, h)| *h == hotkey) {
+            return Err(Error::InvalidHotkey {
+                value: value.to_string(),
+                reason: format!("it's used for both {} and {}", other, action),
+            });
+        }
+
+        parsed.push((action, hotkey));
+    }
+
+    Ok(parsed)
+}
+
+/// Register the given hotkeys, and call `pressed` with the index of each one which is pressed
+/// until `stop` is set. The hotkeys are unregistered again before returning.
+///
+/// Errors with [Error::HotkeysTaken] before listening if any of them are already registered,
+/// like by another program.
+#[cfg(windows)]
+pub fn listen<F>(hotkeys: &[Hotkey], stop: &AtomicBool, mut pressed: F) -> Result<(), Error>
+where
+    F: FnMut(usize),
+{
+    use std::{mem, ptr, sync::atomic::Ordering, thread, time::Duration};
+    use winapi::um::winuser::{
+        PeekMessageW, RegisterHotKey, UnregisterHotKey, MSG, PM_REMOVE, WM_HOTKEY,
+    };
+
+    /// Holding down a hotkey only runs it once.
+    const MOD_NOREPEAT: u32 = 0x4000;
+
+    /// How often `stop` is checked while waiting for a hotkey.
+    const POLL: Duration = Duration::from_millis(50);
+
+    let mut registered = Vec::new();
+    let mut taken = Vec::new();
+
+    // Identifiers start at 1, so that they can't be confused with the ones of the system.
+    for (id, hotkey) in (1..).zip(hotkeys) {
+        let modifiers = hotkey.modifiers | MOD_NOREPEAT;
+
+        if unsafe { RegisterHotKey(ptr::null_mut(), id, modifiers, hotkey.key) } != 0 {
+            registered.push(id);
+        } else {
+            taken.push(hotkey.to_string());
+        }
+    }
+
+    let unregister = |registered: &[i32]| {
+        for id in registered {
+            unsafe { UnregisterHotKey(ptr::null_mut(), *id) };
+        }
+    };
+
+    if !taken.is_empty() {
+        unregister(&registered);
+        return Err(Error::HotkeysTaken { hotkeys: taken });
+    }
+
+    while !stop.load(Ordering::SeqCst) {
+        let mut msg = unsafe { mem::zeroed::<MSG>() };
+
+        while unsafe { PeekMessageW(&mut msg, ptr::null_mut(), 0, 0, PM_REMOVE) } != 0 {
+            if msg.message == WM_HOTKEY && msg.wParam >= 1 && msg.wParam <= hotkeys.len() {
+                pressed(msg.wParam - 1);
+            }
+        }
+
+        thread::sleep(POLL);
+    }
+
+    unregister(&registered);
+    Ok(())
+}
+
+/// Global hotkeys are only supported on Windows, so this always errors with
+/// [Error::HotkeysUnsupported].
+#[cfg(not(windows))]
+pub fn listen<F>(_: &[Hotkey], _: &AtomicBool, _: F) -> Result<(), Error>
+where
+    F: FnMut(usize),
+{
+    Err(Error::HotkeysUnsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_key_combinations() -> Result<(), Error> {
+        let hotkey = Hotkey::parse("ctrl + alt + f5")?;
+        assert_eq!(hotkey.to_string(), "Ctrl+Alt+F5");
+        assert_eq!(hotkey.modifiers, MOD_CONTROL | MOD_ALT);
+        assert_eq!(hotkey.key, 0x74);
+
+        assert_eq!(Hotkey::parse("Shift+Win+s")?.to_string(), "Shift+Win+S");
+        assert_eq!(Hotkey::parse("Alt+Shift+Ctrl+7")?.key, 0x37);
+        assert_eq!(Hotkey::parse("Control+NUM3")?.to_string(), "Ctrl+Num3");
+        assert_eq!(Hotkey::parse("pageup")?.to_string(), "PageUp");
+        assert_eq!(Hotkey::parse("F24")?.key, 0x87);
+
+        for invalid in &[
+            "",
+            "Ctrl+",
+            "Ctrl+Ctrl+F5",
+            "Hyper+F5",
+            "F25",
+            "Ctrl+F5+Alt",
+        ] {
+            assert!(
+                matches!(Hotkey::parse(invalid), Err(Error::InvalidHotkey { .. })),
+                "{:?}",
+                invalid
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_actions() -> Result<(), Error> {
+        let hotkeys = parse_hotkeys(DEFAULT_HOTKEYS.iter().copied())?;
+        assert_eq!(hotkeys[0].0, HotkeyAction::SaveDated);
+        assert_eq!(hotkeys[1].1.to_string(), "Ctrl+Alt+F9");
+
+        let error = parse_hotkeys(vec![("save", "F5")]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid hotkey 'F5', 'save' isn't an action, expected one of save-dated, load-newest"
+        );
+
+        let error = parse_hotkeys(vec![("save-dated", "F5"), ("load-newest", "f5")]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid hotkey 'f5', it's used for both save-dated and load-newest"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    files::{display_name, to_extended_path},
+    ops::Ops,
+    Error,
+};
+use std::{
+    fs, io,
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long a placeholder gets to be downloaded unless another timeout is given.
+pub const DEFAULT_HYDRATE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How often waiting for a placeholder to be downloaded checks if it's been cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Test if the file at the given path is a placeholder of a file which is stored in the cloud,
+/// like the ones OneDrive makes with Files On Demand, which is downloaded when it's read.
+#[cfg(windows)]
+pub(crate) fn is_placeholder(path: &Path) -> bool {
+    use std::{iter, os::windows::ffi::OsStrExt as _};
+    use winapi::um::{
+        fileapi::{GetFileAttributesW, INVALID_FILE_ATTRIBUTES},
+        winnt::{
+            FILE_ATTRIBUTE_OFFLINE, FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS,
+            FILE_ATTRIBUTE_RECALL_ON_OPEN, FILE_ATTRIBUTE_REPARSE_POINT,
+        },
+    };
+
+    const PLACEHOLDER: u32 = FILE_ATTRIBUTE_REPARSE_POINT
+        | FILE_ATTRIBUTE_OFFLINE
+        | FILE_ATTRIBUTE_RECALL_ON_OPEN
+        | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS;
+
+    let wide = to_extended_path(path)
+        .as_os_str()
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect::<Vec<_>>();
+
+    let attributes = unsafe { GetFileAttributesW(wide.as_ptr()) };
+    attributes != INVALID_FILE_ATTRIBUTES && attributes & PLACEHOLDER != 0
+}
+
+#[cfg(not(windows))]
+pub(crate) fn is_placeholder(_: &Path) -> bool {
+    false
+}
+
+/// Make sure that the file at the given path is stored on this device, by reading it through if
+/// it's a placeholder, so that copying it doesn't block on downloading it.
+///
+/// Errors with [Error::Hydrate] if it can't be read within [Ops::hydrate_timeout], and with
+/// [Error::Cancelled] if the operations are cancelled while waiting. The download carries on in
+/// the background either way, since it can't be stopped.
+pub(crate) fn ensure_local(ops: &Ops, path: &Path) -> Result<(), Error> {
+    if !is_placeholder(path) {
+        return Ok(());
+    }
+
+    let name = display_name(path.file_name().unwrap_or_default());
+    out!("hydrating {} from OneDrive...", name);
+
+    let started = Instant::now();
+    let (tx, rx) = mpsc::channel();
+    let extended = to_extended_path(path);
+
+    thread::spawn(move || {
+        let result = fs::File::open(&extended).and_then(|mut f| io::copy(&mut f, &mut io::sink()));
+        let _ = tx.send(result);
+    });
+
+    let timeout = ops.hydrate_timeout;
+
+    let reason = loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(..)) => {
+                out!("hydrated {} in {}ms", name, started.elapsed().as_millis());
+                return Ok(());
+            }
+            Ok(Err(e)) => break e.to_string(),
+            Err(mpsc::RecvTimeoutError::Disconnected) => break "it couldn't be read".to_string(),
+            Err(mpsc::RecvTimeoutError::Timeout) => (),
+        }
+
+        ops.check_cancelled()?;
+
+        if started.elapsed() >= timeout {
+            break format!("it took longer than {} seconds", timeout.as_secs());
+        }
+    };
+
+    Err(Error::Hydrate {
+        path: path.to_owned(),
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_files_are_left_alone() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("SGTA00000");
+        fs::write(&path, b"save")?;
+
+        let ops = Ops {
+            hydrate_timeout: Duration::from_secs(0),
+            ..Ops::default()
+        };
+
+        assert!(!is_placeholder(&path));
+        ensure_local(&ops, &path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{files::to_extended_path, Error};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    fs,
+    io::{BufRead as _, BufReader, Read as _, Seek as _, SeekFrom, Write as _},
+    path::{Path, PathBuf},
+};
+
+/// Name of the journal in the slots directory, which has one JSON record per line.
+pub(crate) const HISTORY: &str = "history.jsonl";
+
+thread_local! {
+    /// The file operations done on this thread since recording started, if it's recording.
+    static RECORDING: RefCell<Option<Vec<HistoryFile>>> = const { RefCell::new(None) };
+}
+
+/// A command which was run in a profile, as recorded in the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the command finished, in RFC 3339 format.
+    pub time: String,
+    /// The command, like `load`.
+    pub command: String,
+    /// The profile the command was run in.
+    pub profile: PathBuf,
+    /// The slot the command was run on, if it names one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<String>,
+    /// Either `ok` or `error`.
+    pub status: String,
+    /// What went wrong, if the command failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The file operations which were done, in order, including the ones done before the
+    /// command failed.
+    pub files: Vec<HistoryFile>,
+}
+
+/// A file operation recorded in the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryFile {
+    /// What was done, like `copy` or `delete`.
+    pub op: String,
+    /// The path operated on, or the source of a copy or rename.
+    pub path: PathBuf,
+    /// The destination of a copy or rename.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<PathBuf>,
+    /// The size of the file which was copied, removed or written, before it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The SHA-256 of the file which was copied, removed or written, before it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// Start recording the file operations done on this thread, returning `false` if they're
+/// already being recorded.
+pub(crate) fn start() -> bool {
+    RECORDING.with(|r| {
+        let mut r = r.borrow_mut();
+
+        if r.is_some() {
+            return false;
+        }
+
+        *r = Some(Vec::new());
+        true
+    })
+}
+
+/// Stop recording, returning the file operations which were recorded.
+pub(crate) fn finish() -> Vec<HistoryFile> {
+    RECORDING.with(|r| r.borrow_mut().take().unwrap_or_default())
+}
+
+/// Test if file operations on this thread are being recorded.
+pub(crate) fn recording() -> bool {
+    RECORDING.with(|r| r.borrow().is_some())
+}
+
+/// Record a file operation which was done. Does nothing unless recording.
+pub(crate) fn record(file: HistoryFile) {
+    RECORDING.with(|r| {
+        if let Some(files) = r.borrow_mut().as_mut() {
+            files.push(file);
+        }
+    });
+}
+
+/// Append an entry to the journal at the given path, creating it if necessary.
+///
+/// The entry is written as a single line with a single write which is flushed to disk, so that
+/// an interrupted write at most leaves a partial last line, which [read] skips.
+pub(crate) fn append(path: &Path, entry: &HistoryEntry) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(to_extended_path(parent)).map_err(|e| Error::io(parent, e))?;
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(to_extended_path(path))
+        .map_err(|e| Error::io(path, e))?;
+
+    let mut line = Vec::new();
+
+    // Start on a new line if the last write was interrupted, so that the entry isn't lost with
+    // the partial line.
+    if !ends_with_newline(&mut file).map_err(|e| Error::io(path, e))? {
+        line.push(b'\n');
+    }
+
+    serde_json::to_writer(&mut line, entry).map_err(|source| Error::InvalidManifest {
+        path: path.to_owned(),
+        source,
+    })?;
+    line.push(b'\n');
+
+    file.write_all(&line).map_err(|e| Error::io(path, e))?;
+    file.sync_data().map_err(|e| Error::io(path, e))
+}
+
+/// Test if the given file is empty or ends with a newline.
+fn ends_with_newline(file: &mut fs::File) -> std::io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+
+    let mut last = [0u8];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+/// Read the entries in the journal at the given path, oldest first, skipping lines which can't
+/// be parsed.
+pub(crate) fn read(path: &Path) -> Result<Vec<HistoryEntry>, Error> {
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+
+    let file = fs::File::open(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+    let mut entries = Vec::new();
+
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| Error::io(path, e))?;
+
+        if let Ok(entry) = serde_json::from_str(&line) {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_and_read_back() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("Slots").join(HISTORY);
+
+        assert!(start());
+        assert!(!start());
+        record(HistoryFile {
+            op: String::from("delete"),
+            path: PathBuf::from("SGTA00000"),
+            to: None,
+            size: Some(4),
+            sha256: None,
+        });
+        let files = finish();
+        assert!(!recording());
+        assert_eq!(files.len(), 1);
+
+        let entry = HistoryEntry {
+            time: String::from("2024-03-01T12:00:00+00:00"),
+            command: String::from("clear"),
+            profile: PathBuf::from("abc"),
+            slot: None,
+            status: String::from("ok"),
+            error: None,
+            files,
+        };
+
+        append(&path, &entry)?;
+        append(&path, &entry)?;
+
+        // A write which was interrupted is skipped, without taking the next entry with it.
+        let mut file = fs::OpenOptions::new().append(true).open(&path)?;
+        file.write_all(b"{\"time\":")?;
+        append(&path, &entry)?;
+
+        let entries = read(&path)?;
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].command, "clear");
+        assert_eq!(entries[1].files[0].size, Some(4));
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+//! Helpers for managing GTA V save files.
+//!
+//! The main entry point is [SaveManager], which manages the save files and slots of a single
+//! profile. Profiles can be found with [find_default_base] or [find_game_dirs], and
+//! [list_profiles].
+
+#[macro_use]
+#[doc(hidden)]
+pub mod output;
+
+mod alias;
+mod archive;
+mod at;
+mod backup;
+mod bundle;
+mod cache;
+mod compress;
+mod config;
+mod crypto;
+mod dedup;
+mod diff;
+pub mod doctor;
+mod download;
+mod error;
+mod files;
+mod fsck;
+mod fuzzy;
+pub mod game;
+mod hash;
+mod header;
+pub mod hotkey;
+mod hydrate;
+mod journal;
+mod links;
+mod lock;
+mod manager;
+mod manifest;
+mod ops;
+mod patterns;
+mod pe;
+pub mod pipe;
+mod profiles;
+mod progress;
+mod prune;
+mod recycle;
+mod remote;
+mod repair;
+mod reuse;
+pub mod serve;
+mod share;
+mod space;
+mod stash;
+mod steam;
+mod sync;
+mod template;
+#[cfg(test)]
+mod test_support;
+pub mod tray;
+mod usage;
+mod watch;
+
+pub use self::at::parse_time;
+pub use self::backup::{BackupEntry, DEFAULT_KEEP_BACKUPS};
+pub use self::bundle::{Bundle, BundleImport, BundledProfile, BundledSlot};
+pub use self::compress::DEFAULT_LEVEL as DEFAULT_COMPRESSION_LEVEL;
+pub use self::config::{default_config_path, Config};
+pub use self::crypto::is_encrypted_archive;
+pub use self::diff::Difference;
+pub use self::error::Error;
+pub use self::files::DEFAULT_MIN_SAVE_SIZE;
+pub use self::fsck::{CheckStatus, SlotCheck};
+pub use self::header::SaveHeader;
+pub use self::hydrate::DEFAULT_HYDRATE_TIMEOUT;
+pub use self::journal::{HistoryEntry, HistoryFile};
+pub use self::lock::{lock_hotkeys, lock_instance, InstanceLock};
+pub use self::manager::{validate_tag, SaveFile, SaveFilesDir, SaveManager, Slot, PROFILE};
+pub use self::output::{open_log, FileOperation};
+pub use self::patterns::{SaveFilePatterns, DEFAULT_INCLUDE};
+pub use self::profiles::{
+    find_default_base, find_game_dirs, list_profiles, parse_edition_choice, Edition, GameDef,
+    GameDir, DEFAULT_GAME,
+};
+pub use self::prune::{Keep, DATED_FORMAT};
+pub use self::remote::{Remote, RemoteBundle, RemoteConfig};
+pub use self::repair::{RepairedFile, SlotRepair};
+pub use self::share::{BundledSaveFile, SlotBundle, SLOT_BUNDLE_EXTENSION};
+pub use self::stash::StashEntry;
+pub use self::template::DATED_NAME;
+pub use self::usage::{ProfileUsage, SlotUsage};
+pub use self::watch::WatchOptions;
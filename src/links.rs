@@ -0,0 +1,59 @@
+use crate::files::to_extended_path as ext;
+use std::{fs, path::Path};
+
+/// Identifies a file on disk, which is the same for every hard link to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileId {
+    /// The volume the file is on.
+    pub(crate) volume: u64,
+    /// The index of the file on its volume.
+    pub(crate) index: u64,
+    /// The number of hard links to the file.
+    pub(crate) links: u64,
+}
+
+/// Identify the file at the given path, or `None` if it can't be read.
+#[cfg(unix)]
+pub(crate) fn file_id(path: &Path) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt as _;
+
+    let meta = fs::metadata(ext(path)).ok()?;
+
+    Some(FileId {
+        volume: meta.dev(),
+        index: meta.ino(),
+        links: meta.nlink(),
+    })
+}
+
+/// Identify the file at the given path, or `None` if it can't be opened.
+#[cfg(windows)]
+pub(crate) fn file_id(path: &Path) -> Option<FileId> {
+    use std::{mem, os::windows::io::AsRawHandle as _};
+    use winapi::um::fileapi::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+
+    let file = fs::File::open(ext(path)).ok()?;
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { mem::zeroed() };
+
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) } == 0 {
+        return None;
+    }
+
+    Some(FileId {
+        volume: info.dwVolumeSerialNumber.into(),
+        index: u64::from(info.nFileIndexHigh) << 32 | u64::from(info.nFileIndexLow),
+        links: info.nNumberOfLinks.into(),
+    })
+}
+
+/// Files can't be identified on other platforms.
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn file_id(_: &Path) -> Option<FileId> {
+    None
+}
+
+/// Test if the file at the given path has other hard links, which writing to it would change
+/// as well.
+pub(crate) fn is_shared(path: &Path) -> bool {
+    file_id(path).is_some_and(|id| id.links > 1)
+}
@@ -0,0 +1,110 @@
+use crate::Error;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Name of the lock file in the GTA V directory.
+const LOCK_FILE: &str = "gtav-saveload.lock";
+
+/// Name of the lock file in the GTA V directory which is held while listening for hotkeys.
+const HOTKEYS_LOCK_FILE: &str = "gtav-saveload-hotkeys.lock";
+
+/// How often the lock is retried while waiting for it.
+const POLL: Duration = Duration::from_millis(100);
+
+/// An exclusive lock on the GTA V directory, held until it's dropped.
+///
+/// The lock is taken by the operating system on an open file, so it's released if the process
+/// holding it dies. The lock file itself is left behind, and a stale one doesn't block anything.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: fs::File,
+}
+
+/// Lock the GTA V directory at `base`, waiting up to `wait` for another instance to release it.
+pub fn lock_instance(base: &Path, wait: Duration) -> Result<InstanceLock, Error> {
+    lock(base.join(LOCK_FILE), wait)
+}
+
+/// Take the lock which is held while listening for hotkeys in the GTA V directory at `base`,
+/// without waiting, so that only one instance listens for them at a time.
+///
+/// This is separate from [lock_instance], which is only taken while a hotkey runs a command.
+pub fn lock_hotkeys(base: &Path) -> Result<InstanceLock, Error> {
+    lock(base.join(HOTKEYS_LOCK_FILE), Duration::from_secs(0))
+}
+
+fn lock(path: PathBuf, wait: Duration) -> Result<InstanceLock, Error> {
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| Error::io(&path, e))?;
+
+    let started = Instant::now();
+    let mut waiting = false;
+
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(InstanceLock { _file: file }),
+            Err(fs::TryLockError::WouldBlock) => (),
+            Err(fs::TryLockError::Error(e)) => return Err(Error::io(&path, e)),
+        }
+
+        if started.elapsed() >= wait {
+            return Err(Error::InstanceLocked { path });
+        }
+
+        if !waiting {
+            eout!("waiting for another instance to finish");
+            waiting = true;
+        }
+
+        thread::sleep(POLL);
+    }
+}
+
+/// The GTA V directory of a profile, which is where [lock_instance] puts the lock.
+pub(crate) fn base_of(profile: &Path) -> Option<PathBuf> {
+    Some(profile.parent()?.parent()?.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_lock_waits_then_fails() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+
+        let lock = lock_instance(dir.path(), Duration::from_secs(0))?;
+
+        match lock_instance(dir.path(), Duration::from_millis(200)) {
+            Err(error @ Error::InstanceLocked { .. }) => assert_eq!(error.exit_code(), 6),
+            other => panic!("expected the directory to be locked: {:?}", other),
+        }
+
+        // Released when dropped, even though the lock file stays around.
+        drop(lock);
+        assert!(dir.path().join(LOCK_FILE).is_file());
+        lock_instance(dir.path(), Duration::from_secs(0))?;
+        Ok(())
+    }
+
+    #[test]
+    fn hotkeys_lock_is_separate() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+
+        let _lock = lock_hotkeys(dir.path())?;
+        assert!(matches!(
+            lock_hotkeys(dir.path()),
+            Err(Error::InstanceLocked { .. })
+        ));
+        lock_instance(dir.path(), Duration::from_secs(0))?;
+        Ok(())
+    }
+}
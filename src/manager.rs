@@ -0,0 +1,4822 @@
+use crate::{
+    alias, archive, at,
+    backup::{self, BackupEntry},
+    bundle::{self, BundleImport, BundledProfile},
+    cache::{HashCache, HASH_CACHE},
+    compress,
+    crypto::Passphrase,
+    dedup,
+    diff::{self, Difference},
+    doctor::{self, Check},
+    download::{self, DownloadKind, Scratch},
+    files::{self, copy_save_files, delete_save_files, list_save_files, to_extended_path},
+    fsck::{self, SlotCheck},
+    fuzzy, hash,
+    header::{self, SaveHeader},
+    journal::{self, HistoryEntry, HISTORY},
+    manifest::{self, DATED_KIND, MANIFEST},
+    ops::{FsOp, Ops},
+    output,
+    prune::{self, Keep, DATED_FORMAT, DATED_PREFIX},
+    remote::Remote,
+    repair::{self, SlotRepair},
+    reuse,
+    share::{self, SlotBundle},
+    stash::{self, StashEntry, STASH},
+    sync,
+    template::{self, DATED_NAME},
+    usage::{ProfileUsage, SlotUsage, Tally},
+    watch::{self, WatchOptions},
+    Edition, Error, GameDef, SaveFilePatterns, SLOT_BUNDLE_EXTENSION,
+};
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+    ffi::{OsStr, OsString},
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+/// Name of the directory in the profile where slots are stored, unless
+/// [SaveManager::slots_dir] says otherwise.
+const SLOTS: &str = "Slots";
+
+/// Name of the directory in [SaveManager::slots_dir] which holds the slots of
+/// [Edition::Enhanced] profiles, so that they're never mixed up with those of the legacy
+/// edition.
+const ENHANCED_SLOTS: &str = "Enhanced";
+
+/// Name of the reserved slot which holds the save files from before the last destructive
+/// operation.
+const BACKUP_SLOT: &str = ".pre-operation";
+
+/// Name of the directory in the profile where downloaded save files are kept, in a directory each.
+const SAVE_FILES: &str = "Save Files";
+
+/// Name of the reserved directory in the slots which holds the slots deleted by the last command
+/// which deleted any, so that deleting them can be undone.
+const DELETED: &str = ".deleted";
+
+/// Commands in the journal which [SaveManager::undo] undoes by restoring the backup slot.
+const UNDO_RESTORES_BACKUP: &[&str] = &[
+    "load",
+    "load-quick",
+    "load-newest",
+    "load-at",
+    "load-save-file",
+    "transfer-profile",
+    "restore-from",
+    "clear",
+    "load-bundle",
+    "load-url",
+];
+
+/// Commands in the journal which [SaveManager::undo] undoes by restoring the deleted slots.
+const UNDO_RESTORES_DELETED: &[&str] = &["delete-slot", "delete-newest", "prune-dated"];
+
+/// Name which refers to the current save files in the profile where a slot is expected, for
+/// operations which only read save files.
+pub const PROFILE: &str = "@profile";
+
+/// A slot stored in a profile.
+#[derive(Debug, Clone)]
+pub struct Slot {
+    /// The name of the slot.
+    pub name: String,
+    /// The directory of the slot.
+    pub path: PathBuf,
+    /// When the newest save file in the slot was modified, or the slot directory itself if it
+    /// doesn't contain any save files.
+    pub modified: SystemTime,
+    /// The number of save files in the slot.
+    pub files: usize,
+    /// The total size of the save files in bytes.
+    pub size: u64,
+    /// The note in the manifest of the slot, if any.
+    pub note: Option<String>,
+    /// The tags in the manifest of the slot.
+    pub tags: Vec<String>,
+    /// The in-game title of the newest save file in the slot, if it could be read.
+    pub title: Option<String>,
+    /// If the slot is protected from being deleted.
+    pub pinned: bool,
+    /// The in-game save slots of the save files in the slot, like 0 for `SGTA00000`, in order.
+    pub save_numbers: Vec<u32>,
+    /// The names of the save files in the slot which are smaller than
+    /// [SaveManager::min_save_size], and might be truncated.
+    pub suspicious: Vec<String>,
+    /// If the slot was saved by [SaveManager::save_dated], as recorded in its manifest or told
+    /// by the `dated-` prefix of slots saved before it was recorded.
+    pub dated: bool,
+    /// The version of the game the slot was saved with, if it was known.
+    pub game_version: Option<String>,
+    /// The name and header of every save file in the slot as recorded in its manifest when it
+    /// was saved, which is empty for slots saved by earlier versions.
+    pub headers: Vec<(String, SaveHeader)>,
+}
+
+impl Slot {
+    /// Test if the slot doesn't contain any save files.
+    pub fn is_empty(&self) -> bool {
+        self.files == 0
+    }
+}
+
+/// A single save file in a slot.
+#[derive(Debug, Clone)]
+pub struct SaveFile {
+    /// The name of the save file, like `SGTA00000`.
+    pub name: String,
+    /// The path to the save file.
+    pub path: PathBuf,
+    /// The size of the save file in bytes.
+    pub size: u64,
+    /// When the save file was last modified.
+    pub modified: SystemTime,
+    /// The SHA-256 digest of the save file, in hex.
+    pub sha256: String,
+    /// The in-game metadata in the header of the save file.
+    pub header: SaveHeader,
+}
+
+/// A directory in the `Save Files` folder of a profile, like a downloaded pack of save files.
+#[derive(Debug, Clone)]
+pub struct SaveFilesDir {
+    /// The name of the directory.
+    pub name: String,
+    /// The directory.
+    pub path: PathBuf,
+    /// When the newest save file in the directory was modified, or the directory itself if it
+    /// doesn't contain any save files.
+    pub modified: SystemTime,
+    /// The number of save files in the directory.
+    pub files: usize,
+}
+
+/// Manages the save files and slots of a single GTA V profile.
+///
+/// The save files the game uses are stored directly in the profile directory, and slots are
+/// stored as directories under `Slots` in it.
+///
+/// Before any operation which removes or replaces the save files in the profile, they are
+/// backed up into a reserved slot which can be restored with [SaveManager::undo].
+#[derive(Debug, Clone)]
+pub struct SaveManager {
+    profile: PathBuf,
+    ops: Ops,
+    backup: bool,
+    force: bool,
+    force_unpin: bool,
+    date_format: String,
+    dated_name: String,
+    slots_dir: Option<PathBuf>,
+    game: Option<String>,
+    shared_slots: bool,
+    compress: bool,
+    compression_level: i32,
+    passphrase: Option<Passphrase>,
+    only: Vec<u32>,
+    include_settings: bool,
+    min_save_size: u64,
+    strict: bool,
+    strict_version: bool,
+    fuzzy: bool,
+    /// The mistyped slot names which [SaveManager::fuzzy] has said which slot it used for, so
+    /// that it's only said once when a command looks a slot up more than once.
+    fuzzy_used: Arc<Mutex<HashSet<String>>>,
+    hash_cache: bool,
+}
+
+impl SaveManager {
+    /// Construct a manager for the given profile directory.
+    pub fn new<P>(profile: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            profile: profile.into(),
+            ops: Ops::default(),
+            backup: true,
+            force: false,
+            force_unpin: false,
+            date_format: DATED_FORMAT.to_string(),
+            dated_name: DATED_NAME.to_string(),
+            slots_dir: None,
+            game: None,
+            shared_slots: false,
+            compress: false,
+            compression_level: compress::DEFAULT_LEVEL,
+            passphrase: None,
+            only: Vec::new(),
+            include_settings: false,
+            min_save_size: files::DEFAULT_MIN_SAVE_SIZE,
+            strict: false,
+            strict_version: false,
+            fuzzy: false,
+            fuzzy_used: Arc::default(),
+            hash_cache: true,
+        }
+    }
+
+    /// Only print the operations which would modify the filesystem instead of performing them.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.ops.dry_run = dry_run;
+        self
+    }
+
+    /// Verify that each copied file is identical to its source. Enabled by default.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.ops.verify = verify;
+        self
+    }
+
+    /// Use the hashes of save files cached in the slots directory, instead of reading the ones
+    /// which haven't changed since they were last hashed. Enabled by default.
+    ///
+    /// New hashes are cached either way, so disabling this rebuilds the cache.
+    pub fn hash_cache(mut self, hash_cache: bool) -> Self {
+        self.hash_cache = hash_cache;
+        self
+    }
+
+    /// Give copied save files the same modification time as the original. Enabled by default.
+    pub fn preserve_times(mut self, preserve_times: bool) -> Self {
+        self.ops.preserve_times = preserve_times;
+        self
+    }
+
+    /// Retry copying and deleting files which another process has open, like an antivirus
+    /// scanner. Enabled by default.
+    pub fn retry(mut self, retry: bool) -> Self {
+        self.ops.retry = retry;
+        self
+    }
+
+    /// Leave read-only save files alone when replacing or deleting save files, instead of
+    /// clearing the attribute. The skipped files are reported.
+    pub fn respect_readonly(mut self, respect_readonly: bool) -> Self {
+        self.ops.respect_readonly = respect_readonly;
+        self
+    }
+
+    /// Delete save files and slots permanently, instead of moving them to the Recycle Bin.
+    /// Other platforms than Windows always delete permanently.
+    pub fn permanent(mut self, permanent: bool) -> Self {
+        self.ops.permanent = permanent;
+        self
+    }
+
+    /// Only save, load and clear the save files of the given in-game save slots, like 0 for
+    /// `SGTA00000`, leaving the other save files alone. Every save file is used if there are none.
+    ///
+    /// Backups made before replacing the save files in the profile still include all of them.
+    pub fn only<I>(mut self, numbers: I) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        self.only = numbers.into_iter().collect();
+        self
+    }
+
+    /// Also save, load and verify the settings files in the profile, like `pc_settings.bin`, which
+    /// are recorded in the manifest of the slot. Clearing the profile never removes them, and
+    /// neither does loading a slot without them.
+    pub fn include_settings(mut self, include_settings: bool) -> Self {
+        self.include_settings = include_settings;
+        self
+    }
+
+    /// Warn about save files smaller than this many bytes when saving and verifying, since
+    /// they're probably truncated, and mark the slots which have them. Defaults to
+    /// [crate::DEFAULT_MIN_SAVE_SIZE].
+    pub fn min_save_size(mut self, min_save_size: u64) -> Self {
+        self.min_save_size = min_save_size;
+        self
+    }
+
+    /// Refuse to save the save files in the profile if any of them are smaller than
+    /// [SaveManager::min_save_size], instead of only warning about them.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// How long to wait for a save file which is only stored in the cloud, like with OneDrive
+    /// Files On Demand, to be downloaded before it's copied. Defaults to
+    /// [crate::DEFAULT_HYDRATE_TIMEOUT].
+    pub fn hydrate_timeout(mut self, timeout: Duration) -> Self {
+        self.ops.hydrate_timeout = timeout;
+        self
+    }
+
+    /// Stop copying and deleting files at the next safe point once `cancel` is set, erroring
+    /// with [Error::Cancelled]. If the save files in the profile had already been changed,
+    /// they're restored from the backup made before.
+    pub fn cancel(mut self, cancel: &'static AtomicBool) -> Self {
+        self.ops.cancel = Some(cancel);
+        self
+    }
+
+    /// Which files count as save files, which are named like `SGTA00000` by default.
+    pub fn save_file_patterns(mut self, patterns: SaveFilePatterns) -> Self {
+        self.ops.patterns = patterns;
+        self
+    }
+
+    /// Print additional details about what's being done.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.ops.verbose = verbose;
+        self
+    }
+
+    /// Back up the save files in the profile before replacing or removing them. Enabled by
+    /// default.
+    pub fn backup(mut self, backup: bool) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Allow replacing slots which already contain save files, and deleting slots which
+    /// contain other files.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Use the slot closest to a slot name which doesn't exist, if only one slot is close enough
+    /// to it, instead of erroring with [Error::SimilarSlotNotFound].
+    pub fn fuzzy(mut self, fuzzy: bool) -> Self {
+        self.fuzzy = fuzzy;
+        self
+    }
+
+    /// Allow deleting and replacing pinned slots.
+    pub fn force_unpin(mut self, force_unpin: bool) -> Self {
+        self.force_unpin = force_unpin;
+        self
+    }
+
+    /// The `strftime` format of the time in the names of dated slots, like `%Y-%m-%d_%H%M%S`
+    /// which is the default.
+    pub fn date_format<S>(mut self, date_format: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.date_format = date_format.into();
+        self
+    }
+
+    /// The template of the names of dated slots, like `dated-{datetime}` which is the default.
+    ///
+    /// `{datetime}` is replaced with the time in [SaveManager::date_format], `{date}` and
+    /// `{time}` with the date and time like `2024-03-01` and `210405`, `{note}` with the note
+    /// given to [SaveManager::save_dated_with_note] made safe for file names, and `{seq}` with
+    /// the lowest number from `001` up which gives a slot name which isn't taken.
+    pub fn dated_name<S>(mut self, dated_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.dated_name = dated_name.into();
+        self
+    }
+
+    /// Store slots in the given directory instead of the `Slots` directory of the profile, like
+    /// a folder which is synced between machines. Each profile gets a directory of its own in
+    /// it, named after the profile, unless [SaveManager::shared_slots] is set.
+    ///
+    /// The backup made before replacing the save files in the profile stays in the profile.
+    pub fn slots_dir<P>(mut self, slots_dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.slots_dir = Some(slots_dir.into());
+        self
+    }
+
+    /// The game the profile belongs to, whose save files are matched unless
+    /// [SaveManager::save_file_patterns] is used afterwards.
+    ///
+    /// The slots of games other than GTA V are kept in a directory named like the game in
+    /// [SaveManager::slots_dir], so that they're never mixed up with those of GTA V.
+    pub fn game(mut self, game: &GameDef) -> Self {
+        self.game = (!game.is_default()).then(|| game.name.clone());
+
+        if let Ok(patterns) = SaveFilePatterns::new(&[game.include()], &[] as &[&str]) {
+            self.ops.patterns = patterns;
+        }
+
+        self
+    }
+
+    /// The edition of the game the profile belongs to, which is recorded in the manifest of
+    /// every slot saved from it.
+    ///
+    /// Slots and slot bundles from another edition aren't loaded into the profile unless
+    /// [SaveManager::force] is set, and the slots of [Edition::Enhanced] profiles are kept in an
+    /// `Enhanced` directory of their own in [SaveManager::slots_dir].
+    pub fn edition(mut self, edition: Edition) -> Self {
+        self.ops.edition = Some(edition);
+        self
+    }
+
+    /// The version of the game the profile is played with, like `1.0.3258.0`, which is
+    /// recorded in the manifest of every slot saved from it.
+    ///
+    /// Loading a slot saved with another version warns about it, or refuses to load it if
+    /// [SaveManager::strict_version] is set.
+    pub fn game_version<S>(mut self, version: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.ops.game_version = Some(version.into());
+        self
+    }
+
+    /// Refuse to load slots and slot bundles saved with another
+    /// [SaveManager::game_version], instead of warning about it.
+    pub fn strict_version(mut self, strict_version: bool) -> Self {
+        self.strict_version = strict_version;
+        self
+    }
+
+    /// Store the slots of every profile directly in [SaveManager::slots_dir], so that they're
+    /// shared between profiles.
+    pub fn shared_slots(mut self, shared_slots: bool) -> Self {
+        self.shared_slots = shared_slots;
+        self
+    }
+
+    /// Compress the save files in dated slots with zstd, as `SGTA00000.zst` and so on.
+    ///
+    /// Slots with compressed save files, or a mix of compressed and uncompressed ones, are
+    /// decompressed transparently when they're read.
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// The zstd compression level from 1 to 22 used when compressing save files, which
+    /// defaults to [DEFAULT_COMPRESSION_LEVEL][crate::DEFAULT_COMPRESSION_LEVEL].
+    pub fn compression_level(mut self, compression_level: i32) -> Self {
+        self.compression_level = compression_level;
+        self
+    }
+
+    /// Encrypt exported archives with a key derived from the given passphrase, and decrypt
+    /// encrypted archives with it when they're imported.
+    pub fn passphrase<S>(mut self, passphrase: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.passphrase = Some(Passphrase::new(passphrase.into()));
+        self
+    }
+
+    /// The profile directory being managed.
+    pub fn profile(&self) -> &Path {
+        &self.profile
+    }
+
+    /// Which files count as save files.
+    pub(crate) fn patterns(&self) -> &SaveFilePatterns {
+        &self.ops.patterns
+    }
+
+    /// Check the save files in the profile, and that slots can be saved.
+    pub fn doctor(&self) -> Vec<Check> {
+        doctor::check_profile(&self.profile, &self.slots_path(), &self.ops.patterns)
+    }
+
+    /// List the current save files in the profile.
+    pub fn save_files(&self) -> Result<Vec<PathBuf>, Error> {
+        let files = list_save_files(&self.profile, &self.ops.patterns)?;
+        Ok(files.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// List the files which [SaveManager::delete_slot] would remove from the named slot.
+    ///
+    /// Errors the same way as deleting would if the slot contains other files than save files.
+    pub fn slot_files_to_delete(&self, name: &str) -> Result<Vec<PathBuf>, Error> {
+        let slot = self.existing_slot(name)?;
+        self.check_unpinned(name, &slot)?;
+        let mut files = list_save_files(&slot, &self.ops.patterns)?;
+        files.extend(self.other_files(name, &slot)?);
+
+        if slot.join(MANIFEST).is_file() {
+            files.push((MANIFEST.into(), slot.join(MANIFEST)));
+        }
+
+        files.sort();
+        Ok(files.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// List all slots, sorted newest first with ties broken by name.
+    pub fn list_slots(&self) -> Result<Vec<Slot>, Error> {
+        let slots = self.slots_path();
+
+        if !slots.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut out = Vec::new();
+
+        for (name, path) in slot_dirs(&slots)? {
+            out.push(read_slot(
+                files::display_name(&name),
+                path,
+                &self.ops.patterns,
+                self.min_save_size,
+            )?);
+        }
+
+        out.sort_by(|a, b| {
+            b.modified
+                .cmp(&a.modified)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Ok(out)
+    }
+
+    /// The names of all slots, sorted by name.
+    ///
+    /// Unlike [SaveManager::list_slots] this doesn't read what's in the slots, and never creates
+    /// the slots directory, so that it's fast enough for shell completions.
+    pub fn slot_names(&self) -> Result<Vec<String>, Error> {
+        let slots = self.slots_path();
+
+        if !slots.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut names = slot_dirs(&slots)?
+            .into_iter()
+            .map(|(name, _)| files::display_name(&name))
+            .collect::<Vec<_>>();
+
+        names.sort();
+        Ok(names)
+    }
+
+    /// Find the nth newest slot, where 1 is the newest.
+    pub fn nth_newest_slot(&self, nth: usize) -> Result<Slot, Error> {
+        if nth == 0 {
+            return Err(Error::InvalidIndex {
+                value: nth.to_string(),
+            });
+        }
+
+        let mut slots = self.list_slots()?;
+
+        if nth > slots.len() {
+            return Err(Error::OutOfRange {
+                nth,
+                slots: slots.into_iter().map(|s| s.name).collect(),
+            });
+        }
+
+        Ok(slots.swap_remove(nth - 1))
+    }
+
+    /// Find the dated slot saved last at or before `time`, going by the time in its name, and
+    /// when it was saved.
+    ///
+    /// Errors with [Error::NoSlotBefore] if every dated slot was saved later, rather than
+    /// picking the oldest one.
+    pub fn dated_slot_at(&self, time: SystemTime) -> Result<(Slot, SystemTime), Error> {
+        let slots = self.list_slots()?.into_iter().filter(|s| s.dated).collect();
+
+        at::select(slots, time, &self.date_format).ok_or(Error::NoSlotBefore { time })
+    }
+
+    /// Save the current save files in the named slot, creating it if necessary, along with a
+    /// `manifest.json` describing them.
+    ///
+    /// Errors if the slot already contains save files, unless [SaveManager::force] is set.
+    pub fn save(&self, name: &str) -> Result<PathBuf, Error> {
+        self.save_slot(name, None, None)
+    }
+
+    /// Save the current save files in the named slot, recording what saved it in the manifest.
+    ///
+    /// If `previous` is given, the slot is new and save files which are identical to the ones
+    /// in `previous` are hard linked to them instead of being copied.
+    fn save_slot(
+        &self,
+        name: &str,
+        kind: Option<&str>,
+        previous: Option<&Path>,
+    ) -> Result<PathBuf, Error> {
+        let name = self.resolve(name)?;
+        let name = validate_new_slot_name(&name)?;
+
+        let ops = self.selected_ops()?;
+        let small =
+            self.warn_small_files("the profile", &files::list_selected(&ops, &self.profile)?)?;
+
+        if self.strict && !small.is_empty() {
+            return Err(Error::SmallSaveFiles {
+                files: small,
+                min_size: self.min_save_size,
+            });
+        }
+
+        let slot = self.ensure_slots_dir()?.join(name);
+
+        if slot.is_dir() {
+            let existing = read_slot(
+                name.to_string(),
+                slot.clone(),
+                &self.ops.patterns,
+                self.min_save_size,
+            )?;
+
+            if !existing.is_empty() && !self.force {
+                return Err(Error::SlotExists {
+                    name: existing.name,
+                    files: existing.files,
+                    modified: existing.modified,
+                });
+            }
+
+            self.check_unpinned(name, &slot)?;
+        } else {
+            self.ops.run(FsOp::CreateDir(&slot))?;
+        }
+
+        match previous {
+            Some(previous) if !self.ops.dry_run => {
+                let mut cache = self.load_hash_cache();
+                let reused = reuse::copy_reusing(
+                    &ops,
+                    &mut cache,
+                    &self.profile,
+                    previous,
+                    &slot,
+                    self.compress,
+                    kind,
+                )?;
+
+                self.save_hash_cache(&mut cache);
+
+                out!(
+                    "reused {} unchanged save files, copied {}",
+                    reused.reused,
+                    reused.copied
+                );
+            }
+            _ => {
+                copy_save_files(&ops, &self.profile, &slot)?;
+                manifest::write(&self.ops, &slot, None, Vec::new(), kind)?;
+            }
+        }
+
+        Ok(slot)
+    }
+
+    /// Set the note of the named slot if `note` is specified, and add the given tags to it.
+    ///
+    /// This only modifies the manifest of the slot, which is created if it doesn't have one.
+    pub fn annotate(&self, name: &str, note: Option<&str>, tags: &[&str]) -> Result<(), Error> {
+        for tag in tags {
+            validate_tag(tag)?;
+        }
+
+        // The slot doesn't exist in a dry run, since saving it was only printed.
+        let slot = if self.ops.dry_run {
+            self.slots_path()
+                .join(validate_slot_name(&self.resolve(name)?)?)
+        } else {
+            self.existing_slot(name)?
+        };
+
+        manifest::update(&self.ops, &slot, |manifest| {
+            if let Some(note) = note {
+                manifest.note = Some(note.to_string());
+            }
+
+            manifest.tags.extend(tags.iter().map(|t| t.to_string()));
+            manifest.tags.sort();
+            manifest.tags.dedup();
+        })
+    }
+
+    /// Pin or unpin the named slot, which protects it from being deleted or replaced unless
+    /// [SaveManager::force_unpin] is set.
+    pub fn pin(&self, name: &str, pinned: bool) -> Result<(), Error> {
+        let slot = self.existing_slot(name)?;
+        manifest::update(&self.ops, &slot, |manifest| manifest.pinned = pinned)
+    }
+
+    /// Save the current save files in a new slot named after the current time with
+    /// [SaveManager::dated_name], returning the name of the slot.
+    ///
+    /// The save files are compressed if [SaveManager::compress] is set.
+    ///
+    /// Errors if [SaveManager::date_format] or [SaveManager::dated_name] isn't valid.
+    pub fn save_dated(&self) -> Result<String, Error> {
+        self.save_dated_with_note(None)
+    }
+
+    /// Save a dated slot like [SaveManager::save_dated], where `{note}` in its name is replaced
+    /// with the given note.
+    ///
+    /// The note isn't stored in the manifest of the slot, see [SaveManager::annotate].
+    pub fn save_dated_with_note(&self, note: Option<&str>) -> Result<String, Error> {
+        self.save_dated_at(chrono::Local::now(), note)
+    }
+
+    /// Save a dated slot named after the given time.
+    ///
+    /// A slot is never replaced, since two saves can happen within the same second. If the name
+    /// is taken, `{seq}` is counted up if the template has it, and otherwise a suffix like `-2`
+    /// is added.
+    fn save_dated_at(
+        &self,
+        time: chrono::DateTime<chrono::Local>,
+        note: Option<&str>,
+    ) -> Result<String, Error> {
+        prune::check_date_format(&self.date_format)?;
+        template::check(&self.dated_name, &self.date_format)?;
+
+        if self.compress {
+            compress::check_level(self.compression_level)?;
+        }
+
+        let slots = self.slots_path();
+        let mut values = template::Values {
+            time,
+            date_format: &self.date_format,
+            note,
+            seq: 1,
+        };
+
+        let base = template::expand(&self.dated_name, &values)?;
+        let mut name = base.clone();
+        let mut suffix = 1;
+
+        while slots.join(&name).exists() {
+            if template::has_seq(&self.dated_name) {
+                values.seq += 1;
+                name = template::expand(&self.dated_name, &values)?;
+            } else {
+                suffix += 1;
+                name = format!("{}-{}", base, suffix);
+            }
+        }
+
+        // Save files which haven't changed since the newest dated slot are linked to it.
+        let previous = self.list_slots()?.into_iter().find(|s| s.dated);
+        let previous = previous.as_ref().map(|s| s.path.as_path());
+
+        let slot = self.save_slot(&name, Some(DATED_KIND), previous)?;
+
+        // There's nothing to compress in a dry run, since nothing was saved.
+        if self.compress && !self.ops.dry_run {
+            self.compress_slot(&slot)?;
+        }
+
+        Ok(name)
+    }
+
+    /// Watch the profile for changes to its save files and save them into a dated slot once
+    /// the game is done writing them, until `stop` is set.
+    ///
+    /// Nothing is saved if the save files are the same as in the newest dated slot.
+    pub fn watch(&self, options: WatchOptions, stop: &AtomicBool) -> Result<(), Error> {
+        watch::watch(self, options, stop)
+    }
+
+    /// Load the save files from the named slot into the profile.
+    ///
+    /// Errors without touching the profile if the slot doesn't exist or doesn't contain any
+    /// save files, or with [Error::EditionMismatch] if it's from another [SaveManager::edition].
+    /// Warns if the save files don't match the manifest of the slot.
+    pub fn load(&self, name: &str) -> Result<(), Error> {
+        let slot = self.existing_slot(name)?;
+        let ops = self.selected_ops()?;
+        let save_files = list_save_files(&slot, &self.ops.patterns)?;
+
+        if save_files.is_empty() {
+            return Err(Error::EmptySlot {
+                name: name.to_string(),
+            });
+        }
+
+        // Loading nothing would only delete the selected save files in the profile.
+        if let Some(only) = ops.only {
+            if !save_files.iter().any(|(n, _)| only.contains(n)) {
+                return Err(Error::NoSelectedSaveFiles {
+                    name: name.to_string(),
+                    only: join_numbers(&self.only),
+                });
+            }
+        }
+
+        let manifest = manifest::read(&slot).ok().flatten();
+        self.check_edition(name, manifest.as_ref().and_then(|m| m.edition))?;
+        self.check_game_version(
+            name,
+            manifest.as_ref().and_then(|m| m.game_version.as_deref()),
+        )?;
+        warn_manifest(&mut HashCache::none(), name, &slot, &self.ops.patterns)?;
+
+        // The manifest tells if the slot was saved with the settings.
+        let has_settings = manifest.is_some_and(|m| !m.settings.is_empty());
+
+        if ops.settings && !has_settings {
+            out!(
+                "slot '{}' was saved without settings, only loading save files",
+                name
+            );
+        }
+
+        let ops = Ops {
+            settings: ops.settings && has_settings,
+            ..ops
+        };
+
+        self.change_profile(|| copy_save_files(&ops, &slot, &self.profile))
+    }
+
+    /// Replace the save files in the profile with the ones in another profile, like the one of
+    /// another Social Club account, backing up the current ones first like [SaveManager::load].
+    ///
+    /// Only save files are transferred, never the settings of the other profile, and every copy
+    /// is verified regardless of [SaveManager::verify]. Errors before changing anything if both
+    /// are the same directory, or there are no save files in `from`.
+    pub fn transfer_from(&self, from: &Path) -> Result<(), Error> {
+        let canonical = |path: &Path| fs::canonicalize(path).map_err(|e| Error::io(path, e));
+
+        if canonical(from)? == canonical(&self.profile)? {
+            return Err(Error::SameProfile {
+                path: self.profile.clone(),
+            });
+        }
+
+        let ops = self.selected_ops()?;
+        let save_files = list_save_files(from, &self.ops.patterns)?;
+
+        if !save_files
+            .iter()
+            .any(|(n, _)| ops.only.is_none_or(|only| only.contains(n)))
+        {
+            return Err(Error::NothingToTransfer {
+                path: from.to_owned(),
+            });
+        }
+
+        self.warn_small_files(&from.display().to_string(), &save_files)?;
+
+        let ops = Ops {
+            verify: true,
+            settings: false,
+            ..ops
+        };
+
+        self.change_profile(|| copy_save_files(&ops, from, &self.profile))
+    }
+
+    /// Run `f`, and record the file operations it did in the journal in the slots directory,
+    /// along with which command it was, the slot it was run on and whether it failed.
+    ///
+    /// Nothing is recorded in a dry run, or if `f` succeeded without doing anything. Failing to
+    /// write the journal is only warned about, since what it records has already been done.
+    pub fn journaled<T>(
+        &self,
+        command: &str,
+        slot: Option<&str>,
+        f: impl FnOnce(&Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        // Only the outermost command is recorded.
+        if self.ops.dry_run || !journal::start() {
+            return f(self);
+        }
+
+        let result = f(self);
+        let files = journal::finish();
+
+        if files.is_empty() && result.is_ok() {
+            return result;
+        }
+
+        let entry = HistoryEntry {
+            time: chrono::Local::now().to_rfc3339(),
+            command: command.to_string(),
+            profile: self.profile.clone(),
+            slot: slot.map(str::to_string),
+            status: String::from(if result.is_ok() { "ok" } else { "error" }),
+            error: result.as_ref().err().map(Error::to_string),
+            files,
+        };
+
+        if let Err(e) = journal::append(&self.slots_path().join(HISTORY), &entry) {
+            eout!("warning: failed to write history: {}", e);
+        }
+
+        result
+    }
+
+    /// The entries in the journal for this profile, oldest first.
+    pub fn history(&self) -> Result<Vec<HistoryEntry>, Error> {
+        let mut entries = journal::read(&self.slots_path().join(HISTORY))?;
+
+        // Slots shared between profiles share the journal as well.
+        entries.retain(|e| e.profile == self.profile);
+        Ok(entries)
+    }
+
+    /// The aliases which can be used in place of slot names as `@alias`, by alias.
+    pub fn aliases(&self) -> Result<BTreeMap<String, String>, Error> {
+        alias::read(&self.slots_path())
+    }
+
+    /// Make `@alias` refer to the named slot, which doesn't have to exist yet, replacing what it
+    /// referred to before.
+    pub fn set_alias(&self, alias: &str, slot: &str) -> Result<(), Error> {
+        let alias = alias::validate(alias.strip_prefix('@').unwrap_or(alias))?;
+        let slot = validate_new_slot_name(slot)?;
+
+        let slots = self.ensure_slots_dir()?;
+        let mut aliases = alias::read(&slots)?;
+        aliases.insert(alias.to_string(), slot.to_string());
+        alias::write(&self.ops, &slots, &aliases)
+    }
+
+    /// Remove the given alias, erroring with [Error::AliasNotFound] if it doesn't exist.
+    pub fn remove_alias(&self, alias: &str) -> Result<(), Error> {
+        let alias = alias.strip_prefix('@').unwrap_or(alias);
+        let slots = self.slots_path();
+        let mut aliases = alias::read(&slots)?;
+
+        if aliases.remove(alias).is_none() {
+            return Err(Error::AliasNotFound {
+                alias: alias.to_string(),
+            });
+        }
+
+        alias::write(&self.ops, &slots, &aliases)
+    }
+
+    /// Exchange the save files in the profile with the ones in the named slot, so that the slot
+    /// holds what was in the profile and the profile holds what was in the slot.
+    ///
+    /// The save files in the profile are copied into a staging directory first. If replacing
+    /// the save files in the slot fails after the profile has been replaced, the profile is
+    /// restored from there, so both sides are left as they were. The slot gets a new manifest,
+    /// like when saving into it.
+    ///
+    /// Errors without changing anything if the slot doesn't exist or doesn't contain any save
+    /// files, or if it's pinned, unless [SaveManager::force_unpin] is set.
+    pub fn swap(&self, name: &str) -> Result<(), Error> {
+        let slot = self.existing_slot(name)?;
+
+        if list_save_files(&slot, &self.ops.patterns)?.is_empty() {
+            return Err(Error::EmptySlot {
+                name: name.to_string(),
+            });
+        }
+
+        self.check_unpinned(name, &slot)?;
+        let manifest = manifest::read(&slot).ok().flatten();
+        self.check_edition(name, manifest.as_ref().and_then(|m| m.edition))?;
+        self.check_game_version(
+            name,
+            manifest.as_ref().and_then(|m| m.game_version.as_deref()),
+        )?;
+        warn_manifest(&mut HashCache::none(), name, &slot, &self.ops.patterns)?;
+
+        if self.ops.dry_run {
+            copy_save_files(&self.ops, &slot, &self.profile)?;
+            return copy_save_files(&self.ops, &self.profile, &slot);
+        }
+
+        self.backup_profile()?;
+
+        let staging = self.slots_path().join(files::STAGING);
+
+        if staging.is_dir() {
+            self.ops.run(FsOp::RemoveDirAll(&staging))?;
+        }
+
+        self.ops.run(FsOp::CreateDir(&staging))?;
+
+        let result = copy_save_files(&self.ops, &self.profile, &staging).and_then(|()| {
+            copy_save_files(&self.ops, &slot, &self.profile)?;
+
+            if let Err(e) = copy_save_files(&self.ops, &staging, &slot) {
+                out!("Restoring the profile, since the slot couldn't be replaced");
+                copy_save_files(&self.ops.uncancellable(), &staging, &self.profile)?;
+                return Err(e);
+            }
+
+            manifest::write(&self.ops, &slot, None, Vec::new(), None)
+        });
+
+        if let Err(e) = self.ops.run(FsOp::RemoveDirAll(&staging)) {
+            out!("Failed to remove staging directory: {}", e);
+        }
+
+        result
+    }
+
+    /// Undo the last operation in the journal which replaced or removed the save files in the
+    /// profile, or deleted slots, printing what is restored before restoring it.
+    ///
+    /// A load or clear is undone by restoring the save files from before it, which errors with
+    /// [Error::ProfileModified] if the save files have changed since, and deleted slots are
+    /// restored under their old names, which errors with [Error::SlotNameTaken] if a slot has
+    /// been created with the same name since, unless [SaveManager::force] is set. If the journal
+    /// doesn't record any such operation, the backup slot is restored as it is.
+    pub fn undo(&self) -> Result<(), Error> {
+        let entry = match self.last_undoable()? {
+            Some(entry) => entry,
+            None => return self.restore_backup(),
+        };
+
+        let mut operation = entry.command.clone();
+
+        if let Some(slot) = &entry.slot {
+            operation.push(' ');
+            operation.push_str(slot);
+        }
+
+        let time = chrono::DateTime::parse_from_rfc3339(&entry.time)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|_| entry.time.clone());
+
+        if UNDO_RESTORES_DELETED.contains(&entry.command.as_str()) {
+            let names = self.deleted_by(&entry)?;
+
+            out!("undoing '{}' from {}", operation, time);
+            out!(
+                "restoring {}: {}",
+                output::count(names.len(), "deleted slot"),
+                names.join(", ")
+            );
+            return self.restore_deleted(&names);
+        }
+
+        // Without a backup from the operation, the backup slot is from an older one.
+        let backup = self.backup_slot();
+        let backed_up = entry.files.iter().any(|f| {
+            f.path.starts_with(&backup) || f.to.as_ref().is_some_and(|t| t.starts_with(&backup))
+        });
+
+        if !backed_up {
+            return Err(Error::NoBackup);
+        }
+
+        if !self.force && !self.unchanged_since(&entry)? {
+            return Err(Error::ProfileModified { command: operation });
+        }
+
+        out!("undoing '{}' from {}", operation, time);
+        self.restore_backup()
+    }
+
+    /// Move the save files in the profile onto the stash, returning the directory of the new
+    /// stash entry, and load the named slot into the profile if `load` is specified. Otherwise
+    /// the profile is left without save files.
+    ///
+    /// The stash is kept in the profile, and entries stay on it until they're popped. Errors
+    /// before changing anything if there are no save files to stash, or the slot to load doesn't
+    /// exist or is empty.
+    pub fn stash(&self, load: Option<&str>) -> Result<PathBuf, Error> {
+        if list_save_files(&self.profile, &self.ops.patterns)?.is_empty() {
+            return Err(Error::NothingToStash);
+        }
+
+        let slot = match load {
+            Some(name) => {
+                let slot = self.existing_slot(name)?;
+
+                if list_save_files(&slot, &self.ops.patterns)?.is_empty() {
+                    return Err(Error::EmptySlot {
+                        name: name.to_string(),
+                    });
+                }
+
+                warn_manifest(&mut HashCache::none(), name, &slot, &self.ops.patterns)?;
+                Some(slot)
+            }
+            None => None,
+        };
+
+        let entry = stash::next_entry(&self.stash_dir(), &self.ops.patterns)?;
+        self.ops.run(FsOp::CreateDir(&entry))?;
+        copy_save_files(&self.ops, &self.profile, &entry)?;
+        manifest::write(&self.ops, &entry, None, Vec::new(), None)?;
+
+        match slot {
+            Some(slot) => copy_save_files(&self.ops, &slot, &self.profile)?,
+            None => delete_save_files(&self.ops, &self.profile)?,
+        }
+
+        Ok(entry)
+    }
+
+    /// Restore the most recently stashed save files into the profile and remove them from the
+    /// stash, returning the name of the entry which was popped.
+    ///
+    /// The save files in the profile are backed up first, like when loading. If backups are
+    /// disabled, errors with [Error::UnsavedChanges] if they aren't saved in any slot, unless
+    /// [SaveManager::force] is set.
+    pub fn pop(&self) -> Result<String, Error> {
+        let entry = match self.stash_list()?.into_iter().next() {
+            Some(entry) => entry,
+            None => return Err(Error::EmptyStash),
+        };
+
+        // Popping an entry without save files would leave the profile empty.
+        if entry.files == 0 {
+            return Err(Error::EmptySlot {
+                name: format!("{}/{}", STASH, entry.name),
+            });
+        }
+
+        if !self.backup && !self.force && self.has_unsaved_changes()? {
+            return Err(Error::UnsavedChanges);
+        }
+
+        self.change_profile(|| copy_save_files(&self.ops, &entry.path, &self.profile))?;
+        self.ops.run(FsOp::RemoveDirAll(&entry.path))?;
+        Ok(entry.name)
+    }
+
+    /// List the entries on the stash, with the most recently stashed first.
+    pub fn stash_list(&self) -> Result<Vec<StashEntry>, Error> {
+        stash::entries(&self.stash_dir(), &self.ops.patterns)
+    }
+
+    /// Back up the save files and settings in the profile into a new directory in
+    /// `<dir>/<profile>` named after the current time, along with a manifest, returning the new
+    /// directory.
+    ///
+    /// Unlike slots, backups are meant to be kept outside of the GTA V directory, so that they
+    /// survive anything which happens to it. Only the `keep` most recent backups of the profile
+    /// are kept, counting the new one, and the older ones are removed once it's made.
+    /// Directories which aren't named like backups are never removed.
+    pub fn backup_to(&self, dir: &Path, keep: usize) -> Result<PathBuf, Error> {
+        if list_save_files(&self.profile, &self.ops.patterns)?.is_empty() {
+            return Err(Error::NothingToBackUp);
+        }
+
+        let dir = self.backups_dir(dir);
+        let older = backup::entries(&dir, &self.ops.patterns)?;
+        let entry = backup::next_entry(&dir, chrono::Local::now());
+
+        // Every save file is backed up regardless of `only`, along with the settings.
+        let ops = self.backup_ops();
+        ops.run(FsOp::CreateDir(&entry))?;
+        copy_save_files(&ops, &self.profile, &entry)?;
+        manifest::write(&ops, &entry, None, Vec::new(), None)?;
+
+        for old in older.iter().skip(keep.max(1) - 1) {
+            self.ops.run(FsOp::RemoveDirAll(&old.path))?;
+        }
+
+        Ok(entry)
+    }
+
+    /// List the backups of the profile in the given directory, made by
+    /// [SaveManager::backup_to], with the most recent first.
+    pub fn backups(&self, dir: &Path) -> Result<Vec<BackupEntry>, Error> {
+        backup::entries(&self.backups_dir(dir), &self.ops.patterns)
+    }
+
+    /// Restore the save files from the named backup of the profile in the given directory, or
+    /// the most recent one, returning the name of the backup.
+    ///
+    /// The save files in the profile are backed up into the backup slot first, like when
+    /// loading. Errors without touching the profile if the backup doesn't exist or doesn't
+    /// contain any save files, and warns if it doesn't match its manifest.
+    pub fn restore_from(&self, dir: &Path, name: Option<&str>) -> Result<String, Error> {
+        let mut backups = self.backups(dir)?.into_iter();
+
+        let entry = match name {
+            Some(name) => backups.find(|e| e.name == name),
+            None => backups.next(),
+        };
+
+        let entry = match (entry, name) {
+            (Some(entry), _) => entry,
+            (None, Some(name)) => {
+                return Err(Error::BackupNotFound {
+                    dir: self.backups_dir(dir),
+                    name: name.to_string(),
+                })
+            }
+            (None, None) => {
+                return Err(Error::NoBackups {
+                    dir: self.backups_dir(dir),
+                })
+            }
+        };
+
+        if entry.files == 0 {
+            return Err(Error::EmptyBackup { path: entry.path });
+        }
+
+        let ops = self.selected_ops()?;
+
+        if let Some(only) = ops.only {
+            let save_files = list_save_files(&entry.path, &self.ops.patterns)?;
+
+            if !save_files.iter().any(|(n, _)| only.contains(n)) {
+                return Err(Error::NoSelectedSaveFiles {
+                    name: entry.name,
+                    only: join_numbers(&self.only),
+                });
+            }
+        }
+
+        let manifest = manifest::read(&entry.path)?;
+
+        if let Some(manifest) = &manifest {
+            for mismatch in manifest::check(&entry.path, manifest, &self.ops.patterns)? {
+                eout!(
+                    "warning: backup '{}' doesn't match its manifest: {}",
+                    entry.name,
+                    mismatch
+                );
+            }
+        }
+
+        let ops = Ops {
+            settings: ops.settings && manifest.is_some_and(|m| !m.settings.is_empty()),
+            ..ops
+        };
+
+        self.change_profile(|| copy_save_files(&ops, &entry.path, &self.profile))?;
+        Ok(entry.name)
+    }
+
+    /// Compare the current save files in the profile against the named slot, where the profile
+    /// is the left-hand side of each difference. The settings files saved in the slot are
+    /// compared too if [SaveManager::include_settings] is set.
+    ///
+    /// Warns if the save files don't match the manifest of the slot, and about save files on
+    /// either side which are smaller than [SaveManager::min_save_size].
+    pub fn compare(&self, name: &str) -> Result<Vec<Difference>, Error> {
+        let slot = self.existing_slot(name)?;
+        let mut cache = self.load_hash_cache();
+        warn_manifest(&mut cache, name, &slot, &self.ops.patterns)?;
+
+        let patterns = &self.ops.patterns;
+        self.warn_small_files("the profile", &list_save_files(&self.profile, patterns)?)?;
+        self.warn_small_files(
+            &format!("slot '{}'", name),
+            &list_save_files(&slot, patterns)?,
+        )?;
+
+        let mut differences =
+            diff::compare_save_files(&mut cache, &self.profile, &slot, &self.ops.patterns)?;
+
+        if self.include_settings {
+            differences.extend(diff::compare_settings_files(
+                &mut cache,
+                &self.profile,
+                &slot,
+            )?);
+        }
+
+        self.save_hash_cache(&mut cache);
+        Ok(differences)
+    }
+
+    /// Read information about the named slot, or the profile if `name` is [PROFILE].
+    pub fn slot(&self, name: &str) -> Result<Slot, Error> {
+        read_slot(
+            name.to_string(),
+            self.readable_slot(name)?,
+            &self.ops.patterns,
+            self.min_save_size,
+        )
+    }
+
+    /// List the save files in the named slot, or the profile if `name` is [PROFILE], sorted by
+    /// name along with their digest and in-game metadata.
+    pub fn show_slot(&self, name: &str) -> Result<Vec<SaveFile>, Error> {
+        let slot = self.readable_slot(name)?;
+        let mut save_files = list_save_files(&slot, &self.ops.patterns)?;
+        save_files.sort();
+
+        let mut out = Vec::new();
+
+        for (name, path) in save_files {
+            let modified = fs::metadata(to_extended_path(&path))
+                .and_then(|m| m.modified())
+                .map_err(|e| Error::io(&path, e))?;
+            let hash = hash::hash_file(&path)?;
+            let header = header::read(&path)?;
+
+            out.push(SaveFile {
+                name: files::display_name(&name),
+                path,
+                size: hash.size,
+                modified,
+                sha256: hash.to_string(),
+                header,
+            });
+        }
+
+        Ok(out)
+    }
+
+    /// Compare the save files in two slots, either of which can be [PROFILE], with the same
+    /// results as [SaveManager::compare] where `left` takes the place of the profile.
+    pub fn diff_slots(&self, left: &str, right: &str) -> Result<Vec<Difference>, Error> {
+        let left = self.readable_slot(left)?;
+        let right = self.readable_slot(right)?;
+
+        let mut cache = self.load_hash_cache();
+        let differences = diff::compare_save_files(&mut cache, &left, &right, &self.ops.patterns)?;
+        self.save_hash_cache(&mut cache);
+        Ok(differences)
+    }
+
+    /// List the directories in `Save Files` whose name contains `name` ignoring case, sorted
+    /// newest first by comparing their names naturally.
+    pub fn find_save_files(&self, name: &str) -> Result<Vec<PathBuf>, Error> {
+        let save_files = self.profile.join(SAVE_FILES);
+        let mut matches = files::list_name_contains(&save_files, name)?;
+        matches.sort_by(|a, b| files::natural_cmp(&b.0.to_string_lossy(), &a.0.to_string_lossy()));
+        Ok(matches.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// List every directory in `Save Files` in the order of [SaveManager::find_save_files], so
+    /// that the index [SaveManager::load_save_file] accepts is the position in the list, where 1
+    /// is the first.
+    pub fn list_save_files_dirs(&self) -> Result<Vec<SaveFilesDir>, Error> {
+        if !self.profile.join(SAVE_FILES).is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut out = Vec::new();
+
+        for path in self.find_save_files("")? {
+            let save_files = list_save_files(&path, &self.ops.patterns)?;
+            let mut modified = None;
+
+            for (_, save_file) in &save_files {
+                let time = fs::metadata(to_extended_path(save_file))
+                    .and_then(|m| m.modified())
+                    .map_err(|e| Error::io(save_file, e))?;
+
+                modified = modified.max(Some(time));
+            }
+
+            let modified = match modified {
+                Some(modified) => modified,
+                None => fs::metadata(to_extended_path(&path))
+                    .and_then(|m| m.modified())
+                    .map_err(|e| Error::io(&path, e))?,
+            };
+
+            out.push(SaveFilesDir {
+                name: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                files: save_files.len(),
+                modified,
+                path,
+            });
+        }
+
+        Ok(out)
+    }
+
+    /// Find the directory in `Save Files` which [SaveManager::load_save_file] loads from, if any
+    /// matches.
+    ///
+    /// `name` is either the index of the directory in [SaveManager::list_save_files_dirs], or
+    /// part of its name, in which case `pick` chooses between the directories listed by
+    /// [SaveManager::find_save_files], where 1 is the newest. Errors with
+    /// [Error::AmbiguousSaveFiles] if several directories match and none is picked.
+    pub fn find_save_file(
+        &self,
+        name: &str,
+        pick: Option<usize>,
+    ) -> Result<Option<PathBuf>, Error> {
+        let names = |matches: &[PathBuf]| {
+            matches
+                .iter()
+                .filter_map(|p| p.file_name())
+                .map(|n| n.to_string_lossy().into_owned())
+                .collect()
+        };
+
+        if let Some(index) = parse_save_files_index(name) {
+            let mut dirs = self.find_save_files("")?;
+
+            if index == 0 || index > dirs.len() {
+                return Err(Error::PickOutOfRange {
+                    pick: index,
+                    matches: names(&dirs),
+                });
+            }
+
+            return Ok(Some(dirs.swap_remove(index - 1)));
+        }
+
+        let mut matches = self.find_save_files(name)?;
+
+        let pick = match pick {
+            Some(pick) => pick,
+            None if matches.len() > 1 => {
+                return Err(Error::AmbiguousSaveFiles {
+                    name: name.to_string(),
+                    matches: names(&matches),
+                })
+            }
+            None => 1,
+        };
+
+        if matches.is_empty() {
+            return Ok(None);
+        }
+
+        if pick == 0 || pick > matches.len() {
+            return Err(Error::PickOutOfRange {
+                pick,
+                matches: names(&matches),
+            });
+        }
+
+        Ok(Some(matches.swap_remove(pick - 1)))
+    }
+
+    /// Load the save files from the directory in `Save Files` found by
+    /// [SaveManager::find_save_file], returning the directory loaded from if any matched.
+    pub fn load_save_file(
+        &self,
+        name: &str,
+        pick: Option<usize>,
+    ) -> Result<Option<PathBuf>, Error> {
+        let from = match self.find_save_file(name, pick)? {
+            Some(from) => from,
+            None => return Ok(None),
+        };
+
+        self.change_profile(|| copy_save_files(&self.ops, &from, &self.profile))?;
+        Ok(Some(from))
+    }
+
+    /// Remove the current save files from the profile.
+    pub fn clear(&self) -> Result<(), Error> {
+        let ops = self.selected_ops()?;
+        self.change_profile(|| delete_save_files(&ops, &self.profile))
+    }
+
+    /// Delete the named slot, including its save files.
+    ///
+    /// Errors if the slot contains anything but save files, unless [SaveManager::force] is set,
+    /// or if it's pinned, unless [SaveManager::force_unpin] is set. The slot is kept in the
+    /// reserved directory `.deleted` until the next command which deletes slots, so that
+    /// [SaveManager::undo] can restore it.
+    pub fn delete_slot(&self, name: &str) -> Result<(), Error> {
+        self.clear_deleted()?;
+        self.delete_kept_slot(name)
+    }
+
+    /// Delete the named slot like [SaveManager::delete_slot], keeping it alongside the slots
+    /// which are already kept.
+    fn delete_kept_slot(&self, name: &str) -> Result<(), Error> {
+        let slot = self.existing_slot(name)?;
+        self.check_unpinned(name, &slot)?;
+
+        let other_files = self.other_files(name, &slot)?;
+        self.keep_deleted(&slot)?;
+
+        if !other_files.is_empty() {
+            return self.ops.remove(&slot);
+        }
+
+        delete_save_files(&self.ops, &slot)?;
+
+        for (_, settings_file) in files::list_settings_files(&slot)? {
+            self.ops.remove(&settings_file)?;
+        }
+
+        if slot.join(MANIFEST).is_file() {
+            self.ops.remove(&slot.join(MANIFEST))?;
+        }
+
+        if let Err(e) = self.ops.run(FsOp::RemoveDir(&slot)) {
+            out!("Failed to remove directory: {}", e);
+        }
+
+        Ok(())
+    }
+
+    /// List the dated slots which aren't kept by `keep`, newest first.
+    ///
+    /// Only slots created by [SaveManager::save_dated] are considered, so manually named slots
+    /// are never pruned. Pinned slots are skipped unless [SaveManager::force_unpin] is set.
+    pub fn prunable_slots(&self, keep: Keep) -> Result<Vec<Slot>, Error> {
+        let mut slots = self
+            .list_slots()?
+            .into_iter()
+            .filter(|s| s.dated)
+            .filter(|s| !s.pinned || self.force_unpin)
+            .collect::<Vec<_>>();
+
+        // Slots saved from the same save files have the same modification time, in which case
+        // the name tells which one was saved last.
+        slots.sort_by(|a, b| {
+            b.modified
+                .cmp(&a.modified)
+                .then_with(|| b.name.cmp(&a.name))
+        });
+
+        Ok(prune::select(
+            slots,
+            keep,
+            SystemTime::now(),
+            &self.date_format,
+        ))
+    }
+
+    /// Delete the given slots as listed by [SaveManager::prunable_slots], printing how many
+    /// slots and bytes were reclaimed.
+    ///
+    /// Like with [SaveManager::delete_slot], the slots are kept until the next command which
+    /// deletes slots, so the bytes are only reclaimed then.
+    pub fn prune(&self, slots: &[Slot]) -> Result<(), Error> {
+        let mut size = 0;
+
+        if !slots.is_empty() {
+            self.clear_deleted()?;
+        }
+
+        for slot in slots {
+            self.delete_kept_slot(&slot.name)?;
+            size += slot.size;
+        }
+
+        let verb = if self.ops.dry_run {
+            "would prune"
+        } else {
+            "pruned"
+        };
+
+        out!(
+            "{} {}, reclaiming {}",
+            verb,
+            output::count(slots.len(), "dated slot"),
+            output::size(size)
+        );
+        Ok(())
+    }
+
+    /// Copy the save files in the slot `from` into a new slot `to`, returning the directory of
+    /// the new slot. The new slot gets its own manifest, with the note and tags of the original.
+    ///
+    /// Errors if `to` already exists, unless [SaveManager::force] is set.
+    pub fn copy_slot(&self, from: &str, to: &str) -> Result<PathBuf, Error> {
+        let from = self.existing_slot(from)?;
+        let to = self.resolve(to)?;
+        let name = validate_new_slot_name(&to)?;
+        let to = self.slots_path().join(name);
+
+        if to.exists() {
+            if !self.force {
+                return Err(Error::SlotNameTaken {
+                    name: name.to_string(),
+                });
+            }
+
+            self.check_unpinned(name, &to)?;
+        } else {
+            self.ops.run(FsOp::CreateDir(&to))?;
+        }
+
+        let (note, tags) = match manifest::read(&from)? {
+            Some(manifest) => (manifest.note, manifest.tags),
+            None => (None, Vec::new()),
+        };
+
+        copy_save_files(&self.ops, &from, &to)?;
+        manifest::write(&self.ops, &to, note, tags, None)?;
+        Ok(to)
+    }
+
+    /// Replace save files which are identical across slots with hard links to a single copy,
+    /// printing how many bytes were reclaimed.
+    ///
+    /// Files are only linked if they were also modified at the same time, so that slots keep
+    /// their order. Loading copies the save files out of a slot and saving replaces them, so
+    /// neither can change the other slots a file is linked with.
+    pub fn dedup(&self) -> Result<(), Error> {
+        let slots = self.list_slots()?;
+        let paths = slots.into_iter().map(|s| s.path).collect::<Vec<_>>();
+
+        let mut cache = self.load_hash_cache();
+        let result = dedup::dedup(&self.ops, &mut cache, &paths);
+        self.save_hash_cache(&mut cache);
+        result
+    }
+
+    /// Compress every save file in every slot which isn't already compressed, printing how many
+    /// bytes were saved.
+    ///
+    /// Save files which are already compressed are left as they are, regardless of the level
+    /// they were compressed with.
+    pub fn recompress_slots(&self) -> Result<(), Error> {
+        compress::check_level(self.compression_level)?;
+
+        let mut compressed = 0;
+        let mut saved = 0;
+
+        for slot in self.list_slots()? {
+            let (files, bytes) = self.compress_slot(&slot.path)?;
+            compressed += files;
+            saved += bytes;
+        }
+
+        let verb = if self.ops.dry_run {
+            "would compress"
+        } else {
+            "compressed"
+        };
+
+        out!(
+            "{} {}, saving {}",
+            verb,
+            output::count(compressed, "save file"),
+            output::size(saved)
+        );
+        Ok(())
+    }
+
+    /// Sync the slots with the slots in the `remote` directory, like a folder which is synced
+    /// to the cloud, in both directions.
+    ///
+    /// Slots which only exist on one side are copied to the other. Slots which differ are
+    /// replaced by the side which changed since the last sync, or by the newer side if they
+    /// haven't been synced before. Slots which changed on both sides are skipped, and reported
+    /// in an [Error::SyncConflicts] once everything else is done. What each slot looked like
+    /// after the last sync is remembered in a file in the local slots directory.
+    pub fn sync(&self, remote: &Path) -> Result<(), Error> {
+        let slots = self.ensure_slots_dir()?;
+        sync::sync(&self.ops, &slots, remote)
+    }
+
+    /// Export the save files in the named slot, or the profile if `name` is [PROFILE], into a
+    /// zip archive at `path` which also contains a `manifest.json` describing them. The archive
+    /// is encrypted if [SaveManager::passphrase] is set.
+    ///
+    /// Errors if `path` already exists, unless [SaveManager::force] is set.
+    pub fn export_slot(&self, name: &str, path: &Path) -> Result<(), Error> {
+        let dir = self.readable_slot(name)?;
+
+        if path.exists() && !self.force {
+            return Err(Error::FileExists {
+                path: path.to_owned(),
+            });
+        }
+
+        if self.ops.dry_run {
+            out!("would export {} -> {}", dir.display(), path.display());
+            return Ok(());
+        }
+
+        archive::export(
+            &dir,
+            name,
+            path,
+            self.passphrase.as_ref(),
+            &self.ops.patterns,
+        )?;
+        out!("{} -> {}", dir.display(), path.display());
+        Ok(())
+    }
+
+    /// Write the save files in the named slot into a [SlotBundle] at `path`, along with its
+    /// name, note and tags, to share it with someone else who can [SaveManager::unbundle] it.
+    ///
+    /// The bundle is read back and checked once it's written. Errors if `path` already exists,
+    /// unless [SaveManager::force] is set, or if the slot doesn't contain any save files.
+    pub fn bundle_slot(&self, name: &str, path: &Path) -> Result<(), Error> {
+        let dir = self.existing_slot(name)?;
+        let slot = files::display_name(dir.file_name().unwrap_or_default());
+
+        if path.exists() && !self.force {
+            return Err(Error::FileExists {
+                path: path.to_owned(),
+            });
+        }
+
+        if list_save_files(&dir, &self.ops.patterns)?.is_empty() {
+            return Err(Error::EmptySlot {
+                name: name.to_string(),
+            });
+        }
+
+        warn_manifest(&mut HashCache::none(), name, &dir, &self.ops.patterns)?;
+
+        if self.ops.dry_run {
+            out!("would bundle {} -> {}", dir.display(), path.display());
+            return Ok(());
+        }
+
+        let manifest = manifest::read(&dir)?;
+        share::write(path, &dir, &slot, manifest.as_ref(), &self.ops.patterns)?;
+        out!("{} -> {}", dir.display(), path.display());
+        Ok(())
+    }
+
+    /// Import the [SlotBundle] at `path` into a new slot, with the note and tags it was bundled
+    /// with, returning the name of the slot.
+    ///
+    /// The slot is named `name`, or like the slot it was bundled from if it's not specified.
+    /// Every save file is checked against the bundle before the slot is created, and once it's
+    /// written. Errors if the slot already exists, unless [SaveManager::force] is set.
+    pub fn unbundle(&self, path: &Path, name: Option<&str>) -> Result<String, Error> {
+        let bundle = SlotBundle::read(path)?;
+
+        let name = match name {
+            Some(name) => self.resolve(name)?.into_owned(),
+            None => bundle.name.clone(),
+        };
+
+        validate_new_slot_name(&name)?;
+        let slots = self.ensure_slots_dir()?;
+        let slot = slots.join(&name);
+
+        if slot.exists() {
+            if !self.force {
+                return Err(Error::SlotNameTaken { name });
+            }
+
+            self.check_unpinned(&name, &slot)?;
+        }
+
+        if self.ops.dry_run {
+            out!("would unbundle {} -> {}", path.display(), slot.display());
+            return Ok(name);
+        }
+
+        // Write into a staging directory first, so that a failure doesn't leave a partial slot
+        // behind.
+        let staging = slots.join(files::STAGING);
+
+        if staging.is_dir() {
+            self.ops.run(FsOp::RemoveDirAll(&staging))?;
+        }
+
+        self.ops.run(FsOp::CreateDir(&staging))?;
+
+        // The slot keeps the edition and version it was bundled from, so that it's never
+        // loaded into a profile of another edition by accident.
+        let ops = Ops {
+            edition: bundle.edition.or(self.ops.edition),
+            game_version: bundle
+                .game_version
+                .clone()
+                .or_else(|| self.ops.game_version.clone()),
+            ..self.ops.clone()
+        };
+
+        let result = share::write_save_files(&self.ops, &bundle, &staging)
+            .and_then(|()| {
+                let (note, tags) = (bundle.note.clone(), bundle.tags.clone());
+                manifest::write(&ops, &staging, note, tags, None)
+            })
+            .and_then(|()| {
+                if slot.exists() {
+                    self.ops.run(FsOp::RemoveDirAll(&slot))?;
+                }
+
+                self.ops.run(FsOp::Rename(&staging, &slot))
+            });
+
+        if result.is_err() && staging.is_dir() {
+            if let Err(e) = self.ops.run(FsOp::RemoveDirAll(&staging)) {
+                out!("Failed to remove staging directory: {}", e);
+            }
+        }
+
+        result?;
+        out!("{} -> {}", path.display(), slot.display());
+        Ok(name)
+    }
+
+    /// Load the save files in the [SlotBundle] at `path` straight into the profile, backing up
+    /// the current ones first like [SaveManager::load], without creating a slot for them.
+    ///
+    /// Every save file is checked against the bundle before anything in the profile is
+    /// changed. Only save files are loaded, never settings. Errors with
+    /// [Error::EditionMismatch] if the bundle is from another [SaveManager::edition].
+    pub fn load_bundle(&self, path: &Path) -> Result<(), Error> {
+        let bundle = SlotBundle::read(path)?;
+        self.check_edition(&bundle.name, bundle.edition)?;
+        self.check_game_version(&bundle.name, bundle.game_version.as_deref())?;
+
+        self.load_written(&bundle.name, path, |staging| {
+            share::write_save_files(&self.ops, &bundle, staging)
+        })
+    }
+
+    /// Download the slot bundle or zip archive of save files at the given HTTPS URL, and import
+    /// it into a new slot named `slot` like [SaveManager::unbundle] and
+    /// [SaveManager::import_slot], or load it straight into the profile like
+    /// [SaveManager::load_bundle] if no slot is given.
+    ///
+    /// Redirects are followed as long as they stay on HTTPS, and the download is removed again
+    /// once it's been imported or anything fails. Errors with [Error::Download] if the server
+    /// doesn't respond with success, or the file is too large or isn't something which can be
+    /// imported.
+    pub fn load_url(&self, url: &str, slot: Option<&str>) -> Result<(), Error> {
+        let download = download::download(&self.ops, url)?;
+        let path = download.path();
+
+        match (download.kind, slot) {
+            (DownloadKind::SlotBundle, Some(slot)) => self.unbundle(path, Some(slot)).map(drop),
+            (DownloadKind::SlotBundle, None) => self.load_bundle(path),
+            (DownloadKind::Archive, Some(slot)) => self.import_slot(path, Some(slot)).map(drop),
+            (DownloadKind::Archive, None) => {
+                let name = files::display_name(path.file_name().unwrap_or_default());
+
+                self.load_written(&name, path, |staging| {
+                    archive::extract(&self.ops, path, staging, self.passphrase.as_ref())
+                })
+            }
+        }
+    }
+
+    /// Bundle the named slot like [SaveManager::bundle_slot] and upload it to the remote as
+    /// `name`, or under the name of the slot if it's not specified, returning the name it was
+    /// uploaded as.
+    ///
+    /// The upload is checked against the bundle once it's complete, and a bundle which fails
+    /// to upload completely never replaces one with the same name. Errors with
+    /// [Error::RemoteNameTaken] if the remote already has a bundle with the name, unless
+    /// [SaveManager::force] is set.
+    pub fn push(&self, remote: &Remote, slot: &str, name: Option<&str>) -> Result<String, Error> {
+        let dir = self.existing_slot(slot)?;
+        let slot = files::display_name(dir.file_name().unwrap_or_default());
+        let name = name.map_or_else(|| slot.clone(), str::to_string);
+        validate_new_slot_name(&name)?;
+
+        if list_save_files(&dir, &self.ops.patterns)?.is_empty() {
+            return Err(Error::EmptySlot { name: slot });
+        }
+
+        if !self.force && remote.exists(&name)? {
+            return Err(Error::RemoteNameTaken { name });
+        }
+
+        warn_manifest(&mut HashCache::none(), &slot, &dir, &self.ops.patterns)?;
+
+        if self.ops.dry_run {
+            out!("would push {} -> {}", dir.display(), remote.url(&name));
+            return Ok(name);
+        }
+
+        let scratch = Scratch::new("push")?;
+        let path = scratch
+            .path()
+            .join(format!("{}.{}", name, SLOT_BUNDLE_EXTENSION));
+        let manifest = manifest::read(&dir)?;
+        share::write(&path, &dir, &slot, manifest.as_ref(), &self.ops.patterns)?;
+
+        let contents = fs::read(to_extended_path(&path)).map_err(|e| Error::io(&path, e))?;
+        remote.upload(&name, &contents, self.force)?;
+        out!("{} -> {}", dir.display(), remote.url(&name));
+        Ok(name)
+    }
+
+    /// Download the bundle with the given name from the remote and import it into a new slot
+    /// like [SaveManager::unbundle], returning the name of the slot.
+    ///
+    /// The slot is named `slot`, or like the bundle if it's not specified. Errors with
+    /// [Error::RemoteBundleNotFound] if the remote has no bundle with the name.
+    pub fn pull(&self, remote: &Remote, name: &str, slot: Option<&str>) -> Result<String, Error> {
+        validate_new_slot_name(name)?;
+        let scratch = Scratch::new("pull")?;
+        let path = scratch
+            .path()
+            .join(format!("{}.{}", name, SLOT_BUNDLE_EXTENSION));
+        remote.download(&self.ops, name, &path)?;
+        self.unbundle(&path, Some(slot.unwrap_or(name)))
+    }
+
+    /// Load the save files which `write` writes into a staging directory into the profile,
+    /// backing up the current ones first like [SaveManager::load], where `name` and `path` say
+    /// where they're from. Only save files are loaded, never settings.
+    fn load_written(
+        &self,
+        name: &str,
+        path: &Path,
+        write: impl FnOnce(&Path) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let ops = self.selected_ops()?;
+
+        if self.ops.dry_run {
+            out!(
+                "would load {} -> {}",
+                path.display(),
+                self.profile.display()
+            );
+            return Ok(());
+        }
+
+        let staging = self.ensure_slots_dir()?.join(files::STAGING);
+
+        if staging.is_dir() {
+            self.ops.run(FsOp::RemoveDirAll(&staging))?;
+        }
+
+        self.ops.run(FsOp::CreateDir(&staging))?;
+
+        let ops = Ops {
+            settings: false,
+            ..ops
+        };
+
+        let result = write(&staging).and_then(|()| {
+            // Loading nothing would only delete the selected save files in the profile.
+            if let Some(only) = ops.only {
+                let save_files = list_save_files(&staging, &self.ops.patterns)?;
+
+                if !save_files.iter().any(|(n, _)| only.contains(n)) {
+                    return Err(Error::NoSelectedSaveFiles {
+                        name: name.to_string(),
+                        only: join_numbers(&self.only),
+                    });
+                }
+            }
+
+            self.change_profile(|| copy_save_files(&ops, &staging, &self.profile))
+        });
+
+        if let Err(e) = self.ops.run(FsOp::RemoveDirAll(&staging)) {
+            out!("Failed to remove staging directory: {}", e);
+        }
+
+        result
+    }
+
+    /// Check every file in every slot against the manifest of the slot, reading all of them
+    /// again instead of trusting the hash cache, and showing progress while doing so.
+    ///
+    /// Slots without a manifest can't be checked, and get one describing the files they
+    /// contain now if `adopt` is set.
+    pub fn fsck(&self, adopt: bool) -> Result<Vec<SlotCheck>, Error> {
+        fsck::check_slots(&self.ops, &self.list_slots()?, adopt)
+    }
+
+    /// Replace every save file in the named slot which doesn't match its manifest with an
+    /// identical copy from another slot, returning which slot each one was copied from.
+    ///
+    /// The other slots are searched newest first, and are never changed. Each copy is checked
+    /// against the manifest before it replaces the broken save file, and the slot is checked
+    /// again once it's repaired. Errors if the slot has no manifest, since there's nothing to
+    /// tell which save files are broken.
+    pub fn repair(&self, name: &str) -> Result<SlotRepair, Error> {
+        let slot = self.existing_slot(name)?;
+
+        let manifest = manifest::read(&slot)?.ok_or_else(|| Error::NoManifest {
+            name: name.to_string(),
+        })?;
+
+        let donors = self
+            .list_slots()?
+            .into_iter()
+            .filter(|s| s.path != slot)
+            .map(|s| (s.name, s.path))
+            .collect::<Vec<_>>();
+
+        repair::repair(&self.ops, &slot, &manifest, &donors)
+    }
+
+    /// Add up how much space the slots of every given profile take up, with the largest
+    /// profiles and slots first.
+    ///
+    /// Save files which are hard linked into several slots, like by [SaveManager::dedup], take
+    /// up space only once. They're counted towards the actual size of the newest slot they're
+    /// in, so that the actual size of an older slot is about what deleting it would free up.
+    /// Slots which are shared between the profiles are only counted for the first one.
+    pub fn space_usage(managers: &[SaveManager]) -> Result<Vec<ProfileUsage>, Error> {
+        let mut tally = Tally::default();
+        let mut counted = HashSet::new();
+        let mut profiles = Vec::new();
+
+        for manager in managers {
+            let mut slots = Vec::new();
+
+            for slot in manager.list_slots()? {
+                if !counted.insert(slot.path.clone()) {
+                    continue;
+                }
+
+                let (apparent, actual) = tally.add_dir(&slot.path)?;
+
+                slots.push(SlotUsage {
+                    name: slot.name,
+                    path: slot.path,
+                    apparent,
+                    actual,
+                });
+            }
+
+            slots.sort_by(|a, b| {
+                b.apparent
+                    .cmp(&a.apparent)
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+
+            profiles.push(ProfileUsage {
+                profile: manager.profile.clone(),
+                apparent: slots.iter().map(|s| s.apparent).sum(),
+                actual: slots.iter().map(|s| s.actual).sum(),
+                slots,
+            });
+        }
+
+        profiles.sort_by_key(|p| Reverse(p.apparent));
+        Ok(profiles)
+    }
+
+    /// Export every slot of every given profile into a single zip archive at `path`, which
+    /// [SaveManager::import_bundled] restores them from, returning the number of slots exported.
+    ///
+    /// Every file in a slot is exported with its modification time, including its manifest,
+    /// and the archive is read back and checked once it's written. Slots which are shared
+    /// between the profiles are only exported once. Errors if `path` already exists, unless
+    /// [SaveManager::force] is set on the first manager.
+    pub fn export_all(managers: &[SaveManager], path: &Path) -> Result<usize, Error> {
+        if path.exists() && !managers.first().is_some_and(|m| m.force) {
+            return Err(Error::FileExists {
+                path: path.to_owned(),
+            });
+        }
+
+        let mut profiles = Vec::new();
+        let mut exported = HashSet::new();
+
+        for manager in managers {
+            let id = files::display_name(manager.profile.file_name().unwrap_or_default());
+
+            let mut slots = manager
+                .list_slots()?
+                .into_iter()
+                .filter(|slot| exported.insert(slot.path.clone()))
+                .map(|slot| (slot.name, slot.path))
+                .collect::<Vec<_>>();
+
+            slots.sort();
+            profiles.push((id, slots));
+        }
+
+        if managers.iter().any(|m| m.ops.dry_run) {
+            let count = profiles.iter().map(|(_, slots)| slots.len()).sum();
+            out!(
+                "would export {} -> {}",
+                output::count(count, "slot"),
+                path.display()
+            );
+            return Ok(count);
+        }
+
+        let count = bundle::write(path, &profiles)?;
+        out!("{} -> {}", output::count(count, "slot"), path.display());
+        Ok(count)
+    }
+
+    /// Import the slots of a profile in a bundle written by [SaveManager::export_all] into the
+    /// slots directory of this profile, whichever profile they were exported from.
+    ///
+    /// Slots which already exist are skipped, unless `overwrite` is set, in which case they're
+    /// replaced unless they're pinned and [SaveManager::force_unpin] isn't set. Each slot is
+    /// written into a staging directory first, so that a failure doesn't leave a partial slot
+    /// behind, and every file is checked after it's written.
+    pub fn import_bundled(
+        &self,
+        profile: &BundledProfile,
+        overwrite: bool,
+    ) -> Result<BundleImport, Error> {
+        let slots = self.ensure_slots_dir()?;
+        let mut import = BundleImport::default();
+
+        for bundled in &profile.slots {
+            let name = validate_new_slot_name(&bundled.name)?;
+            let slot = slots.join(name);
+
+            if slot.exists() {
+                if !overwrite {
+                    import.skipped.push(name.to_string());
+                    continue;
+                }
+
+                self.check_unpinned(name, &slot)?;
+            }
+
+            let staging = slots.join(files::STAGING);
+
+            if staging.is_dir() {
+                self.ops.run(FsOp::RemoveDirAll(&staging))?;
+            }
+
+            self.ops.run(FsOp::CreateDir(&staging))?;
+
+            let result = bundle::write_slot(&self.ops, bundled, &staging).and_then(|()| {
+                if slot.exists() {
+                    self.ops.run(FsOp::RemoveDirAll(&slot))?;
+                }
+
+                self.ops.run(FsOp::Rename(&staging, &slot))
+            });
+
+            if result.is_err() && staging.is_dir() {
+                if let Err(e) = self.ops.run(FsOp::RemoveDirAll(&staging)) {
+                    out!("Failed to remove staging directory: {}", e);
+                }
+            }
+
+            result?;
+            import.imported.push(name.to_string());
+        }
+
+        Ok(import)
+    }
+
+    /// Import the save files in the zip archive at `path` into a new slot, returning the name
+    /// of the slot.
+    ///
+    /// The slot is named `name`, or after the archive if it's not specified. Encrypted archives
+    /// are decrypted with [SaveManager::passphrase]. Errors if the slot already exists, unless
+    /// [SaveManager::force] is set.
+    pub fn import_slot(&self, path: &Path, name: Option<&str>) -> Result<String, Error> {
+        let name = match name {
+            Some(name) => self.resolve(name)?.into_owned(),
+            None => path
+                .file_stem()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+
+        validate_new_slot_name(&name)?;
+        let slot = self.ensure_slots_dir()?.join(&name);
+
+        if slot.exists() {
+            if !self.force {
+                return Err(Error::SlotNameTaken { name });
+            }
+
+            self.check_unpinned(&name, &slot)?;
+        }
+
+        if self.ops.dry_run {
+            out!("would import {} -> {}", path.display(), slot.display());
+            return Ok(name);
+        }
+
+        // Extract into a staging directory first, so that a bad archive doesn't leave a
+        // partial slot behind.
+        let staging = self.slots_path().join(files::STAGING);
+
+        if staging.is_dir() {
+            self.ops.run(FsOp::RemoveDirAll(&staging))?;
+        }
+
+        self.ops.run(FsOp::CreateDir(&staging))?;
+
+        let result = archive::extract(&self.ops, path, &staging, self.passphrase.as_ref())
+            .and_then(|()| {
+                if !slot.is_dir() {
+                    self.ops.run(FsOp::CreateDir(&slot))?;
+                }
+
+                copy_save_files(&self.ops, &staging, &slot)?;
+                manifest::write(&self.ops, &slot, None, Vec::new(), None)
+            });
+
+        if let Err(e) = self.ops.run(FsOp::RemoveDirAll(&staging)) {
+            out!("Failed to remove staging directory: {}", e);
+        }
+
+        result?;
+        Ok(name)
+    }
+
+    /// Rename the slot `from` to `to`, returning the new directory of the slot.
+    pub fn rename_slot(&self, from: &str, to: &str) -> Result<PathBuf, Error> {
+        let from = self.existing_slot(from)?;
+        let to = self.resolve(to)?;
+        let name = validate_new_slot_name(&to)?;
+        let to = self.slots_path().join(name);
+
+        if to.exists() {
+            return Err(Error::SlotNameTaken {
+                name: name.to_string(),
+            });
+        }
+
+        if !self.ops.dry_run {
+            out!("{} -> {}", from.display(), to.display());
+        }
+
+        self.ops.run(FsOp::Rename(&from, &to))?;
+
+        // A renamed slot has a name of its own, so a dated slot stops being pruned.
+        let dated = manifest::read(&to)
+            .ok()
+            .flatten()
+            .is_some_and(|m| m.kind.as_deref() == Some(DATED_KIND));
+
+        if dated {
+            manifest::update(&self.ops, &to, |manifest| manifest.kind = None)?;
+        }
+
+        Ok(to)
+    }
+
+    /// Warn about the given save files in `place` which are smaller than
+    /// [SaveManager::min_save_size], returning their names.
+    fn warn_small_files(
+        &self,
+        place: &str,
+        save_files: &[(OsString, PathBuf)],
+    ) -> Result<Vec<String>, Error> {
+        let small = files::small_save_files(save_files, self.min_save_size)?;
+
+        for (name, size) in &small {
+            match size {
+                0 => eout!("warning: save file {} in {} is empty", name, place),
+                size => eout!(
+                    "warning: save file {} in {} is only {}, it might be truncated",
+                    name,
+                    place,
+                    output::size(*size)
+                ),
+            }
+        }
+
+        Ok(small.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// The operations to save, load and clear with, which only touch the save files selected
+    /// with [SaveManager::only], and the settings files if [SaveManager::include_settings] is set.
+    fn selected_ops(&self) -> Result<Ops, Error> {
+        Ok(Ops {
+            only: files::Selection::new(&self.only)?,
+            settings: self.include_settings,
+            ..self.ops.clone()
+        })
+    }
+
+    /// Copy the current save files into the backup slot, unless backups are disabled.
+    fn backup_profile(&self) -> Result<(), Error> {
+        if !self.backup {
+            return Ok(());
+        }
+
+        let backup = self.backup_slot();
+
+        if !backup.is_dir() {
+            self.ops.run(FsOp::CreateDir(&backup))?;
+        }
+
+        // The settings are always backed up, so that the backup never has stale ones.
+        copy_save_files(&self.backup_ops(), &self.profile, &backup)
+    }
+
+    /// Back up the save files in the profile, and run `f` which replaces or deletes them.
+    ///
+    /// If `f` is cancelled after it has changed the save files, they're restored from the
+    /// backup without it being possible to cancel that, unless backups are disabled.
+    fn change_profile(&self, f: impl FnOnce() -> Result<(), Error>) -> Result<(), Error> {
+        self.backup_profile()?;
+        let result = f();
+
+        if !self.backup || !matches!(result, Err(Error::Cancelled)) {
+            return result;
+        }
+
+        let backup = self.backup_slot();
+
+        // The save files were just replaced, so nothing cached about them can be trusted.
+        let mut cache = HashCache::none();
+
+        if diff::compare_save_files(&mut cache, &self.profile, &backup, &self.ops.patterns)?
+            .is_empty()
+        {
+            return result;
+        }
+
+        out!("cancelled, restoring the save files from before");
+        copy_save_files(&self.backup_ops().uncancellable(), &backup, &self.profile)?;
+        result
+    }
+
+    /// The operations to back up and restore the profile with, which include the settings.
+    fn backup_ops(&self) -> Ops {
+        Ops {
+            settings: true,
+            ..self.ops.clone()
+        }
+    }
+
+    /// Copy the save files in the backup slot into the profile, printing which ones.
+    fn restore_backup(&self) -> Result<(), Error> {
+        let backup = self.backup_slot();
+
+        let mut save_files = match backup.is_dir() {
+            true => list_save_files(&backup, &self.ops.patterns)?,
+            false => Vec::new(),
+        };
+
+        if save_files.is_empty() {
+            return Err(Error::NoBackup);
+        }
+
+        save_files.sort();
+        let names = save_files
+            .iter()
+            .map(|(name, _)| files::display_name(name))
+            .collect::<Vec<_>>();
+
+        out!(
+            "restoring {} from {}: {}",
+            output::count(names.len(), "save file"),
+            backup.display(),
+            names.join(", ")
+        );
+
+        copy_save_files(&self.backup_ops(), &backup, &self.profile)
+    }
+
+    /// The last successful command in the journal which [SaveManager::undo] can undo, or `None`
+    /// if there is none.
+    ///
+    /// Errors with [Error::NotUndoable] if another command has changed the backup slot or the
+    /// deleted slots since, or was an undo.
+    fn last_undoable(&self) -> Result<Option<HistoryEntry>, Error> {
+        let backup = self.backup_slot();
+        let deleted = self.deleted_dir();
+        let reserved = |p: &Path| p.starts_with(&backup) || p.starts_with(&deleted);
+
+        for entry in self.history()?.into_iter().rev() {
+            if entry.status != "ok" {
+                continue;
+            }
+
+            let command = entry.command.as_str();
+
+            if UNDO_RESTORES_BACKUP.contains(&command) || UNDO_RESTORES_DELETED.contains(&command) {
+                return Ok(Some(entry));
+            }
+
+            let changed_reserved = entry
+                .files
+                .iter()
+                .any(|f| reserved(&f.path) || f.to.as_deref().is_some_and(reserved));
+
+            if command == "undo" || changed_reserved {
+                return Err(Error::NotUndoable {
+                    command: entry.command,
+                });
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Test if the save files in the profile which were changed by the given journal entry are
+    /// still the way it left them, by comparing their hashes with the ones it recorded.
+    fn unchanged_since(&self, entry: &HistoryEntry) -> Result<bool, Error> {
+        let mut expected = BTreeMap::<OsString, Option<&str>>::new();
+
+        let in_profile = |p: &&Path| p.parent() == Some(&self.profile);
+
+        for file in &entry.files {
+            let removed = matches!(file.op.as_str(), "rename" | "delete" | "recycle");
+
+            if removed && in_profile(&file.path.as_path()) {
+                let name = file.path.file_name().unwrap_or_default().to_owned();
+                expected.insert(name, None);
+            }
+
+            if let Some(to) = file.to.as_deref().filter(in_profile) {
+                let name = to.file_name().unwrap_or_default().to_owned();
+                expected.insert(name, file.sha256.as_deref());
+            }
+        }
+
+        for (name, sha256) in expected {
+            let path = self.profile.join(&name);
+
+            let current = match path.is_file() {
+                true => Some(hash::hash_file(&path)?.to_string()),
+                false => None,
+            };
+
+            if current.as_deref() != sha256 {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// The names of the slots which were deleted by the given journal entry, and are still held
+    /// in the reserved directory of deleted slots.
+    fn deleted_by(&self, entry: &HistoryEntry) -> Result<Vec<String>, Error> {
+        let deleted = self.deleted_dir();
+        let mut names = Vec::new();
+
+        for file in &entry.files {
+            let kept = file.to.as_deref().and_then(Path::parent);
+
+            if let Some(kept) = kept.filter(|k| k.parent() == Some(&deleted)) {
+                let name = files::display_name(kept.file_name().unwrap_or_default());
+
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+
+        if names.is_empty() || !names.iter().all(|n| deleted.join(n).is_dir()) {
+            return Err(Error::NoBackup);
+        }
+
+        Ok(names)
+    }
+
+    /// Move the named slots out of the reserved directory of deleted slots, back into place.
+    ///
+    /// Errors before restoring any if a slot with the same name exists, unless
+    /// [SaveManager::force] is set, in which case the slot is replaced.
+    fn restore_deleted(&self, names: &[String]) -> Result<(), Error> {
+        let slots = self.slots_path();
+
+        for name in names {
+            if slots.join(name).exists() && !self.force {
+                return Err(Error::SlotNameTaken { name: name.clone() });
+            }
+        }
+
+        let deleted = self.deleted_dir();
+
+        for name in names {
+            let slot = slots.join(name);
+
+            if slot.exists() {
+                self.ops.remove(&slot)?;
+            }
+
+            self.ops.run(FsOp::Rename(&deleted.join(name), &slot))?;
+        }
+
+        Ok(())
+    }
+
+    /// Keep the files of the given slot, which is about to be deleted, in the reserved
+    /// directory of deleted slots, unless backups are disabled.
+    ///
+    /// The files are hard linked where possible, so that keeping them doesn't copy anything.
+    /// Directories in the slot aren't kept.
+    fn keep_deleted(&self, slot: &Path) -> Result<(), Error> {
+        if !self.backup {
+            return Ok(());
+        }
+
+        let kept = self
+            .deleted_dir()
+            .join(slot.file_name().unwrap_or_default());
+        self.ops.run(FsOp::CreateDir(&kept))?;
+
+        for (name, path) in files::find_matching(slot, |p| p.is_file(), |_| true)? {
+            let to = kept.join(name);
+
+            if self.ops.run(FsOp::HardLink(&path, &to)).is_err() {
+                self.ops.run(FsOp::Copy(&path, &to))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove the slots deleted by an earlier command, which can no longer be undone.
+    fn clear_deleted(&self) -> Result<(), Error> {
+        let deleted = self.deleted_dir();
+
+        if deleted.is_dir() {
+            self.ops.run(FsOp::RemoveDirAll(&deleted))?;
+        }
+
+        Ok(())
+    }
+
+    /// Error if the given slot is pinned, unless [SaveManager::force_unpin] is set.
+    fn check_unpinned(&self, name: &str, slot: &Path) -> Result<(), Error> {
+        if self.force_unpin {
+            return Ok(());
+        }
+
+        // A broken manifest can't be trusted to say that the slot isn't pinned.
+        let pinned = match manifest::read(slot) {
+            Ok(manifest) => manifest.map(|m| m.pinned).unwrap_or_default(),
+            Err(..) => true,
+        };
+
+        if pinned {
+            return Err(Error::SlotPinned {
+                name: name.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// List the files in a slot which aren't save files, erroring if there are any unless
+    /// [SaveManager::force] is set.
+    fn other_files(&self, name: &str, slot: &Path) -> Result<Vec<(OsString, PathBuf)>, Error> {
+        let others = files::list_other_files(slot, &self.ops.patterns)?;
+
+        if !others.is_empty() && !self.force {
+            return Err(Error::UnexpectedFiles {
+                name: name.to_string(),
+                files: others.into_iter().map(|(_, path)| path).collect(),
+            });
+        }
+
+        Ok(others)
+    }
+
+    /// The directory where slots are stored, which might not exist yet.
+    ///
+    /// Only commands which store slots should create it, with [SaveManager::ensure_slots_dir].
+    fn slots_path(&self) -> PathBuf {
+        let mut dir = match &self.slots_dir {
+            Some(dir) => dir.clone(),
+            None => return self.profile.join(SLOTS),
+        };
+
+        if let Some(game) = &self.game {
+            dir.push(game);
+        }
+
+        if self.ops.edition == Some(Edition::Enhanced) {
+            dir.push(ENHANCED_SLOTS);
+        }
+
+        if self.shared_slots {
+            return dir;
+        }
+
+        dir.join(self.profile.file_name().unwrap_or_default())
+    }
+
+    /// Error if save files from the given edition can't be loaded into the profile, unless
+    /// [SaveManager::force] is set. Save files from an unknown edition are always loaded.
+    fn check_edition(&self, name: &str, edition: Option<Edition>) -> Result<(), Error> {
+        match (edition, self.ops.edition) {
+            (Some(from), Some(to)) if from != to && !self.force => Err(Error::EditionMismatch {
+                name: name.to_string(),
+                from,
+                to,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Warn if save files from the given version of the game are loaded into a profile played
+    /// with another [SaveManager::game_version], or error if [SaveManager::strict_version] is
+    /// set. Nothing is checked unless both versions are known.
+    fn check_game_version(&self, name: &str, saved: Option<&str>) -> Result<(), Error> {
+        let (saved, current) = match (saved, self.ops.game_version.as_deref()) {
+            (Some(saved), Some(current)) if saved != current => (saved, current),
+            _ => return Ok(()),
+        };
+
+        if self.strict_version {
+            return Err(Error::GameVersionMismatch {
+                name: name.to_string(),
+                saved: saved.to_string(),
+                current: current.to_string(),
+            });
+        }
+
+        eout!(
+            "warning: '{}' was saved with version {} of the game, but version {} is played",
+            name,
+            saved,
+            current
+        );
+        Ok(())
+    }
+
+    /// The directory in `dir` which holds the backups of the profile, named like the profile so
+    /// that several profiles can be backed up into the same directory.
+    fn backups_dir(&self, dir: &Path) -> PathBuf {
+        dir.join(self.profile.file_name().unwrap_or(self.profile.as_os_str()))
+    }
+
+    /// The directory of the reserved backup slot, which is always in the profile.
+    fn backup_slot(&self) -> PathBuf {
+        self.profile.join(SLOTS).join(BACKUP_SLOT)
+    }
+
+    /// The reserved directory of deleted slots, which is kept with the slots so that they can
+    /// be hard linked into it.
+    fn deleted_dir(&self) -> PathBuf {
+        self.slots_path().join(DELETED)
+    }
+
+    /// Load the cache of save file hashes in the slots directory.
+    fn load_hash_cache(&self) -> HashCache {
+        HashCache::load(self.slots_path().join(HASH_CACHE), self.hash_cache)
+    }
+
+    /// Write the cache of save file hashes unless this is a dry run. It only saves reading the
+    /// save files again the next time, so failing to is only warned about.
+    fn save_hash_cache(&self, cache: &mut HashCache) {
+        if self.ops.dry_run {
+            return;
+        }
+
+        if let Err(e) = cache.save() {
+            eout!("warning: failed to write the hash cache: {}", e);
+        }
+    }
+
+    /// The directory of the stash, which is always in the profile.
+    fn stash_dir(&self) -> PathBuf {
+        self.profile.join(SLOTS).join(STASH)
+    }
+
+    /// Test if the profile contains save files which aren't identical to the ones in any slot or
+    /// stash entry.
+    fn has_unsaved_changes(&self) -> Result<bool, Error> {
+        if list_save_files(&self.profile, &self.ops.patterns)?.is_empty() {
+            return Ok(false);
+        }
+
+        let slots = self.list_slots()?.into_iter().map(|s| s.path);
+        let entries = self.stash_list()?.into_iter().map(|e| e.path);
+
+        // Save files are about to be changed, so the cache isn't trusted to say they're saved.
+        let mut cache = HashCache::none();
+
+        for path in slots.chain(entries) {
+            if diff::compare_save_files(&mut cache, &self.profile, &path, &self.ops.patterns)?
+                .is_empty()
+            {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// The directory of the named slot, which must exist.
+    ///
+    /// If it doesn't, the error names the slots which are similar to it, and the only similar
+    /// slot is used instead if [SaveManager::fuzzy] is set. Several equally similar slots are
+    /// never picked between.
+    fn existing_slot(&self, name: &str) -> Result<PathBuf, Error> {
+        let resolved = self.resolve(name)?;
+        let slot = self.slots_path().join(validate_slot_name(&resolved)?);
+
+        if slot.is_dir() {
+            return Ok(slot);
+        }
+
+        if let Cow::Owned(resolved) = resolved {
+            return Err(Error::AliasedSlotNotFound {
+                alias: name.to_string(),
+                name: resolved,
+            });
+        }
+
+        let names = self.slot_names()?;
+        let similar = fuzzy::best_matches(name, names.iter().map(String::as_str));
+
+        match similar.as_slice() {
+            [] => Err(Error::SlotNotFound {
+                name: name.to_string(),
+            }),
+            [only] if self.fuzzy => {
+                let mut used = self.fuzzy_used.lock().unwrap_or_else(|e| e.into_inner());
+
+                if used.insert(name.to_string()) {
+                    out!("slot '{}' does not exist, using '{}'", name, only);
+                }
+
+                Ok(self.slots_path().join(only))
+            }
+            _ => Err(Error::SimilarSlotNotFound {
+                name: name.to_string(),
+                similar: similar.iter().map(|s| s.to_string()).collect(),
+            }),
+        }
+    }
+
+    /// Resolve a slot name like `@1` to the name of the slot it's an alias for, leaving other
+    /// names and [PROFILE] as they are.
+    fn resolve<'a>(&self, name: &'a str) -> Result<Cow<'a, str>, Error> {
+        let alias = match name.strip_prefix('@') {
+            Some(alias) if name != PROFILE => alias,
+            _ => return Ok(Cow::Borrowed(name)),
+        };
+
+        match alias::read(&self.slots_path())?.remove(alias) {
+            Some(slot) => Ok(Cow::Owned(slot)),
+            None => Err(Error::AliasNotFound {
+                alias: alias.to_string(),
+            }),
+        }
+    }
+
+    /// The directory of the named slot, or the profile if `name` is [PROFILE].
+    fn readable_slot(&self, name: &str) -> Result<PathBuf, Error> {
+        if name == PROFILE {
+            return Ok(self.profile.clone());
+        }
+
+        self.existing_slot(name)
+    }
+
+    /// Compress the save files in the given slot which aren't already compressed, returning how
+    /// many were compressed and how many bytes that saved.
+    fn compress_slot(&self, slot: &Path) -> Result<(usize, u64), Error> {
+        let mut save_files = list_save_files(slot, &self.ops.patterns)?;
+        save_files.sort();
+
+        let mut compressed = 0;
+        let mut saved = 0;
+
+        for (_, save_file) in save_files {
+            if compress::is_compressed(&save_file) {
+                continue;
+            }
+
+            let size = compress::size(&save_file)?;
+            let size_compressed =
+                compress::compress_file(&self.ops, &save_file, self.compression_level)?;
+            compressed += 1;
+            saved += size.saturating_sub(size_compressed);
+        }
+
+        Ok((compressed, saved))
+    }
+
+    /// Ensure that the directory where slots are stored exists and return it.
+    fn ensure_slots_dir(&self) -> Result<PathBuf, Error> {
+        let slots = self.slots_path();
+
+        if !slots.is_dir() {
+            self.ops.run(FsOp::CreateDir(&slots))?;
+        }
+
+        Ok(slots)
+    }
+}
+
+/// The name and directory of every slot in the given slots directory, skipping the reserved ones.
+fn slot_dirs(slots: &Path) -> Result<Vec<(OsString, PathBuf)>, Error> {
+    let is_slot = |n: &OsStr| n != BACKUP_SLOT && n != STASH && n != DELETED && n != files::STAGING;
+    files::find_matching(slots, |p| p.is_dir(), is_slot)
+}
+
+/// Read information about the slot in the given directory.
+fn read_slot(
+    name: String,
+    path: PathBuf,
+    patterns: &SaveFilePatterns,
+    min_save_size: u64,
+) -> Result<Slot, Error> {
+    let save_files = list_save_files(&path, patterns)?;
+
+    let suspicious = files::small_save_files(&save_files, min_save_size)?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+
+    let mut save_numbers = save_files
+        .iter()
+        .flat_map(|(name, _)| files::save_number(name))
+        .collect::<Vec<_>>();
+    save_numbers.sort();
+    save_numbers.dedup();
+
+    let mut size = 0;
+    let mut newest = None;
+
+    for (_, save_file) in &save_files {
+        let meta =
+            fs::metadata(to_extended_path(save_file)).map_err(|e| Error::io(save_file, e))?;
+        let modified = meta.modified().map_err(|e| Error::io(save_file, e))?;
+        size += meta.len();
+
+        if newest.as_ref().map(|(m, _)| modified > *m).unwrap_or(true) {
+            newest = Some((modified, save_file));
+        }
+    }
+
+    let (modified, title) = match newest {
+        Some((modified, save_file)) => {
+            // An unreadable header shouldn't prevent the slot from being listed.
+            let title = header::read(save_file).ok().and_then(|h| h.title);
+            (modified, title)
+        }
+        None => {
+            let modified = fs::metadata(to_extended_path(&path))
+                .and_then(|m| m.modified())
+                .map_err(|e| Error::io(&path, e))?;
+            (modified, None)
+        }
+    };
+
+    // A broken manifest shouldn't prevent the slot from being used.
+    let manifest = match manifest::read(&path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eout!("warning: {}", e);
+            None
+        }
+    };
+
+    let (note, tags, pinned, kind, game_version, headers) = match manifest {
+        Some(manifest) => {
+            let headers = manifest
+                .files
+                .iter()
+                .flat_map(|f| Some((f.name.clone(), f.header.as_ref()?.to_header())))
+                .collect();
+
+            (
+                manifest.note,
+                manifest.tags,
+                manifest.pinned,
+                manifest.kind,
+                manifest.game_version,
+                headers,
+            )
+        }
+        None => (None, Vec::new(), false, None, None, Vec::new()),
+    };
+
+    let dated = kind.as_deref() == Some(DATED_KIND) || name.starts_with(DATED_PREFIX);
+
+    Ok(Slot {
+        name,
+        path,
+        modified,
+        files: save_files.len(),
+        size,
+        note,
+        tags,
+        title,
+        pinned,
+        save_numbers,
+        suspicious,
+        dated,
+        game_version,
+        headers,
+    })
+}
+
+/// Parse a name given to [SaveManager::find_save_file] as an index, if it's only digits.
+fn parse_save_files_index(name: &str) -> Option<usize> {
+    if name.is_empty() || !name.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    // An index too large to parse is out of range anyway.
+    Some(name.parse().unwrap_or(usize::MAX))
+}
+
+/// Join the numbers of in-game save slots for display, like `0, 1`.
+fn join_numbers(numbers: &[u32]) -> String {
+    numbers
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Warn about every save file in the slot which doesn't match its manifest, if it has one.
+fn warn_manifest(
+    cache: &mut HashCache,
+    name: &str,
+    slot: &Path,
+    patterns: &SaveFilePatterns,
+) -> Result<(), Error> {
+    let manifest = match manifest::read(slot) {
+        Ok(Some(manifest)) => manifest,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            eout!("warning: {}", e);
+            return Ok(());
+        }
+    };
+
+    for mismatch in manifest::check_with(slot, &manifest, patterns, |p| cache.hash(p))? {
+        eout!(
+            "warning: slot '{}' doesn't match its manifest: {}",
+            name,
+            mismatch
+        );
+    }
+
+    Ok(())
+}
+
+/// Check that a slot name refers to a directory directly inside of Slots.
+fn validate_slot_name(name: &str) -> Result<&str, Error> {
+    let reason = if name.is_empty() {
+        "it's empty"
+    } else if name == "." || name == ".." || name.contains(&['/', '\\'][..]) {
+        "it must name a directory directly inside of Slots"
+    } else {
+        return Ok(name);
+    };
+
+    Err(Error::InvalidSlotName {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+/// Check that a tag is non-empty and doesn't contain whitespace or commas, which would make it
+/// ambiguous when listed.
+pub fn validate_tag(tag: &str) -> Result<&str, Error> {
+    if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',') {
+        return Err(Error::InvalidTag {
+            tag: tag.to_string(),
+        });
+    }
+
+    Ok(tag)
+}
+
+/// Check that a slot name is valid for a slot created by the user, which excludes the reserved
+/// directories like the backup slot and names which can't be used on Windows.
+fn validate_new_slot_name(name: &str) -> Result<&str, Error> {
+    let name = validate_slot_name(name)?;
+
+    let checked = if [BACKUP_SLOT, STASH, DELETED, files::STAGING].contains(&name) {
+        Err(String::from("it's reserved by gtav-saveload"))
+    } else {
+        files::check_file_name(name)
+    };
+
+    if let Err(reason) = checked {
+        return Err(Error::InvalidSlotName {
+            name: name.to_string(),
+            reason,
+        });
+    }
+
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CheckStatus, RepairedFile};
+    use tempfile::TempDir;
+
+    type TestResult<T = ()> = Result<T, Box<dyn std::error::Error>>;
+
+    /// A profile directory with two save files in it.
+    fn profile_with_saves() -> TestResult<TempDir> {
+        let profile = tempfile::tempdir()?;
+        fs::write(profile.path().join("SGTA00000"), b"current")?;
+        fs::write(profile.path().join("SGTA00001"), b"current")?;
+        Ok(profile)
+    }
+
+    #[test]
+    fn load_missing_slot_keeps_profile() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        let error = manager.load("foo").unwrap_err();
+        assert_eq!(error.to_string(), "slot 'foo' does not exist");
+
+        assert_eq!(
+            list_save_files(profile.path(), &SaveFilePatterns::default())?.len(),
+            2
+        );
+        assert!(!profile.path().join("Slots").join("foo").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn load_empty_slot_keeps_profile() -> TestResult {
+        let profile = profile_with_saves()?;
+        fs::create_dir_all(profile.path().join("Slots").join("foo"))?;
+        let manager = SaveManager::new(profile.path());
+
+        let error = manager.load("foo").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "slot 'foo' does not contain any save files"
+        );
+
+        assert_eq!(
+            list_save_files(profile.path(), &SaveFilePatterns::default())?.len(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn load_slot_replaces_profile() -> TestResult {
+        let profile = profile_with_saves()?;
+        let slot = profile.path().join("Slots").join("foo");
+        fs::create_dir_all(&slot)?;
+        fs::write(slot.join("SGTA00000"), b"slot")?;
+
+        SaveManager::new(profile.path()).load("foo")?;
+
+        let files = list_save_files(profile.path(), &SaveFilePatterns::default())?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"slot");
+        Ok(())
+    }
+
+    #[test]
+    fn dry_run_load_keeps_profile() -> TestResult {
+        let profile = profile_with_saves()?;
+        let slot = profile.path().join("Slots").join("foo");
+        fs::create_dir_all(&slot)?;
+        fs::write(slot.join("SGTA00000"), b"slot")?;
+
+        SaveManager::new(profile.path()).dry_run(true).load("foo")?;
+
+        assert_eq!(
+            list_save_files(profile.path(), &SaveFilePatterns::default())?.len(),
+            2
+        );
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"current");
+        Ok(())
+    }
+
+    #[test]
+    fn cancelled_load_keeps_profile() -> TestResult {
+        static CANCEL: AtomicBool = AtomicBool::new(true);
+
+        let profile = profile_with_saves()?;
+        let slot = profile.path().join("Slots").join("foo");
+        fs::create_dir_all(&slot)?;
+        fs::write(slot.join("SGTA00000"), b"slot")?;
+
+        let manager = SaveManager::new(profile.path()).cancel(&CANCEL);
+        assert!(matches!(manager.load("foo"), Err(Error::Cancelled)));
+
+        assert_eq!(
+            list_save_files(profile.path(), &SaveFilePatterns::default())?.len(),
+            2
+        );
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"current");
+        assert!(!profile.path().join(files::STAGING).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn cancelled_changes_are_restored() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        let result = manager.change_profile(|| {
+            let save_file = profile.path().join("SGTA00000");
+            fs::remove_file(&save_file).map_err(|e| Error::io(&save_file, e))?;
+            Err(Error::Cancelled)
+        });
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert_eq!(
+            list_save_files(profile.path(), &SaveFilePatterns::default())?.len(),
+            2
+        );
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"current");
+        Ok(())
+    }
+
+    #[test]
+    fn save_list_and_delete_slots() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        manager.save("foo")?;
+        let dated = manager.save_dated()?;
+
+        let slots = manager.list_slots()?;
+        assert_eq!(slots.len(), 2);
+        assert!(slots.iter().all(|s| s.files == 2 && s.size == 14));
+
+        manager.delete_slot(&dated)?;
+        let slots = manager.list_slots()?;
+        assert_eq!(slots.len(), 1);
+        assert_eq!(manager.nth_newest_slot(1)?.name, "foo");
+        assert!(matches!(
+            manager.nth_newest_slot(0),
+            Err(Error::InvalidIndex { .. })
+        ));
+        assert!(matches!(
+            manager.nth_newest_slot(2),
+            Err(Error::OutOfRange { nth: 2, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn slots_in_another_directory() -> TestResult {
+        let profile = profile_with_saves()?;
+        let slots = tempfile::tempdir()?;
+        let id = profile.path().file_name().unwrap_or_default();
+
+        let manager = SaveManager::new(profile.path()).slots_dir(slots.path());
+        manager.save("foo")?;
+        assert!(slots
+            .path()
+            .join(id)
+            .join("foo")
+            .join("SGTA00000")
+            .is_file());
+        assert!(!profile.path().join("Slots").join("foo").exists());
+
+        assert_eq!(manager.nth_newest_slot(1)?.name, "foo");
+        fs::write(profile.path().join("SGTA00000"), b"changed")?;
+        manager.load("foo")?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"current");
+
+        // The backup stays with the profile.
+        assert!(profile.path().join("Slots").join(BACKUP_SLOT).is_dir());
+        assert_eq!(manager.list_slots()?.len(), 1);
+        manager.undo()?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"changed");
+
+        let shared = SaveManager::new(profile.path())
+            .slots_dir(slots.path())
+            .shared_slots(true);
+        assert!(matches!(
+            shared.load("foo"),
+            Err(Error::SlotNotFound { .. })
+        ));
+        shared.save("bar")?;
+        assert!(slots.path().join("bar").join("SGTA00000").is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn slots_are_kept_apart_by_game() -> TestResult {
+        let profile = tempfile::tempdir()?;
+        fs::write(profile.path().join("SRDR30000"), b"current")?;
+        fs::write(profile.path().join("SGTA00000"), b"other")?;
+
+        let slots = tempfile::tempdir()?;
+        let id = profile.path().file_name().unwrap_or_default();
+        let rdr2 = GameDef::find("rdr2", &Default::default())?;
+
+        let manager = SaveManager::new(profile.path())
+            .slots_dir(slots.path())
+            .game(&rdr2);
+
+        manager.save("foo")?;
+
+        let slot = slots.path().join("rdr2").join(id).join("foo");
+        assert!(slot.join("SRDR30000").is_file());
+        assert!(!slot.join("SGTA00000").exists());
+        assert!(!slots.path().join(id).exists());
+
+        fs::write(profile.path().join("SRDR30000"), b"changed")?;
+        manager.load("foo")?;
+        assert_eq!(fs::read(profile.path().join("SRDR30000"))?, b"current");
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"other");
+        Ok(())
+    }
+
+    #[test]
+    fn slots_are_kept_apart_by_edition() -> TestResult {
+        let profile = profile_with_saves()?;
+        let slots = tempfile::tempdir()?;
+        let id = profile.path().file_name().unwrap_or_default();
+
+        let legacy = SaveManager::new(profile.path())
+            .slots_dir(slots.path())
+            .edition(Edition::Legacy);
+        let enhanced = SaveManager::new(profile.path())
+            .slots_dir(slots.path())
+            .edition(Edition::Enhanced);
+
+        legacy.save("foo")?;
+        enhanced.save("foo")?;
+        assert!(slots.path().join(id).join("foo").is_dir());
+        assert!(slots
+            .path()
+            .join(ENHANCED_SLOTS)
+            .join(id)
+            .join("foo")
+            .is_dir());
+
+        // Slots which are moved between editions still aren't loaded by accident.
+        let profile_slots = profile.path().join("Slots");
+        legacy.copy_slot("foo", "legacy")?;
+        fs::create_dir_all(&profile_slots)?;
+        fs::rename(
+            slots.path().join(id).join("legacy"),
+            profile_slots.join("legacy"),
+        )?;
+
+        let manager = SaveManager::new(profile.path()).edition(Edition::Enhanced);
+        assert!(matches!(
+            manager.load("legacy"),
+            Err(Error::EditionMismatch {
+                from: Edition::Legacy,
+                to: Edition::Enhanced,
+                ..
+            })
+        ));
+        manager.clone().force(true).load("legacy")?;
+
+        // Slots from before editions were recorded are loaded into any profile.
+        SaveManager::new(profile.path()).save("unknown")?;
+        manager.load("unknown")?;
+        Ok(())
+    }
+
+    #[test]
+    fn game_versions_are_compared_at_load() -> TestResult {
+        let profile = profile_with_saves()?;
+        let old = SaveManager::new(profile.path()).game_version("1.0.3095.0");
+        let new = SaveManager::new(profile.path()).game_version("1.0.3258.0");
+
+        old.save("foo")?;
+        assert_eq!(
+            new.list_slots()?[0].game_version.as_deref(),
+            Some("1.0.3095.0")
+        );
+
+        // Another version only warns, unless it's strict.
+        new.load("foo")?;
+        assert!(matches!(
+            new.clone().strict_version(true).load("foo"),
+            Err(Error::GameVersionMismatch { saved, current, .. })
+                if saved == "1.0.3095.0" && current == "1.0.3258.0"
+        ));
+        old.clone().strict_version(true).load("foo")?;
+
+        // Nothing is compared unless both versions are known.
+        SaveManager::new(profile.path()).save("unknown")?;
+        new.clone().strict_version(true).load("unknown")?;
+        SaveManager::new(profile.path())
+            .strict_version(true)
+            .load("foo")?;
+        Ok(())
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn dedup_links_identical_save_files() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        manager.save("foo")?;
+        manager.save("bar")?;
+
+        let slots = profile.path().join("Slots");
+        let (foo, bar) = (slots.join("foo"), slots.join("bar"));
+        let id = |path: &Path| crate::links::file_id(path).map(|id| id.index);
+
+        manager.dry_run(true).dedup()?;
+        assert_ne!(id(&foo.join("SGTA00000")), id(&bar.join("SGTA00000")));
+
+        let manager = SaveManager::new(profile.path());
+        manager.dedup()?;
+        assert_eq!(id(&foo.join("SGTA00000")), id(&bar.join("SGTA00000")));
+        assert_eq!(id(&foo.join("SGTA00001")), id(&bar.join("SGTA00001")));
+        assert!(!bar.join(".dedup-SGTA00000").exists());
+
+        // Saving into one of the slots leaves the other alone.
+        fs::write(profile.path().join("SGTA00000"), b"changed")?;
+        manager.force(true).save("foo")?;
+        assert_eq!(fs::read(foo.join("SGTA00000"))?, b"changed");
+        assert_eq!(fs::read(bar.join("SGTA00000"))?, b"current");
+        Ok(())
+    }
+
+    #[test]
+    fn fsck_checks_every_slot() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        let slots = profile.path().join("Slots");
+
+        manager.save("a")?;
+        manager.save("b")?;
+        manager.save("c")?;
+
+        fs::write(slots.join("b").join("SGTA00000"), b"corrupt")?;
+        fs::write(slots.join("b").join("SGTA00001"), b"short")?;
+        fs::write(slots.join("b").join("SGTA00002"), b"extra")?;
+        fs::remove_file(slots.join("c").join(MANIFEST))?;
+
+        let status = |checks: Vec<SlotCheck>| {
+            let mut checks = checks
+                .into_iter()
+                .map(|c| (c.name, c.status))
+                .collect::<Vec<_>>();
+            checks.sort_by(|a, b| a.0.cmp(&b.0));
+            checks
+        };
+
+        assert_eq!(
+            status(manager.fsck(false)?),
+            vec![
+                (String::from("a"), CheckStatus::Ok),
+                (
+                    String::from("b"),
+                    CheckStatus::Problems(vec![
+                        String::from("SGTA00000: contents differ from manifest"),
+                        String::from("SGTA00001: expected 7 bytes, found 5"),
+                        String::from("SGTA00002: not in manifest"),
+                    ])
+                ),
+                (String::from("c"), CheckStatus::Unverifiable),
+            ]
+        );
+
+        assert_eq!(status(manager.fsck(true)?)[2].1, CheckStatus::Adopted);
+        assert_eq!(status(manager.fsck(false)?)[2].1, CheckStatus::Ok);
+        Ok(())
+    }
+
+    #[test]
+    fn repair_from_other_slots() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        let slots = profile.path().join("Slots");
+        let (a, b) = (slots.join("a"), slots.join("b"));
+
+        fs::write(profile.path().join("SGTA00002"), b"only in a")?;
+        manager.save("a")?;
+        fs::remove_file(profile.path().join("SGTA00002"))?;
+        manager.save("b")?;
+
+        fs::write(a.join("SGTA00000"), b"corrupt")?;
+        fs::remove_file(a.join("SGTA00001"))?;
+        fs::write(a.join("SGTA00002"), b"corrupt too")?;
+
+        let repair = manager.repair("a")?;
+        assert_eq!(
+            repair.repaired,
+            vec![
+                RepairedFile {
+                    name: String::from("SGTA00000"),
+                    donor: String::from("b"),
+                },
+                RepairedFile {
+                    name: String::from("SGTA00001"),
+                    donor: String::from("b"),
+                },
+            ]
+        );
+        assert_eq!(repair.unrepaired, vec![String::from("SGTA00002")]);
+        assert!(repair.problems.is_empty());
+
+        assert_eq!(fs::read(a.join("SGTA00000"))?, b"current");
+        assert_eq!(fs::read(a.join("SGTA00001"))?, b"current");
+        assert_eq!(fs::read(a.join("SGTA00002"))?, b"corrupt too");
+        assert!(!a.join(".repair-SGTA00000").exists());
+
+        // The donor is left alone.
+        let manifest = manifest::read(&b)?.expect("manifest");
+        assert!(manifest::check(&b, &manifest, &SaveFilePatterns::default())?.is_empty());
+
+        fs::remove_file(b.join(MANIFEST))?;
+        assert!(matches!(manager.repair("b"), Err(Error::NoManifest { .. })));
+        Ok(())
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn space_usage_counts_links_once() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        manager.save("foo")?;
+        fs::write(profile.path().join("SGTA00002"), b"only in bar")?;
+        manager.save("bar")?;
+
+        let usage = SaveManager::space_usage(std::slice::from_ref(&manager))?;
+        let names = usage[0].slots.iter().map(|s| s.name.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), ["bar", "foo"]);
+        assert_eq!(usage[0].apparent, usage[0].actual);
+
+        // Both save files in the profile are identical, so all four copies become one.
+        manager.dedup()?;
+        let deduped = SaveManager::space_usage(&[manager])?;
+        assert_eq!(deduped[0].apparent, usage[0].apparent);
+        assert_eq!(deduped[0].actual, usage[0].actual - 21);
+
+        // The shared save files are counted towards the newest slot.
+        let foo = &deduped[0].slots[1];
+        assert_eq!(foo.name, "foo");
+        assert_eq!(foo.actual, foo.apparent - 14);
+        Ok(())
+    }
+
+    #[test]
+    fn dated_saves_reuse_unchanged_save_files() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        let slots = profile.path().join("Slots");
+        let id = |path: &Path| crate::links::file_id(path).map(|id| id.index);
+
+        let first = slots.join(manager.save_dated()?);
+        assert!(!slots.join(HASH_CACHE).exists());
+
+        fs::write(profile.path().join("SGTA00001"), b"changed")?;
+        let second = slots.join(manager.save_dated()?);
+        assert!(slots.join(HASH_CACHE).is_file());
+
+        assert_eq!(id(&first.join("SGTA00000")), id(&second.join("SGTA00000")));
+        assert_ne!(id(&first.join("SGTA00001")), id(&second.join("SGTA00001")));
+        assert_eq!(fs::read(second.join("SGTA00001"))?, b"changed");
+        assert!(!second.join(files::STAGING).exists());
+
+        // The slot is complete, and its manifest matches what's in it.
+        let patterns = SaveFilePatterns::default();
+        let manifest = manifest::read(&second)?.expect("manifest");
+        assert_eq!(manifest.kind.as_deref(), Some(DATED_KIND));
+        assert!(manifest::check(&second, &manifest, &patterns)?.is_empty());
+
+        // Reused files are reused again, since they keep the modification time of the profile.
+        let third = slots.join(manager.save_dated()?);
+        assert_eq!(id(&second.join("SGTA00001")), id(&third.join("SGTA00001")));
+
+        fs::write(profile.path().join("SGTA00000"), b"other")?;
+        manager.load(&second.file_name().unwrap().to_string_lossy())?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"current");
+        assert_eq!(fs::read(first.join("SGTA00000"))?, b"current");
+        Ok(())
+    }
+
+    #[test]
+    fn swap_profile_and_slot() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        let slot = profile.path().join("Slots").join("a");
+
+        fs::write(profile.path().join("SGTA00000"), b"slot")?;
+        let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(profile.path().join("SGTA00000"), old)?;
+        manager.save("a")?;
+
+        fs::write(profile.path().join("SGTA00000"), b"mine")?;
+        fs::remove_file(profile.path().join("SGTA00001"))?;
+
+        manager.swap("a")?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"slot");
+        assert_eq!(
+            list_save_files(profile.path(), &SaveFilePatterns::default())?.len(),
+            2
+        );
+        assert_eq!(fs::read(slot.join("SGTA00000"))?, b"mine");
+        assert_eq!(
+            list_save_files(&slot, &SaveFilePatterns::default())?.len(),
+            1
+        );
+        assert!(!profile.path().join("Slots").join(files::STAGING).exists());
+
+        let meta = fs::metadata(profile.path().join("SGTA00000"))?;
+        assert_eq!(filetime::FileTime::from_last_modification_time(&meta), old);
+
+        let manifest = manifest::read(&slot)?.expect("manifest");
+        assert_eq!(manifest.files.len(), 1);
+        assert!(manifest::check(&slot, &manifest, &SaveFilePatterns::default())?.is_empty());
+
+        // Swapping back restores both sides.
+        manager.swap("a")?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"mine");
+        assert_eq!(
+            list_save_files(&slot, &SaveFilePatterns::default())?.len(),
+            2
+        );
+
+        assert!(matches!(
+            manager.swap("missing"),
+            Err(Error::SlotNotFound { .. })
+        ));
+        fs::create_dir(profile.path().join("Slots").join("empty"))?;
+        assert!(matches!(
+            manager.swap("empty"),
+            Err(Error::EmptySlot { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn reading_slots_leaves_slots_alone() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        assert!(manager.slot_names()?.is_empty());
+        assert!(manager.list_slots()?.is_empty());
+        assert!(matches!(
+            manager.nth_newest_slot(1),
+            Err(Error::OutOfRange { nth: 1, .. })
+        ));
+        assert!(matches!(
+            manager.compare("a"),
+            Err(Error::SlotNotFound { .. })
+        ));
+        assert!(!profile.path().join("Slots").exists());
+
+        manager.save("b")?;
+        manager.save("a")?;
+        manager.stash(None)?;
+        assert_eq!(manager.slot_names()?, vec!["a", "b"]);
+        Ok(())
+    }
+
+    #[test]
+    fn journal_records_operations() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        fs::write(profile.path().join("SGTA00000"), b"test")?;
+        manager.journaled("save", Some("a"), |m| m.save("a"))?;
+        manager.journaled("verify", Some("a"), |m| m.compare("a"))?;
+        assert!(manager
+            .journaled("load", Some("b"), |m| m.load("b"))
+            .is_err());
+
+        let dry_run = SaveManager::new(profile.path()).dry_run(true);
+        dry_run.journaled("clear", None, SaveManager::clear)?;
+
+        let history = manager.history()?;
+        assert_eq!(history.len(), 2);
+
+        assert_eq!(history[0].command, "save");
+        assert_eq!(history[0].slot.as_deref(), Some("a"));
+        assert_eq!(history[0].status, "ok");
+
+        let copied = history[0]
+            .files
+            .iter()
+            .find(|f| f.to.as_deref().and_then(Path::file_name) == Some(OsStr::new("SGTA00000")))
+            .expect("copied save file");
+        assert_eq!(copied.size, Some(4));
+        assert_eq!(
+            copied.sha256.as_deref(),
+            Some(hash::hash_bytes(b"test").to_string().as_str())
+        );
+
+        assert_eq!(history[1].status, "error");
+        assert!(history[1].files.is_empty());
+        assert!(history[1].error.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn undo_follows_journal() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        let forced = manager.clone().force(true);
+
+        manager.journaled("save", Some("a"), |m| m.save("a"))?;
+        fs::write(profile.path().join("SGTA00000"), b"other")?;
+        manager.journaled("save", Some("b"), |m| m.save("b"))?;
+
+        // Undoing a load is refused once the profile has changed since.
+        manager.journaled("load", Some("a"), |m| m.load("a"))?;
+        fs::write(profile.path().join("SGTA00000"), b"played")?;
+        assert!(matches!(
+            manager.journaled("undo", None, SaveManager::undo),
+            Err(Error::ProfileModified { ref command }) if command == "load a"
+        ));
+        fs::write(profile.path().join("SGTA00000"), b"current")?;
+        manager.journaled("undo", None, SaveManager::undo)?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"other");
+
+        // An undo can't be undone.
+        assert!(matches!(
+            manager.undo(),
+            Err(Error::NotUndoable { ref command }) if command == "undo"
+        ));
+
+        // Deleted slots are kept until the next command which deletes slots.
+        manager.journaled("delete-slot", Some("a"), |m| m.delete_slot("a"))?;
+        manager.journaled("delete-slot", Some("b"), |m| m.delete_slot("b"))?;
+        assert!(manager.list_slots()?.is_empty());
+        assert!(!profile
+            .path()
+            .join("Slots")
+            .join(DELETED)
+            .join("a")
+            .exists());
+
+        fs::write(profile.path().join("SGTA00000"), b"new b")?;
+        manager.journaled("save", Some("b"), |m| m.save("b"))?;
+        assert!(matches!(
+            manager.journaled("undo", None, SaveManager::undo),
+            Err(Error::SlotNameTaken { ref name }) if name == "b"
+        ));
+        forced.journaled("undo", None, SaveManager::undo)?;
+
+        let slots = manager.list_slots()?;
+        assert_eq!(slots.len(), 1);
+        assert_eq!(fs::read(slots[0].path.join("SGTA00000"))?, b"other");
+
+        assert_eq!(
+            manager.history()?.last().map(|e| e.command.as_str()),
+            Some("undo")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn aliases_refer_to_slots() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        manager.set_alias("1", "before-heist")?;
+        assert!(matches!(
+            manager.load("@1"),
+            Err(Error::AliasedSlotNotFound { ref alias, ref name })
+                if alias == "@1" && name == "before-heist"
+        ));
+
+        fs::write(profile.path().join("SGTA00000"), b"heist")?;
+        manager.save("@1")?;
+        assert!(profile.path().join("Slots").join("before-heist").is_dir());
+        assert!(manager
+            .list_slots()?
+            .iter()
+            .all(|s| !s.name.starts_with('.')));
+
+        fs::write(profile.path().join("SGTA00000"), b"mine")?;
+        manager.load("@1")?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"heist");
+
+        manager.copy_slot("@1", "copy")?;
+        manager.set_alias("@1", "copy")?;
+        manager.delete_slot("@1")?;
+        assert!(!profile.path().join("Slots").join("copy").exists());
+
+        assert_eq!(
+            manager.aliases()?.get("1").map(String::as_str),
+            Some("copy")
+        );
+        assert!(matches!(
+            manager.set_alias("profile", "copy"),
+            Err(Error::InvalidAlias { .. })
+        ));
+
+        manager.remove_alias("1")?;
+        assert!(manager.aliases()?.is_empty());
+        assert!(matches!(
+            manager.remove_alias("1"),
+            Err(Error::AliasNotFound { .. })
+        ));
+        assert!(matches!(
+            manager.load("@1"),
+            Err(Error::AliasNotFound { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn transfer_between_profiles() -> TestResult {
+        let from = tempfile::tempdir()?;
+        fs::write(from.path().join("SGTA00000"), b"other account")?;
+        fs::write(from.path().join("pc_settings.bin"), b"settings")?;
+
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        assert!(matches!(
+            manager.transfer_from(profile.path()),
+            Err(Error::SameProfile { .. })
+        ));
+
+        manager.transfer_from(from.path())?;
+        assert_eq!(
+            fs::read(profile.path().join("SGTA00000"))?,
+            b"other account"
+        );
+        assert!(!profile.path().join("SGTA00001").exists());
+        assert!(!profile.path().join("pc_settings.bin").exists());
+
+        // The save files from before are backed up.
+        let backup = manager.backup_slot();
+        assert_eq!(fs::read(backup.join("SGTA00001"))?, b"current");
+
+        let empty = tempfile::tempdir()?;
+        assert!(matches!(
+            manager.transfer_from(empty.path()),
+            Err(Error::NothingToTransfer { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn stash_and_pop() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        fs::write(profile.path().join("SGTA00000"), b"test")?;
+        manager.save("test")?;
+        fs::write(profile.path().join("SGTA00000"), b"mine")?;
+
+        let entry = manager.stash(None)?;
+        assert!(entry.ends_with("Slots/.stash/001"));
+        assert!(list_save_files(profile.path(), &SaveFilePatterns::default())?.is_empty());
+        assert!(matches!(manager.stash(None), Err(Error::NothingToStash)));
+
+        fs::write(profile.path().join("SGTA00000"), b"other")?;
+        manager.stash(Some("test"))?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"test");
+        assert_eq!(manager.list_slots()?.len(), 1);
+
+        let entries = manager.stash_list()?;
+        let names = entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["002", "001"]);
+        assert_eq!(entries[0].files, 1);
+
+        // The profile matches the slot it was loaded from, so nothing is lost.
+        let unbacked = SaveManager::new(profile.path()).backup(false);
+        assert_eq!(unbacked.pop()?, "002");
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"other");
+
+        fs::write(profile.path().join("SGTA00000"), b"unsaved")?;
+        assert!(matches!(unbacked.pop(), Err(Error::UnsavedChanges)));
+        unbacked.force(true).pop()?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"mine");
+
+        assert!(matches!(manager.pop(), Err(Error::EmptyStash)));
+        assert!(matches!(
+            manager.save(".stash"),
+            Err(Error::InvalidSlotName { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn backup_to_and_restore_from() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        let backups = tempfile::tempdir()?;
+
+        let first = manager.backup_to(backups.path(), 2)?;
+        assert!(first.starts_with(backups.path().join(profile.path().file_name().unwrap())));
+        assert!(first.join(MANIFEST).is_file());
+
+        fs::write(profile.path().join("SGTA00000"), b"second")?;
+        let second = manager.backup_to(backups.path(), 2)?;
+        fs::write(profile.path().join("SGTA00000"), b"third")?;
+        let third = manager.backup_to(backups.path(), 2)?;
+
+        // Only the two most recent backups are kept.
+        assert!(!first.exists());
+        let names = manager
+            .backups(backups.path())?
+            .into_iter()
+            .map(|e| e.path)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![third.clone(), second.clone()]);
+
+        fs::write(profile.path().join("SGTA00000"), b"current")?;
+        let name = second.file_name().unwrap().to_string_lossy();
+        assert_eq!(manager.restore_from(backups.path(), Some(&name))?, name);
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"second");
+        assert_eq!(
+            fs::read(
+                profile
+                    .path()
+                    .join(SLOTS)
+                    .join(BACKUP_SLOT)
+                    .join("SGTA00000")
+            )?,
+            b"current"
+        );
+
+        let newest = manager.restore_from(backups.path(), None)?;
+        assert_eq!(newest, third.file_name().unwrap().to_string_lossy());
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"third");
+
+        assert!(matches!(
+            manager.restore_from(backups.path(), Some("2000-01-01_000000")),
+            Err(Error::BackupNotFound { .. })
+        ));
+
+        let empty = tempfile::tempdir()?;
+        assert!(matches!(
+            manager.restore_from(empty.path(), None),
+            Err(Error::NoBackups { .. })
+        ));
+
+        manager.clear()?;
+        assert!(matches!(
+            manager.backup_to(backups.path(), 2),
+            Err(Error::NothingToBackUp)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_and_mixed_slots() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path()).compress(true);
+
+        let name = manager.save_dated()?;
+        let slot = profile.path().join("Slots").join(&name);
+        assert!(slot.join("SGTA00000.zst").is_file());
+        assert!(!slot.join("SGTA00000").exists());
+
+        // Read as if they weren't compressed.
+        assert!(manager.compare(&name)?.is_empty());
+        let manifest = manifest::read(&slot)?.expect("manifest");
+        assert!(manifest::check(&slot, &manifest, &SaveFilePatterns::default())?.is_empty());
+        let files = manager.show_slot(&name)?;
+        assert_eq!(files[0].name, "SGTA00000");
+        assert_eq!(files[0].size, 7);
+
+        // Only one of the save files compressed.
+        manager.save("mixed")?;
+        let mixed = profile.path().join("Slots").join("mixed");
+        compress::compress_file(&manager.ops, &mixed.join("SGTA00001"), 1)?;
+
+        fs::write(profile.path().join("SGTA00000"), b"changed")?;
+        manager.load("mixed")?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"current");
+        assert_eq!(fs::read(profile.path().join("SGTA00001"))?, b"current");
+        assert!(!profile.path().join("SGTA00001.zst").exists());
+
+        manager.recompress_slots()?;
+        assert!(mixed.join("SGTA00000.zst").is_file());
+        assert!(!mixed.join("SGTA00000").exists());
+        assert_eq!(manager.nth_newest_slot(1)?.files, 2);
+
+        // Saving over a compressed slot replaces the compressed save files.
+        manager.force(true).save(&name)?;
+        assert!(slot.join("SGTA00000").is_file());
+        assert!(!slot.join("SGTA00000.zst").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn sync_in_both_directions() -> TestResult {
+        let profile = profile_with_saves()?;
+        let remote = tempfile::tempdir()?;
+        let manager = SaveManager::new(profile.path());
+
+        manager.save("here")?;
+        fs::create_dir(remote.path().join("there"))?;
+        fs::write(remote.path().join("there").join("SGTA00000"), b"remote")?;
+
+        manager.sync(remote.path())?;
+        assert!(remote.path().join("here").join("SGTA00001").is_file());
+        assert!(remote.path().join("here").join(MANIFEST).is_file());
+        assert_eq!(
+            fs::read(profile.path().join("Slots/there/SGTA00000"))?,
+            b"remote"
+        );
+
+        // Changed on one side since the last sync.
+        fs::write(remote.path().join("here").join("SGTA00000"), b"changed")?;
+        manager.sync(remote.path())?;
+        assert_eq!(
+            fs::read(profile.path().join("Slots/here/SGTA00000"))?,
+            b"changed"
+        );
+
+        // Changed on both sides, which is left alone.
+        fs::write(remote.path().join("there").join("SGTA00000"), b"one")?;
+        fs::write(profile.path().join("Slots/there/SGTA00000"), b"two")?;
+
+        match manager.sync(remote.path()) {
+            Err(Error::SyncConflicts { slots }) => assert_eq!(slots, vec!["there"]),
+            other => panic!("expected a conflict: {:?}", other),
+        }
+
+        assert_eq!(
+            fs::read(remote.path().join("there").join("SGTA00000"))?,
+            b"one"
+        );
+        assert_eq!(
+            fs::read(profile.path().join("Slots/there/SGTA00000"))?,
+            b"two"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn save_dated_with_custom_format() -> TestResult {
+        let profile = profile_with_saves()?;
+
+        let manager = SaveManager::new(profile.path()).date_format("%Y%m%d");
+        let dated = manager.save_dated()?;
+        assert_eq!(dated.len(), "dated-".len() + 8);
+        assert!(dated
+            .bytes()
+            .skip("dated-".len())
+            .all(|b| b.is_ascii_digit()));
+
+        let manager = SaveManager::new(profile.path()).date_format("%Q");
+        assert!(matches!(
+            manager.save_dated(),
+            Err(Error::InvalidDateFormat { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn save_dated_with_custom_name() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path()).dated_name("{date}_{note}_{seq}");
+
+        let first = manager.save_dated_with_note(Some("Before the heist"))?;
+        assert!(first.ends_with("_before-the-heist_001"));
+        let second = manager.save_dated_with_note(Some("Before the heist"))?;
+        assert!(second.ends_with("_before-the-heist_002"));
+        let third = manager.save_dated()?;
+        assert!(third.ends_with("_001") && !third.contains("heist"));
+
+        // Slots are dated because their manifest says so, or by their name if they're older.
+        manager.save("manual")?;
+        fs::create_dir(profile.path().join("Slots").join("dated-old"))?;
+        fs::write(
+            profile
+                .path()
+                .join("Slots")
+                .join("dated-old")
+                .join("SGTA00000"),
+            b"old",
+        )?;
+
+        let dated = |manager: &SaveManager| -> TestResult<Vec<String>> {
+            let mut names = manager
+                .prunable_slots(Keep::Newest(0))?
+                .into_iter()
+                .map(|s| s.name)
+                .collect::<Vec<_>>();
+            names.sort();
+            Ok(names)
+        };
+
+        let mut expected = vec![first.clone(), second, third, String::from("dated-old")];
+        expected.sort();
+        assert_eq!(dated(&manager)?, expected);
+
+        // A renamed slot has a name of its own, and is no longer pruned.
+        manager.rename_slot(&first, "keep")?;
+        assert!(!dated(&manager)?.contains(&String::from("keep")));
+        assert_eq!(dated(&manager)?.len(), 3);
+
+        let manager = SaveManager::new(profile.path()).dated_name("{date}");
+        assert!(matches!(
+            manager.save_dated(),
+            Err(Error::InvalidDatedName { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn only_selected_save_files() -> TestResult {
+        let profile = profile_with_saves()?;
+        let slot = profile.path().join("Slots").join("before-heist");
+        fs::create_dir_all(&slot)?;
+        fs::write(slot.join("SGTA00000"), b"slot")?;
+        fs::write(slot.join("SGTA00001"), b"slot")?;
+
+        let manager = SaveManager::new(profile.path()).only(vec![0]);
+        manager.load("before-heist")?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"slot");
+        assert_eq!(fs::read(profile.path().join("SGTA00001"))?, b"current");
+
+        // Only the file of the selected slot is deleted, even if the slot doesn't have it.
+        fs::remove_file(slot.join("SGTA00001"))?;
+        fs::write(profile.path().join("SGTA00002"), b"current")?;
+        SaveManager::new(profile.path())
+            .only(vec![0, 2])
+            .load("before-heist")?;
+        assert!(!profile.path().join("SGTA00002").exists());
+        assert!(profile.path().join("SGTA00001").exists());
+
+        assert!(matches!(
+            SaveManager::new(profile.path())
+                .only(vec![1])
+                .load("before-heist"),
+            Err(Error::NoSelectedSaveFiles { .. })
+        ));
+
+        // Saving leaves the other save files in the slot alone.
+        fs::write(profile.path().join("SGTA00001"), b"new")?;
+        SaveManager::new(profile.path())
+            .only(vec![1])
+            .force(true)
+            .save("before-heist")?;
+        assert_eq!(fs::read(slot.join("SGTA00000"))?, b"slot");
+        assert_eq!(fs::read(slot.join("SGTA00001"))?, b"new");
+        assert_eq!(manager.slot("before-heist")?.save_numbers, vec![0, 1]);
+
+        // The backup still has every save file, so that undoing restores all of them.
+        manager.clear()?;
+        assert!(!profile.path().join("SGTA00000").exists());
+        assert!(profile.path().join("SGTA00001").exists());
+        manager.undo()?;
+        assert_eq!(
+            list_save_files(profile.path(), &SaveFilePatterns::default())?.len(),
+            2
+        );
+
+        assert!(matches!(
+            SaveManager::new(profile.path()).only(vec![100]).clear(),
+            Err(Error::InvalidSaveNumber { number: 100 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn include_settings() -> TestResult {
+        let profile = profile_with_saves()?;
+        fs::write(profile.path().join("pc_settings.bin"), b"keybinds")?;
+        fs::write(profile.path().join("cfg.dat"), b"cfg")?;
+
+        let manager = SaveManager::new(profile.path()).include_settings(true);
+        let slot = manager.save("with-settings")?;
+        assert_eq!(fs::read(slot.join("pc_settings.bin"))?, b"keybinds");
+        let manifest = manifest::read(&slot)?.expect("manifest");
+        assert_eq!(manifest.settings.len(), 2);
+
+        // Slots saved without them don't have them, and loading them leaves the settings alone.
+        let plain = SaveManager::new(profile.path());
+        let without = plain.save("without-settings")?;
+        assert!(!without.join("pc_settings.bin").exists());
+
+        fs::write(profile.path().join("pc_settings.bin"), b"clobbered")?;
+        assert!(!manager.compare("with-settings")?.is_empty());
+        assert!(plain.compare("with-settings")?.is_empty());
+
+        manager.load("without-settings")?;
+        assert_eq!(
+            fs::read(profile.path().join("pc_settings.bin"))?,
+            b"clobbered"
+        );
+        plain.load("with-settings")?;
+        assert_eq!(
+            fs::read(profile.path().join("pc_settings.bin"))?,
+            b"clobbered"
+        );
+
+        manager.load("with-settings")?;
+        assert_eq!(
+            fs::read(profile.path().join("pc_settings.bin"))?,
+            b"keybinds"
+        );
+        assert!(manager.compare("with-settings")?.is_empty());
+
+        // Clearing never removes the settings, and they don't count as other files in a slot.
+        manager.clear()?;
+        assert!(list_save_files(profile.path(), &SaveFilePatterns::default())?.is_empty());
+        assert!(profile.path().join("pc_settings.bin").exists());
+        assert!(profile.path().join("cfg.dat").exists());
+
+        manager.delete_slot("with-settings")?;
+        assert!(!slot.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn small_save_files() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path()).min_save_size(4);
+
+        let slot = manager.save("fine")?;
+        assert!(manager.slot("fine")?.suspicious.is_empty());
+
+        // Small save files are only warned about, and the slots which have them are marked.
+        fs::write(profile.path().join("SGTA00001"), b"")?;
+        manager.save("truncated")?;
+        assert_eq!(manager.slot("truncated")?.suspicious, vec!["SGTA00001"]);
+
+        // Unless saving is strict, in which case the slot isn't created.
+        let strict = manager.clone().strict(true);
+        assert!(matches!(
+            strict.save("refused"),
+            Err(Error::SmallSaveFiles { ref files, min_size: 4 }) if files == &["SGTA00001"]
+        ));
+        assert!(!slot.with_file_name("refused").exists());
+        assert!(strict.clone().only([0]).save("selected").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn save_file_patterns() -> TestResult {
+        let profile = profile_with_saves()?;
+        fs::write(profile.path().join("SGTA00000.bak"), b"backup")?;
+
+        // By default anything named like a save file is one.
+        let plain = SaveManager::new(profile.path());
+        let slot = plain.save("everything")?;
+        assert!(slot.join("SGTA00000.bak").exists());
+
+        let patterns = SaveFilePatterns::new(&["SGTA*"], &["*.bak"])?;
+        let manager = SaveManager::new(profile.path()).save_file_patterns(patterns);
+        let slot = manager.save("without-bak")?;
+        assert!(!slot.join("SGTA00000.bak").exists());
+        assert!(slot.join("SGTA00000").exists());
+
+        // Excluded files are left alone when loading and clearing.
+        manager.load("without-bak")?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000.bak"))?, b"backup");
+        manager.clear()?;
+        assert!(!profile.path().join("SGTA00000").exists());
+        assert_eq!(fs::read(profile.path().join("SGTA00000.bak"))?, b"backup");
+        Ok(())
+    }
+
+    #[test]
+    fn dated_saves_in_the_same_second() -> TestResult {
+        use chrono::TimeZone as _;
+
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        let time = chrono::Local
+            .ymd_opt(2024, 3, 1)
+            .and_hms_opt(21, 0, 0)
+            .earliest()
+            .expect("valid local time");
+
+        let first = manager.save_dated_at(time, None)?;
+        let second = manager.save_dated_at(time, None)?;
+        let third = manager.save_dated_at(time, None)?;
+        assert_eq!(first, "dated-2024-03-01_210000");
+        assert_eq!(second, "dated-2024-03-01_210000-2");
+        assert_eq!(third, "dated-2024-03-01_210000-3");
+
+        // They're all dated, and saved at the same time.
+        let slots = manager.prunable_slots(Keep::Newest(0))?;
+        assert_eq!(slots.len(), 3);
+        let (newest, saved) = manager.dated_slot_at(time.into())?;
+        assert_eq!(newest.name, third);
+        assert_eq!(saved, SystemTime::from(time));
+        Ok(())
+    }
+
+    #[test]
+    fn slots_are_sorted_by_newest_save_file() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        for (name, time) in &[("a", 100), ("b", 300), ("c", 200), ("d", 300)] {
+            let slot = manager.save(name)?;
+            let time = filetime::FileTime::from_unix_time(1_000_000_000 + time, 0);
+            filetime::set_file_mtime(slot.join("SGTA00000"), time)?;
+            filetime::set_file_mtime(slot.join("SGTA00001"), time)?;
+        }
+
+        // Empty slots are sorted by the time of the directory, which is now.
+        fs::create_dir(profile.path().join("Slots").join("e"))?;
+
+        let names = manager
+            .list_slots()?
+            .into_iter()
+            .map(|s| s.name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["e", "b", "d", "c", "a"]);
+        assert_eq!(manager.nth_newest_slot(2)?.name, "b");
+        Ok(())
+    }
+
+    #[test]
+    fn save_writes_manifest() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        let slot = manager.save("foo")?;
+        let manifest = manifest::read(&slot)?.expect("manifest");
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(manifest.version, env!("CARGO_PKG_VERSION"));
+
+        // The manifest is never copied into the profile, and doesn't prevent deleting the slot.
+        manager.load("foo")?;
+        assert!(!profile.path().join(MANIFEST).exists());
+        assert_eq!(manager.slot_files_to_delete("foo")?.len(), 3);
+        manager.delete_slot("foo")?;
+        assert!(!slot.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn annotate_slots() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        manager.save("foo")?;
+        manager.annotate("foo", Some("before the heist"), &["prep", "100percent"])?;
+        manager.annotate("foo", None, &["prep", "glitch"])?;
+
+        let slot = manager.nth_newest_slot(1)?;
+        assert_eq!(slot.note.as_deref(), Some("before the heist"));
+        assert_eq!(slot.tags, vec!["100percent", "glitch", "prep"]);
+
+        // Annotating doesn't touch the save files, so they still match the manifest.
+        let manifest = manifest::read(&slot.path)?.expect("manifest");
+        assert!(manifest::check(&slot.path, &manifest, &SaveFilePatterns::default())?.is_empty());
+
+        assert!(matches!(
+            manager.annotate("foo", None, &["two words"]),
+            Err(Error::InvalidTag { .. })
+        ));
+        assert!(matches!(
+            manager.annotate("missing", Some("note"), &[]),
+            Err(Error::SlotNotFound { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_records_headers() -> TestResult {
+        let profile = profile_with_saves()?;
+        let jewel_store = crate::header::tests::fixture("The Jewel Store Job - 12.53%", 1);
+        fs::write(profile.path().join("SGTA00001"), jewel_store)?;
+
+        let manager = SaveManager::new(profile.path());
+        manager.save("foo")?;
+
+        // A header which can't be parsed is recorded with unknown fields.
+        let manifest = fs::read(profile.path().join("Slots").join("foo").join(MANIFEST))?;
+        let manifest = serde_json::from_slice::<serde_json::Value>(&manifest)?;
+        assert_eq!(
+            manifest["files"][0]["header"],
+            serde_json::json!({ "title": null, "saved": null, "completion": null })
+        );
+        assert_eq!(manifest["files"][1]["header"]["completion"], 12.53);
+
+        // The recorded headers stay with the slot even if the save files change.
+        fs::write(
+            profile.path().join("Slots").join("foo").join("SGTA00001"),
+            b"x",
+        )?;
+        let slot = manager.slot("foo")?;
+        assert_eq!(slot.headers.len(), 2);
+        assert_eq!(
+            slot.headers[0],
+            ("SGTA00000".to_string(), SaveHeader::default())
+        );
+        assert_eq!(slot.headers[1].0, "SGTA00001");
+        assert_eq!(
+            slot.headers[1].1.title.as_deref(),
+            Some("The Jewel Store Job - 12.53%")
+        );
+        assert_eq!(
+            slot.headers[1].1.saved,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn show_slot_reads_headers() -> TestResult {
+        let profile = profile_with_saves()?;
+        let jewel_store = crate::header::tests::fixture("Franklin - The Jewel Store Job", 1);
+        fs::write(profile.path().join("SGTA00001"), jewel_store)?;
+        let older = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(profile.path().join("SGTA00000"), older)?;
+
+        let manager = SaveManager::new(profile.path());
+        manager.save("foo")?;
+
+        let files = manager.show_slot("foo")?;
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].name, "SGTA00000");
+        assert_eq!(files[0].size, 7);
+        assert_eq!(files[0].modified, SystemTime::from(older));
+        assert_eq!(files[0].sha256, hash::hash_bytes(b"current").to_string());
+        assert_eq!(files[0].header, SaveHeader::default());
+        assert_eq!(
+            files[1].header.title.as_deref(),
+            Some("Franklin - The Jewel Store Job")
+        );
+
+        // The title of the newest save file is listed.
+        let slot = manager.nth_newest_slot(1)?;
+        assert_eq!(
+            slot.title.as_deref(),
+            Some("Franklin - The Jewel Store Job")
+        );
+
+        // The profile itself can be inspected as well.
+        let profile_files = manager.show_slot(PROFILE)?;
+        assert_eq!(profile_files.len(), 2);
+        assert_eq!(profile_files[1].sha256, files[1].sha256);
+        assert_eq!(manager.slot(PROFILE)?.size, manager.slot("foo")?.size);
+
+        assert!(matches!(
+            manager.show_slot("missing"),
+            Err(Error::SlotNotFound { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn save_refuses_to_replace_slot() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        manager.save("foo")?;
+        fs::write(profile.path().join("SGTA00000"), b"newer")?;
+
+        let error = manager.save("foo").unwrap_err();
+        assert!(matches!(error, Error::SlotExists { files: 2, .. }));
+        let slot = profile.path().join("Slots").join("foo");
+        assert_eq!(fs::read(slot.join("SGTA00000"))?, b"current");
+
+        manager.clone().force(true).save("foo")?;
+        assert_eq!(fs::read(slot.join("SGTA00000"))?, b"newer");
+        Ok(())
+    }
+
+    #[test]
+    fn compare_against_saved_slot() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        manager.save("foo")?;
+        assert!(manager.compare("foo")?.is_empty());
+
+        fs::write(profile.path().join("SGTA00001"), b"changed")?;
+        let differences = manager.compare("foo")?;
+        assert_eq!(differences.len(), 1);
+        assert_eq!(differences[0].name(), "SGTA00001");
+
+        assert!(matches!(
+            manager.compare("bar"),
+            Err(Error::SlotNotFound { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn verify_uses_cached_hashes() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        manager.save("foo")?;
+
+        let reads = hash::files_read();
+        assert!(manager.compare("foo")?.is_empty());
+        assert_eq!(hash::files_read(), reads + 4);
+
+        // Nothing has changed, so nothing is read again.
+        let reads = hash::files_read();
+        assert!(manager.compare("foo")?.is_empty());
+        assert!(manager.diff_slots("foo", PROFILE)?.is_empty());
+        assert_eq!(hash::files_read(), reads);
+
+        let reads = hash::files_read();
+        let uncached = manager.clone().hash_cache(false);
+        assert!(uncached.compare("foo")?.is_empty());
+        assert_eq!(hash::files_read(), reads + 4);
+
+        // A corrupt cache is rebuilt.
+        let cache = profile.path().join("Slots").join(HASH_CACHE);
+        fs::write(&cache, b"corrupt")?;
+        assert!(manager.compare("foo")?.is_empty());
+        let reads = hash::files_read();
+        assert!(manager.compare("foo")?.is_empty());
+        assert_eq!(hash::files_read(), reads);
+
+        fs::write(profile.path().join("SGTA00001"), b"changed")?;
+        assert_eq!(manager.compare("foo")?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn diff_two_slots() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        manager.save("a")?;
+        fs::write(profile.path().join("SGTA00001"), b"changed")?;
+        manager.save("b")?;
+
+        assert!(manager.diff_slots("b", PROFILE)?.is_empty());
+        assert_eq!(
+            manager.diff_slots("a", "b")?,
+            vec![Difference::Contents {
+                name: String::from("SGTA00001")
+            }]
+        );
+        assert!(matches!(
+            manager.diff_slots("a", "missing"),
+            Err(Error::SlotNotFound { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn prune_only_dated_slots() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        for (n, name) in (1..).zip(&["dated-1", "dated-2", "golden", "dated-3"]) {
+            let time = filetime::FileTime::from_unix_time(1_000_000_000 + n, 0);
+            filetime::set_file_mtime(profile.path().join("SGTA00000"), time)?;
+            filetime::set_file_mtime(profile.path().join("SGTA00001"), time)?;
+            manager.save(name)?;
+        }
+
+        let slots = manager.prunable_slots(Keep::Newest(1))?;
+        let names = slots.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["dated-2", "dated-1"]);
+
+        manager.clone().dry_run(true).prune(&slots)?;
+        assert_eq!(manager.list_slots()?.len(), 4);
+
+        manager.prune(&slots)?;
+        let names = manager
+            .list_slots()?
+            .into_iter()
+            .map(|s| s.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["dated-3", "golden"]);
+
+        assert_eq!(manager.prunable_slots(Keep::Days(1))?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn pinned_slots_are_protected() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path()).force(true);
+
+        manager.save("dated-golden")?;
+        manager.save("other")?;
+        manager.pin("dated-golden", true)?;
+        assert!(manager.slot("dated-golden")?.pinned);
+
+        let pinned = |result: Result<(), Error>| matches!(result, Err(Error::SlotPinned { .. }));
+        assert!(pinned(manager.delete_slot("dated-golden")));
+        assert!(pinned(
+            manager.slot_files_to_delete("dated-golden").map(drop)
+        ));
+        assert!(pinned(manager.save("dated-golden").map(drop)));
+        assert!(pinned(manager.copy_slot("other", "dated-golden").map(drop)));
+        assert!(manager.prunable_slots(Keep::Newest(0))?.is_empty());
+
+        // Pinning only touches the manifest.
+        let slot = manager.slot("dated-golden")?;
+        let manifest = manifest::read(&slot.path)?.expect("manifest");
+        assert!(manifest::check(&slot.path, &manifest, &SaveFilePatterns::default())?.is_empty());
+
+        let forced = manager.clone().force_unpin(true);
+        assert_eq!(forced.prunable_slots(Keep::Newest(0))?.len(), 1);
+
+        manager.pin("dated-golden", false)?;
+        manager.delete_slot("dated-golden")?;
+        assert_eq!(manager.list_slots()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_slot_with_other_files() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        let slot = manager.save("foo")?;
+        fs::write(slot.join("notes.txt"), b"important")?;
+
+        assert!(matches!(
+            manager.delete_slot("foo"),
+            Err(Error::UnexpectedFiles { .. })
+        ));
+        assert!(slot.join("SGTA00000").is_file());
+
+        manager.clone().force(true).delete_slot("foo")?;
+        assert!(!slot.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn copy_slot_keeps_modification_times() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        let original = manager.save("foo")?.join("SGTA00000");
+        let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(&original, old)?;
+
+        let copy = manager.copy_slot("foo", "bar")?.join("SGTA00000");
+        let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(&copy)?);
+        assert_eq!(mtime, old);
+        assert_eq!(
+            list_save_files(copy.parent().unwrap(), &SaveFilePatterns::default())?.len(),
+            2
+        );
+
+        assert!(matches!(
+            manager.copy_slot("foo", "bar"),
+            Err(Error::SlotNameTaken { .. })
+        ));
+        assert!(matches!(
+            manager.copy_slot("missing", "baz"),
+            Err(Error::SlotNotFound { .. })
+        ));
+        manager.clone().force(true).copy_slot("foo", "bar")?;
+        Ok(())
+    }
+
+    #[test]
+    fn export_slot_and_profile() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        manager.save("foo")?;
+
+        let out = tempfile::tempdir()?;
+        let path = out.path().join("foo.zip");
+        manager.export_slot("foo", &path)?;
+        assert!(path.is_file());
+
+        assert!(matches!(
+            manager.export_slot("foo", &path),
+            Err(Error::FileExists { .. })
+        ));
+        assert!(matches!(
+            manager.export_slot("missing", &out.path().join("missing.zip")),
+            Err(Error::SlotNotFound { .. })
+        ));
+
+        manager.export_slot(PROFILE, &out.path().join("profile.zip"))?;
+        assert!(out.path().join("profile.zip").is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn import_exported_slot() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        manager.save("foo")?;
+
+        let out = tempfile::tempdir()?;
+        let path = out.path().join("foo-export.zip");
+        manager.export_slot("foo", &path)?;
+
+        assert_eq!(manager.import_slot(&path, None)?, "foo-export");
+        assert!(manager.compare("foo-export")?.is_empty());
+
+        assert!(matches!(
+            manager.import_slot(&path, Some("foo")),
+            Err(Error::SlotNameTaken { .. })
+        ));
+        manager
+            .clone()
+            .force(true)
+            .import_slot(&path, Some("foo"))?;
+
+        let names = manager
+            .list_slots()?
+            .into_iter()
+            .map(|s| s.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names.len(), 2);
+        assert!(!profile.path().join("Slots").join(files::STAGING).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn rename_slot() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        manager.save("foo")?;
+        manager.save("bar")?;
+
+        assert!(matches!(
+            manager.rename_slot("foo", "bar"),
+            Err(Error::SlotNameTaken { .. })
+        ));
+        assert!(matches!(
+            manager.rename_slot("foo", "../baz"),
+            Err(Error::InvalidSlotName { .. })
+        ));
+        assert!(matches!(
+            manager.rename_slot("missing", "baz"),
+            Err(Error::SlotNotFound { .. })
+        ));
+
+        let renamed = manager.rename_slot("foo", "baz")?;
+        assert_eq!(
+            list_save_files(&renamed, &SaveFilePatterns::default())?.len(),
+            2
+        );
+        assert!(!profile.path().join("Slots").join("foo").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn mistyped_slots_are_suggested() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        for name in &["before-heist", "after-heist", "golden"] {
+            manager.save(name)?;
+        }
+
+        let error = manager.load("befor-heist").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "slot 'befor-heist' does not exist, did you mean 'before-heist'?"
+        );
+        assert_eq!(error.exit_code(), 3);
+        assert!(matches!(
+            manager.show_slot("franklin"),
+            Err(Error::SlotNotFound { .. })
+        ));
+
+        // The only similar slot is used, but never one of several.
+        let fuzzy = manager.clone().fuzzy(true);
+        fs::write(profile.path().join("SGTA00000"), b"changed")?;
+        fuzzy.load("befor-heist")?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"current");
+
+        fuzzy.copy_slot("goldn", "golden-2")?;
+        assert!(matches!(
+            fuzzy.delete_slot("heist"),
+            Err(Error::SimilarSlotNotFound { similar, .. })
+                if similar == ["after-heist", "before-heist"]
+        ));
+        fuzzy.delete_slot("aftr-heist")?;
+        assert_eq!(
+            manager.slot_names()?,
+            ["before-heist", "golden", "golden-2"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn load_save_file_picks_naturally() -> TestResult {
+        let profile = profile_with_saves()?;
+        let save_files = profile.path().join("Save Files");
+
+        for (name, contents) in &[("Chop9", "nine"), ("chop10", "ten"), ("Franklin", "f")] {
+            fs::create_dir_all(save_files.join(name))?;
+            fs::write(save_files.join(name).join("SGTA00000"), contents)?;
+        }
+
+        let manager = SaveManager::new(profile.path());
+        assert_eq!(manager.find_save_files("CHOP")?.len(), 2);
+
+        manager.load_save_file("chop", Some(1))?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"ten");
+
+        manager.load_save_file("chop", Some(2))?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"nine");
+
+        assert!(matches!(
+            manager.load_save_file("chop", Some(3)),
+            Err(Error::PickOutOfRange { pick: 3, .. })
+        ));
+        assert_eq!(manager.load_save_file("michael", None)?, None);
+
+        // Several matches are never picked between without being told which.
+        assert!(matches!(
+            manager.load_save_file("chop", None),
+            Err(Error::AmbiguousSaveFiles { matches, .. }) if matches == ["chop10", "Chop9"]
+        ));
+        manager.load_save_file("frank", None)?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"f");
+        Ok(())
+    }
+
+    #[test]
+    fn load_save_file_by_index() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+        assert!(manager.list_save_files_dirs()?.is_empty());
+
+        let save_files = profile.path().join("Save Files");
+        fs::create_dir_all(save_files.join("Empty"))?;
+
+        for name in &["Chop9", "chop10"] {
+            fs::create_dir_all(save_files.join(name))?;
+            fs::write(save_files.join(name).join("SGTA00000"), name)?;
+            fs::write(save_files.join(name).join("SGTA00001"), name)?;
+        }
+
+        let dirs = manager.list_save_files_dirs()?;
+        let names = dirs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Empty", "chop10", "Chop9"]);
+        assert_eq!(dirs.iter().map(|d| d.files).collect::<Vec<_>>(), [0, 2, 2]);
+
+        manager.load_save_file("3", None)?;
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"Chop9");
+
+        for index in &["0", "4", "99999999999999999999999"] {
+            assert!(matches!(
+                manager.load_save_file(index, None),
+                Err(Error::PickOutOfRange { .. })
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn clear_removes_save_files() -> TestResult {
+        let profile = profile_with_saves()?;
+        SaveManager::new(profile.path()).backup(false).clear()?;
+        assert!(list_save_files(profile.path(), &SaveFilePatterns::default())?.is_empty());
+        assert!(!profile.path().join("Slots").join(BACKUP_SLOT).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn undo_restores_backup() -> TestResult {
+        let profile = profile_with_saves()?;
+        let slot = profile.path().join("Slots").join("foo");
+        fs::create_dir_all(&slot)?;
+        fs::write(slot.join("SGTA00000"), b"slot")?;
+
+        let manager = SaveManager::new(profile.path());
+        assert!(matches!(manager.undo(), Err(Error::NoBackup)));
+
+        manager.load("foo")?;
+        assert_eq!(
+            list_save_files(profile.path(), &SaveFilePatterns::default())?.len(),
+            1
+        );
+
+        let slots = manager.list_slots()?;
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].name, "foo");
+
+        manager.undo()?;
+        assert_eq!(
+            list_save_files(profile.path(), &SaveFilePatterns::default())?.len(),
+            2
+        );
+        assert_eq!(fs::read(profile.path().join("SGTA00000"))?, b"current");
+
+        assert!(matches!(
+            manager.save(BACKUP_SLOT),
+            Err(Error::InvalidSlotName { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn slot_names_must_not_escape_slots() -> TestResult {
+        let profile = profile_with_saves()?;
+        let manager = SaveManager::new(profile.path());
+
+        for name in &["", "..", "../foo", "foo\\bar"] {
+            assert!(matches!(
+                manager.save(name),
+                Err(Error::InvalidSlotName { .. })
+            ));
+        }
+
+        // The directories gtav-saveload keeps with the slots can't be replaced with a slot.
+        for name in &[BACKUP_SLOT, STASH, DELETED, files::STAGING] {
+            assert!(matches!(
+                manager.save(name),
+                Err(Error::InvalidSlotName { .. })
+            ));
+        }
+
+        // Names which can't be used on Windows are refused up front, for every new slot.
+        manager.save("foo")?;
+
+        for name in &["con", "a:b", "foo.", "foo ", "a\u{1}b"] {
+            assert!(matches!(
+                manager.save(name),
+                Err(Error::InvalidSlotName { .. })
+            ));
+            assert!(matches!(
+                manager.rename_slot("foo", name),
+                Err(Error::InvalidSlotName { .. })
+            ));
+            assert!(matches!(
+                manager.copy_slot("foo", name),
+                Err(Error::InvalidSlotName { .. })
+            ));
+        }
+
+        assert_eq!(
+            manager.save("Aux.1").unwrap_err().to_string(),
+            "invalid slot name 'Aux.1', 'AUX' is a reserved device name on Windows"
+        );
+        assert!(!profile.path().join("Slots").join("con").exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,409 @@
+use crate::{
+    files::{display_name, list_save_files, list_settings_files, to_extended_path},
+    hash::{self, FileHash},
+    header,
+    ops::{FsOp, Ops},
+    Edition, Error, SaveFilePatterns, SaveHeader,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Name of the manifest stored in slots and exported archives.
+pub(crate) const MANIFEST: &str = "manifest.json";
+
+/// The kind of the slots saved by [crate::SaveManager::save_dated].
+pub(crate) const DATED_KIND: &str = "dated";
+
+/// A single save file in a manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ManifestFile {
+    pub(crate) name: String,
+    pub(crate) size: u64,
+    pub(crate) sha256: String,
+    /// What the header of the save file said when the slot was saved, or `None` for settings
+    /// files and slots saved by earlier versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) header: Option<ManifestHeader>,
+}
+
+impl ManifestFile {
+    pub(crate) fn new(name: String, hash: FileHash) -> Self {
+        Self {
+            name,
+            size: hash.size,
+            sha256: hash.to_string(),
+            header: None,
+        }
+    }
+}
+
+/// The in-game metadata of a save file in a manifest, where each field is `null` if it
+/// couldn't be read from the header.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ManifestHeader {
+    /// The title shown in the in-game save list.
+    pub(crate) title: Option<String>,
+    /// When the game says the file was saved, in RFC 3339 format.
+    pub(crate) saved: Option<String>,
+    /// The completion percentage of the story, if the title has one.
+    pub(crate) completion: Option<f64>,
+}
+
+impl ManifestHeader {
+    /// Read the header of the save file at the given path. A header which can't be read is
+    /// recorded as unknown rather than failing the save.
+    fn read(path: &Path) -> Self {
+        let header = header::read(path).unwrap_or_else(|e| {
+            debug!("{}", e);
+            SaveHeader::default()
+        });
+
+        Self {
+            completion: header.completion(),
+            saved: header
+                .saved
+                .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339()),
+            title: header.title,
+        }
+    }
+
+    /// The header as it was recorded, ignoring a save time which can't be parsed.
+    pub(crate) fn to_header(&self) -> SaveHeader {
+        SaveHeader {
+            title: self.title.clone(),
+            saved: self
+                .saved
+                .as_deref()
+                .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                .map(SystemTime::from),
+        }
+    }
+}
+
+/// The manifest written into a slot when it's saved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SlotManifest {
+    /// When the slot was saved, in RFC 3339 format.
+    pub(crate) created: String,
+    /// The version of the tool which saved the slot.
+    pub(crate) version: String,
+    /// A note describing the slot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) note: Option<String>,
+    /// Labels attached to the slot, sorted and without duplicates.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags: Vec<String>,
+    /// If the slot is protected from being deleted.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub(crate) pinned: bool,
+    /// What saved the slot, like [DATED_KIND], or `None` if it was saved by name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) kind: Option<String>,
+    /// The edition of the game the save files are from, or `None` if it's not known, like for
+    /// slots saved by earlier versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) edition: Option<Edition>,
+    /// The version of the game the save files are from, like `1.0.3258.0`, or `None` if it
+    /// wasn't known when the slot was saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) game_version: Option<String>,
+    /// The save files in the slot.
+    pub(crate) files: Vec<ManifestFile>,
+    /// The settings files saved along with the save files, like `pc_settings.bin`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) settings: Vec<ManifestFile>,
+}
+
+/// Read the manifest in the given slot directory, if it has one.
+pub(crate) fn read(dir: &Path) -> Result<Option<SlotManifest>, Error> {
+    let path = dir.join(MANIFEST);
+
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let bytes = fs::read(to_extended_path(&path)).map_err(|e| Error::io(&path, e))?;
+
+    match serde_json::from_slice(&bytes) {
+        Ok(manifest) => Ok(Some(manifest)),
+        Err(source) => Err(Error::InvalidManifest { path, source }),
+    }
+}
+
+/// Write a manifest describing the save files in the given slot directory.
+pub(crate) fn write(
+    ops: &Ops,
+    dir: &Path,
+    note: Option<String>,
+    tags: Vec<String>,
+    kind: Option<&str>,
+) -> Result<(), Error> {
+    // The save files haven't been copied in a dry run, so there's nothing to hash.
+    if ops.dry_run {
+        return ops.run(FsOp::Write(&dir.join(MANIFEST), &[]));
+    }
+
+    let mut manifest = create(ops, dir, note, tags)?;
+    manifest.kind = kind.map(str::to_string);
+    write_manifest(ops, dir, &manifest)
+}
+
+/// Write a manifest describing the save files in the given slot directory, where the hashes of
+/// the save files in `known` are taken as they are instead of reading the files again.
+pub(crate) fn write_known(
+    ops: &Ops,
+    dir: &Path,
+    kind: Option<&str>,
+    known: &[(OsString, FileHash)],
+) -> Result<(), Error> {
+    if ops.dry_run {
+        return ops.run(FsOp::Write(&dir.join(MANIFEST), &[]));
+    }
+
+    let hash = |name: &OsString, path: &Path| match known.iter().find(|(n, _)| n == name) {
+        Some((_, hash)) => Ok(*hash),
+        None => hash::hash_file(path),
+    };
+
+    let manifest = SlotManifest {
+        created: chrono::Local::now().to_rfc3339(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        note: None,
+        tags: Vec::new(),
+        pinned: false,
+        kind: kind.map(str::to_string),
+        edition: ops.edition,
+        game_version: ops.game_version.clone(),
+        files: describe_save_files(list_save_files(dir, &ops.patterns)?, hash)?,
+        settings: hash_files(list_settings_files(dir)?)?,
+    };
+
+    write_manifest(ops, dir, &manifest)
+}
+
+/// Modify the manifest in the given slot directory, creating one which describes its save
+/// files if it doesn't have one.
+///
+/// The files of an existing manifest are kept, so that modifying it doesn't hide that the save
+/// files have changed since the slot was saved.
+pub(crate) fn update<F>(ops: &Ops, dir: &Path, f: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut SlotManifest),
+{
+    let mut manifest = match read(dir)? {
+        Some(manifest) => manifest,
+        None if ops.dry_run => return ops.run(FsOp::Write(&dir.join(MANIFEST), &[])),
+        None => create(ops, dir, None, Vec::new())?,
+    };
+
+    f(&mut manifest);
+    write_manifest(ops, dir, &manifest)
+}
+
+/// Create a manifest describing the save files in the given slot directory.
+fn create(
+    ops: &Ops,
+    dir: &Path,
+    note: Option<String>,
+    tags: Vec<String>,
+) -> Result<SlotManifest, Error> {
+    Ok(SlotManifest {
+        created: chrono::Local::now().to_rfc3339(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        note,
+        tags,
+        pinned: false,
+        kind: None,
+        edition: ops.edition,
+        game_version: ops.game_version.clone(),
+        files: describe_save_files(list_save_files(dir, &ops.patterns)?, |_, path| {
+            hash::hash_file(path)
+        })?,
+        settings: hash_files(list_settings_files(dir)?)?,
+    })
+}
+
+/// Describe the given files, sorted by name.
+fn hash_files(files: Vec<(OsString, PathBuf)>) -> Result<Vec<ManifestFile>, Error> {
+    hash_files_with(files, |_, path| hash::hash_file(path))
+}
+
+/// Describe the given files sorted by name, hashing each of them with `hash`.
+fn hash_files_with<F>(
+    mut files: Vec<(OsString, PathBuf)>,
+    mut hash: F,
+) -> Result<Vec<ManifestFile>, Error>
+where
+    F: FnMut(&OsString, &Path) -> Result<FileHash, Error>,
+{
+    files.sort();
+
+    let mut out = Vec::new();
+
+    for (name, path) in files {
+        let hash = hash(&name, &path)?;
+        out.push(ManifestFile::new(name.to_string_lossy().into_owned(), hash));
+    }
+
+    Ok(out)
+}
+
+/// Describe the given save files like [hash_files_with], along with what their headers say.
+fn describe_save_files<F>(
+    mut files: Vec<(OsString, PathBuf)>,
+    hash: F,
+) -> Result<Vec<ManifestFile>, Error>
+where
+    F: FnMut(&OsString, &Path) -> Result<FileHash, Error>,
+{
+    files.sort();
+
+    let headers = files
+        .iter()
+        .map(|(_, path)| ManifestHeader::read(path))
+        .collect::<Vec<_>>();
+
+    let mut out = hash_files_with(files, hash)?;
+
+    for (file, header) in out.iter_mut().zip(headers) {
+        file.header = Some(header);
+    }
+
+    Ok(out)
+}
+
+/// Write the given manifest into a slot directory.
+pub(crate) fn write_manifest(ops: &Ops, dir: &Path, manifest: &SlotManifest) -> Result<(), Error> {
+    let path = dir.join(MANIFEST);
+
+    let bytes = serde_json::to_vec_pretty(manifest).map_err(|source| Error::InvalidManifest {
+        path: path.clone(),
+        source,
+    })?;
+
+    ops.run(FsOp::Write(&path, &bytes))
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Check the save files in the given slot directory against its manifest, returning a
+/// description of every mismatch.
+pub(crate) fn check(
+    dir: &Path,
+    manifest: &SlotManifest,
+    patterns: &SaveFilePatterns,
+) -> Result<Vec<String>, Error> {
+    check_with(dir, manifest, patterns, hash::hash_file)
+}
+
+/// Check the save files in the given slot directory like [check], hashing each of them with
+/// `hash`.
+pub(crate) fn check_with<F>(
+    dir: &Path,
+    manifest: &SlotManifest,
+    patterns: &SaveFilePatterns,
+    mut hash: F,
+) -> Result<Vec<String>, Error>
+where
+    F: FnMut(&Path) -> Result<FileHash, Error>,
+{
+    let mut out = Vec::new();
+    let save_files = list_save_files(dir, patterns)?;
+    check_files(&manifest.files, save_files, &mut hash, &mut out)?;
+    check_files(
+        &manifest.settings,
+        list_settings_files(dir)?,
+        &mut hash,
+        &mut out,
+    )?;
+    Ok(out)
+}
+
+fn check_files(
+    manifest: &[ManifestFile],
+    mut save_files: Vec<(OsString, PathBuf)>,
+    hash: &mut dyn FnMut(&Path) -> Result<FileHash, Error>,
+    out: &mut Vec<String>,
+) -> Result<(), Error> {
+    save_files.sort();
+
+    for expected in manifest {
+        let path = match save_files
+            .iter()
+            .find(|(n, _)| n.to_string_lossy() == expected.name)
+        {
+            Some((_, path)) => path,
+            None => {
+                out.push(format!("{}: missing", expected.name));
+                continue;
+            }
+        };
+
+        let actual = hash(path)?;
+
+        if actual.size != expected.size {
+            out.push(format!(
+                "{}: expected {} bytes, found {}",
+                expected.name, expected.size, actual.size
+            ));
+        } else if actual.to_string() != expected.sha256 {
+            out.push(format!("{}: contents differ from manifest", expected.name));
+        }
+    }
+
+    for (name, _) in &save_files {
+        if !manifest.iter().any(|f| name.to_string_lossy() == f.name) {
+            out.push(format!("{}: not in manifest", display_name(name)));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_against_written_manifest() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("SGTA00000"), b"first")?;
+        fs::write(dir.path().join("SGTA00001"), b"second")?;
+        fs::write(dir.path().join("SGTA00002"), b"third")?;
+
+        write(
+            &Ops::default(),
+            dir.path(),
+            Some(String::from("a note")),
+            Vec::new(),
+            None,
+        )?;
+        let manifest = read(dir.path())?.expect("manifest");
+        assert_eq!(manifest.note.as_deref(), Some("a note"));
+        assert_eq!(manifest.files.len(), 3);
+        assert!(check(dir.path(), &manifest, &SaveFilePatterns::default())?.is_empty());
+
+        fs::write(dir.path().join("SGTA00000"), b"tampered")?;
+        fs::write(dir.path().join("SGTA00001"), b"SECOND")?;
+        fs::remove_file(dir.path().join("SGTA00002"))?;
+        fs::write(dir.path().join("SGTA00003"), b"fourth")?;
+
+        assert_eq!(
+            check(dir.path(), &manifest, &SaveFilePatterns::default())?,
+            vec![
+                "SGTA00000: expected 5 bytes, found 8",
+                "SGTA00001: contents differ from manifest",
+                "SGTA00002: missing",
+                "SGTA00003: not in manifest",
+            ]
+        );
+        Ok(())
+    }
+}
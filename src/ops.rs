@@ -0,0 +1,592 @@
+use crate::{
+    files::{to_extended_path as ext, Selection},
+    hash, hydrate,
+    journal::{self, HistoryFile},
+    links,
+    output::{self, Level, Style},
+    progress::Progress,
+    recycle, Edition, Error, SaveFilePatterns,
+};
+use std::{
+    fmt, fs,
+    io::{self, Read as _, Write as _},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How many times an operation is attempted before giving up on a sharing violation.
+const ATTEMPTS: u32 = 5;
+/// How long to wait before the first retry, which doubles for each one after it.
+const RETRY_DELAY: Duration = Duration::from_millis(125);
+/// The size of the chunks files are copied in when progress is shown.
+const CHUNK: usize = 256 * 1024;
+
+/// A single filesystem operation which modifies the filesystem.
+pub(crate) enum FsOp<'a> {
+    Copy(&'a Path, &'a Path),
+    HardLink(&'a Path, &'a Path),
+    Rename(&'a Path, &'a Path),
+    RemoveFile(&'a Path),
+    CreateDir(&'a Path),
+    RemoveDir(&'a Path),
+    RemoveDirAll(&'a Path),
+    Recycle(&'a Path),
+    Write(&'a Path, &'a [u8]),
+}
+
+impl FsOp<'_> {
+    /// The style the operation is printed in, if it copies or deletes files.
+    fn style(&self) -> Option<Style> {
+        match self {
+            FsOp::Copy(..) => Some(Style::Copied),
+            FsOp::RemoveFile(..)
+            | FsOp::RemoveDir(..)
+            | FsOp::RemoveDirAll(..)
+            | FsOp::Recycle(..) => Some(Style::Deleted),
+            _ => None,
+        }
+    }
+
+    /// Describe the operation for structured output.
+    fn record(&self) -> output::FileOperation {
+        let (op, path, to) = match *self {
+            FsOp::Copy(from, to) => ("copy", from, Some(to)),
+            FsOp::HardLink(from, to) => ("hard_link", from, Some(to)),
+            FsOp::Rename(from, to) => ("rename", from, Some(to)),
+            FsOp::RemoveFile(path) => ("delete", path, None),
+            FsOp::CreateDir(path) => ("create_dir", path, None),
+            FsOp::RemoveDir(path) => ("remove_dir", path, None),
+            FsOp::RemoveDirAll(path) => ("remove_dir_all", path, None),
+            FsOp::Recycle(path) => ("recycle", path, None),
+            FsOp::Write(path, _) => ("write", path, None),
+        };
+
+        output::FileOperation {
+            op,
+            path: path.to_owned(),
+            to: to.map(Path::to_owned),
+        }
+    }
+}
+
+impl FsOp<'_> {
+    /// Describe the operation for the journal, with the size and hash of the file it copies,
+    /// removes or writes, which has to be done before it's done.
+    fn journal(&self) -> HistoryFile {
+        let record = self.record();
+
+        let hash = match *self {
+            FsOp::Write(_, contents) => Some(hash::hash_bytes(contents)),
+            _ if record.path.is_file() => hash::hash_file(&record.path).ok(),
+            _ => None,
+        };
+
+        HistoryFile {
+            op: record.op.to_string(),
+            path: record.path,
+            to: record.to,
+            size: hash.map(|h| h.size),
+            sha256: hash.map(|h| h.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for FsOp<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsOp::Copy(from, to) => write!(fmt, "copy {} -> {}", from.display(), to.display()),
+            FsOp::HardLink(from, to) => write!(fmt, "link {} -> {}", from.display(), to.display()),
+            FsOp::Rename(from, to) => write!(fmt, "rename {} -> {}", from.display(), to.display()),
+            FsOp::RemoveFile(path) => write!(fmt, "delete {}", path.display()),
+            FsOp::CreateDir(path) => write!(fmt, "create directory {}", path.display()),
+            FsOp::RemoveDir(path) => write!(fmt, "remove directory {}", path.display()),
+            FsOp::RemoveDirAll(path) => write!(fmt, "remove directory tree {}", path.display()),
+            FsOp::Recycle(path) => write!(fmt, "recycle {}", path.display()),
+            FsOp::Write(path, _) => write!(fmt, "write {}", path.display()),
+        }
+    }
+}
+
+/// Performs filesystem operations, or prints them without touching the filesystem if
+/// `dry_run` is set.
+#[derive(Debug, Clone)]
+pub(crate) struct Ops {
+    pub(crate) dry_run: bool,
+    /// Verify that copies are identical to their source.
+    pub(crate) verify: bool,
+    /// Give copies the same modification time as their source.
+    pub(crate) preserve_times: bool,
+    /// Print additional details about what's being done.
+    pub(crate) verbose: bool,
+    /// Retry operations which fail because another process has the file open.
+    pub(crate) retry: bool,
+    /// Leave read-only save files alone, instead of clearing the attribute to replace or delete
+    /// them.
+    pub(crate) respect_readonly: bool,
+    /// Delete files permanently, instead of moving them to the Recycle Bin.
+    pub(crate) permanent: bool,
+    /// Only copy and delete the save files of these in-game save slots.
+    pub(crate) only: Option<Selection>,
+    /// Copy the settings files in the profile along with the save files.
+    pub(crate) settings: bool,
+    /// Which files are save files.
+    pub(crate) patterns: SaveFilePatterns,
+    /// How long to wait for a placeholder of a file stored in the cloud to be downloaded.
+    pub(crate) hydrate_timeout: Duration,
+    /// Where the bytes copied are counted, if progress is shown.
+    pub(crate) progress: Option<Arc<Progress>>,
+    /// Set to cancel copying and deleting files at the next safe point.
+    pub(crate) cancel: Option<&'static AtomicBool>,
+    /// The edition of the game the profile belongs to, which is recorded in manifests.
+    pub(crate) edition: Option<Edition>,
+    /// The version of the game the profile is played with, which is recorded in manifests.
+    pub(crate) game_version: Option<String>,
+}
+
+impl Default for Ops {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            verify: true,
+            preserve_times: true,
+            verbose: false,
+            retry: true,
+            respect_readonly: false,
+            permanent: false,
+            only: None,
+            settings: false,
+            patterns: SaveFilePatterns::default(),
+            hydrate_timeout: hydrate::DEFAULT_HYDRATE_TIMEOUT,
+            progress: None,
+            cancel: None,
+            edition: None,
+            game_version: None,
+        }
+    }
+}
+
+impl Ops {
+    /// Run the given operation.
+    ///
+    /// Antivirus scanners and cloud sync clients tend to hold save files open right after
+    /// they've been written, so operations failing because of that are retried with an
+    /// exponential backoff unless `retry` is disabled.
+    pub(crate) fn run(&self, op: FsOp<'_>) -> Result<(), Error> {
+        if self.dry_run {
+            out!(style = op.style().map(Style::name), "would {}", op);
+            output::operation(op.record());
+            return Ok(());
+        }
+
+        let journaled = journal::recording().then(|| op.journal());
+
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 1;
+        let mut cleared = false;
+
+        loop {
+            let error = match apply(&op, self.progress.as_deref(), self.cancel) {
+                Ok(()) => {
+                    if output::enabled(Level::Debug) {
+                        debug_op(&op);
+                    }
+
+                    output::operation(op.record());
+
+                    if let Some(file) = journaled {
+                        journal::record(file);
+                    }
+
+                    return Ok(());
+                }
+                Err(error) => error,
+            };
+
+            debug!(attempt, error = %error, "{} failed", op);
+
+            // A copy which was cancelled part of the way through fails, and isn't retried.
+            self.check_cancelled()?;
+
+            if !self.respect_readonly && !cleared {
+                if let Some(path) = readonly_target(&op, &error) {
+                    clear_readonly(path)?;
+                    out!("cleared read-only attribute: {}", path.display());
+                    cleared = true;
+                    continue;
+                }
+            }
+
+            if !self.retry || attempt == ATTEMPTS || !is_sharing_violation(&error) {
+                return Err(error);
+            }
+
+            eout!(
+                "{}, retrying in {}ms ({} of {})",
+                error,
+                delay.as_millis(),
+                attempt,
+                ATTEMPTS - 1
+            );
+
+            thread::sleep(delay);
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+impl Ops {
+    /// Test if cancelling has been asked for.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel.is_some_and(|c| c.load(Ordering::SeqCst))
+    }
+
+    /// Error with [Error::Cancelled] if cancelling has been asked for, which is done at the
+    /// points where stopping leaves everything as it was.
+    pub(crate) fn check_cancelled(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        Ok(())
+    }
+
+    /// The same operations, but which can't be cancelled, for restoring what a cancelled
+    /// operation had already changed.
+    pub(crate) fn uncancellable(&self) -> Self {
+        Self {
+            cancel: None,
+            ..self.clone()
+        }
+    }
+}
+
+impl Ops {
+    /// Delete a save file or an entire slot, moving it to the Recycle Bin unless `permanent`
+    /// is set, and print which one was done.
+    ///
+    /// If the Recycle Bin can't be used, like on network drives, it's deleted permanently
+    /// with a warning instead.
+    pub(crate) fn remove(&self, path: &Path) -> Result<(), Error> {
+        if !self.permanent && recycle::SUPPORTED {
+            match self.run(FsOp::Recycle(path)) {
+                Ok(()) => {
+                    if !self.dry_run {
+                        out_styled!(Style::Deleted, "recycle: {}", path.display());
+                    }
+
+                    return Ok(());
+                }
+                Err(e) => eout!("warning: {}, deleting it permanently", e),
+            }
+        }
+
+        if path.is_dir() {
+            self.run(FsOp::RemoveDirAll(path))?;
+        } else {
+            self.run(FsOp::RemoveFile(path))?;
+        }
+
+        if !self.dry_run {
+            out_styled!(Style::Deleted, "delete: {}", path.display());
+        }
+
+        Ok(())
+    }
+}
+
+/// Output a filesystem call which was made at the debug level, along with how many bytes it
+/// wrote.
+fn debug_op(op: &FsOp<'_>) {
+    match *op {
+        FsOp::Copy(_, to) | FsOp::HardLink(_, to) => match fs::metadata(ext(to)) {
+            Ok(m) => debug!(bytes = m.len(), "{}", op),
+            Err(..) => debug!("{}", op),
+        },
+        FsOp::Write(_, data) => debug!(bytes = data.len(), "{}", op),
+        _ => debug!("{}", op),
+    }
+}
+
+/// Perform the given operation once, counting the bytes copied towards `progress` if it's set,
+/// and stopping a copy in chunks once `cancel` is set.
+///
+/// Files with other hard links are unlinked before they're overwritten, so that writing to
+/// one slot can't change the slots it was deduplicated with.
+fn apply(
+    op: &FsOp<'_>,
+    progress: Option<&Progress>,
+    cancel: Option<&AtomicBool>,
+) -> Result<(), Error> {
+    match *op {
+        FsOp::Copy(from, to) => {
+            unlink_shared(to)?;
+
+            let copied = match progress {
+                Some(progress) => copy_in_chunks(&ext(from), &ext(to), progress, cancel),
+                None => fs::copy(ext(from), ext(to)).map(|_| ()),
+            };
+
+            match copied {
+                Ok(()) => Ok(()),
+                Err(source) => Err(Error::Copy {
+                    from: from.to_owned(),
+                    to: to.to_owned(),
+                    source,
+                }),
+            }
+        }
+        FsOp::HardLink(from, to) => {
+            fs::hard_link(ext(from), ext(to)).map_err(|source| Error::HardLink {
+                from: from.to_owned(),
+                to: to.to_owned(),
+                source,
+            })
+        }
+        FsOp::Rename(from, to) => fs::rename(ext(from), ext(to)).map_err(|source| Error::Rename {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            source,
+        }),
+        FsOp::RemoveFile(path) => fs::remove_file(ext(path)).map_err(|e| Error::io(path, e)),
+        FsOp::CreateDir(path) => fs::create_dir_all(ext(path)).map_err(|e| Error::io(path, e)),
+        FsOp::RemoveDir(path) => fs::remove_dir(ext(path)).map_err(|e| Error::io(path, e)),
+        FsOp::RemoveDirAll(path) => fs::remove_dir_all(ext(path)).map_err(|e| Error::io(path, e)),
+        // The shell doesn't accept extended-length paths.
+        FsOp::Recycle(path) => recycle::recycle(path),
+        FsOp::Write(path, contents) => {
+            unlink_shared(path)?;
+            fs::write(ext(path), contents).map_err(|e| Error::io(path, e))
+        }
+    }
+}
+
+/// Copy a file in chunks, counting each one towards the given progress, and failing between
+/// chunks once `cancel` is set.
+///
+/// Like [fs::copy], the copy gets the same permissions as the original.
+fn copy_in_chunks(
+    from: &Path,
+    to: &Path,
+    progress: &Progress,
+    cancel: Option<&AtomicBool>,
+) -> io::Result<()> {
+    let mut reader = fs::File::open(from)?;
+    let permissions = reader.metadata()?.permissions();
+    let mut writer = fs::File::create(to)?;
+    let mut buf = vec![0; CHUNK];
+
+    loop {
+        if cancel.is_some_and(|c| c.load(Ordering::SeqCst)) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        writer.write_all(&buf[..n])?;
+        progress.add(n as u64);
+    }
+
+    drop(writer);
+    fs::set_permissions(to, permissions)
+}
+
+/// Remove the file at the given path if it has other hard links.
+fn unlink_shared(path: &Path) -> Result<(), Error> {
+    if links::is_shared(path) {
+        fs::remove_file(ext(path)).map_err(|e| Error::io(path, e))?;
+    }
+
+    Ok(())
+}
+
+/// The file which the failed operation couldn't delete or overwrite because it's read-only.
+fn readonly_target<'a>(op: &FsOp<'a>, error: &Error) -> Option<&'a Path> {
+    let path = match *op {
+        FsOp::RemoveFile(path) | FsOp::Copy(_, path) => path,
+        _ => return None,
+    };
+
+    let denied = match error {
+        Error::Io { source, .. } | Error::Copy { source, .. } => {
+            source.kind() == io::ErrorKind::PermissionDenied
+        }
+        _ => false,
+    };
+
+    if denied && is_readonly(path) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Test if the file at the given path is marked read-only.
+pub(crate) fn is_readonly(path: &Path) -> bool {
+    fs::metadata(ext(path))
+        .map(|m| m.permissions().readonly())
+        .unwrap_or_default()
+}
+
+/// Make the file at the given path writable.
+fn clear_readonly(path: &Path) -> Result<(), Error> {
+    let mut permissions = fs::metadata(ext(path))
+        .map_err(|e| Error::io(path, e))?
+        .permissions();
+
+    // Only make it writable by the owner, where clearing the read-only flag would make it
+    // writable by everyone.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        permissions.set_mode(permissions.mode() | 0o200);
+    }
+
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+
+    fs::set_permissions(ext(path), permissions).map_err(|e| Error::io(path, e))
+}
+
+/// Test if the error is because another process has the file open or locked, which usually
+/// goes away on its own, as opposed to errors like missing files or permissions.
+fn is_sharing_violation(error: &Error) -> bool {
+    /// `ERROR_SHARING_VIOLATION` and `ERROR_LOCK_VIOLATION`.
+    const WINDOWS_CODES: [i32; 2] = [32, 33];
+
+    let source = match error {
+        Error::Io { source, .. }
+        | Error::Copy { source, .. }
+        | Error::HardLink { source, .. }
+        | Error::Rename { source, .. } => source,
+        _ => return false,
+    };
+
+    if cfg!(windows) {
+        if let Some(code) = source.raw_os_error() {
+            return WINDOWS_CODES.contains(&code);
+        }
+    }
+
+    source.kind() == io::ErrorKind::ResourceBusy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_in_chunks_counts_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let from = dir.path().join("SGTA00000");
+        let to = dir.path().join("copy");
+        let contents = vec![7u8; CHUNK * 2 + 1];
+        fs::write(&from, &contents)?;
+
+        let progress = Arc::new(Progress::new(
+            output::ProgressStyle::Hidden,
+            "copied",
+            1,
+            contents.len() as u64,
+        ));
+        let ops = Ops {
+            progress: Some(progress.clone()),
+            ..Ops::default()
+        };
+
+        ops.run(FsOp::Copy(&from, &to))?;
+        assert_eq!(fs::read(&to)?, contents);
+        assert_eq!(progress.bytes(), contents.len() as u64);
+        Ok(())
+    }
+
+    #[test]
+    fn cancelled_copies_fail() -> Result<(), Box<dyn std::error::Error>> {
+        static CANCEL: AtomicBool = AtomicBool::new(true);
+
+        let dir = tempfile::tempdir()?;
+        let from = dir.path().join("SGTA00000");
+        let to = dir.path().join("copy");
+        fs::write(&from, vec![7u8; CHUNK])?;
+
+        let ops = Ops {
+            progress: Some(Arc::new(Progress::new(
+                output::ProgressStyle::Hidden,
+                "copied",
+                1,
+                CHUNK as u64,
+            ))),
+            cancel: Some(&CANCEL),
+            ..Ops::default()
+        };
+
+        assert!(matches!(
+            ops.run(FsOp::Copy(&from, &to)),
+            Err(Error::Cancelled)
+        ));
+        assert!(fs::metadata(&to)?.len() < CHUNK as u64);
+
+        ops.uncancellable().run(FsOp::Copy(&from, &to))?;
+        assert_eq!(fs::metadata(&to)?.len(), CHUNK as u64);
+        Ok(())
+    }
+
+    #[test]
+    fn copy_error_names_both_paths() {
+        let missing = tempfile::tempdir().unwrap();
+        let from = missing.path().join("SGTA00000");
+        let to = missing.path().join("nope").join("SGTA00000");
+
+        let error = Ops::default().run(FsOp::Copy(&from, &to)).unwrap_err();
+        let message = error.to_string();
+
+        assert!(message.contains(&from.display().to_string()));
+        assert!(message.contains(&to.display().to_string()));
+    }
+
+    #[test]
+    fn only_sharing_violations_are_retried() {
+        let error = |kind| Error::io(Path::new("SGTA00000"), io::Error::from(kind));
+
+        assert!(is_sharing_violation(&error(io::ErrorKind::ResourceBusy)));
+        assert!(!is_sharing_violation(&error(io::ErrorKind::NotFound)));
+        assert!(!is_sharing_violation(&error(
+            io::ErrorKind::PermissionDenied
+        )));
+        assert!(!is_sharing_violation(&Error::Aborted));
+
+        // Fails right away, without retrying.
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("SGTA00000");
+        let error = Ops::default().run(FsOp::RemoveFile(&missing)).unwrap_err();
+        assert!(!is_sharing_violation(&error));
+    }
+
+    #[test]
+    fn clear_readonly_makes_writable() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("SGTA00000");
+        fs::write(&path, b"current")?;
+
+        let mut permissions = fs::metadata(&path)?.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions)?;
+        assert!(is_readonly(&path));
+
+        clear_readonly(&path)?;
+        assert!(!is_readonly(&path));
+        fs::write(&path, b"changed")?;
+        Ok(())
+    }
+}
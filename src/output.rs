@@ -0,0 +1,821 @@
+use crate::Error;
+use console::Term;
+use serde::Serialize;
+use std::{
+    env,
+    fmt::{self, Write as _},
+    fs,
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::{Mutex, Once},
+};
+use tracing::{
+    field::{Field, Visit},
+    subscriber::Interest,
+    Event, Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, Layer, Registry};
+
+/// Print a line to stdout, and to the log file if it's open.
+///
+/// Like the other output macros, this emits a [tracing] event, so fields can be given before the
+/// message, like `out!(path = %path.display(), "copied")`. Fields are written to the log file,
+/// and to stderr for `debug` and `trace`.
+#[macro_export]
+macro_rules! out {
+    ($($arg:tt)*) => {
+        $crate::output::tracing::info!($($arg)*)
+    };
+}
+
+/// Print a line to stdout like [out!], in the given [Style](output::Style) if it's written to a
+/// terminal which colors are used in.
+#[macro_export]
+macro_rules! out_styled {
+    ($style:expr, $($arg:tt)*) => {
+        $crate::output::tracing::info!(style = $crate::output::Style::name($style), $($arg)*)
+    };
+}
+
+/// Print what a command was asked to show to stdout, like the slots listed, and to the log file
+/// if it's open. Unlike [out!], this is printed at every level.
+#[macro_export]
+macro_rules! report {
+    ($($arg:tt)*) => {
+        $crate::output::tracing::info!(report = true, $($arg)*)
+    };
+}
+
+/// Print a warning to stderr, and to the log file if it's open.
+#[macro_export]
+macro_rules! eout {
+    ($($arg:tt)*) => {
+        $crate::output::tracing::warn!($($arg)*)
+    };
+}
+
+/// Print an error to stderr, and to the log file if it's open. Errors are shown at every level.
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::output::tracing::error!($($arg)*)
+    };
+}
+
+/// Print a line to stderr and the log file if the level is `debug` or lower, like for every
+/// filesystem call which is made.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::output::tracing::debug!($($arg)*)
+    };
+}
+
+/// Print a line to stderr and the log file if the level is `trace`.
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        $crate::output::tracing::trace!($($arg)*)
+    };
+}
+
+#[doc(hidden)]
+pub use tracing;
+
+/// Name of the log file in the GTA V directory.
+const LOG_FILE: &str = "gtav-saveload.log";
+
+/// The log file is rotated when it grows beyond this size.
+const LOG_MAX_SIZE: u64 = 1024 * 1024;
+
+/// The log file which output is copied to, once it's been opened.
+static LOG: Mutex<Option<fs::File>> = Mutex::new(None);
+
+/// Where output is collected instead of being printed to stdout, once capturing has started.
+static CAPTURE: Mutex<Option<Captured>> = Mutex::new(None);
+
+/// The most detailed level of output which is shown.
+static LEVEL: Mutex<Level> = Mutex::new(Level::Info);
+
+/// How the progress of copying save files is shown.
+static PROGRESS: Mutex<ProgressStyle> = Mutex::new(ProgressStyle::Hidden);
+
+/// If nothing is printed to stdout or stderr, not even errors.
+static QUIET: Mutex<bool> = Mutex::new(false);
+
+/// If stdout and stderr are colored, in that order.
+static COLOR: Mutex<(bool, bool)> = Mutex::new((false, false));
+
+/// Installs the layers output is written through.
+static INIT: Once = Once::new();
+
+/// The units sizes are shown in, each 1024 times the one before it.
+const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+
+/// How the progress of copying save files is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressStyle {
+    /// Progress isn't shown, which is the default.
+    Hidden,
+    /// A progress bar is drawn on stderr, which should be a terminal.
+    Bar,
+    /// A line is written to the log file every few seconds, for when there's no console.
+    Log,
+}
+
+/// How a line of output is colored, when it's written to a terminal which colors are used in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// A file which was copied, in green.
+    Copied,
+    /// A file or slot which was deleted, in red.
+    Deleted,
+    /// A warning, in yellow. Every warning is shown in this style.
+    Warning,
+}
+
+impl Style {
+    /// The name of the style, which is what's recorded in the `style` field of an event.
+    pub fn name(self) -> &'static str {
+        match self {
+            Style::Copied => "copied",
+            Style::Deleted => "deleted",
+            Style::Warning => "warning",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "copied" => Some(Style::Copied),
+            "deleted" => Some(Style::Deleted),
+            "warning" => Some(Style::Warning),
+            _ => None,
+        }
+    }
+
+    fn console(self) -> console::Style {
+        let style = console::Style::new().force_styling(true);
+
+        match self {
+            Style::Copied => style.green(),
+            Style::Deleted => style.red(),
+            Style::Warning => style.yellow(),
+        }
+    }
+}
+
+/// How important a line of output is, from the most to the least important.
+///
+/// Only lines at or above the level set with [set_level] are shown, except that the log file
+/// always gets `info` and above so that it says what was done even with `--log-level error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Something failed.
+    Error,
+    /// Something might not be as expected, but was carried on with.
+    Warn,
+    /// What was done, which is the default.
+    Info,
+    /// Every filesystem call which is made, and why profiles and slots were picked.
+    Debug,
+    /// Every file which is looked at, and the details of how it's copied.
+    Trace,
+}
+
+impl Level {
+    /// Every level, from the most to the least important.
+    pub const ALL: &'static [Level] = &[
+        Level::Error,
+        Level::Warn,
+        Level::Info,
+        Level::Debug,
+        Level::Trace,
+    ];
+
+    /// The name of the level, like `warn`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+
+    /// Parse the name of a level, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|l| l.name().eq_ignore_ascii_case(name))
+    }
+
+    fn from_tracing(level: &tracing::Level) -> Self {
+        match *level {
+            tracing::Level::ERROR => Level::Error,
+            tracing::Level::WARN => Level::Warn,
+            tracing::Level::INFO => Level::Info,
+            tracing::Level::DEBUG => Level::Debug,
+            tracing::Level::TRACE => Level::Trace,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+/// A filesystem operation which was performed, or would have been in a dry run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileOperation {
+    /// What was done, like `copy`, `rename`, `delete` or `create_dir`.
+    pub op: &'static str,
+    /// The path operated on, or the source of a copy or rename.
+    pub path: PathBuf,
+    /// The destination of a copy or rename.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<PathBuf>,
+}
+
+/// What was output while capturing, which the command line tool turns into a single JSON
+/// document for `--format json`.
+#[derive(Debug, Default)]
+pub struct Captured {
+    /// The lines which would have been printed to stdout.
+    pub messages: Vec<String>,
+    /// The filesystem operations, in the order they were performed.
+    pub operations: Vec<FileOperation>,
+    /// Structured results, like the slots listed.
+    pub results: Vec<serde_json::Value>,
+}
+
+/// Start collecting output instead of printing it to stdout. Output is still written to the
+/// log file, and stderr is unaffected.
+pub fn capture() {
+    init();
+
+    if let Ok(mut capture) = CAPTURE.lock() {
+        *capture = Some(Captured::default());
+    }
+}
+
+/// Test if output is being captured.
+pub fn capturing() -> bool {
+    CAPTURE.lock().map(|c| c.is_some()).unwrap_or_default()
+}
+
+/// Stop capturing, returning what was captured if capturing had started.
+pub fn take_captured() -> Option<Captured> {
+    CAPTURE.lock().ok().and_then(|mut c| c.take())
+}
+
+/// Add a structured result while capturing. Does nothing otherwise.
+pub fn result(value: serde_json::Value) {
+    with_capture(|c| c.results.push(value));
+}
+
+/// Record a filesystem operation while capturing. Does nothing otherwise.
+pub(crate) fn operation(operation: FileOperation) {
+    with_capture(|c| c.operations.push(operation));
+}
+
+/// Call `f` with the capture if capturing, returning if it was called.
+fn with_capture(f: impl FnOnce(&mut Captured)) -> bool {
+    match CAPTURE.lock() {
+        Ok(mut capture) => match capture.as_mut() {
+            Some(capture) => {
+                f(capture);
+                true
+            }
+            None => false,
+        },
+        Err(..) => false,
+    }
+}
+
+/// Show the progress of copying save files in the given style.
+pub fn show_progress(style: ProgressStyle) {
+    init();
+
+    if let Ok(mut progress) = PROGRESS.lock() {
+        *progress = style;
+    }
+}
+
+/// Color output written to a terminal if `enabled` is set and the `NO_COLOR` environment
+/// variable isn't. Output isn't colored until this is called, and is never colored while it's
+/// captured or in the log file.
+pub fn set_color(enabled: bool) {
+    init();
+
+    let enabled = enabled && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty());
+
+    let streams = (
+        enabled && Term::stdout().features().colors_supported(),
+        enabled && Term::stderr().features().colors_supported(),
+    );
+
+    if let Ok(mut color) = COLOR.lock() {
+        *color = streams;
+    }
+}
+
+/// Print nothing to stdout or stderr, not even errors, for when the exit code says enough. The
+/// log file gets the same as it does otherwise, and output is still captured while capturing.
+pub fn set_quiet(quiet: bool) {
+    init();
+
+    if let Ok(mut current) = QUIET.lock() {
+        *current = quiet;
+    }
+}
+
+/// Test if nothing is printed to stdout or stderr.
+pub fn quiet() -> bool {
+    QUIET.lock().map(|q| *q).unwrap_or_default()
+}
+
+/// Show output at the given level and above.
+pub fn set_level(level: Level) {
+    init();
+
+    if let Ok(mut current) = LEVEL.lock() {
+        *current = level;
+    }
+}
+
+/// The most detailed level of output which is shown.
+pub fn level() -> Level {
+    LEVEL.lock().map(|l| *l).unwrap_or(Level::Info)
+}
+
+/// Test if output at the given level is shown, to avoid working out what to output otherwise.
+pub fn enabled(level: Level) -> bool {
+    level <= self::level()
+}
+
+/// How the progress of copying save files is shown.
+pub(crate) fn progress_style() -> ProgressStyle {
+    PROGRESS.lock().map(|p| *p).unwrap_or(ProgressStyle::Hidden)
+}
+
+/// Open the log file in the given directory, rotating it if it's too large.
+pub fn open_log(dir: &Path) -> Result<(), Error> {
+    init();
+
+    let path = dir.join(LOG_FILE);
+
+    if fs::metadata(&path)
+        .map(|m| m.len() > LOG_MAX_SIZE)
+        .unwrap_or_default()
+    {
+        let rotated = path.with_extension("log.1");
+
+        fs::rename(&path, &rotated).map_err(|source| Error::Rename {
+            from: path.clone(),
+            to: rotated,
+            source,
+        })?;
+    }
+
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| Error::io(&path, e))?;
+
+    if let Ok(mut log) = LOG.lock() {
+        *log = Some(file);
+    }
+
+    Ok(())
+}
+
+/// Install the layers events from this crate and the command line tool are written through, as
+/// the global [tracing] subscriber. This is done by every function which changes how output is
+/// written, so it only has to be called directly to write output with the defaults.
+///
+/// Nothing is installed if another global subscriber already has been, in which case events go
+/// to that instead.
+pub fn init() {
+    INIT.call_once(|| {
+        let subscriber = Registry::default()
+            .with(ConsoleLayer::default())
+            .with(FileLayer);
+        let _ = tracing::subscriber::set_global_default(subscriber);
+    });
+}
+
+/// Test if an event is written, which is if it's from this crate or the command line tool, and
+/// is either shown or at `info` and above, which the log file always gets.
+fn is_written(metadata: &Metadata<'_>) -> bool {
+    metadata.target().starts_with("gtav_")
+        && Level::from_tracing(metadata.level()) <= level().max(Level::Info)
+}
+
+/// The fields of an event.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Fields {
+    message: String,
+    /// If it's what a command was asked to show, from [report!].
+    report: bool,
+    style: Option<Style>,
+    /// Every other field, formatted like `key=value`.
+    rest: String,
+}
+
+impl Fields {
+    fn of(event: &Event<'_>) -> Self {
+        let mut fields = Self::default();
+        event.record(&mut fields);
+        fields
+    }
+
+    fn push(&mut self, field: &Field, value: fmt::Arguments<'_>) {
+        let _ = write!(self.rest, " {}={}", field.name(), value);
+    }
+}
+
+impl Visit for Fields {
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        match field.name() {
+            "report" => self.report = value,
+            _ => self.push(field, format_args!("{}", value)),
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message.push_str(value),
+            "style" => self.style = Style::from_name(value),
+            _ => self.push(field, format_args!("{}", value)),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => {
+                let _ = write!(self.message, "{:?}", value);
+            }
+            _ => self.push(field, format_args!("{:?}", value)),
+        }
+    }
+}
+
+/// Writes events to stdout or stderr, or to the capture while capturing.
+///
+/// Only `info` goes to stdout, where it's captured instead while capturing, regardless of the
+/// level, since it's part of the result. Everything else goes to stderr, so that details don't
+/// get mixed up with the output of commands like `list`. Fields other than the message are only
+/// printed for `debug` and `trace`.
+///
+/// Failing to write is ignored, so that output piped into something like `head` which exits
+/// early doesn't stop the command.
+struct ConsoleLayer {
+    stdout: fn() -> Box<dyn io::Write>,
+    stderr: fn() -> Box<dyn io::Write>,
+}
+
+impl Default for ConsoleLayer {
+    fn default() -> Self {
+        Self {
+            stdout: || Box::new(io::stdout().lock()),
+            stderr: || Box::new(io::stderr().lock()),
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+        // The level can change at any time, so whether an event is written is always asked.
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _: Context<'_, S>) -> bool {
+        is_written(metadata)
+    }
+
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let level = Level::from_tracing(event.metadata().level());
+        let fields = Fields::of(event);
+        let printed = (fields.report || enabled(level)) && !quiet();
+        let (stdout, stderr) = COLOR.lock().map(|c| *c).unwrap_or_default();
+        let style = fields
+            .style
+            .or_else(|| (level == Level::Warn).then_some(Style::Warning));
+
+        let paint = |colored: bool| match style {
+            Some(style) if colored => style.console().apply_to(&fields.message).to_string(),
+            _ => fields.message.clone(),
+        };
+
+        if level == Level::Info {
+            if !with_capture(|c| c.messages.push(fields.message.clone())) && printed {
+                let _ = writeln!((self.stdout)(), "{}", paint(stdout));
+            }
+        } else if printed {
+            let _ = match level {
+                Level::Debug | Level::Trace => {
+                    writeln!(
+                        (self.stderr)(),
+                        "{}: {}{}",
+                        level,
+                        fields.message,
+                        fields.rest
+                    )
+                }
+                _ => writeln!((self.stderr)(), "{}", paint(stderr)),
+            };
+        }
+    }
+}
+
+/// Writes events to the log file, if it's open.
+struct FileLayer;
+
+impl<S: Subscriber> Layer<S> for FileLayer {
+    fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _: Context<'_, S>) -> bool {
+        is_written(metadata)
+    }
+
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let level = Level::from_tracing(event.metadata().level());
+        let fields = Fields::of(event);
+        log_at(level, format_args!("{}{}", fields.message, fields.rest));
+    }
+}
+
+/// Write a line to the log file only, if it's open.
+pub(crate) fn log(args: fmt::Arguments<'_>) {
+    log_at(Level::Info, args);
+}
+
+fn log_at(level: Level, args: fmt::Arguments<'_>) {
+    if let Ok(mut log) = LOG.lock() {
+        if let Some(file) = log.as_mut() {
+            let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+            let _ = writeln!(file, "{} {:5} {}", now, level.name().to_uppercase(), args);
+        }
+    }
+}
+
+/// Format a number of bytes for people to read, like `1.2 MiB`, or like `512 bytes` if it's less
+/// than a kibibyte.
+pub fn size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return count(bytes as usize, "byte");
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    // Rounded like it's printed, so that 1023.99 KiB isn't shown as 1024.0 KiB.
+    while (value * 10.0).round() >= 1024.0 * 10.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Format a number of things, like `1 file` or `3 files`, where the plural is `noun` followed by
+/// an `s`.
+pub fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("{} {}", n, noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+/// Lines of output whose columns are aligned with each other, which are printed with [report!]
+/// once they're all known.
+///
+/// Every column of a row but the last one is padded to the widest cell in that column, so rows
+/// can have a note at the end which is as long as it needs to be. Lines of text in between rows
+/// are printed as they are.
+#[derive(Debug, Default)]
+pub struct Table {
+    /// The columns which are aligned to the right, like indexes.
+    right: Vec<usize>,
+    lines: Vec<TableLine>,
+}
+
+#[derive(Debug)]
+enum TableLine {
+    Row(Vec<String>),
+    Text(String),
+}
+
+impl Table {
+    /// Construct an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Align the given column to the right, counting from 0.
+    pub fn align_right(mut self, column: usize) -> Self {
+        self.right.push(column);
+        self
+    }
+
+    /// Add a row with the given cells.
+    pub fn row<I>(&mut self, cells: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let cells = cells.into_iter().map(Into::into).collect();
+        self.lines.push(TableLine::Row(cells));
+    }
+
+    /// Add a line of text, which isn't aligned with anything.
+    pub fn text(&mut self, line: impl Into<String>) {
+        self.lines.push(TableLine::Text(line.into()));
+    }
+
+    /// Format every line of the table, with the columns aligned.
+    pub fn lines(&self) -> Vec<String> {
+        let mut widths = Vec::<usize>::new();
+
+        for line in &self.lines {
+            if let TableLine::Row(cells) = line {
+                for (column, cell) in cells.iter().enumerate().take(cells.len().saturating_sub(1)) {
+                    let width = console::measure_text_width(cell);
+
+                    match widths.get_mut(column) {
+                        Some(widest) => *widest = width.max(*widest),
+                        None => widths.push(width),
+                    }
+                }
+            }
+        }
+
+        self.lines
+            .iter()
+            .map(|line| match line {
+                TableLine::Row(cells) => {
+                    let mut out = String::new();
+
+                    for (column, cell) in cells.iter().enumerate() {
+                        if column > 0 {
+                            out.push(' ');
+                        }
+
+                        if column + 1 == cells.len() {
+                            out.push_str(cell);
+                            break;
+                        }
+
+                        let padding =
+                            " ".repeat(widths[column] - console::measure_text_width(cell));
+
+                        if self.right.contains(&column) {
+                            out.push_str(&padding);
+                            out.push_str(cell);
+                        } else {
+                            out.push_str(cell);
+                            out.push_str(&padding);
+                        }
+                    }
+
+                    out
+                }
+                TableLine::Text(text) => text.clone(),
+            })
+            .collect()
+    }
+
+    /// Print every line of the table with [report!].
+    pub fn report(&self) {
+        for line in self.lines() {
+            crate::report!("{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(size(0), "0 bytes");
+        assert_eq!(size(1), "1 byte");
+        assert_eq!(size(1023), "1023 bytes");
+        assert_eq!(size(1024), "1.0 KiB");
+        assert_eq!(size(1_500_000), "1.4 MiB");
+        assert_eq!(size(1024 * 1024 - 1), "1.0 MiB");
+        assert_eq!(size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+
+    #[test]
+    fn counts() {
+        assert_eq!(count(0, "file"), "0 files");
+        assert_eq!(count(1, "file"), "1 file");
+        assert_eq!(count(2, "slot"), "2 slots");
+    }
+
+    #[test]
+    fn event_fields() {
+        #[derive(Clone, Default)]
+        struct Recorded(std::sync::Arc<Mutex<Vec<Fields>>>);
+
+        impl<S: Subscriber> Layer<S> for Recorded {
+            fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+                self.0.lock().unwrap().push(Fields::of(event));
+            }
+        }
+
+        let recorded = Recorded::default();
+        let subscriber = Registry::default().with(recorded.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            crate::debug!(bytes = 356, path = %"a b", "write {}", "manifest.json");
+            crate::out_styled!(Style::Copied, "a -> b");
+            crate::report!("1: foo");
+        });
+
+        assert_eq!(
+            *recorded.0.lock().unwrap(),
+            [
+                Fields {
+                    message: String::from("write manifest.json"),
+                    rest: String::from(" bytes=356 path=a b"),
+                    ..Fields::default()
+                },
+                Fields {
+                    message: String::from("a -> b"),
+                    style: Some(Style::Copied),
+                    ..Fields::default()
+                },
+                Fields {
+                    message: String::from("1: foo"),
+                    report: true,
+                    ..Fields::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn closed_pipes_are_ignored() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+        /// Fails like stdout does once whatever it's piped into has exited.
+        struct BrokenPipe;
+
+        impl io::Write for BrokenPipe {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                WRITES.fetch_add(1, Ordering::SeqCst);
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+        }
+
+        let layer = ConsoleLayer {
+            stdout: || Box::new(BrokenPipe),
+            stderr: || Box::new(BrokenPipe),
+        };
+
+        tracing::subscriber::with_default(Registry::default().with(layer), || {
+            crate::out!("a -> b");
+            crate::report!("1: foo");
+            crate::eout!("warning: foo");
+            crate::error!("error: foo");
+        });
+
+        assert!(WRITES.load(Ordering::SeqCst) >= 4);
+    }
+
+    #[test]
+    fn aligned_columns() {
+        let mut table = Table::new().align_right(0);
+        table.row(["1:", "foo", "(2019-10-30)", "3 files"]);
+        table.text("       detail");
+        table.row(["10:", "before-heist", "(2019-10-29)", "empty"]);
+        table.row(["11:", "å", "(2019-10-28)"]);
+
+        assert_eq!(
+            table.lines(),
+            [
+                " 1: foo          (2019-10-30) 3 files",
+                "       detail",
+                "10: before-heist (2019-10-29) empty",
+                "11: å            (2019-10-28)",
+            ]
+        );
+    }
+}
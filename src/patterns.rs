@@ -0,0 +1,114 @@
+use crate::{compress, Error};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::{ffi::OsStr, sync::Arc};
+
+/// The pattern of the names of save files unless others are given, which is what the game
+/// names them.
+pub const DEFAULT_INCLUDE: &str = "SGTA*";
+
+/// Which files count as save files, as glob patterns matched against their names.
+///
+/// Compressed save files are matched by the name they have once they're decompressed, like
+/// `SGTA00000` for `SGTA00000.zst`. The patterns are compiled once, and cloning them is cheap.
+#[derive(Debug, Clone)]
+pub struct SaveFilePatterns {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+impl SaveFilePatterns {
+    /// Match the files whose names match any of the `include` patterns and none of the
+    /// `exclude` patterns, like `SGTA*` and `*.bak`.
+    pub fn new<I, E>(include: I, exclude: E) -> Result<Self, Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        E: IntoIterator,
+        E::Item: AsRef<str>,
+    {
+        Ok(Self {
+            inner: Arc::new(Inner {
+                include: compile(include)?,
+                exclude: compile(exclude)?,
+            }),
+        })
+    }
+
+    /// Test if the file with the given name is a save file.
+    pub(crate) fn matches(&self, name: &OsStr) -> bool {
+        let name = compress::save_file_name(name.to_owned());
+        self.inner.include.is_match(&name) && !self.inner.exclude.is_match(&name)
+    }
+}
+
+impl Default for SaveFilePatterns {
+    /// The files named like [DEFAULT_INCLUDE].
+    fn default() -> Self {
+        Self::new(&[DEFAULT_INCLUDE], &[] as &[&str]).expect("the default pattern is valid")
+    }
+}
+
+fn compile<I>(patterns: I) -> Result<GlobSet, Error>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut set = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        let pattern = pattern.as_ref();
+
+        let glob = Glob::new(pattern).map_err(|e| Error::InvalidPattern {
+            pattern: pattern.to_string(),
+            reason: e.kind().to_string(),
+        })?;
+
+        set.add(glob);
+    }
+
+    set.build().map_err(|e| Error::InvalidPattern {
+        pattern: e.glob().unwrap_or_default().to_string(),
+        reason: e.kind().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_patterns() {
+        let patterns = SaveFilePatterns::default();
+        let matches = |name: &str| patterns.matches(OsStr::new(name));
+
+        assert!(matches("SGTA00000"));
+        assert!(matches("SGTA00000.zst"));
+        assert!(matches("SGTA00000.bak"));
+        assert!(!matches("sgta00000"));
+        assert!(!matches("pc_settings.bin"));
+        assert!(!matches("manifest.json"));
+    }
+
+    #[test]
+    fn include_and_exclude() -> Result<(), Error> {
+        let patterns = SaveFilePatterns::new(&["SGTA*", "SRDR*"], &["*.bak"])?;
+        let matches = |name: &str| patterns.matches(OsStr::new(name));
+
+        assert!(matches("SGTA00000"));
+        assert!(matches("SRDR30000"));
+        assert!(!matches("SGTA00000.bak"));
+        assert!(!matches("SGTA00000.bak.zst"));
+        assert!(!matches("cfg.dat"));
+
+        assert!(matches!(
+            SaveFilePatterns::new(&["SGTA[0"], &[] as &[&str]),
+            Err(Error::InvalidPattern { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{files::to_extended_path, Error};
+use std::{
+    convert::TryInto as _,
+    fs,
+    io::{self, Read as _, Seek as _, SeekFrom},
+    path::Path,
+};
+
+/// How much of the start of the file is read to find the section holding the resources, which
+/// is far more than the headers of any executable take up.
+const HEADERS_SIZE: u64 = 64 * 1024;
+
+/// The largest resource section which is read, since the version resource is only a small
+/// part of it.
+const MAX_RESOURCES_SIZE: u32 = 64 * 1024 * 1024;
+
+/// The name of the section holding the resources.
+const RESOURCES: &[u8; 8] = b".rsrc\0\0\0";
+
+/// The key of the version resource, in UTF-16 like every string in it.
+const VERSION_INFO_KEY: &str = "VS_VERSION_INFO";
+
+/// The signature of the fixed part of the version resource, which holds the file version.
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xfeef_04bd;
+
+/// Read the file version of the Windows executable at the given path, like `1.0.3258.0`, from
+/// its version resource.
+///
+/// This parses the file rather than asking Windows, so it works the same for executables run
+/// through Proton. It's `None` if the file isn't an executable or has no version resource.
+pub(crate) fn file_version(path: &Path) -> Result<Option<String>, Error> {
+    let mut file = fs::File::open(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+
+    let mut headers = Vec::new();
+    (&mut file)
+        .take(HEADERS_SIZE)
+        .read_to_end(&mut headers)
+        .map_err(|e| Error::io(path, e))?;
+
+    let (offset, size) = match resources(&headers) {
+        Some(section) => section,
+        None => return Ok(None),
+    };
+
+    let mut contents = vec![0; size.min(MAX_RESOURCES_SIZE) as usize];
+
+    let read = file
+        .seek(SeekFrom::Start(u64::from(offset)))
+        .and_then(|_| file.read_exact(&mut contents));
+
+    match read {
+        Ok(()) => Ok(fixed_file_version(&contents)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(Error::io(path, e)),
+    }
+}
+
+/// Find the offset and size in the file of the section holding the resources, from the
+/// headers at the start of it.
+fn resources(headers: &[u8]) -> Option<(u32, u32)> {
+    if headers.get(..2)? != b"MZ" {
+        return None;
+    }
+
+    let pe = u32_at(headers, 0x3c)? as usize;
+
+    if headers.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+
+    // The COFF header follows the signature, and the section table the optional header.
+    let sections = usize::from(u16_at(headers, pe + 6)?);
+    let optional = usize::from(u16_at(headers, pe + 20)?);
+    let table = pe + 24 + optional;
+
+    (0..sections).find_map(|index| {
+        let section = headers.get(table + index * 40..table + (index + 1) * 40)?;
+
+        if section.get(..8)? != RESOURCES {
+            return None;
+        }
+
+        Some((u32_at(section, 20)?, u32_at(section, 16)?))
+    })
+}
+
+/// Find the file version in the fixed part of the version resource in the given resources.
+///
+/// The fixed part follows the key of the version resource, aligned to four bytes.
+fn fixed_file_version(resources: &[u8]) -> Option<String> {
+    let key = VERSION_INFO_KEY
+        .encode_utf16()
+        .chain([0])
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+
+    let mut rest = resources;
+
+    while let Some(at) = rest.windows(key.len()).position(|w| w == key.as_slice()) {
+        let end = at + key.len();
+
+        // Padding is relative to the start of the resources, which are aligned themselves.
+        let start = resources.len() - rest.len() + end;
+        let fixed = rest.get(end + (4 - start % 4) % 4..).unwrap_or_default();
+
+        if u32_at(fixed, 0) == Some(FIXED_FILE_INFO_SIGNATURE) {
+            let ms = u32_at(fixed, 8)?;
+            let ls = u32_at(fixed, 12)?;
+            return Some(format!(
+                "{}.{}.{}.{}",
+                ms >> 16,
+                ms & 0xffff,
+                ls >> 16,
+                ls & 0xffff
+            ));
+        }
+
+        rest = &rest[end..];
+    }
+
+    None
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build an executable with nothing but the headers and a resource section holding a
+    /// version resource with the given version.
+    fn executable(version: [u16; 4]) -> Vec<u8> {
+        let mut resources = vec![0u8; 6];
+        resources.extend(
+            VERSION_INFO_KEY
+                .encode_utf16()
+                .chain([0])
+                .flat_map(u16::to_le_bytes),
+        );
+
+        resources.resize(resources.len().next_multiple_of(4), 0);
+
+        resources.extend(FIXED_FILE_INFO_SIGNATURE.to_le_bytes());
+        resources.extend(0x0001_0000u32.to_le_bytes());
+        resources.extend((u32::from(version[0]) << 16 | u32::from(version[1])).to_le_bytes());
+        resources.extend((u32::from(version[2]) << 16 | u32::from(version[3])).to_le_bytes());
+
+        let mut out = vec![0u8; 0x40];
+        out[..2].copy_from_slice(b"MZ");
+        out[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+
+        // The signature and a COFF header with two sections and no optional header.
+        out.extend(b"PE\0\0");
+        let mut coff = [0u8; 20];
+        coff[2..4].copy_from_slice(&2u16.to_le_bytes());
+        out.extend(coff);
+
+        let offset = (out.len() + 80) as u32;
+
+        let mut text = [0u8; 40];
+        text[..8].copy_from_slice(b".text\0\0\0");
+        out.extend(text);
+
+        let mut rsrc = [0u8; 40];
+        rsrc[..8].copy_from_slice(RESOURCES);
+        rsrc[16..20].copy_from_slice(&(resources.len() as u32).to_le_bytes());
+        rsrc[20..24].copy_from_slice(&offset.to_le_bytes());
+        out.extend(rsrc);
+
+        out.extend(resources);
+        out
+    }
+
+    #[test]
+    fn read_file_versions() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("GTA5.exe");
+
+        fs::write(&path, executable([1, 0, 3258, 0]))?;
+        assert_eq!(file_version(&path)?.as_deref(), Some("1.0.3258.0"));
+
+        // The section table points past the end of the file.
+        let mut truncated = executable([1, 0, 3258, 0]);
+        truncated.truncate(truncated.len() - 8);
+        fs::write(&path, truncated)?;
+        assert_eq!(file_version(&path)?, None);
+
+        fs::write(&path, b"#!/bin/sh\n")?;
+        assert_eq!(file_version(&path)?, None);
+
+        assert!(matches!(
+            file_version(&dir.path().join("missing.exe")),
+            Err(Error::Io { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,416 @@
+use crate::Error;
+use std::sync::atomic::AtomicBool;
+
+/// The name of the pipe the daemon listens on.
+pub const PIPE_NAME: &str = r"\\.\pipe\gtav-saveload";
+
+/// Listen on [PIPE_NAME] until `stop` is set, answering each line which is sent to it with the
+/// line `handle` returns for it.
+///
+/// One client is connected at a time, so commands never run at the same time, and the pipe can
+/// only be opened by the current user on this computer. Errors with [Error::Pipe] if another
+/// daemon is already listening.
+#[cfg(windows)]
+pub fn listen<F>(stop: &AtomicBool, mut handle: F) -> Result<(), Error>
+where
+    F: FnMut(&str) -> String,
+{
+    use std::{sync::atomic::Ordering, thread};
+
+    let pipe = imp::create()?;
+
+    while !stop.load(Ordering::SeqCst) {
+        if !imp::connect(&pipe)? {
+            thread::sleep(imp::POLL);
+            continue;
+        }
+
+        let result = imp::serve(&pipe, stop, &mut handle);
+        imp::disconnect(&pipe);
+
+        if let Err(e) = result {
+            eout!("warning: failed to answer a client: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// The daemon is only supported on Windows, so this always errors with
+/// [Error::DaemonUnsupported].
+#[cfg(not(windows))]
+pub fn listen<F>(_: &AtomicBool, _: F) -> Result<(), Error>
+where
+    F: FnMut(&str) -> String,
+{
+    Err(Error::DaemonUnsupported)
+}
+
+/// Send a line to the daemon listening on [PIPE_NAME], returning the line it answers with, or
+/// `None` if no daemon is listening. Waits for a while if it's busy with another client.
+#[cfg(windows)]
+pub fn send(line: &str) -> Result<Option<String>, Error> {
+    use std::{
+        fs,
+        io::{self, BufRead as _, BufReader, Write as _},
+        os::windows::io::FromRawHandle as _,
+        ptr, thread,
+        time::Instant,
+    };
+    use winapi::{
+        shared::winerror::{ERROR_FILE_NOT_FOUND, ERROR_PIPE_BUSY},
+        um::{
+            fileapi::{CreateFileW, OPEN_EXISTING},
+            handleapi::INVALID_HANDLE_VALUE,
+            winnt::{GENERIC_READ, GENERIC_WRITE},
+        },
+    };
+
+    let error = |e: io::Error| Error::Pipe {
+        reason: e.to_string(),
+    };
+
+    let name = imp::wide(PIPE_NAME);
+    let started = Instant::now();
+
+    let handle = loop {
+        let handle = unsafe {
+            CreateFileW(
+                name.as_ptr(),
+                GENERIC_READ | GENERIC_WRITE,
+                0,
+                ptr::null_mut(),
+                OPEN_EXISTING,
+                0,
+                ptr::null_mut(),
+            )
+        };
+
+        if handle != INVALID_HANDLE_VALUE {
+            break handle;
+        }
+
+        let e = io::Error::last_os_error();
+
+        match e.raw_os_error().map(|code| code as u32) {
+            Some(ERROR_FILE_NOT_FOUND) => return Ok(None),
+            Some(ERROR_PIPE_BUSY) if started.elapsed() < imp::BUSY_WAIT => thread::sleep(imp::POLL),
+            Some(ERROR_PIPE_BUSY) => {
+                return Err(Error::Pipe {
+                    reason: String::from("the daemon is busy with another command"),
+                })
+            }
+            _ => return Err(error(e)),
+        }
+    };
+
+    let mut file = unsafe { fs::File::from_raw_handle(handle as _) };
+    file.write_all(format!("{}\n", line).as_bytes())
+        .map_err(error)?;
+
+    let mut reply = String::new();
+    BufReader::new(file).read_line(&mut reply).map_err(error)?;
+
+    if reply.is_empty() {
+        return Err(Error::Pipe {
+            reason: String::from("the daemon closed the pipe without answering"),
+        });
+    }
+
+    Ok(Some(reply.trim_end().to_string()))
+}
+
+/// No daemon can be listening on other platforms than Windows, so this always returns `None`.
+#[cfg(not(windows))]
+pub fn send(_: &str) -> Result<Option<String>, Error> {
+    Ok(None)
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::PIPE_NAME;
+    use crate::Error;
+    use std::{
+        ffi::OsStr,
+        io, iter, mem,
+        os::windows::{
+            ffi::OsStrExt as _,
+            io::{AsRawHandle as _, FromRawHandle as _, OwnedHandle},
+        },
+        ptr,
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+        time::Duration,
+    };
+    use winapi::{
+        shared::{
+            minwindef::DWORD,
+            sddl::{
+                ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW,
+                SDDL_REVISION_1,
+            },
+            winerror::{
+                ERROR_ACCESS_DENIED, ERROR_BROKEN_PIPE, ERROR_NO_DATA, ERROR_PIPE_CONNECTED,
+                ERROR_PIPE_LISTENING,
+            },
+        },
+        um::{
+            fileapi::{ReadFile, WriteFile},
+            handleapi::INVALID_HANDLE_VALUE,
+            minwinbase::SECURITY_ATTRIBUTES,
+            namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe},
+            processthreadsapi::{GetCurrentProcess, OpenProcessToken},
+            securitybaseapi::GetTokenInformation,
+            winbase::{
+                LocalFree, FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX, PIPE_NOWAIT,
+                PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+            },
+            winnt::{TokenUser, HANDLE, PSECURITY_DESCRIPTOR, TOKEN_QUERY, TOKEN_USER},
+        },
+    };
+
+    /// How often the pipe is checked for clients and commands.
+    pub(super) const POLL: Duration = Duration::from_millis(50);
+
+    /// How long a client waits for the daemon to finish with another client.
+    pub(super) const BUSY_WAIT: Duration = Duration::from_secs(30);
+
+    /// The size of the buffers of the pipe.
+    const BUFFER: DWORD = 64 * 1024;
+
+    /// The longest line which is read, so that a misbehaving client can't use up memory.
+    const MAX_LINE: usize = 1024 * 1024;
+
+    pub(super) fn wide(s: &str) -> Vec<u16> {
+        OsStr::new(s).encode_wide().chain(iter::once(0)).collect()
+    }
+
+    fn pipe_error(e: io::Error) -> Error {
+        Error::Pipe {
+            reason: e.to_string(),
+        }
+    }
+
+    /// A security descriptor which only gives access to the current user.
+    struct SecurityDescriptor(PSECURITY_DESCRIPTOR);
+
+    impl SecurityDescriptor {
+        fn current_user() -> io::Result<Self> {
+            let mut token = ptr::null_mut();
+
+            if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let token = unsafe { OwnedHandle::from_raw_handle(token as _) };
+            let token = token.as_raw_handle() as HANDLE;
+
+            let mut size = 0;
+            unsafe { GetTokenInformation(token, TokenUser, ptr::null_mut(), 0, &mut size) };
+
+            // Aligned for the pointer at the start of TOKEN_USER.
+            let mut buffer = vec![0u64; size as usize / mem::size_of::<u64>() + 1];
+
+            let filled = unsafe {
+                GetTokenInformation(token, TokenUser, buffer.as_mut_ptr() as _, size, &mut size)
+            };
+
+            if filled == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let user = unsafe { &*(buffer.as_ptr() as *const TOKEN_USER) };
+            let mut sid = ptr::null_mut();
+
+            if unsafe { ConvertSidToStringSidW(user.User.Sid, &mut sid) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let sid_string = unsafe {
+                let len = (0..).take_while(|&i| *sid.add(i) != 0).count();
+                let sid_string = String::from_utf16_lossy(std::slice::from_raw_parts(sid, len));
+                LocalFree(sid as _);
+                sid_string
+            };
+
+            // Only allow the user, and don't inherit anything which allows anyone else.
+            let sddl = wide(&format!("D:P(A;;GA;;;{})", sid_string));
+            let mut descriptor = ptr::null_mut();
+
+            let converted = unsafe {
+                ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                    sddl.as_ptr(),
+                    SDDL_REVISION_1 as DWORD,
+                    &mut descriptor,
+                    ptr::null_mut(),
+                )
+            };
+
+            if converted == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(Self(descriptor))
+        }
+    }
+
+    impl Drop for SecurityDescriptor {
+        fn drop(&mut self) {
+            unsafe { LocalFree(self.0) };
+        }
+    }
+
+    /// Create the only instance of the pipe.
+    pub(super) fn create() -> Result<OwnedHandle, Error> {
+        let descriptor = SecurityDescriptor::current_user().map_err(pipe_error)?;
+
+        let mut attributes = SECURITY_ATTRIBUTES {
+            nLength: mem::size_of::<SECURITY_ATTRIBUTES>() as DWORD,
+            lpSecurityDescriptor: descriptor.0,
+            bInheritHandle: 0,
+        };
+
+        let name = wide(PIPE_NAME);
+
+        // Non-blocking, so that `stop` can be checked while waiting for clients and commands.
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_NOWAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                1,
+                BUFFER,
+                BUFFER,
+                0,
+                &mut attributes,
+            )
+        };
+
+        if handle == INVALID_HANDLE_VALUE {
+            let e = io::Error::last_os_error();
+
+            if e.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) {
+                return Err(Error::Pipe {
+                    reason: String::from("another daemon is already listening"),
+                });
+            }
+
+            return Err(pipe_error(e));
+        }
+
+        Ok(unsafe { OwnedHandle::from_raw_handle(handle as _) })
+    }
+
+    /// Check if a client has connected.
+    pub(super) fn connect(pipe: &OwnedHandle) -> Result<bool, Error> {
+        let handle = pipe.as_raw_handle() as HANDLE;
+
+        if unsafe { ConnectNamedPipe(handle, ptr::null_mut()) } != 0 {
+            return Ok(true);
+        }
+
+        let e = io::Error::last_os_error();
+
+        match e.raw_os_error().map(|code| code as u32) {
+            Some(ERROR_PIPE_CONNECTED) => Ok(true),
+            Some(ERROR_PIPE_LISTENING) => Ok(false),
+            // The client has already gone away.
+            Some(ERROR_NO_DATA) => {
+                disconnect(pipe);
+                Ok(false)
+            }
+            _ => Err(pipe_error(e)),
+        }
+    }
+
+    pub(super) fn disconnect(pipe: &OwnedHandle) {
+        unsafe { DisconnectNamedPipe(pipe.as_raw_handle() as HANDLE) };
+    }
+
+    /// Answer the lines sent by the connected client until it disconnects or `stop` is set.
+    pub(super) fn serve<F>(pipe: &OwnedHandle, stop: &AtomicBool, handle: &mut F) -> io::Result<()>
+    where
+        F: FnMut(&str) -> String,
+    {
+        let raw = pipe.as_raw_handle() as HANDLE;
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 4096];
+
+        while !stop.load(Ordering::SeqCst) {
+            let mut read = 0;
+
+            let result = unsafe {
+                ReadFile(
+                    raw,
+                    chunk.as_mut_ptr() as _,
+                    chunk.len() as DWORD,
+                    &mut read,
+                    ptr::null_mut(),
+                )
+            };
+
+            if result == 0 {
+                let e = io::Error::last_os_error();
+
+                match e.raw_os_error().map(|code| code as u32) {
+                    Some(ERROR_NO_DATA) => (),
+                    Some(ERROR_BROKEN_PIPE) => return Ok(()),
+                    _ => return Err(e),
+                }
+            }
+
+            if read == 0 {
+                thread::sleep(POLL);
+                continue;
+            }
+
+            buffer.extend_from_slice(&chunk[..read as usize]);
+
+            while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line = buffer.drain(..=end).collect::<Vec<_>>();
+                let line = String::from_utf8_lossy(&line);
+                let reply = format!("{}\n", handle(line.trim_end()));
+                write_all(raw, reply.as_bytes(), stop)?;
+            }
+
+            if buffer.len() > MAX_LINE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the line is too long",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write all of the given bytes, waiting for the client to read them if the buffer of the
+    /// pipe is full.
+    fn write_all(raw: HANDLE, mut bytes: &[u8], stop: &AtomicBool) -> io::Result<()> {
+        while !bytes.is_empty() && !stop.load(Ordering::SeqCst) {
+            let mut written = 0;
+
+            let result = unsafe {
+                WriteFile(
+                    raw,
+                    bytes.as_ptr() as _,
+                    bytes.len() as DWORD,
+                    &mut written,
+                    ptr::null_mut(),
+                )
+            };
+
+            if result == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            if written == 0 {
+                thread::sleep(POLL);
+            }
+
+            bytes = &bytes[written as usize..];
+        }
+
+        Ok(())
+    }
+}
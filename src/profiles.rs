@@ -0,0 +1,472 @@
+use crate::{game, steam, Error, SaveFilePatterns};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// The directory in Documents which holds the directories of every edition.
+const ROCKSTAR_GAMES: &str = "Rockstar Games";
+
+/// The name of the built-in definition of GTA V, which is used unless another game is picked.
+pub const DEFAULT_GAME: &str = "gtav";
+
+/// The keys which are understood in the definition of a game in the config file.
+pub(crate) const GAME_KEYS: &[&str] = &["documents", "prefix", "process"];
+
+/// A game which keeps its profiles in Documents like GTA V does, in
+/// `{documents}\Profiles\{profile}` with save files whose names start with `prefix`.
+///
+/// GTA V and Red Dead Redemption 2 are built in, and more can be defined in the config file
+/// under `[games.{name}]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct GameDef {
+    /// The name the game is picked with, like `rdr2`.
+    #[serde(skip)]
+    pub name: String,
+    /// The directory in Documents which holds `Profiles`, like
+    /// `Rockstar Games\Red Dead Redemption 2`. Both kinds of slashes separate directories.
+    pub documents: String,
+    /// What the names of the save files start with, like `SRDR`.
+    pub prefix: String,
+    /// The name of the process of the game, which save files aren't loaded while it's running.
+    pub process: String,
+    /// If the game has the directories of [Edition::ALL], which only GTA V does.
+    #[serde(skip)]
+    editions: bool,
+}
+
+impl GameDef {
+    /// The built-in definitions, GTA V first.
+    pub fn builtin() -> Vec<GameDef> {
+        vec![
+            GameDef {
+                name: String::from(DEFAULT_GAME),
+                documents: format!("{}\\GTA V", ROCKSTAR_GAMES),
+                prefix: String::from("SGTA"),
+                process: String::from(game::GAME_PROCESS),
+                editions: true,
+            },
+            GameDef {
+                name: String::from("rdr2"),
+                documents: format!("{}\\Red Dead Redemption 2", ROCKSTAR_GAMES),
+                prefix: String::from("SRDR"),
+                process: String::from("RDR2.exe"),
+                editions: false,
+            },
+        ]
+    }
+
+    /// Find the game with the given name among the ones defined in the config file and the
+    /// built-in ones, where definitions in the config file replace built-in ones with the same
+    /// name.
+    pub fn find(name: &str, custom: &BTreeMap<String, GameDef>) -> Result<GameDef, Error> {
+        if let Some(game) = custom.get(name) {
+            return Ok(GameDef {
+                name: name.to_string(),
+                ..game.clone()
+            });
+        }
+
+        let builtin = Self::builtin();
+
+        if let Some(game) = builtin.iter().find(|g| g.name == name) {
+            return Ok(game.clone());
+        }
+
+        let mut known = builtin.into_iter().map(|g| g.name).collect::<Vec<_>>();
+        known.extend(custom.keys().cloned());
+        known.sort();
+        known.dedup();
+
+        Err(Error::UnknownGame {
+            name: name.to_string(),
+            known,
+        })
+    }
+
+    /// The pattern of the names of save files unless others are given.
+    pub fn include(&self) -> String {
+        format!("{}*", self.prefix)
+    }
+
+    /// If the game is GTA V, which slots are stored like before other games were supported.
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT_GAME
+    }
+
+    /// If the game has more than one edition, which only GTA V does.
+    pub fn has_editions(&self) -> bool {
+        self.editions
+    }
+
+    /// Check that the definition can be used, which is that the save files can be matched and
+    /// the directory is in Documents.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        let invalid = |reason: &str| Error::InvalidGame {
+            name: self.name.clone(),
+            reason: reason.to_string(),
+        };
+
+        if self.prefix.is_empty() {
+            return Err(invalid("the prefix of save files is empty"));
+        }
+
+        if SaveFilePatterns::new(&[self.include()], &[] as &[&str]).is_err() {
+            return Err(invalid("the prefix of save files isn't a valid pattern"));
+        }
+
+        let path = self.documents_path();
+
+        if path.iter().next().is_none() {
+            return Err(invalid("documents is empty"));
+        }
+
+        let absolute = self.documents.starts_with(['/', '\\']) || self.documents.contains(':');
+
+        if absolute || path.iter().any(|p| p == "..") {
+            return Err(invalid("documents must be a directory in Documents"));
+        }
+
+        Ok(())
+    }
+
+    /// The directory in Documents which holds `Profiles`.
+    fn documents_path(&self) -> PathBuf {
+        self.documents
+            .split(['/', '\\'])
+            .filter(|part| !part.is_empty())
+            .collect()
+    }
+
+    /// The directories in Documents the given edition of the game might be in, in the order
+    /// they're searched.
+    fn dirs(&self, edition: Edition) -> Vec<PathBuf> {
+        if self.editions {
+            let rockstar = Path::new(ROCKSTAR_GAMES);
+            return edition.dirs().iter().map(|d| rockstar.join(d)).collect();
+        }
+
+        // Other games only have the one edition.
+        match edition {
+            Edition::Legacy => vec![self.documents_path()],
+            Edition::Enhanced => Vec::new(),
+        }
+    }
+}
+
+/// An edition of GTA V, each of which keeps its profiles in a directory of its own in
+/// Documents.
+///
+/// Save files of one edition can't be loaded by the other, so slots are kept apart by
+/// edition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Edition {
+    /// The original PC release, sold through the Rockstar Games Launcher, Steam and Epic Games,
+    /// in `Rockstar Games\GTA V`.
+    Legacy,
+    /// GTA V Enhanced, sold through the same launchers, in `Rockstar Games\GTAV Enhanced`.
+    Enhanced,
+}
+
+impl Edition {
+    /// Every edition, in the order their directories are searched.
+    pub const ALL: &'static [Edition] = &[Edition::Legacy, Edition::Enhanced];
+
+    /// The name of the edition, like `enhanced`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Edition::Legacy => "legacy",
+            Edition::Enhanced => "enhanced",
+        }
+    }
+
+    /// Parse the name of an edition, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|e| e.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The names the directory of the edition has in `Rockstar Games`, which differ between
+    /// launchers and releases, in the order they're searched.
+    fn dirs(self) -> &'static [&'static str] {
+        match self {
+            Edition::Legacy => &["GTA V"],
+            Edition::Enhanced => &["GTAV Enhanced", "GTA V Enhanced"],
+        }
+    }
+
+    /// The edition of the given GTA V directory, going by its name. Directories which aren't
+    /// named like those of any edition, like copies made for testing, are taken to be
+    /// [Edition::Legacy].
+    pub fn of(base: &Path) -> Self {
+        let name = base
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|e| e.dirs().iter().any(|d| d.eq_ignore_ascii_case(&name)))
+            .unwrap_or(Edition::Legacy)
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+/// The value of `--edition` which picks every edition.
+pub(crate) const ALL_EDITIONS: &str = "all";
+
+/// Parse the edition chosen with `--edition`, where `all` is `None`.
+pub fn parse_edition_choice(name: &str) -> Result<Option<Edition>, Error> {
+    match Edition::parse(name) {
+        Some(edition) => Ok(Some(edition)),
+        None if name.eq_ignore_ascii_case(ALL_EDITIONS) => Ok(None),
+        None => Err(Error::InvalidEdition {
+            name: name.to_string(),
+        }),
+    }
+}
+
+/// A GTA V directory with a `Profiles` directory in it, found in Documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameDir {
+    /// The edition the directory belongs to.
+    pub edition: Edition,
+    /// The directory, like `Documents\Rockstar Games\GTA V`.
+    pub path: PathBuf,
+    /// How the Documents folder it's in was found, like `the Known Folder API`.
+    pub method: &'static str,
+}
+
+/// Find the directory of GTA V in the user's Documents folder.
+///
+/// The Documents folder is resolved through the Known Folder API, which respects folder
+/// redirection like OneDrive. If that doesn't contain the GTA V directory,
+/// `%USERPROFILE%\Documents` is probed as well. Elsewhere, the Documents folder in the Proton
+/// prefix of the game is probed in every Steam library, like
+/// `~/.steam/steam/steamapps/compatdata/271590/pfx/drive_c/users/steamuser/Documents`. If the
+/// directories of several editions are found, the one of [Edition::Legacy] is picked.
+pub fn find_default_base() -> Result<PathBuf, Error> {
+    let game = GameDef::find(DEFAULT_GAME, &BTreeMap::new())?;
+    Ok(find_game_dirs(&game, None)?.remove(0).path)
+}
+
+/// Find the directory of every edition of the given game in the user's Documents folder like
+/// [find_default_base], or only the one of `edition` if it's given, in the order of
+/// [Edition::ALL]. Games other than GTA V only have [Edition::Legacy].
+///
+/// Errors if none of them have any profiles.
+pub fn find_game_dirs(game: &GameDef, edition: Option<Edition>) -> Result<Vec<GameDir>, Error> {
+    let mut candidates = Vec::new();
+
+    if let Some(documents) = dirs::document_dir() {
+        let method = if cfg!(windows) {
+            "the Known Folder API"
+        } else {
+            "the XDG user directories"
+        };
+
+        candidates.push((documents, method));
+    }
+
+    if let Some(profile) = env::var_os("USERPROFILE") {
+        let documents = PathBuf::from(profile).join("Documents");
+
+        if !candidates.iter().any(|(c, _)| *c == documents) {
+            candidates.push((documents, "%USERPROFILE%\\Documents"));
+        }
+    }
+
+    // The game runs through Proton everywhere else, in a Wine prefix of its own.
+    if cfg!(not(windows)) {
+        if let Some(home) = dirs::home_dir() {
+            for documents in steam::proton_documents_dirs(&home) {
+                if !candidates.iter().any(|(c, _)| *c == documents) {
+                    candidates.push((documents, "the Proton prefix in Steam"));
+                }
+            }
+        }
+    }
+
+    if candidates.is_empty() {
+        return Err(Error::NoDocuments);
+    }
+
+    let editions = Edition::ALL
+        .iter()
+        .copied()
+        .filter(|e| edition.is_none_or(|edition| *e == edition));
+
+    let mut found = Vec::<GameDir>::new();
+    let mut probed = Vec::new();
+
+    for edition in editions {
+        // The first directory found for each edition is used, like the one in OneDrive.
+        'edition: for (documents, method) in &candidates {
+            for dir in game.dirs(edition) {
+                let path = documents.join(dir);
+
+                if path.join("Profiles").is_dir() {
+                    debug!("found {} {} through {}", edition, path.display(), method);
+                    found.push(GameDir {
+                        edition,
+                        path,
+                        method,
+                    });
+                    break 'edition;
+                }
+
+                trace!(
+                    "no profiles in {}, found through {}",
+                    path.display(),
+                    method
+                );
+                probed.push(path);
+            }
+        }
+    }
+
+    if found.is_empty() {
+        return Err(Error::NoProfiles { searched: probed });
+    }
+
+    Ok(found)
+}
+
+/// List all profile directories in the given GTA V directory, sorted by name.
+///
+/// Errors if there are no profiles.
+pub fn list_profiles(base: &Path) -> Result<Vec<PathBuf>, Error> {
+    let profiles = base.join("Profiles");
+
+    if !profiles.is_dir() {
+        return Err(Error::NoProfiles {
+            searched: vec![base.to_owned()],
+        });
+    }
+
+    let mut existing_profiles = Vec::new();
+
+    debug!("read directory {}", profiles.display());
+
+    for entry in fs::read_dir(&profiles).map_err(|e| Error::io(&profiles, e))? {
+        let entry = entry.map_err(|e| Error::io(&profiles, e))?;
+        let path = entry.path();
+
+        if path.is_dir() {
+            debug!("found profile {}", path.display());
+            existing_profiles.push(path);
+        } else {
+            trace!("skipped {}, which isn't a directory", path.display());
+        }
+    }
+
+    existing_profiles.sort();
+
+    if existing_profiles.is_empty() {
+        return Err(Error::NoProfiles {
+            searched: vec![profiles],
+        });
+    }
+
+    Ok(existing_profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editions_of_directories() {
+        let documents = Path::new("Documents").join(ROCKSTAR_GAMES);
+
+        assert_eq!(Edition::of(&documents.join("GTA V")), Edition::Legacy);
+        assert_eq!(
+            Edition::of(&documents.join("GTAV Enhanced")),
+            Edition::Enhanced
+        );
+        assert_eq!(
+            Edition::of(&documents.join("GTA V Enhanced")),
+            Edition::Enhanced
+        );
+        assert_eq!(Edition::of(Path::new("copy of my saves")), Edition::Legacy);
+
+        assert_eq!(
+            parse_edition_choice("Enhanced").ok(),
+            Some(Some(Edition::Enhanced))
+        );
+        assert_eq!(parse_edition_choice("all").ok(), Some(None));
+        assert!(matches!(
+            parse_edition_choice("next-gen"),
+            Err(Error::InvalidEdition { .. })
+        ));
+    }
+
+    #[test]
+    fn game_definitions() -> Result<(), Box<dyn std::error::Error>> {
+        let none = BTreeMap::new();
+
+        let gtav = GameDef::find(DEFAULT_GAME, &none)?;
+        assert_eq!(gtav.include(), "SGTA*");
+        assert_eq!(
+            gtav.dirs(Edition::Enhanced),
+            vec![
+                Path::new(ROCKSTAR_GAMES).join("GTAV Enhanced"),
+                Path::new(ROCKSTAR_GAMES).join("GTA V Enhanced"),
+            ]
+        );
+
+        let rdr2 = GameDef::find("rdr2", &none)?;
+        assert_eq!(rdr2.include(), "SRDR*");
+        assert_eq!(
+            rdr2.dirs(Edition::Legacy),
+            vec![Path::new(ROCKSTAR_GAMES).join("Red Dead Redemption 2")]
+        );
+        assert!(rdr2.dirs(Edition::Enhanced).is_empty());
+
+        let custom = BTreeMap::from([(
+            String::from("mp3"),
+            GameDef {
+                name: String::new(),
+                documents: String::from("Rockstar Games/Max Payne 3"),
+                prefix: String::from("SMP3"),
+                process: String::from("MaxPayne3.exe"),
+                editions: false,
+            },
+        )]);
+
+        let mp3 = GameDef::find("mp3", &custom)?;
+        assert_eq!(mp3.name, "mp3");
+        mp3.check()?;
+
+        assert!(matches!(
+            GameDef::find("gta6", &custom),
+            Err(Error::UnknownGame { ref known, .. }) if known == &["gtav", "mp3", "rdr2"]
+        ));
+
+        for documents in &["", "..\\Other", "/Rockstar Games", "C:\\Games"] {
+            let game = GameDef {
+                documents: documents.to_string(),
+                ..mp3.clone()
+            };
+
+            assert!(
+                matches!(game.check(), Err(Error::InvalidGame { .. })),
+                "{}",
+                documents
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use crate::output::{self, ProgressStyle};
+use std::{
+    io::{self, Write as _},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How long copying goes on before progress is shown, so that quick copies don't flicker.
+const DELAY: Duration = Duration::from_millis(250);
+
+/// How often the progress bar is redrawn.
+const BAR_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often a line is written to the log file.
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The width of the progress bar, in characters.
+const BAR_WIDTH: usize = 30;
+
+/// The width of the line with the progress bar, which is padded to clear what it replaces.
+const LINE_WIDTH: usize = 79;
+
+/// The progress of copying a number of files, which can be updated from multiple threads.
+#[derive(Debug)]
+pub(crate) struct Progress {
+    style: ProgressStyle,
+    /// What's done to the files, like `copied`.
+    verb: &'static str,
+    files: usize,
+    total: u64,
+    started: Instant,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    files: usize,
+    bytes: u64,
+    /// When progress was last shown, if it has been.
+    shown: Option<Instant>,
+}
+
+impl Progress {
+    /// Start tracking the progress of doing what `verb` says, like `copied`, to `files` files of
+    /// `total` bytes, shown in the style set with [output::show_progress]. Returns `None` if
+    /// progress isn't shown.
+    pub(crate) fn start(verb: &'static str, files: usize, total: u64) -> Option<Arc<Self>> {
+        match output::progress_style() {
+            ProgressStyle::Hidden => None,
+            style => Some(Arc::new(Self::new(style, verb, files, total))),
+        }
+    }
+
+    /// Track the progress of doing what `verb` says, shown in the given style.
+    pub(crate) fn new(style: ProgressStyle, verb: &'static str, files: usize, total: u64) -> Self {
+        Self {
+            style,
+            verb,
+            files,
+            total,
+            started: Instant::now(),
+            state: Mutex::new(State {
+                files: 0,
+                bytes: 0,
+                shown: None,
+            }),
+        }
+    }
+
+    /// Count the given number of bytes as copied.
+    pub(crate) fn add(&self, bytes: u64) {
+        self.update(|state| state.bytes += bytes);
+    }
+
+    /// The number of bytes which have been copied.
+    #[cfg(test)]
+    pub(crate) fn bytes(&self) -> u64 {
+        self.state.lock().map(|s| s.bytes).unwrap_or_default()
+    }
+
+    /// Count a file as done.
+    pub(crate) fn file_done(&self) {
+        self.update(|state| state.files += 1);
+    }
+
+    /// Stop showing progress, clearing the progress bar if it was drawn.
+    pub(crate) fn finish(&self) {
+        let state = match self.state.lock() {
+            Ok(state) => state,
+            Err(..) => return,
+        };
+
+        if state.shown.is_none() {
+            return;
+        }
+
+        match self.style {
+            ProgressStyle::Bar => {
+                let _ = write!(io::stderr(), "\r{:width$}\r", "", width = LINE_WIDTH);
+            }
+            ProgressStyle::Log => output::log(format_args!("{}", self.describe(&state))),
+            ProgressStyle::Hidden => (),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(..) => return,
+        };
+
+        f(&mut state);
+
+        let interval = match self.style {
+            ProgressStyle::Hidden => return,
+            ProgressStyle::Bar => BAR_INTERVAL,
+            ProgressStyle::Log => LOG_INTERVAL,
+        };
+
+        let now = Instant::now();
+
+        let due = match state.shown {
+            Some(shown) => now.duration_since(shown) >= interval,
+            None => now.duration_since(self.started) >= DELAY,
+        };
+
+        if !due {
+            return;
+        }
+
+        state.shown = Some(now);
+
+        match self.style {
+            ProgressStyle::Bar => {
+                let line = format!("\r{} {}", self.bar(&state), self.describe(&state));
+                let mut stderr = io::stderr();
+                let _ = write!(stderr, "{:width$}", line, width = LINE_WIDTH);
+                let _ = stderr.flush();
+            }
+            ProgressStyle::Log => output::log(format_args!("{}", self.describe(&state))),
+            ProgressStyle::Hidden => (),
+        }
+    }
+
+    /// Describe the progress, like `copied 1/2 files, 512.0 KiB of 1.0 MiB`.
+    fn describe(&self, state: &State) -> String {
+        format!(
+            "{} {}/{} files, {} of {}",
+            self.verb,
+            state.files,
+            self.files,
+            output::size(state.bytes),
+            output::size(self.total)
+        )
+    }
+
+    /// Draw the progress bar, like `[=====>    ]`.
+    fn bar(&self, state: &State) -> String {
+        let filled = match self.total {
+            0 => BAR_WIDTH,
+            total => (state.bytes.min(total) as f64 / total as f64 * BAR_WIDTH as f64) as usize,
+        };
+
+        let mut bar = "=".repeat(filled);
+
+        if filled < BAR_WIDTH {
+            bar.push('>');
+        }
+
+        format!("[{:width$}]", bar, width = BAR_WIDTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_progress() {
+        let progress = Progress::new(ProgressStyle::Hidden, "copied", 2, 2 * 1024 * 1024);
+        progress.add(512 * 1024);
+        progress.file_done();
+
+        let state = progress.state.lock().expect("not poisoned");
+        assert_eq!(
+            progress.describe(&state),
+            "copied 1/2 files, 512.0 KiB of 2.0 MiB"
+        );
+        assert_eq!(progress.bar(&state), format!("[{:30}]", "=======>"));
+    }
+}
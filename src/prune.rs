@@ -0,0 +1,293 @@
+use crate::{Error, Slot};
+use chrono::{
+    format::{Item, StrftimeItems},
+    Datelike as _, Local, NaiveDate, NaiveDateTime, TimeZone as _,
+};
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    time::{Duration, SystemTime},
+};
+
+/// Prefix of the slots created by [crate::SaveManager::save_dated] before their manifest recorded
+/// it, which are still considered for pruning.
+pub(crate) const DATED_PREFIX: &str = "dated-";
+/// The default `strftime` format of the local time in the names of dated slots, see
+/// [crate::SaveManager::date_format].
+pub const DATED_FORMAT: &str = "%Y-%m-%d_%H%M%S";
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Which dated slots to keep when pruning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    /// Keep the given number of newest slots.
+    Newest(usize),
+    /// Keep the slots modified within the given number of days.
+    Days(u64),
+    /// Keep every slot from the last 24 hours, the newest slot of each day for the last week,
+    /// of each week for the last month, and of each month before that.
+    Policy,
+}
+
+/// The bucket of a slot under [Keep::Policy], where only the newest slot in each is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Bucket {
+    /// Slots from the last 24 hours, which all get their own bucket.
+    Recent(usize),
+    Day(NaiveDate),
+    Week(i32, u32),
+    Month(i32, u32),
+}
+
+/// Error if the given `strftime` format of the time in dated slot names isn't valid.
+pub(crate) fn check_date_format(format: &str) -> Result<(), Error> {
+    let invalid = format.is_empty() || StrftimeItems::new(format).any(|i| i == Item::Error);
+
+    if invalid {
+        return Err(Error::InvalidDateFormat {
+            format: format.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Select the slots to prune, given slots sorted newest first.
+///
+/// [Keep::Policy] goes by the time in the name of each slot instead, parsed with `format`, and
+/// returns the slots sorted by it.
+pub(crate) fn select(slots: Vec<Slot>, keep: Keep, now: SystemTime, format: &str) -> Vec<Slot> {
+    match keep {
+        Keep::Newest(n) => slots.into_iter().skip(n).collect(),
+        Keep::Days(days) => {
+            let cutoff = now
+                .checked_sub(Duration::from_secs(days * DAY))
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+
+            slots.into_iter().filter(|s| s.modified < cutoff).collect()
+        }
+        Keep::Policy => select_policy(slots, now, format),
+    }
+}
+
+fn select_policy(slots: Vec<Slot>, now: SystemTime, format: &str) -> Vec<Slot> {
+    let mut slots = slots
+        .into_iter()
+        .map(|s| (saved_at(&s, format), s))
+        .collect::<Vec<_>>();
+
+    // Newest first, so that the newest slot in each bucket is the one kept.
+    slots.sort_by_key(|(saved, _)| Reverse(*saved));
+
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+
+    for (index, (saved, slot)) in slots.into_iter().enumerate() {
+        if !seen.insert(bucket(index, saved, now)) {
+            out.push(slot);
+        }
+    }
+
+    out
+}
+
+/// When a dated slot was saved according to its name, falling back to when it was modified.
+///
+/// Slots saved before the format was changed from the default are still recognized, as are
+/// slots with a suffix like `-2` since another one was saved in the same second.
+pub(crate) fn saved_at(slot: &Slot, format: &str) -> SystemTime {
+    let parse = |time: &str| {
+        NaiveDateTime::parse_from_str(time, format)
+            .or_else(|_| NaiveDateTime::parse_from_str(time, DATED_FORMAT))
+            .ok()
+    };
+
+    let without_suffix = |time: &str| {
+        let (time, suffix) = time.rsplit_once('-')?;
+        suffix.parse::<u32>().ok()?;
+        parse(time)
+    };
+
+    let parsed = slot
+        .name
+        .strip_prefix(DATED_PREFIX)
+        .and_then(|time| parse(time).or_else(|| without_suffix(time)))
+        .and_then(|time| Local.from_local_datetime(&time).earliest());
+
+    match parsed {
+        Some(time) => time.into(),
+        None => slot.modified,
+    }
+}
+
+fn bucket(index: usize, saved: SystemTime, now: SystemTime) -> Bucket {
+    // Slots from the future are treated as recent.
+    let age = now.duration_since(saved).unwrap_or_default().as_secs();
+    let date = chrono::DateTime::<Local>::from(saved).date().naive_local();
+
+    if age < DAY {
+        Bucket::Recent(index)
+    } else if age < 7 * DAY {
+        Bucket::Day(date)
+    } else if age < 30 * DAY {
+        let week = date.iso_week();
+        Bucket::Week(week.year(), week.week())
+    } else {
+        Bucket::Month(date.year(), date.month())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn slot(name: &str, modified: SystemTime) -> Slot {
+        Slot {
+            name: name.to_string(),
+            path: PathBuf::from(name),
+            modified,
+            files: 1,
+            size: 1,
+            note: None,
+            tags: Vec::new(),
+            title: None,
+            pinned: false,
+            save_numbers: vec![0],
+            suspicious: Vec::new(),
+            dated: true,
+            game_version: None,
+            headers: Vec::new(),
+        }
+    }
+
+    fn names(slots: Vec<Slot>) -> Vec<String> {
+        slots.into_iter().map(|s| s.name).collect()
+    }
+
+    #[test]
+    fn select_newest_and_days() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100 * DAY);
+        let slots = || {
+            vec![
+                slot("a", now - Duration::from_secs(DAY / 2)),
+                slot("b", now - Duration::from_secs(2 * DAY)),
+                slot("c", now - Duration::from_secs(10 * DAY)),
+            ]
+        };
+
+        assert_eq!(
+            names(select(slots(), Keep::Newest(1), now, DATED_FORMAT)),
+            vec!["b", "c"]
+        );
+        assert!(select(slots(), Keep::Newest(5), now, DATED_FORMAT).is_empty());
+        assert_eq!(
+            names(select(slots(), Keep::Newest(0), now, DATED_FORMAT)).len(),
+            3
+        );
+
+        assert_eq!(
+            names(select(slots(), Keep::Days(1), now, DATED_FORMAT)),
+            vec!["b", "c"]
+        );
+        assert_eq!(
+            names(select(slots(), Keep::Days(7), now, DATED_FORMAT)),
+            vec!["c"]
+        );
+        assert!(select(slots(), Keep::Days(1000), now, DATED_FORMAT).is_empty());
+    }
+
+    fn local(y: i32, m: u32, d: u32, h: u32) -> SystemTime {
+        Local
+            .ymd_opt(y, m, d)
+            .and_hms_opt(h, 0, 0)
+            .earliest()
+            .expect("valid local time")
+            .into()
+    }
+
+    fn dated(y: i32, m: u32, d: u32, h: u32) -> Slot {
+        let name = format!("dated-{:04}-{:02}-{:02}_{:02}0000", y, m, d, h);
+        // The modification time is ignored when the name can be parsed.
+        slot(&name, SystemTime::UNIX_EPOCH)
+    }
+
+    #[test]
+    fn select_by_policy() {
+        let now = local(2024, 3, 31, 12);
+
+        let slots = vec![
+            // Within the last 24 hours, all kept.
+            dated(2024, 3, 31, 11),
+            dated(2024, 3, 31, 1),
+            dated(2024, 3, 30, 20),
+            // One per day for the last week.
+            dated(2024, 3, 29, 18),
+            dated(2024, 3, 29, 9),
+            dated(2024, 3, 26, 10),
+            // One per week for the last month.
+            dated(2024, 3, 20, 12),
+            dated(2024, 3, 19, 12),
+            dated(2024, 3, 10, 12),
+            // One per month before that.
+            dated(2024, 2, 20, 12),
+            dated(2024, 2, 5, 12),
+            dated(2023, 11, 1, 12),
+            slot("dated-unparsable", local(2023, 11, 15, 12)),
+        ];
+
+        assert_eq!(
+            names(select(slots, Keep::Policy, now, DATED_FORMAT)),
+            vec![
+                "dated-2024-03-29_090000",
+                "dated-2024-03-19_120000",
+                "dated-2024-02-05_120000",
+                "dated-2023-11-01_120000",
+            ]
+        );
+    }
+
+    #[test]
+    fn select_by_policy_in_any_order() {
+        let now = local(2024, 3, 31, 12);
+
+        // The newest slot in a bucket is kept even if it's listed last.
+        let slots = vec![
+            dated(2024, 1, 2, 12),
+            slot("dated-unparsable", local(2024, 1, 20, 12)),
+            dated(2024, 1, 10, 12),
+        ];
+
+        assert_eq!(
+            names(select(slots, Keep::Policy, now, DATED_FORMAT)),
+            vec!["dated-2024-01-10_120000", "dated-2024-01-02_120000"]
+        );
+
+        assert!(select(Vec::new(), Keep::Policy, now, DATED_FORMAT).is_empty());
+    }
+
+    #[test]
+    fn select_by_policy_with_custom_format() {
+        let now = local(2024, 3, 31, 12);
+
+        // Slots named with the default format before it was changed are still recognized.
+        let slots = vec![
+            slot("dated-20240110-1200", SystemTime::UNIX_EPOCH),
+            dated(2024, 1, 2, 12),
+        ];
+
+        assert_eq!(
+            names(select(slots, Keep::Policy, now, "%Y%m%d-%H%M")),
+            vec!["dated-2024-01-02_120000"]
+        );
+    }
+
+    #[test]
+    fn date_formats() {
+        assert!(check_date_format(DATED_FORMAT).is_ok());
+        assert!(check_date_format("%d.%m.%Y").is_ok());
+        assert!(check_date_format("%Q").is_err());
+        assert!(check_date_format("").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use crate::Error;
+use std::path::Path;
+
+/// If there's a Recycle Bin on this platform, otherwise files are always deleted permanently.
+pub(crate) const SUPPORTED: bool = cfg!(windows);
+
+/// Move the file or directory at the given path to the Recycle Bin.
+///
+/// Fails if the drive doesn't have a Recycle Bin, like network drives, instead of silently
+/// deleting it permanently like the shell does.
+#[cfg(windows)]
+pub(crate) fn recycle(path: &Path) -> Result<(), Error> {
+    use std::{iter, mem, os::windows::ffi::OsStrExt as _};
+    use winapi::um::shellapi::{
+        SHFileOperationW, SHQueryRecycleBinW, FOF_ALLOWUNDO, FOF_NOCONFIRMATION, FOF_NOERRORUI,
+        FOF_SILENT, FO_DELETE, SHFILEOPSTRUCTW, SHQUERYRBINFO,
+    };
+
+    let error = |reason: String| Error::Recycle {
+        path: path.to_owned(),
+        reason,
+    };
+
+    // The shell doesn't know about the working directory of the process.
+    let absolute = std::path::absolute(path).map_err(|e| Error::io(path, e))?;
+    let root = absolute.ancestors().last().unwrap_or(&absolute);
+
+    let root_wide = root
+        .as_os_str()
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect::<Vec<_>>();
+
+    let mut info: SHQUERYRBINFO = unsafe { mem::zeroed() };
+    info.cbSize = mem::size_of::<SHQUERYRBINFO>() as u32;
+
+    if unsafe { SHQueryRecycleBinW(root_wide.as_ptr(), &mut info) } < 0 {
+        return Err(error(format!("{} has no recycle bin", root.display())));
+    }
+
+    // A list of paths, terminated by an empty one.
+    let from = absolute
+        .as_os_str()
+        .encode_wide()
+        .chain(iter::repeat_n(0, 2))
+        .collect::<Vec<_>>();
+
+    let mut op: SHFILEOPSTRUCTW = unsafe { mem::zeroed() };
+    op.wFunc = FO_DELETE.into();
+    op.pFrom = from.as_ptr();
+    op.fFlags = FOF_ALLOWUNDO | FOF_NOCONFIRMATION | FOF_NOERRORUI | FOF_SILENT;
+
+    let code = unsafe { SHFileOperationW(&mut op) };
+
+    if code != 0 {
+        return Err(error(format!("shell error {:#x}", code)));
+    }
+
+    if op.fAnyOperationsAborted != 0 {
+        return Err(error("aborted".to_string()));
+    }
+
+    Ok(())
+}
+
+/// There's no Recycle Bin to use on other platforms.
+#[cfg(not(windows))]
+pub(crate) fn recycle(path: &Path) -> Result<(), Error> {
+    Err(Error::Recycle {
+        path: path.to_owned(),
+        reason: "not supported on this platform".to_string(),
+    })
+}
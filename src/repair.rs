@@ -0,0 +1,193 @@
+use crate::{
+    compress,
+    files::{self, list_save_files},
+    hash::{self, FileHash},
+    manifest::{self, ManifestFile, SlotManifest},
+    ops::{FsOp, Ops},
+    Error,
+};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+/// Prefix of the copy which replaces a broken save file, which mustn't look like a save file if
+/// it's left behind.
+const TEMPORARY_PREFIX: &str = ".repair-";
+
+/// A save file which [crate::SaveManager::repair] replaced with an identical copy from another
+/// slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairedFile {
+    /// The name of the save file, like `SGTA00000`.
+    pub name: String,
+    /// The name of the slot it was copied from.
+    pub donor: String,
+}
+
+/// What [crate::SaveManager::repair] did to a slot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SlotRepair {
+    /// The save files which were replaced, in order of their names.
+    pub repaired: Vec<RepairedFile>,
+    /// The names of the save files which don't match the manifest, and which no other slot has
+    /// an identical copy of.
+    pub unrepaired: Vec<String>,
+    /// Every other way the slot doesn't match its manifest once it was repaired, like save files
+    /// which aren't in the manifest. Always empty for a dry run, since nothing is checked again.
+    pub problems: Vec<String>,
+}
+
+/// Replace every save file in `slot` which doesn't match `manifest` with a copy of a save file
+/// in one of the `donors` which does, in the order they're given.
+///
+/// The donors are only ever read. Each copy is checked against the manifest before it replaces
+/// the broken save file, and the slot is checked against it again once it's repaired.
+pub(crate) fn repair(
+    ops: &Ops,
+    slot: &Path,
+    manifest: &SlotManifest,
+    donors: &[(String, PathBuf)],
+) -> Result<SlotRepair, Error> {
+    let mut save_files = list_save_files(slot, &ops.patterns)?;
+    save_files.sort();
+
+    let mut hashes = HashMap::new();
+    let mut out = SlotRepair::default();
+
+    for expected in &manifest.files {
+        ops.check_cancelled()?;
+
+        let current = save_files
+            .iter()
+            .find(|(n, _)| n.to_string_lossy() == expected.name)
+            .map(|(_, path)| path);
+
+        if let Some(path) = current {
+            if matches(expected, &hash::hash_file(path)?) {
+                continue;
+            }
+        }
+
+        match find_donor(ops, expected, donors, &mut hashes)? {
+            Some((donor, path)) => {
+                replace(ops, slot, expected, current.map(PathBuf::as_path), path)?;
+
+                out.repaired.push(RepairedFile {
+                    name: expected.name.clone(),
+                    donor: donor.to_string(),
+                });
+            }
+            None => out.unrepaired.push(expected.name.clone()),
+        }
+    }
+
+    if !ops.dry_run {
+        out.problems = manifest::check(slot, manifest, &ops.patterns)?
+            .into_iter()
+            .filter(|p| {
+                !out.unrepaired.iter().any(|n| {
+                    p.strip_prefix(n.as_str())
+                        .is_some_and(|p| p.starts_with(':'))
+                })
+            })
+            .collect();
+    }
+
+    Ok(out)
+}
+
+/// Find a save file in the donors which matches what the manifest expects.
+///
+/// Only files with the expected size are hashed, and each of them only once.
+fn find_donor<'a>(
+    ops: &Ops,
+    expected: &ManifestFile,
+    donors: &'a [(String, PathBuf)],
+    hashes: &mut HashMap<PathBuf, FileHash>,
+) -> Result<Option<(&'a str, PathBuf)>, Error> {
+    for (donor, dir) in donors {
+        let mut save_files = list_save_files(dir, &ops.patterns)?;
+        save_files.sort();
+
+        // A save file with the same name is most likely to be the same one.
+        save_files.sort_by_key(|(n, _)| n.to_string_lossy() != expected.name);
+
+        for (_, path) in save_files {
+            ops.check_cancelled()?;
+
+            let hash = match hashes.get(&path) {
+                Some(hash) => *hash,
+                None => {
+                    if compress::size(&path)? != expected.size {
+                        continue;
+                    }
+
+                    let hash = hash::hash_file(&path)?;
+                    hashes.insert(path.clone(), hash);
+                    hash
+                }
+            };
+
+            if matches(expected, &hash) {
+                return Ok(Some((donor, path)));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Replace the broken save file in `slot`, if there is one, with a copy of `donor`.
+///
+/// The copy keeps the name of the donor, since it might be compressed when the broken file
+/// isn't or the other way around.
+fn replace(
+    ops: &Ops,
+    slot: &Path,
+    expected: &ManifestFile,
+    broken: Option<&Path>,
+    donor: PathBuf,
+) -> Result<(), Error> {
+    let name = donor
+        .file_name()
+        .map_or_else(|| OsString::from(&expected.name), OsString::from);
+
+    let mut temporary = OsString::from(TEMPORARY_PREFIX);
+    temporary.push(&name);
+    let temporary = slot.join(temporary);
+
+    if temporary.is_file() {
+        ops.run(FsOp::RemoveFile(&temporary))?;
+    }
+
+    ops.run(FsOp::Copy(&donor, &temporary))?;
+
+    if ops.preserve_times && !ops.dry_run {
+        files::copy_times(&donor, &temporary)?;
+    }
+
+    // The donor could have changed since it was hashed.
+    if !ops.dry_run && !matches(expected, &hash::hash_file(&temporary)?) {
+        ops.run(FsOp::RemoveFile(&temporary))?;
+
+        return Err(Error::Corrupt {
+            from: donor,
+            to: temporary,
+            reason: String::from("the copy doesn't match the manifest"),
+        });
+    }
+
+    if let Some(broken) = broken {
+        if broken.file_name() != Some(name.as_os_str()) {
+            ops.run(FsOp::RemoveFile(broken))?;
+        }
+    }
+
+    ops.run(FsOp::Rename(&temporary, &slot.join(&name)))
+}
+
+fn matches(expected: &ManifestFile, hash: &FileHash) -> bool {
+    expected.size == hash.size && expected.sha256 == hash.to_string()
+}
@@ -0,0 +1,193 @@
+use crate::{
+    cache::HashCache,
+    compress,
+    files::{self, list_save_files, list_settings_files, to_extended_path, STAGING},
+    hash::FileHash,
+    hydrate, manifest,
+    ops::{FsOp, Ops},
+    space, Error,
+};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// How many files were reused from the previous slot, and how many were copied.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Reused {
+    pub(crate) reused: usize,
+    pub(crate) copied: usize,
+}
+
+/// A file to put into the new slot.
+struct Planned {
+    name: OsString,
+    path: PathBuf,
+    hash: Option<FileHash>,
+    /// The identical file in the previous slot, which is linked to instead of copying `path`.
+    reuse: Option<PathBuf>,
+}
+
+/// Copy the files in `from` into the empty slot `to` like [files::copy_save_files], except that
+/// save files which are identical to the ones in `previous` are hard linked to them instead of
+/// being copied again.
+///
+/// A save file in `previous` is only reused if its manifest says it has the same contents, it
+/// was modified at the same time as the one in `from`, and it's compressed exactly when
+/// `compress` is set. Files are staged inside of `to` first, so that `to` never ends up with
+/// only some of them, and the manifest of `to` is written from the hashes which are already
+/// known.
+pub(crate) fn copy_reusing(
+    ops: &Ops,
+    cache: &mut HashCache,
+    from: &Path,
+    previous: &Path,
+    to: &Path,
+    compress: bool,
+    kind: Option<&str>,
+) -> Result<Reused, Error> {
+    let old_manifest = manifest::read(previous).ok().flatten();
+    let old_files = list_save_files(previous, &ops.patterns)?;
+
+    let mut save_files = files::list_selected(ops, from)?;
+    save_files.sort();
+
+    let mut planned = Vec::new();
+
+    for (name, path) in save_files {
+        ops.check_cancelled()?;
+        hydrate::ensure_local(ops, &path)?;
+        let hash = cache.hash(&path)?;
+
+        let reuse = old_files
+            .iter()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, old)| {
+                let expected = old_manifest
+                    .as_ref()?
+                    .files
+                    .iter()
+                    .find(|f| name.to_string_lossy() == f.name)?;
+
+                let unchanged = expected.size == hash.size
+                    && expected.sha256 == hash.to_string()
+                    && compress::is_compressed(old) == compress
+                    && modified(old).ok()? == modified(&path).ok()?;
+
+                unchanged.then(|| old.clone())
+            });
+
+        planned.push(Planned {
+            name,
+            path,
+            hash: Some(hash),
+            reuse,
+        });
+    }
+
+    if ops.settings {
+        let mut settings = list_settings_files(from)?;
+        settings.sort();
+
+        for (name, path) in settings {
+            planned.push(Planned {
+                name,
+                path,
+                hash: None,
+                reuse: None,
+            });
+        }
+    }
+
+    let mut needed = 0;
+
+    for file in planned.iter().filter(|f| f.reuse.is_none()) {
+        needed += compress::size(&file.path)?;
+    }
+
+    space::check(to, needed)?;
+
+    let staging = to.join(STAGING);
+
+    if staging.is_dir() {
+        ops.run(FsOp::RemoveDirAll(&staging))?;
+    }
+
+    ops.run(FsOp::CreateDir(&staging))?;
+
+    let result = stage(ops, &staging, &planned).and_then(|(staged, reused)| {
+        ops.check_cancelled()?;
+
+        for name in &staged {
+            ops.run(FsOp::Rename(&staging.join(name), &to.join(name)))?;
+        }
+
+        Ok(reused)
+    });
+
+    if let Err(e) = ops.run(FsOp::RemoveDirAll(&staging)) {
+        out!("Failed to remove staging directory: {}", e);
+    }
+
+    let reused = result?;
+
+    let known = planned
+        .iter()
+        .filter_map(|f| Some((f.name.clone(), f.hash?)))
+        .collect::<Vec<_>>();
+
+    manifest::write_known(ops, to, kind, &known)?;
+
+    let copied = planned.iter().filter(|f| f.hash.is_some()).count() - reused;
+    Ok(Reused { reused, copied })
+}
+
+/// Link or copy every planned file into `staging`, returning the names they were given there
+/// and how many were linked.
+///
+/// Files which can't be linked, like on a file system without hard links, are copied instead.
+fn stage(ops: &Ops, staging: &Path, planned: &[Planned]) -> Result<(Vec<OsString>, usize), Error> {
+    let mut staged = Vec::new();
+    let mut reused = 0;
+
+    for file in planned {
+        ops.check_cancelled()?;
+
+        if let Some(old) = &file.reuse {
+            // Named like the file it's linked to, which might be compressed.
+            let link = old
+                .file_name()
+                .map_or_else(|| file.name.clone(), OsString::from);
+
+            match ops.run(FsOp::HardLink(old, &staging.join(&link))) {
+                Ok(()) => {
+                    staged.push(link);
+                    reused += 1;
+                    continue;
+                }
+                Err(Error::HardLink { source, .. }) => {
+                    debug!(
+                        "copying {} instead of linking it: {}",
+                        old.display(),
+                        source
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        files::stage_save_file(ops, &file.path, &staging.join(&file.name))?;
+        staged.push(file.name.clone());
+    }
+
+    Ok((staged, reused))
+}
+
+/// When the file at the given path was modified.
+fn modified(path: &Path) -> Result<SystemTime, Error> {
+    fs::metadata(to_extended_path(path))
+        .and_then(|m| m.modified())
+        .map_err(|e| Error::io(path, e))
+}
@@ -0,0 +1,491 @@
+use crate::Error;
+use std::{
+    io::{self, BufReader, Read},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+use subtle::ConstantTimeEq as _;
+
+/// The port listened on unless another one is given.
+pub const DEFAULT_PORT: u16 = 7373;
+
+/// The header which has to hold the shared secret token, if one is set.
+pub const TOKEN_HEADER: &str = "X-Token";
+
+/// How often `stop` is checked while waiting for a request.
+const POLL: Duration = Duration::from_millis(50);
+
+/// How long a client gets to send its request, so that a stalled one can't hold up the others.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest request which is read, headers and body together.
+const MAX_REQUEST: u64 = 64 * 1024;
+
+/// A request to the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    token: Option<String>,
+    origin: Option<String>,
+    host: Option<String>,
+}
+
+impl Request {
+    /// The method of the request, like `GET` or `POST`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The path of the request without the query, like `/load`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The first value of the given query parameter, decoded.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Why the request is refused before it's handled, if it might have been sent by a web page
+    /// instead of a program on this computer.
+    ///
+    /// Browsers send the `Origin` header with requests from web pages, which could otherwise
+    /// load save files without the player knowing. A host other than 127.0.0.1 or localhost with
+    /// the port served on means that a web page might have pointed its own name at 127.0.0.1.
+    fn forbidden(&self, port: u16) -> Option<&'static str> {
+        if self.origin.is_some() {
+            return Some("requests from web pages are refused");
+        }
+
+        let local = match self.host.as_deref().and_then(|host| host.rsplit_once(':')) {
+            Some((name, p)) => {
+                (name == "127.0.0.1" || name.eq_ignore_ascii_case("localhost"))
+                    && p.parse() == Ok(port)
+            }
+            None => false,
+        };
+
+        if !local {
+            return Some("the host isn't 127.0.0.1 or localhost with the port served on");
+        }
+
+        None
+    }
+
+    /// Test if the request has the given token, if there is one, without taking longer the more
+    /// of it is right.
+    fn authorized(&self, token: Option<&str>) -> bool {
+        match (token, self.token.as_deref()) {
+            (Some(expected), Some(token)) => token.as_bytes().ct_eq(expected.as_bytes()).into(),
+            (Some(..), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    /// Parse the request line and headers of a request, reading and ignoring its body.
+    fn read(reader: &mut impl io::BufRead) -> Result<Self, String> {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|e| e.to_string())?;
+
+        let mut parts = line.split_whitespace();
+
+        let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/") => {
+                (method.to_string(), target.to_string())
+            }
+            _ => return Err(String::from("the request line is invalid")),
+        };
+
+        let mut token = None;
+        let mut origin = None;
+        let mut host = None;
+        let mut length = 0;
+
+        loop {
+            line.clear();
+
+            if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+                return Err(String::from("the headers are cut short"));
+            }
+
+            let header = line.trim_end();
+
+            if header.is_empty() {
+                break;
+            }
+
+            let (name, value) = match header.split_once(':') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => return Err(format!("'{}' isn't a header", header)),
+            };
+
+            if name.eq_ignore_ascii_case(TOKEN_HEADER) {
+                token = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("Origin") {
+                origin = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("Host") {
+                host = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("Content-Length") {
+                length = value
+                    .parse::<u64>()
+                    .map_err(|_| format!("'{}' isn't a content length", value))?;
+            }
+        }
+
+        io::copy(&mut reader.take(length), &mut io::sink()).map_err(|e| e.to_string())?;
+
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (target.as_str(), ""),
+        };
+
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((name, value)) => Ok((decode(name)?, decode(value)?)),
+                None => Ok((decode(pair)?, String::new())),
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            method,
+            path: decode(path)?,
+            query,
+            token,
+            origin,
+            host,
+        })
+    }
+}
+
+/// A response to a request, which always has a JSON body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    status: u16,
+    body: String,
+}
+
+impl Response {
+    /// A response with the given status code and JSON body.
+    pub fn json(status: u16, body: String) -> Self {
+        Self { status, body }
+    }
+
+    /// An error response with the given status code, shaped like the errors of `--format json`.
+    pub fn error(status: u16, kind: &str, message: &str) -> Self {
+        let body = serde_json::json!({
+            "status": "error",
+            "error": { "kind": kind, "message": message },
+        });
+
+        Self::json(status, body.to_string())
+    }
+
+    /// The status code of the response.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    fn write(&self, stream: &mut impl io::Write) -> io::Result<()> {
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason(self.status),
+            self.body.len(),
+            self.body
+        )?;
+
+        stream.flush()
+    }
+}
+
+/// The HTTP status code which describes an error, like 404 for a slot which doesn't exist.
+pub fn status_code(error: &Error) -> u16 {
+    match error {
+        Error::Multiple { errors } | Error::ProfilesFailed { errors, .. } => {
+            errors.first().map_or(500, status_code)
+        }
+        Error::InvalidIndex { .. }
+        | Error::InvalidSlotName { .. }
+        | Error::InvalidTag { .. }
+        | Error::InvalidTime { .. } => 400,
+        Error::GameRunning { .. }
+        | Error::InstanceLocked { .. }
+        | Error::SlotPinned { .. }
+        | Error::UnsavedChanges
+        | Error::Cancelled => 409,
+        error => match error.exit_code() {
+            3 | 5 => 404,
+            _ => 500,
+        },
+    }
+}
+
+/// Serve requests on the given port of 127.0.0.1 until `stop` is set, answering each one with
+/// `handle`.
+///
+/// Requests are answered one at a time, so requests which overlap never run at the same time.
+/// Requests which might have been sent by a web page, which have an `Origin` header or a `Host`
+/// other than 127.0.0.1 or localhost with `port`, are refused with 403 without being handled. If
+/// a token is given, requests which don't have it in the [TOKEN_HEADER] header are refused with
+/// 401 as well.
+pub fn serve<F>(
+    port: u16,
+    token: Option<&str>,
+    stop: &AtomicBool,
+    mut handle: F,
+) -> Result<(), Error>
+where
+    F: FnMut(&Request) -> Response,
+{
+    let error = |source| Error::Serve { port, source };
+
+    // Only this computer can connect, since anyone who can may load save files.
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).map_err(error)?;
+    listener.set_nonblocking(true).map_err(error)?;
+
+    while !stop.load(Ordering::SeqCst) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(POLL);
+                continue;
+            }
+            Err(e) => return Err(error(e)),
+        };
+
+        if let Err(e) = respond(stream, port, token, &mut handle) {
+            eout!("warning: failed to respond to a request: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn respond<F>(
+    mut stream: TcpStream,
+    port: u16,
+    token: Option<&str>,
+    handle: &mut F,
+) -> io::Result<()>
+where
+    F: FnMut(&Request) -> Response,
+{
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut reader = BufReader::new(Read::take(&stream, MAX_REQUEST));
+
+    let response = match Request::read(&mut reader) {
+        Ok(request) => {
+            let response = match request.forbidden(port) {
+                Some(reason) => Response::error(403, "forbidden", reason),
+                None if !request.authorized(token) => {
+                    Response::error(401, "unauthorized", "the token is missing or wrong")
+                }
+                None => handle(&request),
+            };
+
+            out!("{} {}: {}", request.method, request.path, response.status);
+            response
+        }
+        Err(reason) => Response::error(400, "bad_request", &reason),
+    };
+
+    response.write(&mut stream)
+}
+
+/// Decode a percent-encoded part of a URL, where `+` is a space.
+fn decode(value: &str) -> Result<String, String> {
+    let invalid = || format!("'{}' isn't encoded correctly", value);
+
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut it = value.bytes();
+
+    while let Some(b) = it.next() {
+        match b {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [
+                    it.next().ok_or_else(invalid)?,
+                    it.next().ok_or_else(invalid)?,
+                ];
+                let hex = std::str::from_utf8(&hex).map_err(|_| invalid())?;
+                bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            }
+            b => bytes.push(b),
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write as _;
+
+    #[test]
+    fn read_requests() -> Result<(), String> {
+        let raw = "POST /load?slot=my+run%21&nth HTTP/1.1\r\nHost: localhost\r\nx-token: secret\r\nContent-Length: 4\r\n\r\nbody";
+        let request = Request::read(&mut raw.as_bytes())?;
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.path(), "/load");
+        assert_eq!(request.param("slot"), Some("my run!"));
+        assert_eq!(request.param("nth"), Some(""));
+        assert_eq!(request.param("other"), None);
+        assert_eq!(request.token.as_deref(), Some("secret"));
+
+        for invalid in &[
+            "GET /slots\r\n\r\n",
+            "GET /slots HTTP/1.1\r\nHost: localhost\r\n",
+            "GET /load?slot=%zz HTTP/1.1\r\n\r\n",
+        ] {
+            assert!(
+                Request::read(&mut invalid.as_bytes()).is_err(),
+                "{:?}",
+                invalid
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn refuse_requests_from_web_pages() -> Result<(), String> {
+        let read = |headers: &str| {
+            let raw = format!("POST /load?slot=foo HTTP/1.1\r\n{}\r\n", headers);
+            Request::read(&mut raw.as_bytes())
+        };
+
+        for local in &["Host: 127.0.0.1:7373\r\n", "host: LocalHost:7373\r\n"] {
+            assert_eq!(read(local)?.forbidden(7373), None, "{:?}", local);
+        }
+
+        for forbidden in &[
+            "Host: 127.0.0.1:7373\r\nOrigin: https://example.com\r\n",
+            "Host: localhost:7373\r\nOrigin: null\r\n",
+            "Host: evil.example.com:7373\r\n",
+            "Host: 127.0.0.1:8080\r\n",
+            "Host: 127.0.0.1\r\n",
+            "",
+        ] {
+            assert!(
+                read(forbidden)?.forbidden(7373).is_some(),
+                "{:?}",
+                forbidden
+            );
+        }
+
+        let request = read("Host: 127.0.0.1:7373\r\nX-Token: secret\r\n")?;
+        assert!(request.authorized(Some("secret")));
+        assert!(request.authorized(None));
+        assert!(!request.authorized(Some("secrets")));
+        assert!(!read("Host: 127.0.0.1:7373\r\n")?.authorized(Some("secret")));
+        Ok(())
+    }
+
+    #[test]
+    fn status_codes() {
+        let not_found = Error::SlotNotFound {
+            name: "foo".to_string(),
+        };
+        assert_eq!(status_code(&not_found), 404);
+
+        let invalid = Error::InvalidIndex {
+            value: "0".to_string(),
+        };
+        assert_eq!(status_code(&invalid), 400);
+
+        let multiple = Error::Multiple {
+            errors: vec![Error::Cancelled],
+        };
+        assert_eq!(status_code(&multiple), 409);
+        assert_eq!(status_code(&Error::MultipleProfiles), 500);
+    }
+
+    #[test]
+    fn serve_requests() -> Result<(), Box<dyn std::error::Error>> {
+        let stop = AtomicBool::new(false);
+
+        // Picks a free port, which is then released for the server to take.
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+            .local_addr()?
+            .port();
+
+        let responses = thread::scope(|s| {
+            let server = s.spawn(|| {
+                serve(port, Some("secret"), &stop, |request| {
+                    Response::json(200, format!("{:?}", request.param("slot")))
+                })
+            });
+
+            let request = |raw: &str| -> io::Result<String> {
+                let mut stream = loop {
+                    match TcpStream::connect((Ipv4Addr::LOCALHOST, port)) {
+                        Ok(stream) => break stream,
+                        Err(..) => thread::sleep(POLL),
+                    }
+                };
+
+                stream.write_all(raw.as_bytes())?;
+                let mut response = String::new();
+                stream.read_to_string(&mut response)?;
+                Ok(response)
+            };
+
+            let host = format!("Host: 127.0.0.1:{}\r\n", port);
+
+            let responses = (
+                request(&format!(
+                    "GET /slots?slot=a HTTP/1.1\r\n{}X-Token: secret\r\n\r\n",
+                    host
+                )),
+                request(&format!("GET /slots HTTP/1.1\r\n{}\r\n", host)),
+                request(&format!(
+                    "POST /load?slot=a HTTP/1.1\r\n{}Origin: https://example.com\r\nX-Token: secret\r\n\r\n",
+                    host
+                )),
+            );
+
+            stop.store(true, Ordering::SeqCst);
+            (responses, server.join())
+        });
+
+        let ((ok, unauthorized, forbidden), server) = responses;
+        assert!(matches!(server, Ok(Ok(()))));
+
+        let ok = ok?;
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"), "{}", ok);
+        assert!(ok.ends_with("\r\n\r\nSome(\"a\")"), "{}", ok);
+
+        let unauthorized = unauthorized?;
+        assert!(unauthorized.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+
+        let forbidden = forbidden?;
+        assert!(forbidden.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,516 @@
+use crate::{
+    archive::{system_time, zip_time, EntryReader},
+    bundle::{self, BundledFile},
+    compress,
+    files::{check_file_name, list_save_files, to_extended_path, total_size},
+    hash, header,
+    manifest::{ManifestFile, SlotManifest},
+    ops::Ops,
+    space, validate_tag, Edition, Error, SaveFilePatterns, SaveHeader,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, Cursor, Seek, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use zip::write::FileOptions;
+
+/// The extension of the files written by [crate::SaveManager::bundle_slot].
+pub const SLOT_BUNDLE_EXTENSION: &str = "gtavslot";
+
+/// The version of the layout of slot bundles which is written, and the newest one which can be
+/// read.
+const FORMAT: u32 = 1;
+
+/// Name of the manifest at the top of a slot bundle.
+pub(crate) const SLOT_BUNDLE_MANIFEST: &str = "gtavslot.json";
+
+/// Name of the directory in a slot bundle which holds the save files.
+const SAVE_DIR: &str = "save";
+
+/// Just enough of a manifest to tell which version of the layout it has, since the rest of it
+/// might look different in a newer one.
+#[derive(Debug, Deserialize)]
+struct Format {
+    format: u32,
+}
+
+/// Describes the contents of a slot bundle.
+#[derive(Debug, Serialize, Deserialize)]
+struct SlotBundleManifest {
+    /// The version of the layout of the bundle, which is [FORMAT] when it's written.
+    format: u32,
+    /// When the bundle was created, in RFC 3339 format.
+    created: String,
+    /// The version of the tool which created the bundle.
+    version: String,
+    /// The name of the slot the bundle was created from.
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// The edition of the game the save files are from, if it's known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    edition: Option<Edition>,
+    /// The version of the game the save files are from, if it's known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    game_version: Option<String>,
+    /// The save files, which are stored decompressed under [SAVE_DIR].
+    files: Vec<SaveFileManifest>,
+}
+
+/// Describes a single save file in a slot bundle, along with what the game says about it.
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveFileManifest {
+    #[serde(flatten)]
+    file: ManifestFile,
+    /// The title shown in the in-game save list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    /// When the game says the file was saved, in RFC 3339 format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    saved: Option<String>,
+}
+
+/// A single slot in a file of its own, as written by
+/// [SaveManager::bundle_slot](crate::SaveManager::bundle_slot) to share it with someone else.
+///
+/// A slot bundle is a zip archive with a `gtavslot.json` manifest at the top, which has the
+/// version of the layout, the name, note and tags of the slot, and the size, SHA-256 digest and
+/// in-game metadata of every save file. The save files are stored decompressed in a `save`
+/// directory, like `save/SGTA00000`.
+#[derive(Debug)]
+pub struct SlotBundle {
+    /// The name of the slot the bundle was created from.
+    pub name: String,
+    /// The note of the slot, if it had one.
+    pub note: Option<String>,
+    /// The tags of the slot.
+    pub tags: Vec<String>,
+    /// The edition of the game the save files are from, if it's known.
+    pub edition: Option<Edition>,
+    /// The version of the game the save files are from, if it's known.
+    pub game_version: Option<String>,
+    /// The save files in the bundle, sorted by name.
+    pub save_files: Vec<BundledSaveFile>,
+    files: Vec<BundledFile>,
+}
+
+/// A single save file in a [SlotBundle].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundledSaveFile {
+    /// The name of the save file, like `SGTA00000`.
+    pub name: String,
+    /// The size of the save file.
+    pub size: u64,
+    /// The in-game metadata of the save file.
+    pub header: SaveHeader,
+}
+
+impl SlotBundle {
+    /// Read the slot bundle at `path`, checking every save file in it against the size and
+    /// SHA-256 digest in its manifest.
+    ///
+    /// Errors with [Error::InvalidSlotBundle] saying what's wrong if the file isn't a slot
+    /// bundle, has a newer layout than this version can read, or doesn't match its manifest,
+    /// and with [Error::UnsafeArchiveEntry] if an entry isn't a save file in the `save`
+    /// directory.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let invalid = |reason: String| Error::InvalidSlotBundle {
+            path: path.to_owned(),
+            reason,
+        };
+
+        let zip_error = |source| Error::Zip {
+            path: path.to_owned(),
+            source,
+        };
+
+        let contents = fs::read(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+
+        let mut zip = zip::ZipArchive::new(Cursor::new(contents)).map_err(|e| {
+            invalid(format!(
+                "it isn't a zip archive ({}), it might not have been downloaded completely",
+                e
+            ))
+        })?;
+
+        let mut manifest = None;
+        let mut files = Vec::new();
+        let mut reader = EntryReader::new(path);
+
+        for index in 0..zip.len() {
+            let mut entry = zip.by_index(index).map_err(zip_error)?;
+
+            // Some tools add entries for the directories when repacking an archive.
+            if entry.is_dir() && entry.name().trim_end_matches('/') == SAVE_DIR {
+                continue;
+            }
+
+            let name = entry.name().to_string();
+
+            let contents = reader.read(&mut entry, &name)?;
+
+            if name == SLOT_BUNDLE_MANIFEST {
+                manifest = Some(contents);
+                continue;
+            }
+
+            let file = match name.split_once('/') {
+                Some((SAVE_DIR, file)) if check_file_name(file).is_ok() => file,
+                _ => {
+                    return Err(Error::UnsafeArchiveEntry {
+                        path: path.to_owned(),
+                        name,
+                    })
+                }
+            };
+
+            files.push(BundledFile {
+                entry: path.join(&name),
+                name: file.to_string(),
+                contents,
+                modified: system_time(entry.last_modified()),
+            });
+        }
+
+        let manifest = match manifest {
+            Some(manifest) => manifest,
+            None => {
+                return Err(invalid(format!(
+                    "{} is missing, use import-slot for plain zip archives",
+                    SLOT_BUNDLE_MANIFEST
+                )))
+            }
+        };
+
+        let unreadable = |e| invalid(format!("{} can't be read: {}", SLOT_BUNDLE_MANIFEST, e));
+        let format = serde_json::from_slice::<Format>(&manifest).map_err(unreadable)?;
+
+        if format.format > FORMAT {
+            return Err(invalid(format!(
+                "it has format version {}, but only version {} is supported, upgrade gtav-saveload to read it",
+                format.format, FORMAT
+            )));
+        }
+
+        if format.format != FORMAT {
+            return Err(invalid(format!(
+                "format version {} doesn't exist",
+                format.format
+            )));
+        }
+
+        let manifest =
+            serde_json::from_slice::<SlotBundleManifest>(&manifest).map_err(unreadable)?;
+
+        for tag in &manifest.tags {
+            validate_tag(tag).map_err(|e| invalid(e.to_string()))?;
+        }
+
+        if manifest.files.is_empty() {
+            return Err(invalid(String::from("it doesn't contain any save files")));
+        }
+
+        for expected in &manifest.files {
+            let expected = &expected.file;
+
+            let file = match files.iter().find(|f| f.name == expected.name) {
+                Some(file) => file,
+                None => return Err(invalid(format!("{} is missing", expected.name))),
+            };
+
+            let actual = hash::hash_bytes(&file.contents);
+
+            if actual.size != expected.size || actual.to_string() != expected.sha256 {
+                return Err(invalid(format!(
+                    "{} is corrupt, expected SHA-256 {}, got {}",
+                    expected.name, expected.sha256, actual
+                )));
+            }
+        }
+
+        if let Some(extra) = files
+            .iter()
+            .find(|f| !manifest.files.iter().any(|e| e.file.name == f.name))
+        {
+            return Err(invalid(format!("{} isn't in the manifest", extra.name)));
+        }
+
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let save_files = files
+            .iter()
+            .map(|f| BundledSaveFile {
+                name: f.name.clone(),
+                size: f.contents.len() as u64,
+                header: header::parse(&f.contents),
+            })
+            .collect();
+
+        Ok(Self {
+            name: manifest.name,
+            note: manifest.note,
+            tags: manifest.tags,
+            edition: manifest.edition,
+            game_version: manifest.game_version,
+            save_files,
+            files,
+        })
+    }
+}
+
+/// Write the save files of the slot in `dir` into a new slot bundle at `path`, under the given
+/// name and with the note and tags from its manifest, if it has one.
+///
+/// The bundle is read back and checked once it's written, and removed again if anything fails.
+/// Errors before writing anything if the save files wouldn't fit decompressed.
+pub(crate) fn write(
+    path: &Path,
+    dir: &Path,
+    name: &str,
+    manifest: Option<&SlotManifest>,
+    patterns: &SaveFilePatterns,
+) -> Result<SlotBundle, Error> {
+    let mut save_files = list_save_files(dir, patterns)?;
+    save_files.sort();
+    space::check(path, total_size(&save_files)?)?;
+
+    let mut bundle = SlotBundleManifest {
+        format: FORMAT,
+        created: chrono::Local::now().to_rfc3339(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        name: name.to_string(),
+        note: manifest.and_then(|m| m.note.clone()),
+        tags: manifest.map(|m| m.tags.clone()).unwrap_or_default(),
+        edition: manifest.and_then(|m| m.edition),
+        game_version: manifest.and_then(|m| m.game_version.clone()),
+        files: Vec::new(),
+    };
+
+    let result = fs::File::create(to_extended_path(path))
+        .map_err(|e| Error::io(path, e))
+        .and_then(|file| {
+            write_bundle(file, path, &mut bundle, &save_files)?;
+            SlotBundle::read(path)
+        });
+
+    if result.is_err() {
+        let _ = fs::remove_file(to_extended_path(path));
+    }
+
+    result
+}
+
+fn write_bundle<W>(
+    file: W,
+    path: &Path,
+    manifest: &mut SlotBundleManifest,
+    save_files: &[(OsString, PathBuf)],
+) -> Result<W, Error>
+where
+    W: Write + Seek,
+{
+    let zip_error = |source| Error::Zip {
+        path: path.to_owned(),
+        source,
+    };
+
+    let mut zip = zip::ZipWriter::new(file);
+
+    for (name, save_file) in save_files {
+        // Save files are shared decompressed, since whoever they're shared with might not
+        // compress theirs.
+        let contents = compress::read(save_file)?;
+        let modified = fs::metadata(to_extended_path(save_file))
+            .and_then(|m| m.modified())
+            .map_err(|e| Error::io(save_file, e))?;
+
+        let name = name.to_string_lossy().into_owned();
+        let options = FileOptions::default().last_modified_time(zip_time(modified));
+        zip.start_file(format!("{}/{}", SAVE_DIR, name), options)
+            .map_err(zip_error)?;
+        zip.write_all(&contents).map_err(|e| Error::io(path, e))?;
+
+        let header = header::parse(&contents);
+
+        manifest.files.push(SaveFileManifest {
+            file: ManifestFile::new(name, hash::hash_bytes(&contents)),
+            title: header.title,
+            saved: header
+                .saved
+                .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339()),
+        });
+    }
+
+    let options = FileOptions::default().last_modified_time(zip_time(SystemTime::now()));
+    zip.start_file(SLOT_BUNDLE_MANIFEST, options)
+        .map_err(zip_error)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest)
+        .map_err(|e| Error::io(path, io::Error::from(e)))?;
+    zip.finish().map_err(zip_error)
+}
+
+/// Write the save files in a slot bundle into `dir`, giving them the modification times they
+/// had when they were bundled, and checking that each one was written correctly.
+pub(crate) fn write_save_files(ops: &Ops, bundle: &SlotBundle, dir: &Path) -> Result<(), Error> {
+    bundle::write_files(ops, &bundle.files, dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_zip;
+    use crate::SaveManager;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn bundle_and_unbundle_slot() -> Result<(), Box<dyn std::error::Error>> {
+        let profile = tempfile::tempdir()?;
+        let contents = header::tests::fixture("Franklin - The Jewel Store Job", 1_400_000_000);
+        fs::write(profile.path().join("SGTA00000"), &contents)?;
+
+        let manager = SaveManager::new(profile.path()).edition(Edition::Legacy);
+        manager.save("heist")?;
+        manager.annotate("heist", Some("before the jewel store"), &["story"])?;
+
+        let out = tempfile::tempdir()?;
+        let path = out.path().join("heist.gtavslot");
+        manager.bundle_slot("heist", &path)?;
+
+        let bundle = SlotBundle::read(&path)?;
+        assert_eq!(bundle.name, "heist");
+        assert_eq!(bundle.note.as_deref(), Some("before the jewel store"));
+        assert_eq!(bundle.tags, vec!["story"]);
+        assert_eq!(bundle.edition, Some(Edition::Legacy));
+        assert_eq!(bundle.save_files.len(), 1);
+        assert_eq!(
+            bundle.save_files[0].header.title.as_deref(),
+            Some("Franklin - The Jewel Store Job")
+        );
+        assert_eq!(
+            bundle.save_files[0].header.saved,
+            Some(UNIX_EPOCH + Duration::from_secs(1_400_000_000))
+        );
+
+        // Bundles are imported into whichever profile they're given to.
+        let other = tempfile::tempdir()?;
+        let other = SaveManager::new(other.path());
+        assert_eq!(other.unbundle(&path, None)?, "heist");
+        assert!(matches!(
+            other.unbundle(&path, None),
+            Err(Error::SlotNameTaken { .. })
+        ));
+        assert_eq!(other.unbundle(&path, Some("copy"))?, "copy");
+
+        let slot = other.slot("heist")?;
+        assert_eq!(fs::read(slot.path.join("SGTA00000"))?, contents);
+        assert_eq!(slot.note.as_deref(), Some("before the jewel store"));
+        assert!(other
+            .fsck(false)?
+            .iter()
+            .all(|check| check.status == crate::CheckStatus::Ok));
+
+        other.load_bundle(&path)?;
+        assert_eq!(fs::read(other.profile().join("SGTA00000"))?, contents);
+
+        // The slot keeps the edition of the bundle, so it's refused like the bundle is.
+        let enhanced = other.edition(Edition::Enhanced);
+        assert_eq!(enhanced.unbundle(&path, Some("enhanced"))?, "enhanced");
+        assert!(matches!(
+            enhanced.load("enhanced"),
+            Err(Error::EditionMismatch { .. })
+        ));
+        assert!(matches!(
+            enhanced.load_bundle(&path),
+            Err(Error::EditionMismatch { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_bundles_are_refused() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("heist.gtavslot");
+
+        let manifest = |format: u32, sha256: &str| {
+            serde_json::json!({
+                "format": format,
+                "created": "2019-10-30T18:04:12+01:00",
+                "version": "0.1.0",
+                "name": "heist",
+                "files": [{ "name": "SGTA00000", "size": 4, "sha256": sha256 }],
+            })
+            .to_string()
+        };
+
+        let digest = hash::hash_bytes(b"save").to_string();
+
+        let reason = |entries: &[(&str, &[u8])]| -> Result<String, Box<dyn std::error::Error>> {
+            write_zip(&path, entries)?;
+
+            match SlotBundle::read(&path) {
+                Err(Error::InvalidSlotBundle { reason, .. }) => Ok(reason),
+                other => Err(format!("expected an invalid bundle, got {:?}", other).into()),
+            }
+        };
+
+        write_zip(
+            &path,
+            &[
+                ("save/SGTA00000", b"save"),
+                (SLOT_BUNDLE_MANIFEST, manifest(1, &digest).as_bytes()),
+            ],
+        )?;
+        assert_eq!(SlotBundle::read(&path)?.save_files[0].size, 4);
+
+        let newer = reason(&[
+            ("save/SGTA00000", b"save"),
+            (SLOT_BUNDLE_MANIFEST, manifest(2, &digest).as_bytes()),
+        ])?;
+        assert!(newer.contains("format version 2"), "{}", newer);
+
+        let corrupt = reason(&[
+            ("save/SGTA00000", b"evil"),
+            (SLOT_BUNDLE_MANIFEST, manifest(1, &digest).as_bytes()),
+        ])?;
+        assert!(corrupt.starts_with("SGTA00000 is corrupt"), "{}", corrupt);
+
+        let missing = reason(&[(SLOT_BUNDLE_MANIFEST, manifest(1, &digest).as_bytes())])?;
+        assert_eq!(missing, "SGTA00000 is missing");
+
+        let plain = reason(&[("save/SGTA00000", b"save")])?;
+        assert!(plain.contains("use import-slot"), "{}", plain);
+
+        fs::write(&path, b"not a zip")?;
+        assert!(matches!(
+            SlotBundle::read(&path),
+            Err(Error::InvalidSlotBundle { .. })
+        ));
+
+        for name in &["save/../SGTA00000", "save/a/SGTA00000", "SGTA00000"] {
+            write_zip(
+                &path,
+                &[
+                    (name, b"save"),
+                    (SLOT_BUNDLE_MANIFEST, manifest(1, &digest).as_bytes()),
+                ],
+            )?;
+
+            assert!(
+                matches!(
+                    SlotBundle::read(&path),
+                    Err(Error::UnsafeArchiveEntry { .. })
+                ),
+                "{}",
+                name
+            );
+        }
+
+        Ok(())
+    }
+}
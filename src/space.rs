@@ -0,0 +1,88 @@
+use crate::Error;
+use std::path::Path;
+
+/// Space to leave free on top of what's being copied, so that a copy doesn't fill the disk
+/// completely.
+const MARGIN: u64 = 1024 * 1024;
+
+/// Error if there isn't room for `needed` more bytes on the volume of the given path.
+///
+/// The check is skipped if the free space can't be determined, which is always the case on
+/// other platforms than Windows.
+pub(crate) fn check(path: &Path, needed: u64) -> Result<(), Error> {
+    match available_space(path) {
+        Some(available) => check_available(path, needed, available),
+        None => Ok(()),
+    }
+}
+
+fn check_available(path: &Path, needed: u64, available: u64) -> Result<(), Error> {
+    if needed.saturating_add(MARGIN) <= available {
+        return Ok(());
+    }
+
+    Err(Error::InsufficientSpace {
+        path: path.to_owned(),
+        needed: needed.saturating_add(MARGIN),
+        available,
+    })
+}
+
+/// The space available to this user on the volume of the given path, which might not exist
+/// yet.
+#[cfg(windows)]
+pub(crate) fn available_space(path: &Path) -> Option<u64> {
+    use std::{iter, os::windows::ffi::OsStrExt as _, ptr};
+    use winapi::um::{fileapi::GetDiskFreeSpaceExW, winnt::ULARGE_INTEGER};
+
+    let existing = path.ancestors().find(|p| p.is_dir())?;
+
+    let wide = existing
+        .as_os_str()
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect::<Vec<_>>();
+
+    let mut available: ULARGE_INTEGER = unsafe { std::mem::zeroed() };
+
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    };
+
+    if ok == 0 {
+        return None;
+    }
+
+    Some(unsafe { *available.QuadPart() })
+}
+
+#[cfg(not(windows))]
+pub(crate) fn available_space(_: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requires_a_margin() {
+        let path = Path::new("Slots");
+
+        assert!(check_available(path, 1000, 1000 + MARGIN).is_ok());
+
+        let error = check_available(path, 7 * MARGIN / 2, 2 * MARGIN).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "not enough space for Slots, need 4.5 MiB, have 2.0 MiB"
+        );
+        assert_eq!(error.exit_code(), 4);
+
+        assert!(check_available(path, u64::MAX - 1, u64::MAX - 1).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    files::{self, list_save_files, to_extended_path},
+    manifest, Error, SaveFilePatterns,
+};
+use std::{
+    cmp::Reverse,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Name of the reserved directory in the slots of a profile which holds the stash.
+pub(crate) const STASH: &str = ".stash";
+
+/// Save files which were stashed away from the profile.
+#[derive(Debug, Clone)]
+pub struct StashEntry {
+    /// The name of the entry, like `001`, where higher numbers were stashed later.
+    pub name: String,
+    /// The directory of the entry.
+    pub path: PathBuf,
+    /// When the save files were stashed.
+    pub stashed: SystemTime,
+    /// The number of save files in the entry.
+    pub files: usize,
+}
+
+/// List the entries in the given stash directory, with the most recently stashed first.
+pub(crate) fn entries(stash: &Path, patterns: &SaveFilePatterns) -> Result<Vec<StashEntry>, Error> {
+    if !stash.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut numbered = Vec::new();
+
+    for (name, path) in files::find_matching(stash, |p| p.is_dir(), |_| true)? {
+        // Anything which isn't named like an entry was put there by someone else.
+        if let Some(number) = name.to_str().and_then(|n| n.parse::<u32>().ok()) {
+            numbered.push((number, name, path));
+        }
+    }
+
+    numbered.sort_by_key(|(number, ..)| Reverse(*number));
+
+    let mut out = Vec::new();
+
+    for (_, name, path) in numbered {
+        out.push(read_entry(files::display_name(&name), path, patterns)?);
+    }
+
+    Ok(out)
+}
+
+/// The directory of the next entry in the given stash directory, which doesn't exist yet.
+pub(crate) fn next_entry(stash: &Path, patterns: &SaveFilePatterns) -> Result<PathBuf, Error> {
+    let next = match entries(stash, patterns)?.first() {
+        Some(newest) => newest.name.parse::<u32>().unwrap_or_default() + 1,
+        None => 1,
+    };
+
+    Ok(stash.join(format!("{:03}", next)))
+}
+
+fn read_entry(
+    name: String,
+    path: PathBuf,
+    patterns: &SaveFilePatterns,
+) -> Result<StashEntry, Error> {
+    let files = list_save_files(&path, patterns)?.len();
+
+    // The manifest records when the entry was created, which the directory might not.
+    let created = manifest::read(&path)
+        .ok()
+        .flatten()
+        .and_then(|m| chrono::DateTime::parse_from_rfc3339(&m.created).ok());
+
+    let stashed = match created {
+        Some(created) => created.into(),
+        None => fs::metadata(to_extended_path(&path))
+            .and_then(|m| m.modified())
+            .map_err(|e| Error::io(&path, e))?,
+    };
+
+    Ok(StashEntry {
+        name,
+        path,
+        stashed,
+        files,
+    })
+}
@@ -0,0 +1,272 @@
+use crate::Edition;
+use std::{
+    collections::HashSet,
+    fs, iter,
+    path::{Path, PathBuf},
+};
+
+/// The Steam app id of each edition, which names the Proton prefix it runs in.
+const APP_IDS: &[(Edition, &str)] = &[(Edition::Legacy, "271590"), (Edition::Enhanced, "3240220")];
+
+/// Where Steam keeps its own library relative to the home directory: the link the native
+/// package maintains, where it's usually installed, and where the Flatpak installs it.
+const STEAM_ROOTS: &[&str] = &[
+    ".steam/steam",
+    ".local/share/Steam",
+    ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+];
+
+/// The file in `steamapps` which lists every library, including ones on other drives.
+const LIBRARY_FOLDERS: &str = "libraryfolders.vdf";
+
+/// The Documents folder of the user Proton runs games as, in the prefix of a game.
+const PREFIX_DOCUMENTS: &[&str] = &["pfx", "drive_c", "users", "steamuser", "Documents"];
+
+/// The Steam app id of the given edition.
+pub(crate) fn app_id(edition: Edition) -> &'static str {
+    APP_IDS
+        .iter()
+        .find(|(e, _)| *e == edition)
+        .map(|(_, id)| *id)
+        .unwrap_or_default()
+}
+
+/// Find the Documents folders in the Proton prefixes of every edition of the game, in every
+/// Steam library under the given home directory or listed in its `libraryfolders.vdf`.
+///
+/// Only folders which exist are returned, each once.
+pub(crate) fn proton_documents_dirs(home: &Path) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+
+    for library in home_libraries(home) {
+        for (edition, id) in APP_IDS {
+            let mut documents = library.join("steamapps").join("compatdata").join(id);
+            documents.extend(PREFIX_DOCUMENTS);
+
+            if documents.is_dir() {
+                debug!(
+                    "found the {} Proton prefix in {}",
+                    edition,
+                    library.display()
+                );
+                out.push(documents);
+            } else {
+                trace!("no {} Proton prefix in {}", edition, library.display());
+            }
+        }
+    }
+
+    out
+}
+
+/// The Steam libraries under the given home directory, and the ones they list.
+pub(crate) fn home_libraries(home: &Path) -> Vec<PathBuf> {
+    libraries(STEAM_ROOTS.iter().map(|root| home.join(root)))
+}
+
+/// The Steam libraries in the given directories Steam is installed in, and the ones listed in
+/// their `libraryfolders.vdf`, each once.
+pub(crate) fn libraries<I>(roots: I) -> Vec<PathBuf>
+where
+    I: IntoIterator<Item = PathBuf>,
+{
+    let mut seen = HashSet::new();
+    let mut libraries = Vec::new();
+
+    for root in roots {
+        let steamapps = root.join("steamapps");
+
+        if !steamapps.is_dir() {
+            trace!("no Steam library in {}", root.display());
+            continue;
+        }
+
+        let listed = match fs::read_to_string(steamapps.join(LIBRARY_FOLDERS)) {
+            Ok(contents) => values(&contents, "path")
+                .into_iter()
+                .map(PathBuf::from)
+                .collect(),
+            Err(e) => {
+                debug!(
+                    "failed to read {}: {}",
+                    steamapps.join(LIBRARY_FOLDERS).display(),
+                    e
+                );
+                Vec::new()
+            }
+        };
+
+        for library in iter::once(root).chain(listed) {
+            // The same library is often reachable through a link.
+            if seen.insert(fs::canonicalize(&library).unwrap_or_else(|_| library.clone())) {
+                libraries.push(library);
+            }
+        }
+    }
+
+    libraries
+}
+
+/// Find the directory the app with the given id is installed in, in the first of the given
+/// libraries which has its `appmanifest_{id}.acf`.
+///
+/// The manifest names the directory in `steamapps/common`, which isn't checked to exist.
+pub(crate) fn app_dir(libraries: &[PathBuf], id: &str) -> Option<PathBuf> {
+    for library in libraries {
+        let steamapps = library.join("steamapps");
+        let manifest = steamapps.join(format!("appmanifest_{}.acf", id));
+
+        let contents = match fs::read_to_string(&manifest) {
+            Ok(contents) => contents,
+            Err(e) => {
+                trace!("failed to read {}: {}", manifest.display(), e);
+                continue;
+            }
+        };
+
+        match values(&contents, "installdir").into_iter().next() {
+            Some(dir) => return Some(steamapps.join("common").join(dir)),
+            None => debug!("no installdir in {}", manifest.display()),
+        }
+    }
+
+    None
+}
+
+/// The values of the given key in a VDF file, like `libraryfolders.vdf` which holds an entry
+/// like `"path" "/mnt/games/SteamLibrary"` for each library.
+///
+/// Only the lines with the key and a value are looked at, so the rest of the file doesn't have
+/// to be understood.
+fn values(contents: &str, key: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| match quoted(line).as_slice() {
+            [k, value] if k.eq_ignore_ascii_case(key) => Some(value.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The quoted strings in a line of a VDF file, where backslashes escape the next character.
+fn quoted(line: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut chars = line.chars();
+
+    while chars.any(|c| c == '"') {
+        let mut value = String::new();
+
+        loop {
+            match chars.next() {
+                Some('\\') => value.extend(chars.next()),
+                Some('"') => break,
+                Some(c) => value.push(c),
+                // Unterminated strings are ignored.
+                None => return out,
+            }
+        }
+
+        out.push(value);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_library_paths() {
+        let vdf = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/me/.local/share/Steam"
+		"label"		""
+		"apps"
+		{
+			"271590"		"94251994856"
+		}
+	}
+	"1"
+	{
+		"path"		"/mnt/games/Steam \"Library\""
+	}
+	"2"
+	{
+		"path"		"D:\\SteamLibrary"
+		"path"
+	}
+}
+"#;
+
+        assert_eq!(
+            values(vdf, "path"),
+            vec![
+                "/home/me/.local/share/Steam",
+                "/mnt/games/Steam \"Library\"",
+                "D:\\SteamLibrary",
+            ]
+        );
+    }
+
+    #[test]
+    fn find_proton_prefixes() -> Result<(), Box<dyn std::error::Error>> {
+        let home = tempfile::tempdir()?;
+        let other = tempfile::tempdir()?;
+
+        let steam = home.path().join(".local/share/Steam");
+        fs::create_dir_all(steam.join("steamapps"))?;
+
+        // The library Steam is installed in is listed as well.
+        let library_folders = format!(
+            "\"libraryfolders\"\n{{\n\"0\"\n{{\n\"path\" \"{}\"\n}}\n\"1\"\n{{\n\"path\" \"{}\"\n}}\n}}",
+            steam.display(),
+            other.path().display()
+        );
+        fs::write(
+            steam.join("steamapps").join(LIBRARY_FOLDERS),
+            library_folders,
+        )?;
+
+        let prefix = |library: &Path, id: &str| {
+            let mut documents = library.join("steamapps").join("compatdata").join(id);
+            documents.extend(PREFIX_DOCUMENTS);
+            documents
+        };
+
+        fs::create_dir_all(prefix(&steam, "271590"))?;
+        fs::create_dir_all(prefix(other.path(), "3240220"))?;
+
+        assert_eq!(
+            proton_documents_dirs(home.path()),
+            vec![prefix(&steam, "271590"), prefix(other.path(), "3240220")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn find_app_dirs() -> Result<(), Box<dyn std::error::Error>> {
+        let first = tempfile::tempdir()?;
+        let second = tempfile::tempdir()?;
+        let libraries = vec![first.path().to_owned(), second.path().to_owned()];
+
+        fs::create_dir_all(first.path().join("steamapps"))?;
+        fs::create_dir_all(second.path().join("steamapps"))?;
+        assert_eq!(app_dir(&libraries, "271590"), None);
+
+        fs::write(
+            second.path().join("steamapps").join("appmanifest_271590.acf"),
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"271590\"\n\t\"installdir\"\t\t\"Grand Theft Auto V\"\n}\n",
+        )?;
+
+        assert_eq!(
+            app_dir(&libraries, "271590"),
+            Some(second.path().join("steamapps/common/Grand Theft Auto V"))
+        );
+        assert_eq!(app_dir(&libraries, "3240220"), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,314 @@
+use crate::{
+    files::{self, copy_save_files, list_save_files, to_extended_path},
+    hash,
+    manifest::MANIFEST,
+    ops::{FsOp, Ops},
+    Error, SaveFilePatterns,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::{OsStr, OsString},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Name of the file in the local slots directory which remembers what each slot looked like
+/// after the last sync with each remote directory.
+const STATE_FILE: &str = ".sync-state.json";
+
+/// What slots looked like after the last sync.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    /// The digest of each slot after the last sync, by remote directory and slot name.
+    remotes: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// The contents of a slot on one side of a sync.
+#[derive(Debug)]
+struct Side {
+    path: PathBuf,
+    /// Digest of the names and contents of every save file in the slot.
+    digest: String,
+    /// When the newest save file in the slot was modified.
+    modified: Option<SystemTime>,
+}
+
+/// What to do with a slot in a sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    InSync,
+    /// Copy the local slot to the remote directory, for the given reason.
+    Push(&'static str),
+    /// Copy the remote slot to the local directory, for the given reason.
+    Pull(&'static str),
+    /// Both sides have changed since the last sync.
+    Conflict,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::InSync => write!(fmt, "in sync"),
+            Step::Push(reason) => write!(fmt, "push, {}", reason),
+            Step::Pull(reason) => write!(fmt, "pull, {}", reason),
+            Step::Conflict => write!(fmt, "conflict, changed on both sides since the last sync"),
+        }
+    }
+}
+
+/// Sync the slots in `local` with the slots in `remote`, printing the plan as it goes.
+///
+/// Slots which only exist on one side are copied to the other. Slots which exist on both sides
+/// but differ are replaced by the side which changed since the last sync, or the newer side if
+/// they haven't been synced before. Slots which changed on both sides are skipped, and reported
+/// in an [Error::SyncConflicts] once everything else is done.
+pub(crate) fn sync(ops: &Ops, local: &Path, remote: &Path) -> Result<(), Error> {
+    if !remote.is_dir() {
+        let error = io::Error::new(io::ErrorKind::NotFound, "no such directory");
+        return Err(Error::io(remote, error));
+    }
+
+    let state_path = local.join(STATE_FILE);
+    let mut state = read_state(&state_path)?;
+    let key = std::path::absolute(remote)
+        .unwrap_or_else(|_| remote.to_owned())
+        .to_string_lossy()
+        .into_owned();
+    let bases = state.remotes.entry(key).or_default();
+
+    let local_slots = list_slots(local)?;
+    let remote_slots = list_slots(remote)?;
+    let names = local_slots
+        .keys()
+        .chain(remote_slots.keys())
+        .collect::<BTreeSet<_>>();
+
+    let mut pushed = 0;
+    let mut pulled = 0;
+    let mut conflicts = Vec::new();
+
+    for name in names {
+        let shown = files::display_name(name);
+        let key = name.to_string_lossy().into_owned();
+
+        let local_side = local_slots
+            .get(name)
+            .map(|p| side(p, &ops.patterns))
+            .transpose()?;
+        let remote_side = remote_slots
+            .get(name)
+            .map(|p| side(p, &ops.patterns))
+            .transpose()?;
+
+        let step = decide(
+            local_side.as_ref(),
+            remote_side.as_ref(),
+            bases.get(&key).map(String::as_str),
+        );
+
+        if step != Step::InSync || ops.verbose {
+            out!("{}: {}", shown, step);
+        }
+
+        let (from, to, digest) = match (step, &local_side, &remote_side) {
+            (Step::Push(..), Some(from), _) => (from, remote.join(name), &from.digest),
+            (Step::Pull(..), _, Some(from)) => (from, local.join(name), &from.digest),
+            (Step::InSync, Some(side), _) => {
+                bases.insert(key, side.digest.clone());
+                continue;
+            }
+            _ => {
+                conflicts.push(shown);
+                continue;
+            }
+        };
+
+        copy_slot(ops, &from.path, &to)?;
+        bases.insert(key, digest.clone());
+
+        if matches!(step, Step::Push(..)) {
+            pushed += 1;
+        } else {
+            pulled += 1;
+        }
+    }
+
+    write_state(ops, &state_path, &state)?;
+
+    let (push, pull) = if ops.dry_run {
+        ("would push", "pull")
+    } else {
+        ("pushed", "pulled")
+    };
+
+    out!(
+        "{} {} and {} {} slots, {} conflicts with {}",
+        push,
+        pushed,
+        pull,
+        pulled,
+        conflicts.len(),
+        remote.display()
+    );
+
+    if !conflicts.is_empty() {
+        return Err(Error::SyncConflicts { slots: conflicts });
+    }
+
+    Ok(())
+}
+
+/// Decide what to do with a slot, given both sides of it and its digest after the last sync.
+fn decide(local: Option<&Side>, remote: Option<&Side>, base: Option<&str>) -> Step {
+    let (local, remote) = match (local, remote) {
+        (Some(local), Some(remote)) => (local, remote),
+        (Some(..), None) => return Step::Push("only here"),
+        (None, Some(..)) => return Step::Pull("only in remote"),
+        (None, None) => return Step::InSync,
+    };
+
+    if local.digest == remote.digest {
+        return Step::InSync;
+    }
+
+    match base {
+        Some(base) if base == local.digest => Step::Pull("changed in remote"),
+        Some(base) if base == remote.digest => Step::Push("changed here"),
+        Some(..) => Step::Conflict,
+        None if remote.modified > local.modified => Step::Pull("newer in remote"),
+        None if local.modified > remote.modified => Step::Push("newer here"),
+        None => Step::Conflict,
+    }
+}
+
+/// List the slots in the given directory by name, skipping reserved directories like the backup
+/// slot.
+fn list_slots(dir: &Path) -> Result<BTreeMap<OsString, PathBuf>, Error> {
+    if !dir.is_dir() {
+        return Ok(BTreeMap::new());
+    }
+
+    let is_slot = |n: &OsStr| !n.as_encoded_bytes().starts_with(b".");
+    let slots = files::find_matching(dir, |p| p.is_dir(), is_slot)?;
+    Ok(slots.into_iter().collect())
+}
+
+/// Describe the save files in the given slot.
+fn side(path: &Path, patterns: &SaveFilePatterns) -> Result<Side, Error> {
+    let mut save_files = list_save_files(path, patterns)?;
+    save_files.sort();
+
+    let mut contents = Vec::new();
+    let mut modified = None;
+
+    for (name, save_file) in &save_files {
+        let hash = hash::hash_file(save_file)?;
+        contents.extend_from_slice(name.as_encoded_bytes());
+        contents.extend_from_slice(format!(" {}\n", hash).as_bytes());
+
+        let meta =
+            fs::metadata(to_extended_path(save_file)).map_err(|e| Error::io(save_file, e))?;
+        let time = meta.modified().map_err(|e| Error::io(save_file, e))?;
+        modified = modified.max(Some(time));
+    }
+
+    Ok(Side {
+        path: path.to_owned(),
+        digest: hash::hash_bytes(&contents).to_string(),
+        modified,
+    })
+}
+
+/// Replace the save files and manifest of the slot `to` with the ones in `from`.
+fn copy_slot(ops: &Ops, from: &Path, to: &Path) -> Result<(), Error> {
+    if !to.is_dir() {
+        ops.run(FsOp::CreateDir(to))?;
+    }
+
+    copy_save_files(ops, from, to)?;
+
+    let (from, to) = (from.join(MANIFEST), to.join(MANIFEST));
+
+    if from.is_file() {
+        ops.run(FsOp::Copy(&from, &to))?;
+    } else if to.is_file() {
+        ops.run(FsOp::RemoveFile(&to))?;
+    }
+
+    Ok(())
+}
+
+fn read_state(path: &Path) -> Result<SyncState, Error> {
+    if !path.is_file() {
+        return Ok(SyncState::default());
+    }
+
+    let bytes = fs::read(to_extended_path(path)).map_err(|e| Error::io(path, e))?;
+
+    serde_json::from_slice(&bytes).map_err(|source| Error::InvalidManifest {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+fn write_state(ops: &Ops, path: &Path, state: &SyncState) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        if !parent.is_dir() {
+            ops.run(FsOp::CreateDir(parent))?;
+        }
+    }
+
+    let bytes = serde_json::to_vec_pretty(state).map_err(|source| Error::InvalidManifest {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    ops.run(FsOp::Write(path, &bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn side(digest: &str, modified: u64) -> Side {
+        Side {
+            path: PathBuf::from(digest),
+            digest: digest.to_string(),
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(modified)),
+        }
+    }
+
+    #[test]
+    fn decide_steps() {
+        let (a, b) = (side("a", 1), side("b", 2));
+
+        assert_eq!(decide(Some(&a), None, None), Step::Push("only here"));
+        assert_eq!(
+            decide(None, Some(&a), Some("a")),
+            Step::Pull("only in remote")
+        );
+        assert_eq!(decide(Some(&a), Some(&a), None), Step::InSync);
+
+        // Without a previous sync, the newer side wins.
+        assert_eq!(
+            decide(Some(&a), Some(&b), None),
+            Step::Pull("newer in remote")
+        );
+        assert_eq!(decide(Some(&b), Some(&a), None), Step::Push("newer here"));
+        assert_eq!(decide(Some(&a), Some(&side("c", 1)), None), Step::Conflict);
+
+        // Otherwise the side which changed wins, regardless of when it was modified.
+        assert_eq!(
+            decide(Some(&b), Some(&a), Some("b")),
+            Step::Pull("changed in remote")
+        );
+        assert_eq!(
+            decide(Some(&a), Some(&b), Some("b")),
+            Step::Push("changed here")
+        );
+        assert_eq!(decide(Some(&a), Some(&b), Some("c")), Step::Conflict);
+    }
+}
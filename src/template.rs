@@ -0,0 +1,264 @@
+use crate::{files, Error};
+use chrono::{DateTime, Local};
+
+/// The default template of the names of dated slots, see [crate::SaveManager::dated_name].
+pub const DATED_NAME: &str = "dated-{datetime}";
+
+/// Characters which separate the parts of a name, which are dropped next to an empty `{note}`.
+const SEPARATORS: &[char] = &['-', '_', '.', ' '];
+
+/// A part of a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part<'a> {
+    Literal(&'a str),
+    /// The time formatted with the date format.
+    DateTime,
+    /// The date, like `2024-03-01`.
+    Date,
+    /// The time of day, like `210000`.
+    Time,
+    /// The note given when saving, made safe for file names.
+    Note,
+    /// A number which makes the name unique.
+    Seq,
+}
+
+/// What the placeholders in a template are replaced with.
+pub(crate) struct Values<'a> {
+    pub(crate) time: DateTime<Local>,
+    pub(crate) date_format: &'a str,
+    pub(crate) note: Option<&'a str>,
+    pub(crate) seq: usize,
+}
+
+fn parse(template: &str) -> Result<Vec<Part<'_>>, Error> {
+    let invalid = |reason: String| Error::InvalidDatedName {
+        template: template.to_string(),
+        reason,
+    };
+
+    let mut parts = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find(&['{', '}'][..]) {
+        if rest[start..].starts_with('}') {
+            return Err(invalid(String::from("'}' without a matching '{'")));
+        }
+
+        if start > 0 {
+            parts.push(Part::Literal(&rest[..start]));
+        }
+
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => return Err(invalid(String::from("'{' without a matching '}'"))),
+        };
+
+        parts.push(match &rest[start + 1..end] {
+            "datetime" => Part::DateTime,
+            "date" => Part::Date,
+            "time" => Part::Time,
+            "note" => Part::Note,
+            "seq" => Part::Seq,
+            other => return Err(invalid(format!("unknown placeholder '{{{}}}'", other))),
+        });
+
+        rest = &rest[end + 1..];
+    }
+
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest));
+    }
+
+    Ok(parts)
+}
+
+/// Check that a template of the names of dated slots is valid, which means that it only uses
+/// known placeholders, tells slots saved at different times apart, and produces valid file
+/// names.
+pub(crate) fn check(template: &str, date_format: &str) -> Result<(), Error> {
+    let parts = parse(template)?;
+
+    let invalid = |reason: String| Error::InvalidDatedName {
+        template: template.to_string(),
+        reason,
+    };
+
+    let distinct = parts
+        .iter()
+        .any(|p| matches!(p, Part::DateTime | Part::Time | Part::Seq));
+
+    if !distinct {
+        return Err(invalid(String::from(
+            "it needs {datetime}, {time} or {seq} to tell slots apart",
+        )));
+    }
+
+    // The date format might produce characters which aren't valid in file names.
+    for note in &[None, Some("note")] {
+        let values = Values {
+            time: Local::now(),
+            date_format,
+            note: *note,
+            seq: 1,
+        };
+
+        let example = expand_parts(&parts, &values);
+
+        // Names starting with a dot are reserved.
+        let checked = if example.starts_with('.') {
+            Err(String::from("it starts with a dot"))
+        } else {
+            files::check_file_name(&example)
+        };
+
+        if let Err(reason) = checked {
+            return Err(invalid(format!(
+                "it produces invalid names like '{}' since {}",
+                example, reason
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Expand the given template, which has been checked with [check].
+pub(crate) fn expand(template: &str, values: &Values<'_>) -> Result<String, Error> {
+    Ok(expand_parts(&parse(template)?, values))
+}
+
+/// Test if the given template uses `{seq}`.
+pub(crate) fn has_seq(template: &str) -> bool {
+    template.contains("{seq}")
+}
+
+fn expand_parts(parts: &[Part<'_>], values: &Values<'_>) -> String {
+    let mut out = String::new();
+    let mut skip_separator = false;
+
+    for part in parts {
+        let text = match *part {
+            Part::Literal(literal) if skip_separator => {
+                skip_separator = false;
+                literal
+                    .strip_prefix(SEPARATORS)
+                    .unwrap_or(literal)
+                    .to_string()
+            }
+            Part::Literal(literal) => literal.to_string(),
+            Part::DateTime => values.time.format(values.date_format).to_string(),
+            Part::Date => values.time.format("%Y-%m-%d").to_string(),
+            Part::Time => values.time.format("%H%M%S").to_string(),
+            Part::Seq => format!("{:03}", values.seq),
+            Part::Note => {
+                let slug = values.note.map(slug).unwrap_or_default();
+
+                // Drop a separator next to an empty note, so that `{date}_{note}` doesn't leave
+                // a trailing `_`.
+                if slug.is_empty() {
+                    if out.ends_with(SEPARATORS) {
+                        out.pop();
+                    } else {
+                        skip_separator = true;
+                    }
+                }
+
+                slug
+            }
+        };
+
+        if !matches!(part, Part::Literal(..)) {
+            skip_separator &= text.is_empty();
+        }
+
+        out.push_str(&text);
+    }
+
+    out
+}
+
+/// Make a note safe for use in a file name, like `heist-finale` for `Heist: finale!`.
+fn slug(note: &str) -> String {
+    let mut out = String::new();
+
+    for c in note.chars() {
+        if c.is_alphanumeric() {
+            out.extend(c.to_lowercase());
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+
+    out.truncate(out.trim_end_matches('-').len());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DATED_FORMAT;
+    use chrono::TimeZone as _;
+
+    fn values(note: Option<&str>) -> Values<'_> {
+        Values {
+            time: Local
+                .ymd_opt(2024, 3, 1)
+                .and_hms_opt(21, 4, 5)
+                .earliest()
+                .expect("valid local time"),
+            date_format: DATED_FORMAT,
+            note,
+            seq: 7,
+        }
+    }
+
+    #[test]
+    fn expand_templates() -> Result<(), Error> {
+        let expand = |template, note| expand(template, &values(note));
+
+        assert_eq!(expand(DATED_NAME, None)?, "dated-2024-03-01_210405");
+        assert_eq!(
+            expand("{date}_{time}_{note}", Some("Heist: finale!"))?,
+            "2024-03-01_210405_heist-finale"
+        );
+        assert_eq!(expand("{date}_{time}_{note}", None)?, "2024-03-01_210405");
+        assert_eq!(expand("{note}-{seq}", None)?, "007");
+        assert_eq!(
+            expand("{date} {note} {seq}", Some("a"))?,
+            "2024-03-01 a 007"
+        );
+        assert_eq!(expand("{date} {note} {seq}", None)?, "2024-03-01 007");
+        Ok(())
+    }
+
+    #[test]
+    fn check_templates() {
+        assert!(check(DATED_NAME, DATED_FORMAT).is_ok());
+        assert!(check("{date}_{time}_{note}", DATED_FORMAT).is_ok());
+        assert!(check("save {seq}", DATED_FORMAT).is_ok());
+
+        for invalid in &[
+            "{date}",
+            "{date}_{note}",
+            "{time",
+            "time}",
+            "{when}",
+            ".{seq}",
+            "a/{seq}",
+            "{seq}?",
+        ] {
+            assert!(
+                matches!(
+                    check(invalid, DATED_FORMAT),
+                    Err(Error::InvalidDatedName { .. })
+                ),
+                "{:?}",
+                invalid
+            );
+        }
+
+        // Colons can't be used in file names on Windows.
+        assert!(check(DATED_NAME, "%H:%M:%S").is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use std::{fs, io::Write as _, path::Path};
+use zip::write::FileOptions;
+
+/// Write a zip archive with the given entries.
+pub(crate) fn write_zip(
+    path: &Path,
+    entries: &[(&str, &[u8])],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut zip = zip::ZipWriter::new(fs::File::create(path)?);
+
+    for (name, contents) in entries {
+        zip.start_file(*name, FileOptions::default())?;
+        zip.write_all(contents)?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
@@ -0,0 +1,366 @@
+use crate::Error;
+use std::{sync::atomic::AtomicBool, time::Duration};
+
+/// How many of the newest slots are listed in the menu of the tray icon.
+pub const MENU_SLOTS: usize = 10;
+
+/// How long a notification shown by [notify] stays up.
+pub const NOTIFY_DURATION: Duration = Duration::from_secs(6);
+
+/// What was picked in the menu of the tray icon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrayCommand {
+    /// Save the save files into a new dated slot, like `save-dated`.
+    SaveDated,
+    /// Load the newest slot, like `load-newest`.
+    LoadNewest,
+    /// Load the named slot, like `load`.
+    Load(String),
+}
+
+/// Show an icon with the given tooltip in the notification area until "Exit" is picked in its
+/// menu or `stop` is set.
+///
+/// The menu lists the first [MENU_SLOTS] of the slots returned by `slots`, which is called each
+/// time the menu is opened so that it's never stale. What's picked is passed to `run` one at a
+/// time on a worker thread, so that the menu stays responsive while it runs, and the message it
+/// returns or the error it fails with is shown as a notification. Exiting waits for what's
+/// running to finish.
+#[cfg(windows)]
+pub fn run<S, F>(tooltip: &str, stop: &AtomicBool, mut slots: S, mut run: F) -> Result<(), Error>
+where
+    S: FnMut() -> Vec<String>,
+    F: FnMut(&TrayCommand) -> Result<String, Error> + Send,
+{
+    use std::{mem, ptr, sync::atomic::Ordering, sync::mpsc, thread};
+    use winapi::{
+        shared::windef::HWND,
+        um::{
+            shellapi::{Shell_NotifyIconW, NIF_MESSAGE, NIIF_ERROR, NIIF_INFO, NIM_DELETE},
+            winuser::{
+                DestroyWindow, DispatchMessageW, GetMessageW, KillTimer, PostQuitMessage, SetTimer,
+                TranslateMessage, MSG, WM_TIMER,
+            },
+        },
+    };
+
+    let (hwnd, mut data) = imp::add_icon(tooltip, |data| {
+        data.uFlags |= NIF_MESSAGE;
+        data.uCallbackMessage = imp::CALLBACK;
+    })?;
+
+    unsafe { SetTimer(hwnd, imp::TIMER_ID, imp::POLL_MS, None) };
+
+    // Windows can be used from any thread, but aren't marked as such.
+    let window = hwnd as usize;
+    let (tx, rx) = mpsc::channel::<TrayCommand>();
+
+    thread::scope(|s| {
+        s.spawn(move || {
+            for command in rx {
+                let (flags, message) = match run(&command) {
+                    Ok(message) => (NIIF_INFO, message),
+                    Err(e) => (NIIF_ERROR, e.to_string()),
+                };
+
+                imp::notify(window as HWND, tooltip, &message, flags);
+            }
+        });
+
+        let mut msg = unsafe { mem::zeroed::<MSG>() };
+
+        while unsafe { GetMessageW(&mut msg, ptr::null_mut(), 0, 0) } > 0 {
+            match msg.message {
+                imp::SHOW_MENU => match imp::show_menu(hwnd, &slots()) {
+                    Some(imp::Picked::Command(command)) => {
+                        let _ = tx.send(command);
+                    }
+                    Some(imp::Picked::Exit) => unsafe { PostQuitMessage(0) },
+                    None => (),
+                },
+                WM_TIMER if stop.load(Ordering::SeqCst) => unsafe { PostQuitMessage(0) },
+                _ => unsafe {
+                    TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                },
+            }
+        }
+
+        // Lets the worker finish what it's running, and then stop.
+        drop(tx);
+    });
+
+    unsafe {
+        Shell_NotifyIconW(NIM_DELETE, &mut data);
+        KillTimer(hwnd, imp::TIMER_ID);
+        DestroyWindow(hwnd);
+    }
+
+    Ok(())
+}
+
+/// The tray icon is only supported on Windows, so this always errors with
+/// [Error::TrayUnsupported].
+#[cfg(not(windows))]
+pub fn run<S, F>(_: &str, _: &AtomicBool, _: S, _: F) -> Result<(), Error>
+where
+    S: FnMut() -> Vec<String>,
+    F: FnMut(&TrayCommand) -> Result<String, Error> + Send,
+{
+    Err(Error::TrayUnsupported)
+}
+
+/// Show a notification like the ones of the tray icon, for when there's no other way to tell
+/// what happened, like when running without a console.
+///
+/// The icon it's shown from is only added for [NOTIFY_DURATION], which this blocks for, since
+/// the notification goes away along with it.
+#[cfg(windows)]
+pub fn notify(title: &str, message: &str, error: bool) -> Result<(), Error> {
+    use std::thread;
+    use winapi::um::{
+        shellapi::{Shell_NotifyIconW, NIF_INFO, NIIF_ERROR, NIIF_INFO, NIM_DELETE},
+        winuser::DestroyWindow,
+    };
+
+    let (hwnd, mut data) = imp::add_icon(title, |data| {
+        data.uFlags |= NIF_INFO;
+        data.dwInfoFlags = if error { NIIF_ERROR } else { NIIF_INFO };
+        imp::copy_wide(&mut data.szInfoTitle, title);
+        imp::copy_wide(&mut data.szInfo, message);
+    })?;
+
+    thread::sleep(NOTIFY_DURATION);
+
+    unsafe {
+        Shell_NotifyIconW(NIM_DELETE, &mut data);
+        DestroyWindow(hwnd);
+    }
+
+    Ok(())
+}
+
+/// Notifications are only shown on Windows, so this does nothing.
+#[cfg(not(windows))]
+pub fn notify(_: &str, _: &str, _: bool) -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::{TrayCommand, MENU_SLOTS};
+    use crate::Error;
+    use std::{ffi::OsStr, io, iter, mem, os::windows::ffi::OsStrExt as _, ptr};
+    use winapi::{
+        shared::{
+            minwindef::{DWORD, LPARAM, LRESULT, UINT, WPARAM},
+            windef::{HMENU, HWND, POINT},
+            winerror::ERROR_CLASS_ALREADY_EXISTS,
+        },
+        um::{
+            errhandlingapi::GetLastError,
+            libloaderapi::GetModuleHandleW,
+            shellapi::{
+                Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_TIP, NIM_ADD, NIM_MODIFY,
+                NOTIFYICONDATAW,
+            },
+            winuser::{
+                AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu,
+                DestroyWindow, GetCursorPos, LoadIconW, PostMessageW, RegisterClassW,
+                SetForegroundWindow, TrackPopupMenu, IDI_APPLICATION, MF_GRAYED, MF_POPUP,
+                MF_SEPARATOR, MF_STRING, TPM_RETURNCMD, TPM_RIGHTBUTTON, WM_APP, WM_CONTEXTMENU,
+                WM_LBUTTONUP, WM_NULL, WM_RBUTTONUP, WNDCLASSW,
+            },
+        },
+    };
+
+    /// The identifier of the icon among the ones of the window.
+    pub(super) const ICON_ID: UINT = 1;
+    /// The message the icon sends to the window when it's clicked.
+    pub(super) const CALLBACK: UINT = WM_APP + 1;
+    /// The message posted to the message loop to open the menu.
+    pub(super) const SHOW_MENU: UINT = WM_APP + 2;
+    /// The timer which checks if the icon should be removed.
+    pub(super) const TIMER_ID: usize = 1;
+    /// How often the timer checks, in milliseconds.
+    pub(super) const POLL_MS: UINT = 250;
+
+    const SAVE_DATED: usize = 1;
+    const LOAD_NEWEST: usize = 2;
+    const EXIT: usize = 3;
+    /// The first item of the slots, which are numbered in order from it.
+    const FIRST_SLOT: usize = 100;
+
+    /// What was picked in the menu.
+    pub(super) enum Picked {
+        Command(TrayCommand),
+        Exit,
+    }
+
+    /// Add an icon with the given tooltip in the notification area, from a window which is
+    /// never shown. `f` fills in the rest of what the icon is added with.
+    pub(super) fn add_icon<F>(tooltip: &str, f: F) -> Result<(HWND, NOTIFYICONDATAW), Error>
+    where
+        F: FnOnce(&mut NOTIFYICONDATAW),
+    {
+        let error = || Error::Tray {
+            reason: io::Error::last_os_error().to_string(),
+        };
+
+        let class = wide("gtav-saveload-tray");
+        let title = wide(tooltip);
+
+        let hwnd = unsafe {
+            let instance = GetModuleHandleW(ptr::null());
+
+            let class = WNDCLASSW {
+                lpfnWndProc: Some(window_proc),
+                hInstance: instance,
+                lpszClassName: class.as_ptr(),
+                ..mem::zeroed()
+            };
+
+            if RegisterClassW(&class) == 0 && GetLastError() != ERROR_CLASS_ALREADY_EXISTS {
+                return Err(error());
+            }
+
+            // A window which isn't message-only can be put in the foreground, which the menu
+            // needs to close when clicking elsewhere.
+            CreateWindowExW(
+                0,
+                class.lpszClassName,
+                title.as_ptr(),
+                0,
+                0,
+                0,
+                0,
+                0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                instance,
+                ptr::null_mut(),
+            )
+        };
+
+        if hwnd.is_null() {
+            return Err(error());
+        }
+
+        let mut data = unsafe { mem::zeroed::<NOTIFYICONDATAW>() };
+        data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+        data.hWnd = hwnd;
+        data.uID = ICON_ID;
+        data.uFlags = NIF_ICON | NIF_TIP;
+        data.hIcon = unsafe { LoadIconW(ptr::null_mut(), IDI_APPLICATION) };
+        copy_wide(&mut data.szTip, tooltip);
+        f(&mut data);
+
+        if unsafe { Shell_NotifyIconW(NIM_ADD, &mut data) } == 0 {
+            let e = error();
+            unsafe { DestroyWindow(hwnd) };
+            return Err(e);
+        }
+
+        Ok((hwnd, data))
+    }
+
+    pub(super) fn wide(s: &str) -> Vec<u16> {
+        OsStr::new(s).encode_wide().chain(iter::once(0)).collect()
+    }
+
+    /// Copy a string into a fixed size buffer, cutting it short if it doesn't fit.
+    pub(super) fn copy_wide(dest: &mut [u16], s: &str) {
+        let len = dest.len() - 1;
+
+        for (d, c) in dest.iter_mut().zip(OsStr::new(s).encode_wide().take(len)) {
+            *d = c;
+        }
+    }
+
+    /// Reposts clicks on the icon to the message loop, where the menu is opened.
+    pub(super) unsafe extern "system" fn window_proc(
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if msg == CALLBACK {
+            let event = lparam as UINT;
+
+            if event == WM_LBUTTONUP || event == WM_RBUTTONUP || event == WM_CONTEXTMENU {
+                PostMessageW(hwnd, SHOW_MENU, 0, 0);
+            }
+
+            return 0;
+        }
+
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+
+    /// Show a notification from the icon of the given window.
+    pub(super) fn notify(hwnd: HWND, title: &str, message: &str, flags: DWORD) {
+        let mut data = unsafe { mem::zeroed::<NOTIFYICONDATAW>() };
+        data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+        data.hWnd = hwnd;
+        data.uID = ICON_ID;
+        data.uFlags = NIF_INFO;
+        data.dwInfoFlags = flags;
+        copy_wide(&mut data.szInfoTitle, title);
+        copy_wide(&mut data.szInfo, message);
+
+        unsafe { Shell_NotifyIconW(NIM_MODIFY, &mut data) };
+    }
+
+    /// Open the menu where the cursor is, and wait for something to be picked in it.
+    pub(super) fn show_menu(hwnd: HWND, slots: &[String]) -> Option<Picked> {
+        let slots = &slots[..slots.len().min(MENU_SLOTS)];
+
+        let append = |menu: HMENU, flags: UINT, id: usize, text: &str| {
+            let text = wide(text);
+            unsafe { AppendMenuW(menu, flags, id, text.as_ptr()) };
+        };
+
+        let picked = unsafe {
+            let menu = CreatePopupMenu();
+            let load = CreatePopupMenu();
+
+            append(menu, MF_STRING, SAVE_DATED, "Save dated now");
+            append(menu, MF_STRING, LOAD_NEWEST, "Load newest");
+
+            for (id, slot) in (FIRST_SLOT..).zip(slots) {
+                append(load, MF_STRING, id, slot);
+            }
+
+            if slots.is_empty() {
+                append(load, MF_STRING | MF_GRAYED, 0, "No slots");
+            }
+
+            // The submenu is destroyed along with the menu.
+            append(menu, MF_POPUP, load as usize, "Load");
+            AppendMenuW(menu, MF_SEPARATOR, 0, ptr::null());
+            append(menu, MF_STRING, EXIT, "Exit");
+
+            let mut point = POINT { x: 0, y: 0 };
+            GetCursorPos(&mut point);
+
+            // Otherwise the menu doesn't close when clicking outside of it.
+            SetForegroundWindow(hwnd);
+
+            let flags = TPM_RETURNCMD | TPM_RIGHTBUTTON;
+            let picked = TrackPopupMenu(menu, flags, point.x, point.y, 0, hwnd, ptr::null());
+            PostMessageW(hwnd, WM_NULL, 0, 0);
+            DestroyMenu(menu);
+            picked as usize
+        };
+
+        match picked {
+            SAVE_DATED => Some(Picked::Command(TrayCommand::SaveDated)),
+            LOAD_NEWEST => Some(Picked::Command(TrayCommand::LoadNewest)),
+            EXIT => Some(Picked::Exit),
+            id if id >= FIRST_SLOT => slots
+                .get(id - FIRST_SLOT)
+                .map(|slot| Picked::Command(TrayCommand::Load(slot.clone()))),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{files::to_extended_path, links, Error};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// How much space a slot takes up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotUsage {
+    /// The name of the slot.
+    pub name: String,
+    /// The directory of the slot.
+    pub path: PathBuf,
+    /// The size of every file in the slot.
+    pub apparent: u64,
+    /// The size of the files in the slot which aren't hard links to a file counted before it.
+    pub actual: u64,
+}
+
+/// How much space the slots of a profile take up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileUsage {
+    /// The directory of the profile.
+    pub profile: PathBuf,
+    /// The slots of the profile, largest first.
+    pub slots: Vec<SlotUsage>,
+    /// The size of every file in every slot of the profile.
+    pub apparent: u64,
+    /// The size of the files in the slots of the profile, counting each one only once no
+    /// matter how many slots it's hard linked into.
+    pub actual: u64,
+}
+
+/// Adds up the size of files, remembering which ones have been counted so that other hard
+/// links to them aren't counted again.
+#[derive(Debug, Default)]
+pub(crate) struct Tally {
+    seen: HashSet<(u64, u64)>,
+}
+
+impl Tally {
+    /// Add up the size of every file in the given directory and the directories in it,
+    /// returning their apparent and actual size.
+    ///
+    /// A file is only counted towards the actual size the first time it's seen. Files which
+    /// can't be identified are always counted.
+    pub(crate) fn add_dir(&mut self, dir: &Path) -> Result<(u64, u64), Error> {
+        let mut apparent = 0;
+        let mut actual = 0;
+        let mut queue = vec![dir.to_owned()];
+
+        while let Some(dir) = queue.pop() {
+            let entries = fs::read_dir(to_extended_path(&dir)).map_err(|e| Error::io(&dir, e))?;
+
+            for entry in entries {
+                let entry = entry.map_err(|e| Error::io(&dir, e))?;
+                let path = dir.join(entry.file_name());
+                let meta = entry.metadata().map_err(|e| Error::io(&path, e))?;
+
+                if meta.is_dir() {
+                    queue.push(path);
+                    continue;
+                }
+
+                if !meta.is_file() {
+                    continue;
+                }
+
+                apparent += meta.len();
+
+                let first = match links::file_id(&path) {
+                    Some(id) => self.seen.insert((id.volume, id.index)),
+                    None => true,
+                };
+
+                if first {
+                    actual += meta.len();
+                }
+            }
+        }
+
+        Ok((apparent, actual))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn hard_links_are_counted_once() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        fs::create_dir_all(a.join("nested"))?;
+        fs::create_dir(&b)?;
+
+        fs::write(a.join("SGTA00000"), b"shared")?;
+        fs::write(a.join("nested").join("SGTA00001"), b"nested")?;
+        fs::hard_link(a.join("SGTA00000"), b.join("SGTA00000"))?;
+        fs::write(b.join("SGTA00001"), b"own")?;
+
+        let mut tally = Tally::default();
+        assert_eq!(tally.add_dir(&a)?, (12, 12));
+        assert_eq!(tally.add_dir(&b)?, (9, 3));
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{files, lock, Error, SaveFilePatterns, SaveManager, PROFILE};
+use notify::{EventKind, RecursiveMode, Watcher as _};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    time::{Duration, Instant},
+};
+
+/// How often the stop flag is checked while waiting for changes.
+const POLL: Duration = Duration::from_millis(250);
+
+/// How long to wait for another instance to finish before saving.
+const LOCK_WAIT: Duration = Duration::from_secs(5);
+
+/// How a profile is watched by [SaveManager::watch].
+#[derive(Debug, Clone, Copy)]
+pub struct WatchOptions {
+    /// How long to wait after the last write to a save file before saving, so that the burst of
+    /// writes the game does when saving results in a single slot.
+    pub debounce: Duration,
+    /// The minimum time between two saved slots.
+    pub interval: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_secs(5),
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Decides when to save based on when the save files were written.
+struct Debounce {
+    options: WatchOptions,
+    /// When a save file was last written, if it hasn't been saved since.
+    written: Option<Instant>,
+    /// When the last slot was saved.
+    saved: Option<Instant>,
+}
+
+impl Debounce {
+    fn new(options: WatchOptions) -> Self {
+        Self {
+            options,
+            written: None,
+            saved: None,
+        }
+    }
+
+    fn write(&mut self, now: Instant) {
+        self.written = Some(now);
+    }
+
+    /// Test if the writes have settled and enough time has passed since the last save.
+    fn ready(&self, now: Instant) -> bool {
+        let written = match self.written {
+            Some(written) => written,
+            None => return false,
+        };
+
+        if now.duration_since(written) < self.options.debounce {
+            return false;
+        }
+
+        match self.saved {
+            Some(saved) => now.duration_since(saved) >= self.options.interval,
+            None => true,
+        }
+    }
+
+    fn save(&mut self, now: Instant) {
+        self.written = None;
+        self.saved = Some(now);
+    }
+}
+
+/// Test if the event is a change to a save file directly in the watched directory.
+fn is_save_file_change(event: &notify::Event, patterns: &SaveFilePatterns) -> bool {
+    if let EventKind::Access(..) = event.kind {
+        return false;
+    }
+
+    event.paths.iter().any(|path| {
+        path.file_name()
+            .map(|n| files::is_save_file_name(n, patterns))
+            .unwrap_or_default()
+    })
+}
+
+pub(crate) fn watch(
+    manager: &SaveManager,
+    options: WatchOptions,
+    stop: &AtomicBool,
+) -> Result<(), Error> {
+    let profile = manager.profile();
+
+    let watch_error = |source| Error::Watch {
+        path: profile.to_owned(),
+        source,
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(watch_error)?;
+    watcher
+        .watch(profile, RecursiveMode::NonRecursive)
+        .map_err(watch_error)?;
+
+    out!("watching {} for new save files", profile.display());
+
+    // Compared against to skip saving files which are already in the newest dated slot, which
+    // also covers the files being touched without being changed.
+    let mut last_slot = manager
+        .list_slots()?
+        .into_iter()
+        .find(|s| s.dated)
+        .map(|s| s.name);
+
+    let mut debounce = Debounce::new(options);
+
+    while !stop.load(Ordering::SeqCst) {
+        match rx.recv_timeout(POLL) {
+            Ok(Ok(event)) => {
+                if is_save_file_change(&event, manager.patterns()) {
+                    debounce.write(Instant::now());
+                }
+            }
+            Ok(Err(source)) => return Err(watch_error(source)),
+            Err(mpsc::RecvTimeoutError::Timeout) => (),
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+
+        if !debounce.ready(now) {
+            continue;
+        }
+
+        // The lock is only held while saving, so that other commands can be used while watching.
+        // If another instance holds it for too long, saving is tried again later.
+        let _lock = match lock::base_of(profile) {
+            Some(base) => match lock::lock_instance(&base, LOCK_WAIT) {
+                Ok(lock) => Some(lock),
+                Err(Error::InstanceLocked { .. }) => continue,
+                Err(e) => return Err(e),
+            },
+            None => None,
+        };
+
+        debounce.save(now);
+
+        if let Some(slot) = &last_slot {
+            if let Ok(differences) = manager.diff_slots(PROFILE, slot) {
+                if differences.is_empty() {
+                    continue;
+                }
+            }
+        }
+
+        last_slot = Some(manager.journaled("watch", None, SaveManager::save_dated)?);
+    }
+
+    out!("stopped watching {}", profile.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn debounce_coalesces_writes() {
+        let options = WatchOptions {
+            debounce: Duration::from_secs(5),
+            interval: Duration::from_secs(60),
+        };
+
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut debounce = Debounce::new(options);
+        assert!(!debounce.ready(at(100)));
+
+        // A burst of writes is saved once they've settled.
+        debounce.write(at(0));
+        debounce.write(at(3));
+        assert!(!debounce.ready(at(7)));
+        assert!(debounce.ready(at(8)));
+        debounce.save(at(8));
+        assert!(!debounce.ready(at(20)));
+
+        // Writes shortly after a save wait for the interval.
+        debounce.write(at(10));
+        assert!(!debounce.ready(at(67)));
+        assert!(debounce.ready(at(68)));
+    }
+
+    #[test]
+    fn only_save_file_changes_count() {
+        let event = |kind, name: &str| notify::Event {
+            kind,
+            paths: vec![PathBuf::from("Profiles").join("abc").join(name)],
+            attrs: Default::default(),
+        };
+
+        let modify = EventKind::Modify(notify::event::ModifyKind::Any);
+        let access = EventKind::Access(notify::event::AccessKind::Any);
+
+        let patterns = SaveFilePatterns::default();
+        let change = |event| is_save_file_change(&event, &patterns);
+
+        assert!(change(event(modify, "SGTA00000")));
+        assert!(!change(event(access, "SGTA00000")));
+        assert!(!change(event(modify, "Slots")));
+        assert!(!change(event(modify, "cfg.dat")));
+
+        let patterns = SaveFilePatterns::new(&["SGTA*"], &["*.bak"]).expect("valid patterns");
+        assert!(!is_save_file_change(
+            &event(modify, "SGTA00000.bak"),
+            &patterns
+        ));
+    }
+}